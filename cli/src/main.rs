@@ -0,0 +1,690 @@
+//! `flyte`: pitch, fingering chart, layout fitting and exports for saved design files
+//! (the web app's JSON, or the same document written as TOML).
+
+use clap::{Parser, Subcommand, ValueEnum};
+use flyte_core::document::{self, DesignDocument};
+use flyte_core::ergonomics::{self, HandSize, OneHandedOptions};
+use flyte_core::geometry::{self, ExportUnits, MeshOptions, Severity, UpAxis};
+use flyte_core::panflute::{self, PanFluteOptions};
+use flyte_core::tolerance::{self, ToleranceOptions};
+use flyte_core::transpose::{self, TransposeOptions};
+use flyte_core::{export, import, synth, tuning, Flute, FlyteError};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+#[cfg(feature = "serve")]
+mod serve;
+
+#[derive(Parser)]
+#[command(name = "flyte", version, about = "Headless flute design processing")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Sounding pitch of the design, or of one fingering
+    Pitch {
+        design: PathBuf,
+        /// Bitmask of open holes (bit i = hole i), e.g. 7 or 0b0111. Default: as saved
+        #[arg(long, value_parser = parse_mask)]
+        fingering: Option<u32>,
+        /// Jet velocity in cm/s; omit for the default
+        #[arg(long)]
+        jet: Option<f64>,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Fingering chart, all holes closed first
+    Chart {
+        design: PathBuf,
+        #[arg(long)]
+        jet: Option<f64>,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Score several candidate designs against the first one's saved targets, best first
+    Leaderboard {
+        #[arg(required = true)]
+        designs: Vec<PathBuf>,
+        #[arg(long)]
+        jet: Option<f64>,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Monte Carlo spread of every note when the design is made within workshop tolerances
+    Tolerance {
+        design: PathBuf,
+        #[arg(long, default_value_t = 1000)]
+        samples: usize,
+        /// Random seed; the same seed gives the same study
+        #[arg(long, default_value_t = 1)]
+        seed: u64,
+        /// Tolerance on length, bore and hole diameters, wall and hole positions in mm,
+        /// e.g. 0.5,0.05,0.1,0.2,0.05. Default: the manufacturing spec's
+        #[arg(long, value_delimiter = ',', num_args = 5)]
+        tolerances: Option<Vec<f64>>,
+        #[arg(long)]
+        jet: Option<f64>,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Validate the design; exits with status 1 if it has errors
+    Check {
+        design: PathBuf,
+        /// Also fail on warnings
+        #[arg(long)]
+        strict: bool,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Fit a one-handed hole layout to target notes and write the new design
+    Optimize {
+        design: PathBuf,
+        /// Finger holes to keep, one to four
+        #[arg(long, default_value_t = 4)]
+        holes: usize,
+        #[arg(long, value_enum, default_value_t = Hand::Medium)]
+        hand: Hand,
+        /// Target notes in Hz, lowest first. Default: the design's saved targets
+        #[arg(long, value_delimiter = ',')]
+        targets: Vec<f64>,
+        /// Output design file (.json or .toml); stdout if omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Scale the design to a new key and re-fit it; writes the new design
+    Transpose {
+        design: PathBuf,
+        /// New all-holes-closed note in Hz
+        #[arg(long)]
+        key: f64,
+        /// Bore and holes scale with the length ratio to this power (1 = proportional)
+        #[arg(long, default_value_t = 0.5)]
+        bore_exponent: f64,
+        /// Scale the wall thickness with the bore too
+        #[arg(long)]
+        scale_wall: bool,
+        #[arg(long)]
+        jet: Option<f64>,
+        /// Output design file (.json or .toml); stdout if omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Pipe lengths and cut list for a pan-flute set, optionally as a printable STL
+    Panflute {
+        /// Pipe notes in Hz
+        #[arg(long, value_delimiter = ',', required = true)]
+        notes: Vec<f64>,
+        /// Inner diameter of the tube stock in mm
+        #[arg(long, default_value_t = 12.0)]
+        bore: f64,
+        /// Wall thickness of the tube stock in mm
+        #[arg(long, default_value_t = 1.5)]
+        wall: f64,
+        /// Stopper below each air column in mm
+        #[arg(long, default_value_t = 10.0)]
+        stopper: f64,
+        /// Length of each stock tube in mm, to pack the cuts
+        #[arg(long)]
+        stock: Option<f64>,
+        #[arg(long)]
+        json: bool,
+        /// Write the set as one STL raft (mm)
+        #[arg(long)]
+        stl: Option<PathBuf>,
+    },
+    /// Print the JSON Schema of the design file format
+    Schema,
+    /// Convert a design from another calculator into a flyte design file
+    Import {
+        input: PathBuf,
+        #[arg(long, value_enum)]
+        from: Source,
+        /// Output design file (.json or .toml); stdout if omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Serve the engine as a JSON HTTP API: POST a design to /evaluate, /chart or /export
+    #[cfg(feature = "serve")]
+    Serve {
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        addr: String,
+    },
+    /// Write a fabrication or mesh file
+    Export {
+        design: PathBuf,
+        #[arg(long, value_enum)]
+        format: Format,
+        #[arg(long, value_enum, default_value_t = Units::Mm)]
+        units: Units,
+        /// Z-up instead of Y-up (mesh formats)
+        #[arg(long)]
+        z_up: bool,
+        /// Default: the design's name with the format's extension
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Hand {
+    Small,
+    Medium,
+    Large,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Source {
+    /// Flutomat form values as JSON
+    Flutomat,
+    /// TWCalc `Name = value` calculation
+    Twcalc,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Units {
+    Mm,
+    Cm,
+    In,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Stl,
+    #[value(name = "3mf")]
+    ThreeMf,
+    Obj,
+    Glb,
+    /// Section and plan drawing
+    Dxf,
+    /// Drill template
+    Svg,
+    /// Printable plan with tuning chart
+    Pdf,
+    Gcode,
+    /// The scale as a MIDI file with pitch bends for the intonation
+    Midi,
+    /// Every note of the scale, synthesized
+    Wav,
+    /// Manufacturing spec JSON
+    Spec,
+    /// Hole table for spreadsheets
+    Csv,
+    /// Flutomat form values as JSON
+    Flutomat,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Stl => "stl",
+            Format::ThreeMf => "3mf",
+            Format::Obj => "obj",
+            Format::Glb => "glb",
+            Format::Dxf => "dxf",
+            Format::Svg => "svg",
+            Format::Pdf => "pdf",
+            Format::Gcode => "gcode",
+            Format::Midi => "mid",
+            Format::Wav => "wav",
+            Format::Spec => "spec.json",
+            Format::Csv => "csv",
+            Format::Flutomat => "flutomat.json",
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(command: Command) -> Result<ExitCode, Box<dyn Error>> {
+    match command {
+        Command::Pitch {
+            design,
+            fingering,
+            jet,
+            json,
+        } => {
+            let (mut flute, _) = load_design(&design)?;
+            let jet = jet.unwrap_or(f64::NAN);
+            let frequency = match fingering {
+                Some(mask) => tuning::pitch_for_fingering(&flute, mask, jet),
+                None => flute.checked_pitch(jet)?,
+            };
+            let note = tuning::note_info(frequency);
+            if json {
+                let out = serde_json::json!({ "frequency": frequency, "note": note });
+                println!("{}", serde_json::to_string_pretty(&out)?);
+            } else {
+                println!(
+                    "{:.2} Hz  {} {:+.1} cents",
+                    frequency, note.name, note.cents
+                );
+            }
+        }
+        Command::Chart { design, jet, json } => {
+            let (flute, _) = load_design(&design)?;
+            let chart = tuning::fingering_chart(&flute, jet.unwrap_or(f64::NAN));
+            if json {
+                println!("{}", serde_json::to_string_pretty(&chart)?);
+            } else {
+                let holes = flute.holes.len();
+                for row in &chart {
+                    let pattern: String = (0..holes)
+                        .map(|i| {
+                            if row.fingering >> i & 1 == 1 {
+                                'o'
+                            } else {
+                                '●'
+                            }
+                        })
+                        .collect();
+                    println!(
+                        "{}  {:>8.2} Hz  {:<4} {:+6.1}",
+                        pattern, row.frequency, row.note.name, row.note.cents
+                    );
+                }
+            }
+        }
+        Command::Leaderboard { designs, jet, json } => {
+            let mut candidates = Vec::new();
+            let mut first = None;
+            for path in &designs {
+                let (flute, doc) = load_design(path)?;
+                first.get_or_insert(doc);
+                candidates.push(flute);
+            }
+            let doc = first.ok_or("no designs given")?;
+            if doc.targets.is_empty() {
+                return Err(format!("{} has no targets", designs[0].display()).into());
+            }
+            let fingerings =
+                tuning::target_fingerings(&candidates[0], &doc.fingerings, doc.targets.len());
+            let board = tuning::leaderboard(
+                &candidates,
+                &fingerings,
+                &doc.targets,
+                jet.unwrap_or(f64::NAN),
+            )?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&board)?);
+            } else {
+                for (place, &i) in board.ranking.iter().enumerate() {
+                    let row = &board.rows[i];
+                    let cents: Vec<String> =
+                        row.cents.iter().map(|c| format!("{:+6.1}", c)).collect();
+                    println!(
+                        "{:>2}. {}  rms {:5.1}  worst {:5.1}  {}",
+                        place + 1,
+                        designs[i].display(),
+                        row.rms_cents,
+                        row.worst_cents,
+                        cents.join(" ")
+                    );
+                }
+            }
+        }
+        Command::Tolerance {
+            design,
+            samples,
+            seed,
+            tolerances,
+            jet,
+            json,
+        } => {
+            let (flute, _) = load_design(&design)?;
+            let mut options = ToleranceOptions {
+                samples,
+                seed,
+                jet_velocity: jet.unwrap_or(f64::NAN),
+                ..ToleranceOptions::default()
+            };
+            if let Some(&[length, bore, wall, position, diameter]) = tolerances.as_deref() {
+                options.length = length;
+                options.bore_diameter = bore;
+                options.wall_thickness = wall;
+                options.hole_position = position;
+                options.hole_diameter = diameter;
+            }
+            let study = tolerance::tolerance_study(&flute, &options);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&study)?);
+            } else {
+                println!("{} samples", study.samples);
+                for note in &study.notes {
+                    println!(
+                        "{:>8.2} Hz  {:<4} {:+6.1} ± {:4.1} cents  ({:+.1} to {:+.1})",
+                        note.frequency,
+                        tuning::note_info(note.frequency).name,
+                        note.mean_cents,
+                        note.std_cents,
+                        note.min_cents,
+                        note.max_cents
+                    );
+                }
+            }
+        }
+        Command::Check {
+            design,
+            strict,
+            json,
+        } => {
+            let (flute, _) = load_design(&design)?;
+            let issues = geometry::check_geometry(&flute);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&issues)?);
+            } else {
+                for issue in &issues {
+                    let level = match issue.severity {
+                        Severity::Error => "error",
+                        Severity::Warning => "warning",
+                    };
+                    println!("{}: {} [{}]", level, issue.message, issue.code);
+                }
+            }
+            let failed = issues
+                .iter()
+                .any(|i| strict || i.severity == Severity::Error);
+            if failed {
+                return Ok(ExitCode::FAILURE);
+            }
+            if !json {
+                println!("ok");
+            }
+        }
+        Command::Optimize {
+            design,
+            holes,
+            hand,
+            targets,
+            output,
+        } => {
+            let (mut flute, doc) = load_design(&design)?;
+            let options = OneHandedOptions {
+                hand_size: match hand {
+                    Hand::Small => HandSize::Small,
+                    Hand::Medium => HandSize::Medium,
+                    Hand::Large => HandSize::Large,
+                },
+                holes,
+                targets: if targets.is_empty() {
+                    doc.targets.clone()
+                } else {
+                    targets
+                },
+                ..OneHandedOptions::default()
+            };
+            let layout = ergonomics::one_handed_layout(&flute, &options);
+            for note in &layout.notes {
+                eprintln!(
+                    "{:>8.2} Hz -> {:>8.2} Hz  {:+6.1} cents",
+                    note.target, note.frequency, note.cents
+                );
+            }
+            eprintln!("spacing {:.2} cm", layout.spacing);
+            flute.holes = layout.relaid_holes(&flute)?;
+            let mut result = DesignDocument::new(&flute);
+            result.fingerings = doc.fingerings;
+            result.targets = options.targets;
+            match output {
+                Some(path) => save_design(&path, &result)?,
+                None => println!("{}", result.to_json()),
+            }
+        }
+        Command::Transpose {
+            design,
+            key,
+            bore_exponent,
+            scale_wall,
+            jet,
+            output,
+        } => {
+            let (flute, doc) = load_design(&design)?;
+            let options = TransposeOptions {
+                key,
+                bore_exponent,
+                scale_wall,
+                jet_velocity: jet.unwrap_or(f64::NAN),
+            };
+            let transposed = transpose::transpose(&flute, &options)?;
+            for note in &transposed.notes {
+                eprintln!(
+                    "{:>8.2} Hz -> {:>8.2} Hz  {:+6.1} cents",
+                    note.target, note.frequency, note.cents
+                );
+            }
+            eprintln!(
+                "length x{:.3}, bore x{:.3}",
+                transposed.length_scale, transposed.bore_scale
+            );
+            let mut result = DesignDocument::new(&transposed.flute);
+            result.fingerings = doc.fingerings;
+            result.targets = doc
+                .targets
+                .iter()
+                .map(|f| f / transposed.length_scale)
+                .collect();
+            match output {
+                Some(path) => save_design(&path, &result)?,
+                None => println!("{}", result.to_json()),
+            }
+        }
+        Command::Panflute {
+            notes,
+            bore,
+            wall,
+            stopper,
+            stock,
+            json,
+            stl,
+        } => {
+            let options = PanFluteOptions {
+                notes,
+                bore_radius: bore / 20.0,
+                wall_thickness: wall / 10.0,
+                stopper: stopper / 10.0,
+                stock_length: stock.map_or(f64::NAN, |mm| mm / 10.0),
+                ..PanFluteOptions::default()
+            };
+            let set = panflute::design_pan_flute(&options)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&set)?);
+            } else {
+                for pipe in &set.pipes {
+                    println!(
+                        "{:<4} {:>8.2} Hz  air {:>6.1} mm  cut {:>6.1} mm",
+                        pipe.note.name,
+                        pipe.target,
+                        10.0 * pipe.air_length,
+                        10.0 * pipe.cut_length
+                    );
+                }
+                for (n, piece) in set.cut_list.iter().enumerate() {
+                    let names: Vec<&str> = piece
+                        .pipes
+                        .iter()
+                        .map(|&i| set.pipes[i].note.name.as_str())
+                        .collect();
+                    println!(
+                        "stock {}: {}  (offcut {:.0} mm)",
+                        n + 1,
+                        names.join(" "),
+                        10.0 * piece.offcut
+                    );
+                }
+            }
+            if let Some(path) = stl {
+                let opts = mesh_options(Units::Mm, false);
+                let mesh = panflute::pan_flute_mesh(&set, &opts);
+                fs::write(
+                    &path,
+                    export::stl::export_stl(&mesh, opts.units, opts.up_axis),
+                )?;
+                eprintln!("wrote {}", path.display());
+            }
+        }
+        Command::Schema => println!("{}", document::json_schema()),
+        Command::Import {
+            input,
+            from,
+            output,
+        } => {
+            let text = fs::read_to_string(&input)
+                .map_err(|e| format!("cannot read {}: {}", input.display(), e))?;
+            let doc = match from {
+                Source::Flutomat => import::flutomat::import_flutomat(&text)?,
+                Source::Twcalc => import::twcalc::import_twcalc(&text)?,
+            };
+            doc.to_flute().validate()?;
+            match output {
+                Some(path) => save_design(&path, &doc)?,
+                None => println!("{}", doc.to_json()),
+            }
+        }
+        Command::Export {
+            design,
+            format,
+            units,
+            z_up,
+            output,
+        } => {
+            let (flute, _) = load_design(&design)?;
+            let bytes = export_bytes(&flute, format, &mesh_options(units, z_up));
+            let path = output.unwrap_or_else(|| design.with_extension(format.extension()));
+            fs::write(&path, bytes)?;
+            eprintln!("wrote {}", path.display());
+        }
+        #[cfg(feature = "serve")]
+        Command::Serve { addr } => serve::serve(&addr)?,
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn mesh_options(units: Units, z_up: bool) -> MeshOptions {
+    MeshOptions {
+        units: match units {
+            Units::Mm => ExportUnits::Millimeters,
+            Units::Cm => ExportUnits::Centimeters,
+            Units::In => ExportUnits::Inches,
+        },
+        up_axis: if z_up { UpAxis::Z } else { UpAxis::Y },
+        ..MeshOptions::default()
+    }
+}
+
+fn export_bytes(flute: &Flute, format: Format, opts: &MeshOptions) -> Vec<u8> {
+    let mesh = || geometry::generate_flute_mesh_with(flute, opts);
+    match format {
+        Format::Stl => export::stl::export_stl(&mesh(), opts.units, opts.up_axis),
+        Format::ThreeMf => export::threemf::export_3mf(&mesh(), opts.units),
+        Format::Obj => mesh().to_obj_string(opts.units, opts.up_axis).into_bytes(),
+        Format::Glb => export::gltf::export_glb(&mesh()),
+        Format::Dxf => export::dxf::export_dxf(flute).into_bytes(),
+        Format::Svg => export::svg::drill_template(flute).into_bytes(),
+        Format::Pdf => export::pdf::export_pdf_plan(flute, f64::NAN),
+        Format::Gcode => export::gcode::export_gcode(flute, &Default::default()).into_bytes(),
+        Format::Midi => export::midi::export_scale_midi(flute, f64::NAN),
+        Format::Wav => {
+            let opts = synth::SynthOptions::default();
+            let rate = opts.wav_sample_rate();
+            let opts = synth::SynthOptions {
+                sample_rate: rate.into(),
+                ..opts
+            };
+            let samples = synth::render_scale(flute, &opts);
+            export::wav::export_wav(&samples, rate)
+        }
+        Format::Spec => export::spec::export_spec_json(flute).into_bytes(),
+        Format::Csv => export::csv::export_hole_csv(flute, f64::NAN).into_bytes(),
+        Format::Flutomat => export::flutomat::export_flutomat_json(flute, f64::NAN).into_bytes(),
+    }
+}
+
+/// Read a design document from JSON or TOML (by extension) and check it
+fn load_design(path: &Path) -> Result<(Flute, DesignDocument), Box<dyn Error>> {
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    let doc = parse_design(&text, is_toml(path))?;
+    let flute = doc.to_flute();
+    flute.validate()?;
+    Ok((flute, doc))
+}
+
+fn parse_design(text: &str, toml: bool) -> Result<DesignDocument, FlyteError> {
+    if toml {
+        let value = toml::from_str(text).map_err(FlyteError::invalid_input)?;
+        DesignDocument::from_value(value)
+    } else {
+        DesignDocument::from_json(text)
+    }
+}
+
+fn save_design(path: &Path, doc: &DesignDocument) -> Result<(), Box<dyn Error>> {
+    let text = if is_toml(path) {
+        toml::to_string_pretty(doc)?
+    } else {
+        doc.to_json()
+    };
+    fs::write(path, text)?;
+    Ok(())
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("toml"))
+}
+
+fn parse_mask(s: &str) -> Result<u32, String> {
+    match s.strip_prefix("0b") {
+        Some(bits) => u32::from_str_radix(bits, 2),
+        None => s.parse(),
+    }
+    .map_err(|e| format!("invalid fingering {:?}: {}", s, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toml_design_matches_json() {
+        let toml = r#"
+            version = 1
+            units = "millimeters"
+
+            [instrument]
+            kind = "transverse"
+
+            [geometry]
+            length = 600.0
+            bore_radius = 9.5
+            wall_thickness = 4.0
+
+            [[geometry.holes]]
+            position = 400.0
+            radius = 3.5
+        "#;
+        let from_toml = parse_design(toml, true).unwrap().to_flute();
+        assert!((from_toml.length - 60.0).abs() < 1e-12);
+        assert!((from_toml.holes[0].radius - 0.35).abs() < 1e-12);
+
+        let doc = DesignDocument::new(&from_toml);
+        let text = toml::to_string_pretty(&doc).unwrap();
+        assert_eq!(parse_design(&text, true).unwrap(), doc);
+
+        assert_eq!(parse_mask("0b101"), Ok(5));
+        assert_eq!(parse_mask("6"), Ok(6));
+        assert!(parse_mask("x").is_err());
+    }
+}
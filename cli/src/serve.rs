@@ -0,0 +1,190 @@
+//! `flyte serve`: a thin JSON HTTP layer over the same calls as the other subcommands.
+//!
+//! Every POST body is a design document in the `to_json` format (any version). Options go
+//! in the query string:
+//!
+//! - `GET  /health`
+//! - `GET  /schema` JSON Schema of the design document
+//! - `POST /evaluate?jet=` pitch, nearest note and `check_geometry` issues
+//! - `POST /chart?jet=` fingering chart
+//! - `POST /export?format=stl&units=mm&z_up=true` the file bytes
+//!
+//! Failures come back as the `FlyteError` object (`{ code, message, ... }`) with status 400.
+
+use crate::{export_bytes, mesh_options, parse_design, Format, Units};
+use clap::ValueEnum;
+use flyte_core::{document, geometry, tuning, FlyteError};
+use serde_json::{json, Value};
+use std::error::Error;
+use std::io::Read;
+use tiny_http::{Header, Method, Response, Server};
+
+/// Designs are a few kB; anything much larger is not a design
+const MAX_BODY: u64 = 1 << 20;
+
+struct Reply {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Reply {
+    fn json(status: u16, value: &Value) -> Self {
+        Reply {
+            status,
+            content_type: "application/json",
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    fn error(status: u16, error: &FlyteError) -> Self {
+        let mut value = serde_json::to_value(error).unwrap_or_else(|_| json!({}));
+        value["message"] = json!(error.to_string());
+        Reply::json(status, &value)
+    }
+}
+
+pub fn serve(addr: &str) -> Result<(), Box<dyn Error>> {
+    let server = Server::http(addr).map_err(|e| format!("cannot listen on {}: {}", addr, e))?;
+    eprintln!("listening on http://{}", addr);
+    for mut request in server.incoming_requests() {
+        let mut body = String::new();
+        let reply = match request.as_reader().take(MAX_BODY).read_to_string(&mut body) {
+            Ok(_) => route(request.method(), request.url(), &body),
+            Err(e) => Reply::error(400, &FlyteError::invalid_input(e)),
+        };
+        let header =
+            Header::from_bytes("Content-Type", reply.content_type).expect("static header is valid");
+        let response = Response::from_data(reply.body)
+            .with_status_code(reply.status)
+            .with_header(header);
+        if let Err(e) = request.respond(response) {
+            eprintln!("failed to send response: {}", e);
+        }
+    }
+    Ok(())
+}
+
+fn route(method: &Method, url: &str, body: &str) -> Reply {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let param = |name: &str| {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    };
+    let result = match (method, path) {
+        (Method::Get, "/health") => Ok(Reply::json(
+            200,
+            &json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION") }),
+        )),
+        (Method::Get, "/schema") => Ok(Reply {
+            status: 200,
+            content_type: "application/schema+json",
+            body: document::json_schema().into_bytes(),
+        }),
+        (Method::Post, "/evaluate") => evaluate(body, param("jet")),
+        (Method::Post, "/chart") => chart(body, param("jet")),
+        (Method::Post, "/export") => export(body, param("format"), param("units"), param("z_up")),
+        _ => {
+            return Reply::error(
+                404,
+                &FlyteError::invalid_input(format!("no route for {} {}", method, path)),
+            )
+        }
+    };
+    result.unwrap_or_else(|error| Reply::error(400, &error))
+}
+
+fn evaluate(body: &str, jet: Option<&str>) -> Result<Reply, FlyteError> {
+    let mut flute = parse_design(body, false)?.to_flute();
+    flute.validate()?;
+    let frequency = flute.checked_pitch(parse_f64("jet", jet)?)?;
+    Ok(Reply::json(
+        200,
+        &json!({
+            "frequency": frequency,
+            "note": tuning::note_info(frequency),
+            "issues": geometry::check_geometry(&flute),
+        }),
+    ))
+}
+
+fn chart(body: &str, jet: Option<&str>) -> Result<Reply, FlyteError> {
+    let flute = parse_design(body, false)?.to_flute();
+    flute.validate()?;
+    let chart = tuning::fingering_chart(&flute, parse_f64("jet", jet)?);
+    Ok(Reply::json(200, &json!(chart)))
+}
+
+fn export(
+    body: &str,
+    format: Option<&str>,
+    units: Option<&str>,
+    z_up: Option<&str>,
+) -> Result<Reply, FlyteError> {
+    let flute = parse_design(body, false)?.to_flute();
+    flute.validate()?;
+    let format = Format::from_str(format.unwrap_or("stl"), true)
+        .map_err(|e| FlyteError::invalid_input(format!("format: {}", e)))?;
+    let units = Units::from_str(units.unwrap_or("mm"), true)
+        .map_err(|e| FlyteError::invalid_input(format!("units: {}", e)))?;
+    let z_up = matches!(z_up, Some("1" | "true"));
+    let content_type = match format {
+        Format::Obj | Format::Dxf | Format::Gcode => "text/plain",
+        Format::Svg => "image/svg+xml",
+        Format::Pdf => "application/pdf",
+        Format::Midi => "audio/midi",
+        Format::Wav => "audio/wav",
+        Format::Spec | Format::Flutomat => "application/json",
+        Format::Csv => "text/csv",
+        Format::Glb => "model/gltf-binary",
+        Format::Stl | Format::ThreeMf => "application/octet-stream",
+    };
+    Ok(Reply {
+        status: 200,
+        content_type,
+        body: export_bytes(&flute, format, &mesh_options(units, z_up)),
+    })
+}
+
+/// A numeric query parameter; missing means NaN, the engine's "use the default"
+fn parse_f64(name: &str, value: Option<&str>) -> Result<f64, FlyteError> {
+    value.map_or(Ok(f64::NAN), |v| {
+        v.parse().map_err(|_| {
+            FlyteError::invalid_input(format!("{} must be a number, got {:?}", name, v))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_routes() {
+        let design = r#"{"length": 60, "bore_radius": 0.95, "wall_thickness": 0.4,
+            "holes": [{"position": 40, "radius": 0.35}]}"#;
+
+        let reply = route(&Method::Post, "/evaluate", design);
+        assert_eq!(reply.status, 200);
+        let value: Value = serde_json::from_slice(&reply.body).unwrap();
+        assert!(value["frequency"].as_f64().unwrap() > 200.0);
+
+        let reply = route(&Method::Post, "/chart?jet=0", design);
+        let value: Value = serde_json::from_slice(&reply.body).unwrap();
+        assert_eq!(value.as_array().unwrap().len(), 2);
+
+        let reply = route(&Method::Post, "/export?format=3mf&units=cm", design);
+        assert_eq!(reply.status, 200);
+        assert_eq!(&reply.body[..2], b"PK");
+
+        let reply = route(&Method::Post, "/evaluate", r#"{"length": -1}"#);
+        assert_eq!(reply.status, 400);
+        let value: Value = serde_json::from_slice(&reply.body).unwrap();
+        assert_eq!(value["code"], "invalid_input");
+
+        assert_eq!(route(&Method::Get, "/nope", "").status, 404);
+    }
+}
@@ -0,0 +1,137 @@
+#ifndef FLYTE_H
+#define FLYTE_H
+
+/* Generated by cbindgen from src/ffi.rs with the `ffi` feature. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Mesh file format for `flyte_engine_export_mesh`
+typedef enum FlyteMeshFormat {
+  // OBJ text, one group per part
+  FLYTE_MESH_FORMAT_OBJ = 0,
+  // Binary STL
+  FLYTE_MESH_FORMAT_STL = 1,
+  FLYTE_MESH_FORMAT_THREE_MF = 2,
+  // Binary glTF, metres, Y-up
+  FLYTE_MESH_FORMAT_GLB = 3,
+} FlyteMeshFormat;
+
+// Result of a fallible call. Matches the `code` of `FlyteError`.
+typedef enum FlyteStatus {
+  FLYTE_STATUS_OK = 0,
+  FLYTE_STATUS_NULL_POINTER = 1,
+  FLYTE_STATUS_INVALID_GEOMETRY = 2,
+  FLYTE_STATUS_FAILED_CHECK = 3,
+  FLYTE_STATUS_INDEX_OUT_OF_BOUNDS = 4,
+  FLYTE_STATUS_SOLVER_DID_NOT_CONVERGE = 5,
+  FLYTE_STATUS_INVALID_INPUT = 6,
+  FLYTE_STATUS_SERIALIZATION = 7,
+} FlyteStatus;
+
+// Opaque handle to a design and its export settings
+typedef struct FlyteEngine FlyteEngine;
+
+// Bytes owned by the library
+typedef struct FlyteBuffer {
+  uint8_t *data;
+  size_t len;
+} FlyteBuffer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Message for the last failed call on this thread, or NULL. Valid until the next failing
+// call on the same thread; do not free.
+const char *flyte_last_error(void);
+
+// A new engine with no holes, or NULL if the dimensions (cm) are invalid
+struct FlyteEngine *flyte_engine_new(double length, double bore_radius, double wall_thickness);
+
+// Load a design saved by `flyte_engine_to_json` or the web app. NULL on failure.
+//
+// # Safety
+// `json` must be NULL or a NUL-terminated string.
+struct FlyteEngine *flyte_engine_from_json(const char *json);
+
+// # Safety
+// `engine` must be NULL or a pointer from `flyte_engine_new` / `flyte_engine_from_json`
+// that has not been freed.
+void flyte_engine_free(struct FlyteEngine *engine);
+
+// Set the tube dimensions (cm). Pass NaN to keep a value.
+//
+// # Safety
+// `engine` must be a live engine pointer.
+enum FlyteStatus flyte_engine_set_geometry(struct FlyteEngine *engine,
+                                           double length,
+                                           double bore_radius,
+                                           double wall_thickness);
+
+// Replace every hole. `open` may be NULL for all open; otherwise nonzero = open.
+//
+// # Safety
+// `engine` must be a live engine pointer; `positions` and `radii` must point to `count`
+// doubles and `open` (if not NULL) to `count` bytes.
+enum FlyteStatus flyte_engine_set_holes(struct FlyteEngine *engine,
+                                        const double *positions,
+                                        const double *radii,
+                                        const uint8_t *open,
+                                        size_t count);
+
+// Open or close one hole
+//
+// # Safety
+// `engine` must be a live engine pointer.
+enum FlyteStatus flyte_engine_set_hole_open(struct FlyteEngine *engine, size_t index, bool open);
+
+// Sounding pitch (Hz) of the current open/closed state into `out_hz`. NaN jet velocity
+// uses the default.
+//
+// # Safety
+// `engine` must be a live engine pointer and `out_hz` writable.
+enum FlyteStatus flyte_engine_calculate_pitch(struct FlyteEngine *engine,
+                                              double jet_velocity,
+                                              double *out_hz);
+
+// Units and up axis used by `flyte_engine_export_mesh`: 0 = mm, 1 = cm, 2 = inches;
+// `z_up` false = Y-up
+//
+// # Safety
+// `engine` must be a live engine pointer.
+enum FlyteStatus flyte_engine_set_export_units(struct FlyteEngine *engine,
+                                               uint32_t units,
+                                               bool z_up);
+
+// Build the export mesh in `format` into `out`, to be freed with `flyte_buffer_free`
+//
+// # Safety
+// `engine` must be a live engine pointer and `out` writable.
+enum FlyteStatus flyte_engine_export_mesh(const struct FlyteEngine *engine,
+                                          enum FlyteMeshFormat format,
+                                          struct FlyteBuffer *out);
+
+// # Safety
+// `buffer` must come from this library and not have been freed.
+void flyte_buffer_free(struct FlyteBuffer buffer);
+
+// The design as a versioned JSON document, to be freed with `flyte_string_free`.
+// NULL if `engine` is NULL.
+//
+// # Safety
+// `engine` must be NULL or a live engine pointer.
+char *flyte_engine_to_json(const struct FlyteEngine *engine);
+
+// # Safety
+// `s` must be NULL or a string returned by this library that has not been freed.
+void flyte_string_free(char *s);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* FLYTE_H */
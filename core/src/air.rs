@@ -0,0 +1,355 @@
+//! The air in the bore. The model runs at one fixed speed of sound; real air carries sound
+//! faster when warm or humid and slower with the CO2 of breath, and a played instrument is
+//! warmest at the blowing end. Every frequency the model finds scales with the speed of
+//! sound its standing wave sees, so a chart for other air is the model's chart rescaled:
+//! cold against played, or over a range of room temperatures.
+
+use crate::physics::{Flute, SPEED_OF_SOUND};
+use crate::tuning::{self, ComparisonRow};
+use serde::{Deserialize, Serialize};
+
+/// J/(mol K)
+const GAS_CONSTANT: f64 = 8.314_462;
+const ZERO_CELSIUS: f64 = 273.15;
+/// hPa
+const ATMOSPHERE: f64 = 1013.25;
+/// Molar mass (kg/mol) and ratio of specific heats of dry air, water vapour and CO2
+const DRY_AIR: (f64, f64) = (0.028_964, 1.400);
+const WATER: (f64, f64) = (0.018_015, 1.330);
+const CARBON_DIOXIDE: (f64, f64) = (0.044_010, 1.289);
+/// Most temperatures `temperature_chart` evaluates
+pub const MAX_TEMPERATURES: usize = 500;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct Air {
+    /// °C
+    pub temperature: f64,
+    /// Relative humidity, %
+    pub humidity: f64,
+    /// CO2 beyond ordinary air's, % by volume: 0 for room air, a few % for breath
+    pub co2: f64,
+}
+
+impl Default for Air {
+    /// A 20 °C room at 50 % humidity
+    fn default() -> Self {
+        Air {
+            temperature: 20.0,
+            humidity: 50.0,
+            co2: 0.0,
+        }
+    }
+}
+
+impl Air {
+    /// cm/s, from the mixture's molar mass and ratio of specific heats: c = sqrt(gamma R T / M)
+    pub fn speed_of_sound(&self) -> f64 {
+        let t = self.temperature + ZERO_CELSIUS;
+        // Magnus formula for the saturation vapour pressure, hPa
+        let saturation = 6.1094 * (17.625 * self.temperature / (self.temperature + 243.04)).exp();
+        let water = (self.humidity / 100.0).clamp(0.0, 1.0) * saturation / ATMOSPHERE;
+        let co2 = (self.co2 / 100.0).clamp(0.0, 1.0 - water);
+        let parts = [
+            (1.0 - water - co2, DRY_AIR),
+            (water, WATER),
+            (co2, CARBON_DIOXIDE),
+        ];
+        let molar_mass: f64 = parts.iter().map(|&(x, (m, _))| x * m).sum();
+        // Heat capacities add by mole fraction: Cv = R / (gamma - 1)
+        let cv: f64 = parts.iter().map(|&(x, (_, g))| x / (g - 1.0)).sum();
+        let gamma = 1.0 + 1.0 / cv;
+        100.0 * (gamma * GAS_CONSTANT * t / molar_mass).sqrt()
+    }
+}
+
+/// The instrument at rest in the room and while played: warm, humid breath at the
+/// blowing end, cooling towards the foot
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct PlayedOptions {
+    /// The room, and the air in the bore before it is played
+    pub ambient: Air,
+    /// Air temperature in the bore at the blowing end while played, °C
+    pub breath_temperature: f64,
+    /// The same at the foot, °C; the air in between warms linearly
+    pub foot_temperature: f64,
+    /// Relative humidity of the air in the played bore, %
+    pub breath_humidity: f64,
+    /// CO2 the breath leaves in the bore, % by volume
+    pub breath_co2: f64,
+    /// NaN uses the solver default
+    pub jet_velocity: f64,
+}
+
+impl Default for PlayedOptions {
+    fn default() -> Self {
+        PlayedOptions {
+            ambient: Air::default(),
+            breath_temperature: 30.0,
+            foot_temperature: 25.0,
+            breath_humidity: 90.0,
+            breath_co2: 2.0,
+            jet_velocity: f64::NAN,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ColdVsPlayed {
+    /// Speed of sound in the room and in the played bore at the blowing end, cm/s
+    pub ambient_speed: f64,
+    pub breath_speed: f64,
+    /// Every progressive fingering: `a` cold, `b` played, `cents` b from a
+    pub notes: Vec<ComparisonRow>,
+}
+
+/// The tuning chart as a maker measures it, in room air, next to the same chart while
+/// played. Each note hears the mean speed of sound along its sounding length, which is
+/// taken at the temperature halfway down it, so short high notes sit in warmer air and
+/// shift further than the long low ones.
+pub fn cold_vs_played(flute: &Flute, opts: &PlayedOptions) -> ColdVsPlayed {
+    let fingerings = tuning::progressive_fingerings(flute);
+    let model = tuning::pitches_for_fingerings(flute, &fingerings, opts.jet_velocity);
+    let ambient_speed = opts.ambient.speed_of_sound();
+    let notes = fingerings
+        .into_iter()
+        .zip(model)
+        .map(|(fingering, f)| {
+            let played = opts.bore_air(midpoint(flute, f)).speed_of_sound();
+            let (a, b) = (
+                f * ambient_speed / SPEED_OF_SOUND,
+                f * played / SPEED_OF_SOUND,
+            );
+            ComparisonRow {
+                fingering,
+                a,
+                b,
+                cents: 1200.0 * (b / a).log2(),
+            }
+        })
+        .collect();
+    ColdVsPlayed {
+        ambient_speed,
+        breath_speed: opts.bore_air(0.0).speed_of_sound(),
+        notes,
+    }
+}
+
+impl PlayedOptions {
+    /// Air in the played bore `along` the way from the blowing end (0) to the foot (1)
+    fn bore_air(&self, along: f64) -> Air {
+        Air {
+            temperature: self.breath_temperature
+                + along * (self.foot_temperature - self.breath_temperature),
+            humidity: self.breath_humidity,
+            co2: self.breath_co2,
+        }
+    }
+
+    /// The same breath in a room at `temperature`: the foot keeps its share of the breath's
+    /// warming over the room
+    fn in_room(&self, temperature: f64) -> PlayedOptions {
+        let warming = self.breath_temperature - self.ambient.temperature;
+        let share = if warming.abs() > 1e-9 {
+            (self.foot_temperature - self.ambient.temperature) / warming
+        } else {
+            1.0
+        };
+        PlayedOptions {
+            ambient: Air {
+                temperature,
+                ..self.ambient
+            },
+            foot_temperature: temperature + share * (self.breath_temperature - temperature),
+            ..self.clone()
+        }
+    }
+}
+
+/// Fraction of the way down the bore to the middle of the sounding length of a note at `f`
+fn midpoint(flute: &Flute, f: f64) -> f64 {
+    let ends = if flute.instrument.is_stopped() {
+        4.0
+    } else {
+        2.0
+    };
+    0.5 * (SPEED_OF_SOUND / (ends * f)).min(flute.length) / flute.length
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct TemperatureChartOptions {
+    /// Fingerings to chart (bit i = `holes[i]` open); empty charts every progressive one
+    pub fingerings: Vec<u32>,
+    /// Room temperatures to sweep, °C, at `points` evenly spaced steps (up to
+    /// `MAX_TEMPERATURES`)
+    pub from: f64,
+    pub to: f64,
+    pub points: usize,
+    /// Room temperature the instrument is tuned at; every curve is cents from its note there
+    pub reference: f64,
+    /// Chart the instrument as played, with the breath of `breath` warming the bore over
+    /// each room temperature; false charts the bore full of room air
+    pub played: bool,
+    /// Breath conditions when `played`, and the room's humidity and CO2 otherwise
+    pub breath: PlayedOptions,
+}
+
+impl Default for TemperatureChartOptions {
+    fn default() -> Self {
+        TemperatureChartOptions {
+            fingerings: Vec::new(),
+            from: 10.0,
+            to: 30.0,
+            points: 21,
+            reference: 20.0,
+            played: true,
+            breath: PlayedOptions::default(),
+        }
+    }
+}
+
+/// One note's tuning over the temperature sweep
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TemperatureCurve {
+    pub fingering: u32,
+    /// Hz at the reference temperature
+    pub frequency: f64,
+    /// From `frequency` at each of the chart's temperatures, positive = sharp
+    pub cents: Vec<f64>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TemperatureChart {
+    /// °C
+    pub temperatures: Vec<f64>,
+    pub notes: Vec<TemperatureCurve>,
+}
+
+/// Tuning deviation curves over a range of room temperatures, ready to plot. In room air
+/// every note moves alike, about 3 cents per degree; played, the breath keeps the blowing
+/// end warm whatever the room, so the notes move less and the long low ones, which reach
+/// further into the room-temperature foot, move most.
+pub fn temperature_chart(flute: &Flute, opts: &TemperatureChartOptions) -> TemperatureChart {
+    let points = opts.points.clamp(2, MAX_TEMPERATURES);
+    let temperatures: Vec<f64> = (0..points)
+        .map(|i| opts.from + (opts.to - opts.from) * i as f64 / (points - 1) as f64)
+        .collect();
+    let fingerings = if opts.fingerings.is_empty() {
+        tuning::progressive_fingerings(flute)
+    } else {
+        opts.fingerings.clone()
+    };
+    let model = tuning::pitches_for_fingerings(flute, &fingerings, opts.breath.jet_velocity);
+    let speed = |temperature: f64, along: f64| {
+        if opts.played {
+            opts.breath.in_room(temperature).bore_air(along)
+        } else {
+            Air {
+                temperature,
+                ..opts.breath.ambient
+            }
+        }
+        .speed_of_sound()
+    };
+    let notes = fingerings
+        .into_iter()
+        .zip(model)
+        .map(|(fingering, f)| {
+            let along = midpoint(flute, f);
+            let reference = speed(opts.reference, along);
+            TemperatureCurve {
+                fingering,
+                frequency: f * reference / SPEED_OF_SOUND,
+                cents: temperatures
+                    .iter()
+                    .map(|&t| 1200.0 * (speed(t, along) / reference).log2())
+                    .collect(),
+            }
+        })
+        .collect();
+    TemperatureChart {
+        temperatures,
+        notes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Hole;
+
+    #[test]
+    fn test_cold_vs_played() {
+        // 343.2 m/s in dry air at 20 °C; damp air is faster and CO2 slower
+        let dry = Air {
+            humidity: 0.0,
+            ..Air::default()
+        };
+        assert!((dry.speed_of_sound() - 34_325.0).abs() < 30.0);
+        assert!(Air::default().speed_of_sound() > dry.speed_of_sound());
+        let stale = Air { co2: 4.0, ..dry };
+        assert!(stale.speed_of_sound() < dry.speed_of_sound());
+
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for pos in [40.0, 35.0, 30.0] {
+            flute.holes.push(Hole::new(pos, 0.4, true)).unwrap();
+        }
+        let played = cold_vs_played(&flute, &PlayedOptions::default());
+        assert_eq!(played.notes.len(), 4);
+        assert!(played.breath_speed > played.ambient_speed);
+        // Warm breath plays sharp, by the tens of cents players know, and the high notes
+        // more so
+        for note in &played.notes {
+            assert!(note.cents > 10.0 && note.cents < 60.0, "{:?}", note);
+        }
+        assert!(played.notes[3].cents > played.notes[0].cents);
+
+        // Breathing room air changes nothing
+        let same = PlayedOptions {
+            breath_temperature: 20.0,
+            foot_temperature: 20.0,
+            breath_humidity: 50.0,
+            breath_co2: 0.0,
+            ..PlayedOptions::default()
+        };
+        for note in cold_vs_played(&flute, &same).notes {
+            assert!(note.cents.abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_temperature_chart() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for pos in [40.0, 35.0, 30.0] {
+            flute.holes.push(Hole::new(pos, 0.4, true)).unwrap();
+        }
+        let room = TemperatureChartOptions {
+            played: false,
+            ..TemperatureChartOptions::default()
+        };
+        let chart = temperature_chart(&flute, &room);
+        assert_eq!(chart.temperatures.len(), 21);
+        assert_eq!(chart.temperatures[10], 20.0);
+        // Room air: every note alike, zero at the reference and about 3 cents per degree
+        for note in &chart.notes {
+            assert!(note.cents[10].abs() < 1e-9);
+            assert!((note.cents[20] / 10.0 - 3.0).abs() < 0.5, "{:?}", note);
+            assert!((note.cents[20] - chart.notes[0].cents[20]).abs() < 1e-9);
+        }
+
+        // Played, the warm head end steadies every note, the short high ones most
+        let played = temperature_chart(
+            &flute,
+            &TemperatureChartOptions {
+                fingerings: vec![0, 0b111],
+                ..TemperatureChartOptions::default()
+            },
+        );
+        assert_eq!(played.notes.len(), 2);
+        let (low, high) = (&played.notes[0], &played.notes[1]);
+        assert!(low.cents[20] > 0.0 && low.cents[20] < chart.notes[0].cents[20]);
+        assert!(high.cents[20] < low.cents[20]);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::error::FlyteError;
+use crate::geometry::{self, Severity};
+use crate::physics::{Flute, Hole, InstrumentType, SideResonator};
+
+/// Fluent construction of a `Flute`, validated in `build`:
+///
+/// ```
+/// use flyte_core::FluteBuilder;
+///
+/// let flute = FluteBuilder::new()
+///     .length(60.0)
+///     .bore(0.95)
+///     .wall(0.4)
+///     .embouchure(0.5, 0.5)
+///     .hole(40.0, 0.35)
+///     .hole(35.0, 0.35)
+///     .build()
+///     .unwrap();
+/// assert_eq!(flute.holes.len(), 2);
+/// ```
+///
+/// Length, bore and wall have no defaults; everything else starts at the `Flute::new` values.
+#[derive(Debug, Clone)]
+pub struct FluteBuilder {
+    flute: Flute,
+    /// First hole `Holes::push` turned away, reported by `build`
+    rejected: Option<FlyteError>,
+}
+
+impl Default for FluteBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FluteBuilder {
+    pub fn new() -> Self {
+        FluteBuilder {
+            flute: Flute::new(f64::NAN, f64::NAN, f64::NAN),
+            rejected: None,
+        }
+    }
+
+    /// Sounding length from the embouchure centre (cm)
+    pub fn length(mut self, length: f64) -> Self {
+        self.flute.length = length;
+        self
+    }
+
+    /// Bore radius (cm)
+    pub fn bore(mut self, radius: f64) -> Self {
+        self.flute.bore_radius = radius;
+        self
+    }
+
+    pub fn wall(mut self, thickness: f64) -> Self {
+        self.flute.wall_thickness = thickness;
+        self
+    }
+
+    /// Embouchure hole radius and chimney height (cm)
+    pub fn embouchure(mut self, radius: f64, chimney: f64) -> Self {
+        self.flute.embouchure_hole_radius = radius;
+        self.flute.embouchure_chimney = chimney;
+        self
+    }
+
+    pub fn embouchure_oval(mut self, oval: f64) -> Self {
+        self.flute.embouchure_oval = oval;
+        self
+    }
+
+    pub fn cork(mut self, position: f64) -> Self {
+        self.flute.cork_position = position;
+        self
+    }
+
+    pub fn instrument(mut self, instrument: InstrumentType) -> Self {
+        self.flute.instrument = instrument;
+        self
+    }
+
+    /// An open finger hole on top of the tube
+    pub fn hole(self, position: f64, radius: f64) -> Self {
+        self.push_hole(Hole::new(position, radius, true))
+    }
+
+    /// An open hole rotated around the tube (degrees, 180 = thumb side)
+    pub fn hole_at(self, position: f64, radius: f64, azimuth: f64) -> Self {
+        let mut hole = Hole::new(position, radius, true);
+        hole.azimuth = azimuth;
+        self.push_hole(hole)
+    }
+
+    fn push_hole(mut self, hole: Hole) -> Self {
+        if let Err(error) = self.flute.holes.push(hole) {
+            self.rejected.get_or_insert(error);
+        }
+        self
+    }
+
+    /// A closed Helmholtz cavity off the bore (cm, cm^3)
+    pub fn resonator(
+        mut self,
+        position: f64,
+        neck_radius: f64,
+        neck_length: f64,
+        volume: f64,
+    ) -> Self {
+        self.flute.resonators.push(SideResonator::new(
+            position,
+            neck_radius,
+            neck_length,
+            volume,
+        ));
+        self
+    }
+
+    /// The flute, once every dimension is in range and no `check_geometry` error remains.
+    /// Wall thickness below the printable minimum is allowed; that only matters for export.
+    pub fn build(self) -> Result<Flute, FlyteError> {
+        if let Some(error) = self.rejected {
+            return Err(error);
+        }
+        self.flute.validate()?;
+        if let Some(issue) = geometry::check_geometry(&self.flute)
+            .into_iter()
+            .find(|i| i.severity == Severity::Error && i.code != "thin_wall")
+        {
+            return Err(FlyteError::FailedCheck {
+                check: issue.code.to_string(),
+                message: issue.message,
+                hole: issue.hole,
+            });
+        }
+        Ok(self.flute)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_validates() {
+        let flute = FluteBuilder::new()
+            .length(60.0)
+            .bore(0.95)
+            .wall(0.4)
+            .cork(2.0)
+            .hole(40.0, 0.35)
+            .hole_at(30.0, 0.3, 180.0)
+            .build()
+            .unwrap();
+        assert_eq!(flute.cork_position, 2.0);
+        assert_eq!(flute.holes[1].azimuth, 180.0);
+
+        let missing = FluteBuilder::new().length(60.0).bore(0.95).build();
+        assert!(matches!(
+            missing,
+            Err(FlyteError::InvalidGeometry { field, .. }) if field == "wall_thickness"
+        ));
+
+        let overlapping = FluteBuilder::new()
+            .length(60.0)
+            .bore(0.95)
+            .wall(0.4)
+            .hole(40.0, 0.35)
+            .hole(40.5, 0.35)
+            .build();
+        assert!(matches!(
+            overlapping,
+            Err(FlyteError::FailedCheck { check, hole: Some(0), .. }) if check == "holes_overlap"
+        ));
+    }
+}
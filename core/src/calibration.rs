@@ -0,0 +1,404 @@
+//! Fitting the model's empirical constants to real instruments. Makers who have measured
+//! the notes of instruments they built can fit the end corrections and loss factor in
+//! `Calibration` to them, so designs in the same family come out as their bench does
+//! rather than as the textbook values predict.
+
+use crate::document::DesignDocument;
+use crate::ergonomics::pitch_near;
+use crate::error::FlyteError;
+use crate::physics::{Calibration, Flute};
+use serde::{Deserialize, Serialize};
+
+/// Relative step of the finite-difference Jacobian
+const JACOBIAN_STEP: f64 = 1e-3;
+/// Levenberg-Marquardt damping to start from, and its growth and shrink factors
+const INITIAL_DAMPING: f64 = 1e-3;
+const DAMPING_UP: f64 = 4.0;
+const DAMPING_DOWN: f64 = 3.0;
+/// The fit stops once a step improves the RMS error by less than this, cents
+const CONVERGED: f64 = 1e-4;
+
+/// One of the constants `fit_calibration` can adjust
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Constant {
+    OpenEndCorrection,
+    HoleEndCorrection,
+    EmbouchureEndCorrection,
+    LossFactor,
+}
+
+impl Constant {
+    pub const ALL: [Constant; 4] = [
+        Constant::OpenEndCorrection,
+        Constant::HoleEndCorrection,
+        Constant::EmbouchureEndCorrection,
+        Constant::LossFactor,
+    ];
+
+    fn get(self, calibration: &Calibration) -> f64 {
+        match self {
+            Constant::OpenEndCorrection => calibration.open_end_correction,
+            Constant::HoleEndCorrection => calibration.hole_end_correction,
+            Constant::EmbouchureEndCorrection => calibration.embouchure_end_correction,
+            Constant::LossFactor => calibration.loss_factor,
+        }
+    }
+
+    fn set(self, calibration: &mut Calibration, value: f64) {
+        let (lo, hi) = self.range();
+        let value = value.clamp(lo, hi);
+        match self {
+            Constant::OpenEndCorrection => calibration.open_end_correction = value,
+            Constant::HoleEndCorrection => calibration.hole_end_correction = value,
+            Constant::EmbouchureEndCorrection => calibration.embouchure_end_correction = value,
+            Constant::LossFactor => calibration.loss_factor = value,
+        }
+    }
+
+    /// Values the fit may move to; the published measurements all sit well inside
+    fn range(self) -> (f64, f64) {
+        match self {
+            Constant::OpenEndCorrection => (0.3, 1.0),
+            Constant::HoleEndCorrection | Constant::EmbouchureEndCorrection => (0.3, 4.0),
+            Constant::LossFactor => (0.25, 4.0),
+        }
+    }
+}
+
+/// A note measured on a made instrument
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct MeasuredNote {
+    /// Bitmask of open holes (bit i = `holes[i]`)
+    pub fingering: u32,
+    /// Hz
+    pub frequency: f64,
+}
+
+/// An instrument as built and the notes it actually plays
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KnownInstrument {
+    #[serde(default)]
+    pub name: String,
+    pub design: DesignDocument,
+    pub notes: Vec<MeasuredNote>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct CalibrationOptions {
+    /// Where the fit starts; constants left out of `fit` keep these values
+    pub initial: Calibration,
+    /// Constants to fit. Loss moves pitch only a little, so it is best fitted from a table
+    /// with many notes, or left out.
+    pub fit: Vec<Constant>,
+    pub max_iterations: usize,
+}
+
+impl Default for CalibrationOptions {
+    fn default() -> Self {
+        CalibrationOptions {
+            initial: Calibration::default(),
+            fit: Constant::ALL.to_vec(),
+            max_iterations: 30,
+        }
+    }
+}
+
+/// How far the model is from one measured note, cents (positive = model sharp), with the
+/// starting and the fitted constants
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Residual {
+    /// Index into the instruments given
+    pub instrument: usize,
+    pub fingering: u32,
+    pub measured: f64,
+    pub before: f64,
+    pub after: f64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CalibrationFit {
+    pub calibration: Calibration,
+    pub rms_before: f64,
+    pub rms_after: f64,
+    pub residuals: Vec<Residual>,
+    pub iterations: usize,
+}
+
+/// The constants in `opts.fit` that best match the model to every measured note of every
+/// instrument, by damped least squares (Levenberg-Marquardt) on the cents errors. Each
+/// instrument's own saved calibration is ignored; all are played with the one being fitted.
+pub fn fit_calibration(
+    instruments: &[KnownInstrument],
+    opts: &CalibrationOptions,
+) -> Result<CalibrationFit, FlyteError> {
+    trace_span!(INFO, "fit_calibration", instruments = instruments.len());
+    opts.initial.validate()?;
+    let mut flutes: Vec<Flute> = Vec::with_capacity(instruments.len());
+    for instrument in instruments {
+        let flute = instrument.design.to_flute();
+        flute.validate()?;
+        if let Some(bad) = instrument
+            .notes
+            .iter()
+            .find(|n| !n.frequency.is_finite() || n.frequency <= 0.0)
+        {
+            return Err(FlyteError::invalid_input(format!(
+                "measured frequencies must be positive, got {} for {:?}",
+                bad.frequency, instrument.name
+            )));
+        }
+        flutes.push(flute);
+    }
+    if instruments.iter().all(|i| i.notes.is_empty()) {
+        return Err(FlyteError::invalid_input("no measured notes to fit"));
+    }
+
+    let residuals = |calibration: &Calibration| -> Vec<f64> {
+        instruments
+            .iter()
+            .zip(&flutes)
+            .flat_map(|(instrument, flute)| {
+                let mut played = flute.clone();
+                played.calibration = *calibration;
+                instrument
+                    .notes
+                    .iter()
+                    .map(move |note| {
+                        let f = pitch_near(&played, note.fingering, note.frequency);
+                        1200.0 * (f / note.frequency).log2()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    };
+    let mut fit: Vec<Constant> = Vec::new();
+    for &constant in &opts.fit {
+        if !fit.contains(&constant) {
+            fit.push(constant);
+        }
+    }
+
+    let mut calibration = opts.initial;
+    for constant in &fit {
+        // Start inside the range the fit keeps to
+        let value = constant.get(&calibration);
+        constant.set(&mut calibration, value);
+    }
+    let start = residuals(&calibration);
+    if start.iter().any(|r| !r.is_finite()) {
+        return Err(FlyteError::invalid_input(
+            "a measured note has no resonance near it with the starting constants",
+        ));
+    }
+    let mut current = start.clone();
+    let mut damping = INITIAL_DAMPING;
+    let mut iterations = 0;
+    while iterations < opts.max_iterations && !fit.is_empty() {
+        iterations += 1;
+        // Columns of the Jacobian, cents per unit of each constant
+        let columns: Vec<Vec<f64>> = fit
+            .iter()
+            .map(|&constant| {
+                let value = constant.get(&calibration);
+                let step = JACOBIAN_STEP * value.abs().max(0.1);
+                let mut probed = calibration;
+                constant.set(&mut probed, value + step);
+                // At the top of the range step down instead
+                let moved = constant.get(&probed) - value;
+                let (probed, moved) = if moved.abs() < 0.5 * step {
+                    let mut below = calibration;
+                    constant.set(&mut below, value - step);
+                    (below, -step)
+                } else {
+                    (probed, moved)
+                };
+                residuals(&probed)
+                    .iter()
+                    .zip(&current)
+                    .map(|(r, r0)| (r - r0) / moved)
+                    .collect()
+            })
+            .collect();
+        let n = fit.len();
+        let mut normal = vec![vec![0.0; n]; n];
+        let mut gradient = vec![0.0; n];
+        for i in 0..n {
+            for j in 0..n {
+                normal[i][j] = dot(&columns[i], &columns[j]);
+            }
+            gradient[i] = -dot(&columns[i], &current);
+        }
+
+        // Damp until a step lowers the error or the damping gives up
+        let before = rms(&current);
+        let mut improved = None;
+        while damping < 1e8 {
+            let mut damped = normal.clone();
+            for (i, row) in damped.iter_mut().enumerate() {
+                row[i] += damping * (normal[i][i] + 1e-9);
+            }
+            let Some(step) = solve(damped, gradient.clone()) else {
+                damping *= DAMPING_UP;
+                continue;
+            };
+            let mut trial = calibration;
+            for (constant, delta) in fit.iter().zip(&step) {
+                constant.set(&mut trial, constant.get(&calibration) + delta);
+            }
+            let after = residuals(&trial);
+            if rms(&after) < before {
+                improved = Some((trial, after));
+                damping /= DAMPING_DOWN;
+                break;
+            }
+            damping *= DAMPING_UP;
+        }
+        let Some((trial, after)) = improved else {
+            break;
+        };
+        let gain = before - rms(&after);
+        calibration = trial;
+        current = after;
+        if gain < CONVERGED {
+            break;
+        }
+    }
+
+    let mut index = 0;
+    let mut rows = Vec::with_capacity(start.len());
+    for (i, instrument) in instruments.iter().enumerate() {
+        for note in &instrument.notes {
+            rows.push(Residual {
+                instrument: i,
+                fingering: note.fingering,
+                measured: note.frequency,
+                before: start[index],
+                after: current[index],
+            });
+            index += 1;
+        }
+    }
+    Ok(CalibrationFit {
+        calibration,
+        rms_before: rms(&start),
+        rms_after: rms(&current),
+        residuals: rows,
+        iterations,
+    })
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn rms(values: &[f64]) -> f64 {
+    (dot(values, values) / values.len().max(1) as f64).sqrt()
+}
+
+/// `a x = b` by Gaussian elimination with partial pivoting; None if `a` is singular
+fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-300 || !a[pivot][col].is_finite() {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        let (above, below) = a.split_at_mut(col + 1);
+        let pivot_row = &above[col];
+        for (row, lower) in below.iter_mut().enumerate() {
+            let factor = lower[col] / pivot_row[col];
+            for (value, p) in lower[col..].iter_mut().zip(&pivot_row[col..]) {
+                *value -= factor * p;
+            }
+            b[col + 1 + row] -= factor * b[col];
+        }
+    }
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let tail: f64 = (row + 1..n).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - tail) / a[row][row];
+    }
+    Some(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{DuctGeometry, Hole, InstrumentType};
+    use crate::tuning;
+
+    #[test]
+    fn test_fit_calibration_recovers_the_constants() {
+        // A transverse flute and a whistle "measured" with constants off the textbook ones
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for pos in [42.0, 38.0, 34.0] {
+            flute.holes.push(Hole::new(pos, 0.4, true)).unwrap();
+        }
+        let mut whistle = Flute::new(28.0, 0.65, 0.15);
+        whistle.instrument = InstrumentType::Duct(DuctGeometry::default());
+        for pos in [20.0, 17.5, 15.0] {
+            whistle.holes.push(Hole::new(pos, 0.3, true)).unwrap();
+        }
+        let truth = Calibration {
+            open_end_correction: 0.7,
+            hole_end_correction: 1.2,
+            embouchure_end_correction: 1.9,
+            loss_factor: 1.0,
+        };
+        let instruments: Vec<KnownInstrument> = [flute, whistle]
+            .iter()
+            .map(|design| {
+                let mut made = design.clone();
+                made.calibration = truth;
+                let notes = tuning::progressive_fingerings(design)
+                    .into_iter()
+                    .map(|fingering| MeasuredNote {
+                        fingering,
+                        frequency: tuning::pitch_for_fingering(&made, fingering, f64::NAN),
+                    })
+                    .collect();
+                KnownInstrument {
+                    name: String::new(),
+                    design: DesignDocument::new(design),
+                    notes,
+                }
+            })
+            .collect();
+
+        let opts = CalibrationOptions {
+            fit: vec![
+                Constant::OpenEndCorrection,
+                Constant::HoleEndCorrection,
+                Constant::EmbouchureEndCorrection,
+            ],
+            ..CalibrationOptions::default()
+        };
+        let fit = fit_calibration(&instruments, &opts).unwrap();
+        assert!(fit.rms_before > 10.0, "{:?}", fit);
+        assert!(fit.rms_after < 0.1, "{:?}", fit);
+        let c = fit.calibration;
+        assert!((c.open_end_correction - 0.7).abs() < 0.01, "{:?}", c);
+        assert!((c.hole_end_correction - 1.2).abs() < 0.01, "{:?}", c);
+        assert!((c.embouchure_end_correction - 1.9).abs() < 0.02, "{:?}", c);
+        assert_eq!(c.loss_factor, 1.0);
+        assert_eq!(fit.residuals.len(), 8);
+
+        // Saved with a design only when changed
+        let mut design = DesignDocument::new(&Flute::new(60.0, 0.95, 0.4));
+        assert!(!design.to_json().contains("calibration"));
+        design.calibration = truth;
+        let loaded = DesignDocument::from_json(&design.to_json()).unwrap();
+        assert_eq!(loaded.to_flute().calibration, truth);
+
+        let empty = [KnownInstrument {
+            name: String::new(),
+            design: instruments[0].design.clone(),
+            notes: Vec::new(),
+        }];
+        assert!(fit_calibration(&empty, &opts).is_err());
+    }
+}
@@ -0,0 +1,343 @@
+use crate::error::FlyteError;
+use crate::geometry::ExportUnits;
+use crate::physics::{
+    default_cork_position, default_embouchure_chimney, default_embouchure_hole_radius,
+    default_embouchure_oval, is_round, Calibration, Flute, Hole, Holes, InstrumentType,
+    SideResonator, SolverConfig,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Version written by `DesignDocument::to_json`. Bump it together with a new step in
+/// `migrate` whenever the document shape changes.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Leads every share string, naming its encoding (compact JSON, raw DEFLATE, base64url)
+const SHARE_PREFIX: &str = "f1.";
+/// Decoded share strings larger than this are rejected rather than inflated
+const MAX_SHARED_JSON: usize = 1 << 20;
+const BASE64URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// A saved design. Files carry their format version so older ones can be migrated on load;
+/// files without a version are the bare `Flute` JSON written before this format existed.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct DesignDocument {
+    pub version: u32,
+    /// Unit of every length in `instrument` and `geometry`. Written as centimetres
+    #[serde(default = "default_units")]
+    pub units: ExportUnits,
+    #[serde(default)]
+    pub instrument: InstrumentType,
+    pub geometry: DesignGeometry,
+    /// Fingerings the player cares about as hole bitmasks (bit i set = hole i open)
+    #[serde(default)]
+    pub fingerings: Vec<u32>,
+    /// Target pitches in Hz
+    #[serde(default)]
+    pub targets: Vec<f64>,
+    /// Pitch-search settings, written only when they differ from the defaults
+    #[serde(default, skip_serializing_if = "SolverConfig::is_default")]
+    pub solver: SolverConfig,
+    /// Model constants, written only when they differ from the defaults
+    #[serde(default, skip_serializing_if = "Calibration::is_default")]
+    pub calibration: Calibration,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct DesignGeometry {
+    pub length: f64,
+    pub bore_radius: f64,
+    pub wall_thickness: f64,
+    #[serde(default)]
+    pub holes: Vec<Hole>,
+    #[serde(default = "default_cork_position")]
+    pub cork_position: f64,
+    #[serde(default = "default_embouchure_hole_radius")]
+    pub embouchure_hole_radius: f64,
+    #[serde(default = "default_embouchure_chimney")]
+    pub embouchure_chimney: f64,
+    #[serde(default = "default_embouchure_oval")]
+    pub embouchure_oval: f64,
+    #[serde(default, skip_serializing_if = "is_round")]
+    pub bore_ovality: f64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resonators: Vec<SideResonator>,
+}
+
+fn default_units() -> ExportUnits {
+    ExportUnits::Centimeters
+}
+
+impl DesignDocument {
+    pub fn new(flute: &Flute) -> Self {
+        DesignDocument {
+            version: FORMAT_VERSION,
+            units: ExportUnits::Centimeters,
+            instrument: flute.instrument.clone(),
+            geometry: DesignGeometry {
+                length: flute.length,
+                bore_radius: flute.bore_radius,
+                wall_thickness: flute.wall_thickness,
+                holes: flute.holes.to_vec(),
+                cork_position: flute.cork_position,
+                embouchure_hole_radius: flute.embouchure_hole_radius,
+                embouchure_chimney: flute.embouchure_chimney,
+                embouchure_oval: flute.embouchure_oval,
+                bore_ovality: flute.bore_ovality,
+                resonators: flute.resonators.clone(),
+            },
+            fingerings: Vec::new(),
+            targets: Vec::new(),
+            solver: flute.solver,
+            calibration: flute.calibration,
+        }
+    }
+
+    /// The design in the model's centimetres
+    pub fn to_flute(&self) -> Flute {
+        let g = &self.geometry;
+        let mut flute = Flute::new(g.length, g.bore_radius, g.wall_thickness);
+        flute.holes = Holes::from_unchecked(g.holes.clone());
+        flute.cork_position = g.cork_position;
+        flute.embouchure_hole_radius = g.embouchure_hole_radius;
+        flute.embouchure_chimney = g.embouchure_chimney;
+        flute.embouchure_oval = g.embouchure_oval;
+        flute.bore_ovality = g.bore_ovality;
+        flute.resonators = g.resonators.clone();
+        flute.instrument = self.instrument.clone();
+        flute.solver = self.solver;
+        flute.calibration = self.calibration;
+        let (k, _, _) = self.units.scale((1.0, 0.0, 0.0));
+        if k != 1.0 {
+            scale_lengths(&mut flute, 1.0 / k);
+        }
+        flute
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Parse a document of any known version, migrating it to the current one
+    pub fn from_json(json: &str) -> Result<Self, FlyteError> {
+        let value: Value = serde_json::from_str(json).map_err(FlyteError::invalid_input)?;
+        Self::from_value(value)
+    }
+
+    /// The document as a short string safe in a URL query or fragment: compact JSON,
+    /// DEFLATE-compressed and base64url-encoded without padding
+    pub fn to_share_string(&self) -> String {
+        let json = serde_json::to_string(self).unwrap_or_default();
+        let packed = miniz_oxide::deflate::compress_to_vec(json.as_bytes(), 9);
+        let mut out = String::from(SHARE_PREFIX);
+        encode_base64url(&packed, &mut out);
+        out
+    }
+
+    /// Read a `to_share_string` string, migrating the document inside like `from_json`
+    pub fn from_share_string(share: &str) -> Result<Self, FlyteError> {
+        let payload = share
+            .trim()
+            .strip_prefix(SHARE_PREFIX)
+            .ok_or_else(|| FlyteError::invalid_input("not a flyte share string"))?;
+        let packed = decode_base64url(payload)?;
+        let json = miniz_oxide::inflate::decompress_to_vec_with_limit(&packed, MAX_SHARED_JSON)
+            .map_err(|e| FlyteError::invalid_input(format!("corrupt share string: {}", e)))?;
+        let value: Value = serde_json::from_slice(&json).map_err(FlyteError::invalid_input)?;
+        Self::from_value(value)
+    }
+
+    /// `from_json` for a document already parsed from JSON or another format such as TOML
+    pub fn from_value(value: Value) -> Result<Self, FlyteError> {
+        serde_json::from_value(migrate(value)?).map_err(FlyteError::invalid_input)
+    }
+}
+
+/// JSON Schema (draft 2020-12) of the current document format, for editors and tools that
+/// write designs. Only the current `version` validates; older files are migrated on load
+/// rather than described here.
+pub fn json_schema() -> String {
+    let mut schema = schemars::schema_for!(DesignDocument);
+    schema.insert("title".into(), json!("Flyte design document"));
+    if let Some(version) = schema.pointer_mut("/properties/version") {
+        *version = json!({ "const": FORMAT_VERSION });
+    }
+    serde_json::to_string_pretty(&schema).unwrap_or_default()
+}
+
+/// Upgrade a parsed document one version at a time until it reaches `FORMAT_VERSION`
+fn migrate(mut value: Value) -> Result<Value, FlyteError> {
+    loop {
+        let version = match value.get("version") {
+            None => 0,
+            Some(v) => v
+                .as_u64()
+                .ok_or_else(|| FlyteError::invalid_input("design version is not a number"))?,
+        };
+        value = match version {
+            0 => migrate_v0(value)?,
+            v if v == FORMAT_VERSION as u64 => return Ok(value),
+            v => {
+                return Err(FlyteError::invalid_input(format!(
+                    "design format version {} is newer than this build supports ({})",
+                    v, FORMAT_VERSION
+                )))
+            }
+        };
+    }
+}
+
+/// v0 is a serialized `Flute`: the instrument type sits beside the dimensions, all in cm
+fn migrate_v0(value: Value) -> Result<Value, FlyteError> {
+    let Value::Object(mut geometry) = value else {
+        return Err(FlyteError::invalid_input("design is not a JSON object"));
+    };
+    let instrument = geometry.remove("instrument");
+    let mut document = json!({
+        "version": 1,
+        "units": "centimeters",
+        "geometry": geometry,
+    });
+    if let Some(instrument) = instrument {
+        document["instrument"] = instrument;
+    }
+    Ok(document)
+}
+
+fn encode_base64url(data: &[u8], out: &mut String) {
+    for chunk in data.chunks(3) {
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, &b)| acc | (b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(BASE64URL[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+}
+
+fn decode_base64url(text: &str) -> Result<Vec<u8>, FlyteError> {
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut bits, mut count) = (0u32, 0);
+    for c in text.bytes() {
+        let value = BASE64URL.iter().position(|&b| b == c).ok_or_else(|| {
+            FlyteError::invalid_input(format!("invalid character {:?} in share string", c as char))
+        })?;
+        bits = bits << 6 | value as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            out.push((bits >> count) as u8);
+        }
+    }
+    Ok(out)
+}
+
+fn scale_lengths(flute: &mut Flute, k: f64) {
+    flute.length *= k;
+    flute.bore_radius *= k;
+    flute.wall_thickness *= k;
+    flute.cork_position *= k;
+    flute.embouchure_hole_radius *= k;
+    flute.embouchure_chimney *= k;
+    for hole in &mut flute.holes {
+        hole.position *= k;
+        hole.radius *= k;
+    }
+    for resonator in &mut flute.resonators {
+        resonator.position *= k;
+        resonator.neck_radius *= k;
+        resonator.neck_length *= k;
+        resonator.volume *= k.powi(3);
+    }
+    match &mut flute.instrument {
+        InstrumentType::Transverse => {}
+        InstrumentType::Notch(notch) => {
+            notch.width *= k;
+            notch.depth *= k;
+        }
+        InstrumentType::Duct(duct) => {
+            duct.windway_length *= k;
+            duct.windway_width *= k;
+            duct.windway_height *= k;
+            duct.window_length *= k;
+            duct.labium_chamfer *= k;
+        }
+        InstrumentType::OrganPipe(pipe) => {
+            pipe.mouth_width *= k;
+            pipe.cut_up *= k;
+            pipe.flue_depth *= k;
+            pipe.languid_length *= k;
+            if let Some(slot) = &mut pipe.tuning_slot {
+                slot.length *= k;
+                slot.width *= k;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::NotchCut;
+
+    #[test]
+    fn test_document_round_trip_and_migration() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, false)).unwrap();
+        flute.instrument = InstrumentType::Notch(NotchCut::default());
+        let mut doc = DesignDocument::new(&flute);
+        doc.fingerings = vec![0, 1, 3];
+        doc.targets = vec![293.66, 329.63];
+        let loaded = DesignDocument::from_json(&doc.to_json()).unwrap();
+        assert_eq!(loaded, doc);
+
+        // A bare `Flute` from before versioning still loads, defaults filled in
+        let old = DesignDocument::from_json(&serde_json::to_string(&flute).unwrap()).unwrap();
+        assert_eq!(old.version, FORMAT_VERSION);
+        assert_eq!(old.instrument, flute.instrument);
+        assert_eq!(old.geometry.holes, flute.holes.to_vec());
+        let minimal = DesignDocument::from_json(
+            r#"{"length": 60, "bore_radius": 0.95, "wall_thickness": 0.4, "holes": []}"#,
+        )
+        .unwrap();
+        assert_eq!(minimal.instrument, InstrumentType::Transverse);
+        assert_eq!(minimal.geometry.cork_position, 1.7);
+
+        // Lengths in other units come back in cm
+        doc.units = ExportUnits::Millimeters;
+        doc.geometry.length = 600.0;
+        let converted = doc.to_flute();
+        assert!((converted.length - 60.0).abs() < 1e-12);
+        assert!((converted.holes[0].position - 4.0).abs() < 1e-12);
+
+        let share = doc.to_share_string();
+        assert!(share.len() < serde_json::to_string(&doc).unwrap().len());
+        assert!(share
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"-_.".contains(&b)));
+        assert_eq!(DesignDocument::from_share_string(&share).unwrap(), doc);
+        assert!(DesignDocument::from_share_string("f1.a+b").is_err());
+        assert!(DesignDocument::from_share_string("f1.AAAA").is_err());
+
+        let future = r#"{"version": 99, "geometry": {}}"#;
+        assert!(matches!(
+            DesignDocument::from_json(future),
+            Err(FlyteError::InvalidInput { .. })
+        ));
+    }
+
+    #[test]
+    fn test_published_schema_is_current() {
+        // Regenerate with `cargo run -p flyte_cli -- schema > core/schema/design.schema.json`
+        let published = include_str!("../schema/design.schema.json");
+        assert_eq!(published.trim_end(), json_schema());
+        let schema: Value = serde_json::from_str(published).unwrap();
+        assert_eq!(schema["properties"]["version"]["const"], FORMAT_VERSION);
+        assert!(schema["$defs"]["Hole"]["required"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("position")));
+    }
+}
@@ -0,0 +1,302 @@
+//! How a note follows the breath. The jet takes a time l / (0.4 U) to cross from the lips
+//! or flue to the edge; at its preferred speed (the same f = 0.2 U / l rule the pitch
+//! guess uses) that delay is half a period and the note sits on the bore resonance. Blown
+//! harder the jet arrives early, and the air column has to make up the phase by sounding
+//! above the resonance, where its impedance leads; blown softer, below. How far a note
+//! moves for a given breath change therefore depends on how sharp the resonance is, which
+//! is what separates designs that hold their pitch through a crescendo from ones that don't.
+
+use crate::error::FlyteError;
+use crate::physics::{Flute, InstrumentType, AIR_DENSITY};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// Jet disturbances travel at about 0.4 of the jet speed, which puts the preferred
+/// frequency at 0.2 U / l
+const CONVECTION: f64 = 0.4;
+/// Furthest either side of the resonance the phase balance is looked for: two semitones
+const WINDOW: f64 = 1.122_462_048_309_373; // 2^(2/12)
+/// Steps of the walk out to it, an eighth of a semitone
+const SCAN_STEP: f64 = 1.007_246_412_223_704; // 2^(1/96)
+const BISECTIONS: usize = 40;
+/// Most points `dynamics_trace` will return
+pub const MAX_TRACE_POINTS: usize = 10_000;
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct TraceOptions {
+    /// Bitmask of open holes (bit i = `holes[i]`); `None` plays the design as it is set
+    pub fingering: Option<u32>,
+    /// Seconds
+    pub duration: f64,
+    pub points: usize,
+    /// Jet speed at the start and the end, as a fraction of the speed that prefers the
+    /// note; an end above the start is a crescendo
+    pub start: f64,
+    pub end: f64,
+    /// Breath vibrato, Hz
+    pub vibrato_rate: f64,
+    /// Breath vibrato as a fraction of the jet speed either side
+    pub vibrato_depth: f64,
+}
+
+impl Default for TraceOptions {
+    fn default() -> Self {
+        TraceOptions {
+            fingering: None,
+            duration: 3.0,
+            points: 150,
+            start: 0.8,
+            end: 1.25,
+            vibrato_rate: 5.0,
+            vibrato_depth: 0.05,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TracePoint {
+    /// Seconds
+    pub time: f64,
+    /// cm/s
+    pub jet_velocity: f64,
+    /// Blowing pressure that gives that jet, Pa
+    pub pressure: f64,
+    /// Hz; NaN where the jet is too far from its preferred speed to drive the note
+    pub frequency: f64,
+    /// `frequency` from the bore resonance, positive = sharp
+    pub cents: f64,
+    /// Loudness relative to the jet at its preferred speed, dB
+    pub level: f64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DynamicsTrace {
+    /// The bore resonance the note is played on, Hz
+    pub resonance: f64,
+    /// Jet speed whose preferred frequency is the resonance, cm/s
+    pub preferred_jet: f64,
+    pub points: Vec<TracePoint>,
+    /// Pitch swing of the vibrato at the middle of the trace, cents either side
+    pub vibrato_cents: f64,
+    /// Loudness swing of the same vibrato, dB either side
+    pub vibrato_level: f64,
+    /// Slope of pitch against loudness over the trace, cents per dB: near zero for a note
+    /// that holds its pitch as it gets louder
+    pub cents_per_db: f64,
+}
+
+/// Edge distance the jet crosses: the window of a duct flute, the cut-up of an organ pipe,
+/// and the embouchure hole's width across which a player's lips sit otherwise
+fn jet_length(flute: &Flute) -> f64 {
+    match &flute.instrument {
+        InstrumentType::Duct(duct) => duct.window_length,
+        InstrumentType::OrganPipe(pipe) => pipe.cut_up,
+        InstrumentType::Transverse | InstrumentType::Notch(_) => 2.0 * flute.embouchure_hole_radius,
+    }
+}
+
+/// Pitch and loudness of a note as the breath slowly changes: a ramp from `opts.start` to
+/// `opts.end` times the preferred jet speed with a breath vibrato on top. Each point is
+/// quasi-static, the frequency near the resonance where the bore's impedance phase makes
+/// up the jet's phase error; the level follows the jet speed and how well it is phased.
+pub fn dynamics_trace(flute: &Flute, opts: &TraceOptions) -> Result<DynamicsTrace, FlyteError> {
+    trace_span!(INFO, "dynamics_trace", points = opts.points);
+    flute.validate()?;
+    if !opts.duration.is_finite() || opts.duration <= 0.0 {
+        return Err(FlyteError::invalid_input(format!(
+            "duration must be positive, got {}",
+            opts.duration
+        )));
+    }
+    if opts.points < 2 || opts.points > MAX_TRACE_POINTS {
+        return Err(FlyteError::invalid_input(format!(
+            "points must be between 2 and {}, got {}",
+            MAX_TRACE_POINTS, opts.points
+        )));
+    }
+    let speeds = [opts.start, opts.end];
+    if speeds.iter().any(|s| !s.is_finite() || *s <= 0.0)
+        || !(0.0..1.0).contains(&opts.vibrato_depth)
+        || !opts.vibrato_rate.is_finite()
+    {
+        return Err(FlyteError::invalid_input(
+            "start and end must be positive, vibrato_depth between 0 and 1",
+        ));
+    }
+
+    let mut fingered = flute.clone();
+    if let Some(fingering) = opts.fingering {
+        for (i, hole) in fingered.holes.iter_mut().enumerate() {
+            hole.open = i < 32 && fingering & (1 << i) != 0;
+        }
+    }
+    let resonance = fingered.calculate_pitch(f64::NAN);
+    if !resonance.is_finite() {
+        return Err(FlyteError::invalid_input(
+            "the note has no resonance to play on",
+        ));
+    }
+    let length = jet_length(&fingered);
+    let preferred_jet = 2.0 * resonance * length / CONVECTION;
+    let bore = fingered.bore(&fingered.hole_order_back_to_front());
+
+    // Phase the jet is off by at frequency f with speed u: zero at the preferred speed
+    let mismatch = |f: f64, u: f64| PI - 2.0 * PI * f * length / (CONVECTION * u);
+    let phase = |f: f64| bore.impedance(f).arg();
+    let play = |u: f64| -> (f64, f64) {
+        // The bore's phase rises through zero at the resonance; walk out towards the side
+        // the balance lies on until it is crossed, giving up where the phase turns back (a
+        // neighbouring impedance maximum) or the window ends
+        let balance = |f: f64| phase(f) - mismatch(f, u);
+        let step = if balance(resonance) > 0.0 {
+            1.0 / SCAN_STEP
+        } else {
+            SCAN_STEP
+        };
+        let (mut near, mut far) = (resonance, resonance * step);
+        loop {
+            if (balance(far) > 0.0) != (balance(near) > 0.0) {
+                break;
+            }
+            let turned = (phase(far) - phase(near)) * (step - 1.0) < 0.0;
+            if turned || (far / resonance).ln().abs() > WINDOW.ln() {
+                return (f64::NAN, f64::NAN);
+            }
+            near = far;
+            far *= step;
+        }
+        let (mut lo, mut hi) = if step > 1.0 { (near, far) } else { (far, near) };
+        for _ in 0..BISECTIONS {
+            let mid = (lo * hi).sqrt();
+            if balance(mid) < 0.0 {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        let f = (lo * hi).sqrt();
+        let drive = (u / preferred_jet) * mismatch(f, u).cos();
+        let level = if drive > 0.0 {
+            20.0 * drive.log10()
+        } else {
+            f64::NAN
+        };
+        (f, level)
+    };
+    let cents = |f: f64| 1200.0 * (f / resonance).log2();
+
+    let points: Vec<TracePoint> = (0..opts.points)
+        .map(|i| {
+            let t = i as f64 / (opts.points - 1) as f64;
+            let time = t * opts.duration;
+            let ramp = opts.start + (opts.end - opts.start) * t;
+            let wobble = 1.0 + opts.vibrato_depth * (2.0 * PI * opts.vibrato_rate * time).sin();
+            let jet_velocity = preferred_jet * ramp * wobble;
+            let (frequency, level) = play(jet_velocity);
+            TracePoint {
+                time,
+                jet_velocity,
+                // 0.5 rho U^2 in dyn/cm^2, a tenth of a pascal
+                pressure: 0.5 * AIR_DENSITY * jet_velocity.powi(2) / 10.0,
+                frequency,
+                cents: cents(frequency),
+                level,
+            }
+        })
+        .collect();
+
+    let middle = preferred_jet * 0.5 * (opts.start + opts.end);
+    let (high, low) = (
+        play(middle * (1.0 + opts.vibrato_depth)),
+        play(middle * (1.0 - opts.vibrato_depth)),
+    );
+    Ok(DynamicsTrace {
+        resonance,
+        preferred_jet,
+        vibrato_cents: 0.5 * (cents(high.0) - cents(low.0)),
+        vibrato_level: 0.5 * (high.1 - low.1),
+        cents_per_db: slope(&points),
+        points,
+    })
+}
+
+/// Least-squares slope of cents against level over the points that sounded
+fn slope(points: &[TracePoint]) -> f64 {
+    let sounded: Vec<(f64, f64)> = points
+        .iter()
+        .filter(|p| p.cents.is_finite() && p.level.is_finite())
+        .map(|p| (p.level, p.cents))
+        .collect();
+    let n = sounded.len() as f64;
+    let mean_x = sounded.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = sounded.iter().map(|p| p.1).sum::<f64>() / n;
+    let (mut xy, mut xx) = (0.0, 0.0);
+    for (x, y) in &sounded {
+        xy += (x - mean_x) * (y - mean_y);
+        xx += (x - mean_x).powi(2);
+    }
+    xy / xx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Hole;
+
+    #[test]
+    fn test_dynamics_trace() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for pos in [40.0, 35.0] {
+            flute.holes.push(Hole::new(pos, 0.35, true)).unwrap();
+        }
+        let still = TraceOptions {
+            vibrato_depth: 0.0,
+            ..TraceOptions::default()
+        };
+        let trace = dynamics_trace(&flute, &still).unwrap();
+        assert_eq!(trace.points.len(), 150);
+        // A crescendo sharpens the note and gets louder; at the preferred jet speed it
+        // sits on the resonance
+        let (first, last) = (&trace.points[0], &trace.points[149]);
+        assert!(
+            first.cents < 0.0 && last.cents > 0.0,
+            "{:?} {:?}",
+            first,
+            last
+        );
+        assert!(last.level > first.level);
+        assert!(trace.cents_per_db > 0.0);
+        let slope = trace.cents_per_db;
+        let at_preferred = TraceOptions {
+            start: 1.0,
+            end: 1.0,
+            ..still.clone()
+        };
+        let steady = dynamics_trace(&flute, &at_preferred).unwrap();
+        assert!(steady.points.iter().all(|p| p.cents.abs() < 0.01));
+        assert_eq!(steady.vibrato_cents, 0.0);
+
+        // A breath vibrato shows up in the pitch
+        let trace = dynamics_trace(&flute, &TraceOptions::default()).unwrap();
+        assert!(trace.vibrato_cents > 0.5 && trace.vibrato_level > 0.0);
+
+        // A narrow bore loses more to its walls, so its resonance is broader and gives way
+        // more to the breath
+        let mut narrow = flute.clone();
+        narrow.bore_radius = 0.6;
+        let loose = dynamics_trace(&narrow, &still).unwrap();
+        assert!(
+            loose.cents_per_db > slope,
+            "{} {}",
+            loose.cents_per_db,
+            slope
+        );
+
+        let bad = TraceOptions {
+            points: 1,
+            ..TraceOptions::default()
+        };
+        assert!(dynamics_trace(&flute, &bad).is_err());
+    }
+}
@@ -0,0 +1,253 @@
+use crate::geometry::Severity;
+use crate::physics::Flute;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "optimize")]
+mod layout;
+#[cfg(feature = "optimize")]
+pub use layout::{
+    one_handed_layout, LayoutNote, OneHandedLayout, OneHandedOptions, OneHandedSearch,
+};
+
+const FIT_ITERATIONS: usize = 30;
+
+/// Centre-to-centre reach limits along the tube, in cm
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct FingerSpans {
+    /// Between neighbouring fingers: comfortable, and the most that can still be covered
+    pub finger_comfortable: f64,
+    pub finger_max: f64,
+    /// Index to ring finger of one hand (pinky adds one more finger step)
+    pub hand_comfortable: f64,
+    pub hand_max: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HandSize {
+    Small,
+    #[default]
+    Medium,
+    Large,
+    Custom(FingerSpans),
+}
+
+impl HandSize {
+    pub fn spans(self) -> FingerSpans {
+        let (finger_comfortable, finger_max, hand_comfortable, hand_max) = match self {
+            HandSize::Small => (2.0, 2.6, 5.5, 7.0),
+            HandSize::Medium => (2.3, 3.0, 6.5, 8.0),
+            HandSize::Large => (2.8, 3.6, 8.0, 10.0),
+            HandSize::Custom(spans) => return spans,
+        };
+        FingerSpans {
+            finger_comfortable,
+            finger_max,
+            hand_comfortable,
+            hand_max,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Hand {
+    Left,
+    Right,
+}
+
+/// A stretch between two holes covered by the same hand
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ReachIssue {
+    pub hand: Hand,
+    /// Warning = uncomfortable, Error = out of reach
+    pub severity: Severity,
+    /// Indices into `holes`, nearer the blowing end first
+    pub holes: (usize, usize),
+    pub span: f64,
+    pub limit: f64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ErgonomicReport {
+    pub issues: Vec<ReachIssue>,
+    /// Holes beyond the eight fingers (thumb holes excluded)
+    pub unassigned_holes: Vec<usize>,
+    /// Sum of squared stretch past the comfortable spans (cm^2), unreachable stretches and
+    /// unassigned holes weighted heavily. Zero for a comfortable layout, so it can be used
+    /// directly as an optimiser penalty.
+    pub penalty: f64,
+}
+
+const UNREACHABLE_WEIGHT: f64 = 10.0;
+const UNASSIGNED_PENALTY: f64 = 100.0;
+
+/// Whether a hole faces the thumb side rather than the fingers
+pub fn is_thumb_hole(azimuth: f64) -> bool {
+    let a = azimuth.rem_euclid(360.0);
+    a > 90.0 && a < 270.0
+}
+
+/// Which finger holes each hand covers, in playing order: the upper hand takes the first
+/// half (rounded up), each hand at most four fingers
+pub fn hand_assignment(flute: &Flute) -> (Vec<usize>, Vec<usize>, Vec<usize>) {
+    let fingers: Vec<usize> = flute
+        .hole_order()
+        .into_iter()
+        .filter(|&i| !is_thumb_hole(flute.holes[i].azimuth))
+        .collect();
+    let left = fingers.len().div_ceil(2).min(4);
+    let right = (fingers.len() - left).min(4);
+    (
+        fingers[..left].to_vec(),
+        fingers[left..left + right].to_vec(),
+        fingers[left + right..].to_vec(),
+    )
+}
+
+/// Flag finger stretches that are uncomfortable or unreachable for the given hand size
+pub fn check_ergonomics(flute: &Flute, hand: HandSize) -> ErgonomicReport {
+    let spans = hand.spans();
+    let (left, right, unassigned) = hand_assignment(flute);
+    let mut issues = Vec::new();
+    let mut penalty = UNASSIGNED_PENALTY * unassigned.len() as f64;
+
+    let mut check = |hand: Hand, a: usize, b: usize, comfortable: f64, max: f64| {
+        let span = (flute.holes[b].position - flute.holes[a].position).abs();
+        if span <= comfortable {
+            return;
+        }
+        let (severity, limit) = if span > max {
+            (Severity::Error, max)
+        } else {
+            (Severity::Warning, comfortable)
+        };
+        penalty += (span - comfortable).powi(2);
+        if span > max {
+            penalty += UNREACHABLE_WEIGHT * (span - max).powi(2);
+        }
+        issues.push(ReachIssue {
+            hand,
+            severity,
+            holes: (a, b),
+            span,
+            limit,
+        });
+    };
+
+    for (hand, holes) in [(Hand::Left, &left), (Hand::Right, &right)] {
+        for pair in holes.windows(2) {
+            check(
+                hand,
+                pair[0],
+                pair[1],
+                spans.finger_comfortable,
+                spans.finger_max,
+            );
+        }
+        if holes.len() >= 3 {
+            // A pinky hole adds one more finger step to the whole-hand stretch
+            let extra = (holes.len() - 3) as f64;
+            check(
+                hand,
+                holes[0],
+                holes[holes.len() - 1],
+                spans.hand_comfortable + extra * spans.finger_comfortable,
+                spans.hand_max + extra * spans.finger_max,
+            );
+        }
+    }
+
+    ErgonomicReport {
+        issues,
+        unassigned_holes: unassigned,
+        penalty,
+    }
+}
+
+/// Resonance nearest the note being fitted. Small holes far down the tube throw off both
+/// the open-hole length guess and the secant search, so bracket around the target instead.
+pub(crate) fn pitch_near(flute: &Flute, fingering: u32, target: f64) -> f64 {
+    let mut fingered = flute.clone();
+    for (i, hole) in fingered.holes.iter_mut().enumerate() {
+        hole.open = i < 32 && fingering & (1 << i) != 0;
+    }
+    fingered.resonance_near(target)
+}
+
+/// Bisect `x` in `range` so that `pitch(x)` meets `target`. `rising` says whether the
+/// pitch goes up with `x`; out-of-range targets settle on the nearer end.
+pub(crate) fn fit(
+    range: (f64, f64),
+    target: f64,
+    rising: bool,
+    mut pitch: impl FnMut(f64) -> f64,
+) -> f64 {
+    let (mut lo, mut hi) = range;
+    for _ in 0..FIT_ITERATIONS {
+        let mid = 0.5 * (lo + hi);
+        if (pitch(mid) < target) == rising {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    0.5 * (lo + hi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Hole;
+
+    fn six_hole(spacing: f64) -> Flute {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for k in 0..6 {
+            flute
+                .holes
+                .push(Hole::new(30.0 + spacing * k as f64, 0.3, true))
+                .unwrap();
+        }
+        flute
+    }
+
+    #[test]
+    fn test_reach_by_hand_size() {
+        let comfortable = check_ergonomics(&six_hole(2.0), HandSize::Medium);
+        assert!(comfortable.issues.is_empty());
+        assert_eq!(comfortable.penalty, 0.0);
+
+        // 2.7 cm: a stretch for medium hands, fine for large, out of reach for small
+        let wide = six_hole(2.7);
+        let medium = check_ergonomics(&wide, HandSize::Medium);
+        assert!(medium
+            .issues
+            .iter()
+            .all(|i| i.severity == Severity::Warning));
+        assert!(medium.penalty > 0.0);
+        assert!(medium.issues.iter().any(|i| i.hand == Hand::Right));
+        let small = check_ergonomics(&wide, HandSize::Small);
+        assert!(small.issues.iter().any(|i| i.severity == Severity::Error));
+        assert!(small.penalty > medium.penalty);
+        assert!(check_ergonomics(&wide, HandSize::Large).issues.is_empty());
+    }
+
+    #[test]
+    fn test_thumb_and_extra_holes() {
+        let mut flute = six_hole(2.0);
+        flute.holes.push(Hole::new(29.0, 0.3, true)).unwrap();
+        flute.holes[6].azimuth = 180.0;
+        let (left, right, extra) = hand_assignment(&flute);
+        assert_eq!((left.len(), right.len(), extra.len()), (3, 3, 0));
+
+        for k in 0..3 {
+            flute
+                .holes
+                .push(Hole::new(42.0 + 2.0 * k as f64, 0.3, true))
+                .unwrap();
+        }
+        let report = check_ergonomics(&flute, HandSize::Medium);
+        assert_eq!(report.unassigned_holes, vec![9]);
+        assert!(report.penalty >= UNASSIGNED_PENALTY);
+    }
+}
@@ -0,0 +1,337 @@
+use super::{fit, is_thumb_hole, pitch_near, HandSize};
+use crate::error::FlyteError;
+use crate::geometry::MIN_BRIDGE;
+use crate::physics::{Flute, Hole, Holes};
+use crate::tuning;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Settings for `one_handed_layout`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct OneHandedOptions {
+    pub hand_size: HandSize,
+    /// Finger holes to keep, one to four
+    pub holes: usize,
+    /// Notes to reach in Hz, all holes closed first. Empty = the current design's lowest
+    /// notes, rounded to equal temperament
+    pub targets: Vec<f64>,
+    /// Only used to read the current design's notes
+    pub jet_velocity: f64,
+}
+
+impl Default for OneHandedOptions {
+    fn default() -> Self {
+        OneHandedOptions {
+            hand_size: HandSize::Medium,
+            holes: 4,
+            targets: Vec::new(),
+            jet_velocity: 0.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LayoutNote {
+    pub target: f64,
+    pub frequency: f64,
+    pub cents: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OneHandedLayout {
+    /// Replacement finger holes, nearer the blowing end first
+    pub holes: Vec<Hole>,
+    /// Centre-to-centre spacing, within the comfortable reach of one hand
+    pub spacing: f64,
+    /// One entry per progressive fingering, lowest note first
+    pub notes: Vec<LayoutNote>,
+}
+
+impl OneHandedLayout {
+    /// What the search minimises: squared cents over the notes the layout sets. Infinite
+    /// for an empty layout, so any real one beats it.
+    pub(crate) fn error(&self) -> f64 {
+        layout_error(&self.notes)
+    }
+
+    /// `flute`'s holes with the finger holes swapped for the laid-out ones. Thumb holes stay,
+    /// with their ids.
+    pub fn relaid_holes(&self, flute: &Flute) -> Result<Holes, FlyteError> {
+        let mut holes = thumb_holes(flute);
+        for hole in &self.holes {
+            holes.push(hole.clone())?;
+        }
+        Ok(holes)
+    }
+}
+
+fn thumb_holes(flute: &Flute) -> Holes {
+    let mut holes = flute.holes.clone();
+    for i in (0..holes.len()).rev() {
+        if !is_thumb_hole(holes[i].azimuth) {
+            holes.remove(i);
+        }
+    }
+    holes
+}
+
+fn layout_error(notes: &[LayoutNote]) -> f64 {
+    match notes.split_first() {
+        // The all-closed note is down to the tube length, not the layout
+        Some((_, stepped)) => stepped.iter().map(|n| n.cents.powi(2)).sum(),
+        None => f64::INFINITY,
+    }
+}
+
+const MIN_LAYOUT_RADIUS: f64 = 0.1; // cm
+const FOOT_CANDIDATES: usize = 5;
+const LAYOUT_PASSES: usize = 3;
+
+/// Re-lay the finger holes for a single hand: the kept holes sit at one comfortable finger
+/// spacing, the group slides along the tube until the lowest hole sounds the first step up,
+/// then each hole above is sized for its note. Notes out of reach within the printable hole
+/// sizes show up as a cents error rather than a stretch.
+pub fn one_handed_layout(flute: &Flute, opts: &OneHandedOptions) -> OneHandedLayout {
+    trace_span!(INFO, "one_handed_layout", holes = opts.holes);
+    let search = OneHandedSearch::new(flute, opts);
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        // One candidate per task; the earlier candidate wins a tie, as in the serial search
+        let layouts: Vec<OneHandedLayout> = (0..search.total_steps())
+            .into_par_iter()
+            .map(|k| search.clone().restrict(k..k + 1).run())
+            .collect();
+        layouts
+            .into_iter()
+            .reduce(|best, layout| {
+                if layout.error() < best.error() {
+                    layout
+                } else {
+                    best
+                }
+            })
+            .unwrap_or_else(|| search.finish())
+    }
+    #[cfg(not(feature = "parallel"))]
+    search.run()
+}
+
+/// `one_handed_layout` one foot-hole candidate at a time, for callers that need to yield
+/// between steps
+#[derive(Debug, Clone)]
+pub struct OneHandedSearch {
+    design: Flute,
+    targets: Vec<f64>,
+    spacing: f64,
+    r_max: f64,
+    start: usize,
+    end: usize,
+    next: usize,
+    best: Option<(f64, Vec<Hole>, Vec<LayoutNote>)>,
+}
+
+impl OneHandedSearch {
+    pub fn new(flute: &Flute, opts: &OneHandedOptions) -> Self {
+        let mut targets: Vec<f64> = if opts.targets.is_empty() {
+            tuning::fingering_chart(flute, opts.jet_velocity)
+                .into_iter()
+                .map(|row| row.note.target)
+                .collect()
+        } else {
+            opts.targets.clone()
+        };
+        targets.truncate(opts.holes.clamp(1, 4) + 1);
+        let n = targets.len().saturating_sub(1);
+
+        let spans = opts.hand_size.spans();
+        let mut spacing = spans.finger_comfortable;
+        if n >= 3 {
+            let hand = spans.hand_comfortable + (n - 3) as f64 * spans.finger_comfortable;
+            spacing = spacing.min(hand / (n - 1) as f64);
+        }
+        let r_max = (0.8 * flute.bore_radius)
+            .min(0.5 * (spacing - MIN_BRIDGE))
+            .max(MIN_LAYOUT_RADIUS);
+        // Thumb holes stay where they are, closed for every note
+        let mut design = flute.clone();
+        design.holes = thumb_holes(flute);
+        OneHandedSearch {
+            design,
+            targets,
+            spacing: if n == 0 { 0.0 } else { spacing },
+            r_max,
+            start: 0,
+            end: FOOT_CANDIDATES,
+            next: if n == 0 { FOOT_CANDIDATES } else { 0 },
+            best: None,
+        }
+    }
+
+    /// Only try the foot-hole candidates in `range` (of `0..total_steps()`), so several
+    /// searches can share out one layout
+    pub fn restrict(mut self, range: Range<usize>) -> Self {
+        self.start = range.start.min(FOOT_CANDIDATES);
+        self.end = range.end.clamp(self.start, FOOT_CANDIDATES);
+        self.next = if self.targets.len() < 2 {
+            self.end
+        } else {
+            self.start
+        };
+        self.best = None;
+        self
+    }
+
+    pub fn total_steps(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn steps_done(&self) -> usize {
+        self.next.min(self.end) - self.start
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next >= self.end
+    }
+
+    /// Fit the layout for the next foot-hole size and keep it if it beats the best so far.
+    /// A smaller foot hole sits further down and leaves the holes above more room to step
+    /// the pitch up.
+    pub fn step(&mut self) {
+        if self.is_done() {
+            return;
+        }
+        let t = self.next as f64 / (FOOT_CANDIDATES - 1) as f64;
+        let r_foot = self.r_max + t * (MIN_LAYOUT_RADIUS - self.r_max);
+        trace_span!(DEBUG, "layout_candidate", candidate = self.next, r_foot);
+        let (holes, notes) = self.layout_for(r_foot);
+        self.next += 1;
+        let error = layout_error(&notes);
+        trace_event!(debug, error, "candidate fitted");
+        if self.best.as_ref().is_none_or(|(e, _, _)| error < *e) {
+            self.best = Some((error, holes, notes));
+        }
+    }
+
+    fn run(mut self) -> OneHandedLayout {
+        while !self.is_done() {
+            self.step();
+        }
+        self.finish()
+    }
+
+    /// The best layout found so far
+    pub fn finish(self) -> OneHandedLayout {
+        let (holes, notes) = self
+            .best
+            .map(|(_, holes, notes)| (holes, notes))
+            .unwrap_or_default();
+        OneHandedLayout {
+            holes,
+            spacing: self.spacing,
+            notes,
+        }
+    }
+
+    fn layout_for(&self, r_foot: f64) -> (Vec<Hole>, Vec<LayoutNote>) {
+        let targets = &self.targets;
+        let n = targets.len() - 1;
+        let (spacing, r_max) = (self.spacing, self.r_max);
+        // Playing order: hole n-1 is the foot hole
+        let lowest_open = |open: usize| ((1u32 << n) - 1) & !((1u32 << (n - open)) - 1);
+        let mut design = self.design.clone();
+        let mut holes = vec![Hole::new(0.0, r_max, true); n];
+        holes.extend(self.design.holes.iter().cloned());
+        design.holes = Holes::from_unchecked(holes);
+        design.holes[n - 1].radius = r_foot;
+        let place = |design: &mut Flute, foot: f64| {
+            for (i, hole) in design.holes.iter_mut().take(n).enumerate() {
+                hole.position = foot - (n - 1 - i) as f64 * spacing;
+            }
+        };
+
+        let foot_range = (
+            (n - 1) as f64 * spacing + r_max + 1.0,
+            design.length - r_max - 0.5,
+        );
+        // Closed holes still load the bore, so re-slide after sizing until it settles
+        for _ in 0..LAYOUT_PASSES {
+            // Slide the group; the foot hole alone sets the first step
+            let foot = fit(foot_range, targets[1], false, |foot| {
+                place(&mut design, foot);
+                pitch_near(&design, lowest_open(1), targets[1])
+            });
+            place(&mut design, foot);
+
+            // Size the holes above, each with every hole below it open
+            for (open, &target) in targets.iter().enumerate().skip(2) {
+                let i = n - open;
+                design.holes[i].radius = fit((MIN_LAYOUT_RADIUS, r_max), target, true, |radius| {
+                    design.holes[i].radius = radius;
+                    pitch_near(&design, lowest_open(open), target)
+                });
+            }
+        }
+
+        let notes = (0..=n)
+            .map(|open| {
+                let mask = if open == 0 { 0 } else { lowest_open(open) };
+                let frequency = pitch_near(&design, mask, targets[open]);
+                LayoutNote {
+                    target: targets[open],
+                    frequency,
+                    cents: 1200.0 * (frequency / targets[open]).log2(),
+                }
+            })
+            .collect();
+        let mut holes: Vec<Hole> = design.holes.into();
+        holes.truncate(n);
+        (holes, notes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ergonomics::check_ergonomics;
+
+    #[test]
+    fn test_one_handed_layout() {
+        // Six holes 3.5 cm apart: too wide for one medium hand
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for k in 0..6 {
+            flute
+                .holes
+                .push(Hole::new(30.0 + 3.5 * k as f64, 0.3, true))
+                .unwrap();
+        }
+        // A thumb hole under the upper hand stays put
+        let mut thumb = Hole::new(28.0, 0.3, false);
+        thumb.azimuth = 180.0;
+        flute.holes.push(thumb.clone()).unwrap();
+        assert!(check_ergonomics(&flute, HandSize::Medium).penalty > 0.0);
+
+        let layout = one_handed_layout(&flute, &OneHandedOptions::default());
+        assert_eq!(layout.holes.len(), 4);
+        assert_eq!(layout.notes.len(), 5);
+
+        let mut relaid = flute.clone();
+        relaid.holes = layout.relaid_holes(&flute).unwrap();
+        assert_eq!(relaid.holes.len(), 5);
+        assert_eq!(relaid.holes[0], thumb);
+        assert_eq!(relaid.holes.ids()[0], flute.holes.ids()[6]);
+        assert!(check_ergonomics(&relaid, HandSize::Medium).penalty < 1e-9);
+        let span = relaid.holes[4].position - relaid.holes[1].position;
+        assert!(span <= HandSize::Medium.spans().hand_comfortable + 2.3 + 1e-9);
+        for pair in layout.notes.windows(2) {
+            assert!(pair[1].frequency > pair[0].frequency, "{:?}", layout.notes);
+        }
+        // Sliding the group and resizing holes recovers every fingered note
+        assert!(
+            layout.notes[1..].iter().all(|n| n.cents.abs() < 5.0),
+            "{:?}",
+            layout.notes
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use serde::Serialize;
+use std::fmt;
+
+/// Errors surfaced through the public API. Over wasm they arrive as a plain object
+/// `{ code: "index_out_of_bounds", message, index, len }` so frontends can branch on `code`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum FlyteError {
+    /// A dimension is non-finite or outside its physical range
+    InvalidGeometry {
+        field: String,
+        value: f64,
+        reason: String,
+    },
+    /// An error-level `check_geometry` finding, e.g. `holes_overlap`; `hole` indexes `holes`
+    FailedCheck {
+        check: String,
+        message: String,
+        hole: Option<usize>,
+    },
+    IndexOutOfBounds {
+        index: usize,
+        len: usize,
+    },
+    /// The resonance search stopped without settling on an impedance minimum
+    SolverDidNotConverge {
+        frequency: f64,
+    },
+    /// Malformed options object, design file or mismatched input arrays
+    InvalidInput {
+        message: String,
+    },
+    /// A result could not be converted for the caller
+    Serialization {
+        message: String,
+    },
+}
+
+impl FlyteError {
+    pub fn invalid_geometry(field: &str, value: f64, reason: &str) -> Self {
+        FlyteError::InvalidGeometry {
+            field: field.to_string(),
+            value,
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn invalid_input(message: impl fmt::Display) -> Self {
+        FlyteError::InvalidInput {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for FlyteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlyteError::InvalidGeometry {
+                field,
+                value,
+                reason,
+            } => write!(f, "invalid {}: {} ({})", field, value, reason),
+            FlyteError::FailedCheck { message, .. } => write!(f, "{}", message),
+            FlyteError::IndexOutOfBounds { index, len } => {
+                write!(f, "hole index {} out of bounds for {} holes", index, len)
+            }
+            FlyteError::SolverDidNotConverge { frequency } => {
+                write!(
+                    f,
+                    "resonance search did not converge (last {:.2} Hz)",
+                    frequency
+                )
+            }
+            FlyteError::InvalidInput { message } => write!(f, "invalid input: {}", message),
+            FlyteError::Serialization { message } => write!(f, "serialization failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for FlyteError {}
+
+/// The error's fields plus its display string as `message`, unless the variant carries a
+/// `message` of its own
+#[cfg(feature = "wasm")]
+#[derive(Serialize)]
+struct ErrorPayload<'a> {
+    #[serde(flatten)]
+    error: &'a FlyteError,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+#[cfg(feature = "wasm")]
+impl<'a> ErrorPayload<'a> {
+    fn new(error: &'a FlyteError) -> Self {
+        let has_message = matches!(
+            error,
+            FlyteError::FailedCheck { .. }
+                | FlyteError::InvalidInput { .. }
+                | FlyteError::Serialization { .. }
+        );
+        ErrorPayload {
+            error,
+            message: (!has_message).then(|| error.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl From<FlyteError> for wasm_bindgen::JsValue {
+    fn from(error: FlyteError) -> Self {
+        let payload = ErrorPayload::new(&error);
+        let serializer = serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true);
+        payload
+            .serialize(&serializer)
+            .unwrap_or_else(|_| wasm_bindgen::JsValue::from_str(&error.to_string()))
+    }
+}
+
+/// Raised in Python as `ValueError` with the same message
+#[cfg(feature = "python")]
+impl From<FlyteError> for pyo3::PyErr {
+    fn from(error: FlyteError) -> Self {
+        pyo3::exceptions::PyValueError::new_err(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes_and_context() {
+        let error = FlyteError::IndexOutOfBounds { index: 7, len: 3 };
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["code"], "index_out_of_bounds");
+        assert_eq!(json["index"], 7);
+        assert_eq!(error.to_string(), "hole index 7 out of bounds for 3 holes");
+
+        let error = FlyteError::invalid_geometry("length", -1.0, "must be positive");
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["code"], "invalid_geometry");
+        assert_eq!(json["field"], "length");
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_payload_has_one_message() {
+        let errors = [
+            FlyteError::IndexOutOfBounds { index: 7, len: 3 },
+            FlyteError::InvalidInput {
+                message: "bad options".to_string(),
+            },
+            FlyteError::FailedCheck {
+                check: "holes_overlap".to_string(),
+                message: "holes 1 and 2 overlap".to_string(),
+                hole: Some(1),
+            },
+        ];
+        for error in &errors {
+            let json = serde_json::to_string(&ErrorPayload::new(error)).unwrap();
+            assert_eq!(json.matches("\"message\"").count(), 1, "{}", json);
+        }
+        let json = serde_json::to_value(ErrorPayload::new(&errors[0])).unwrap();
+        assert_eq!(json["message"], "hole index 7 out of bounds for 3 holes");
+        let json = serde_json::to_value(ErrorPayload::new(&errors[1])).unwrap();
+        assert_eq!(json["message"], "bad options");
+    }
+}
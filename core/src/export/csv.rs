@@ -0,0 +1,71 @@
+use crate::physics::Flute;
+use crate::tuning;
+use std::fmt::Write;
+
+/// Column header written by `export_hole_csv`
+pub const HOLE_CSV_HEADER: &str = "hole,position_mm,diameter_mm,azimuth_deg,frequency_hz";
+
+/// Hole table for spreadsheets and other calculators, one row per hole numbered front to
+/// back (embouchure to foot) after a header row:
+///
+/// - `position_mm`: hole centre from the embouchure centre (the labium edge on duct and
+///   notch flutes)
+/// - `diameter_mm`: finished diameter
+/// - `azimuth_deg`: around the tube, 0 = top, 180 = thumb side
+/// - `frequency_hz`: the note sounding with this hole and every hole below it open; empty
+///   if the solver finds none
+///
+/// The body itself (length, bore, wall) is not part of the table; use the Flutomat JSON or
+/// the design document to carry a whole design.
+pub fn export_hole_csv(flute: &Flute, jet_velocity: f64) -> String {
+    let chart = tuning::fingering_chart(flute, jet_velocity);
+    let order = flute.hole_order();
+    let mut out = String::from(HOLE_CSV_HEADER);
+    out.push_str("\r\n");
+    for (n, &i) in order.iter().enumerate() {
+        let hole = &flute.holes[i];
+        // The chart opens holes from the foot up: row 1 is the last hole in `order`
+        let frequency = chart
+            .get(order.len() - n)
+            .map(|row| row.frequency)
+            .filter(|f| f.is_finite());
+        let _ = write!(
+            out,
+            "{},{:.2},{:.2},{:.1},",
+            n + 1,
+            hole.position * 10.0,
+            hole.radius * 20.0,
+            hole.azimuth
+        );
+        if let Some(frequency) = frequency {
+            let _ = write!(out, "{:.2}", frequency);
+        }
+        out.push_str("\r\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Hole;
+
+    #[test]
+    fn test_hole_csv() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, true)).unwrap();
+        flute.holes.push(Hole::new(35.0, 0.3, true)).unwrap();
+        let csv = export_hole_csv(&flute, f64::NAN);
+        let rows: Vec<Vec<&str>> = csv.lines().map(|l| l.split(',').collect()).collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].join(","), HOLE_CSV_HEADER);
+        assert_eq!(rows[1][..4], ["1", "350.00", "6.00", "0.0"]);
+        assert_eq!(rows[2][1], "400.00");
+
+        // The foot hole plays the chart's second note, the front hole its third
+        let chart = tuning::fingering_chart(&flute, f64::NAN);
+        let hz = |row: &[&str]| row[4].parse::<f64>().unwrap();
+        assert!((hz(&rows[2]) - chart[1].frequency).abs() < 0.01);
+        assert!((hz(&rows[1]) - chart[2].frequency).abs() < 0.01);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::geometry::head_extension;
+use crate::physics::Flute;
+use std::fmt::Write;
+
+/// Layer name and AutoCAD colour index
+const LAYERS: [(&str, u8); 4] = [("OUTER", 7), ("BORE", 5), ("HOLES", 1), ("CENTERLINES", 3)];
+
+/// Minimal ASCII DXF (R12) writer: lines, polylines and circles on named layers. R12 has
+/// no units header, so drawings carry bare numbers; every Flyte drawing is in centimetres
+/// and should be imported as such.
+pub struct Dxf {
+    entities: String,
+}
+
+impl Default for Dxf {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Dxf {
+    pub fn new() -> Self {
+        Dxf {
+            entities: String::new(),
+        }
+    }
+
+    pub fn line(&mut self, layer: &str, x1: f64, y1: f64, x2: f64, y2: f64) {
+        let _ = write!(
+            self.entities,
+            "0\nLINE\n8\n{}\n10\n{:.4}\n20\n{:.4}\n30\n0.0\n11\n{:.4}\n21\n{:.4}\n31\n0.0\n",
+            layer, x1, y1, x2, y2
+        );
+    }
+
+    /// Open 2-D polyline through `points`
+    pub fn polyline(&mut self, layer: &str, points: &[(f64, f64)]) {
+        let _ = write!(self.entities, "0\nPOLYLINE\n8\n{}\n66\n1\n70\n0\n", layer);
+        for (x, y) in points {
+            let _ = write!(
+                self.entities,
+                "0\nVERTEX\n8\n{}\n10\n{:.4}\n20\n{:.4}\n30\n0.0\n",
+                layer, x, y
+            );
+        }
+        let _ = write!(self.entities, "0\nSEQEND\n8\n{}\n", layer);
+    }
+
+    pub fn circle(&mut self, layer: &str, x: f64, y: f64, r: f64) {
+        let _ = write!(
+            self.entities,
+            "0\nCIRCLE\n8\n{}\n10\n{:.4}\n20\n{:.4}\n30\n0.0\n40\n{:.4}\n",
+            layer, x, y, r
+        );
+    }
+
+    pub fn finish(self) -> String {
+        let mut out = String::new();
+        out.push_str("0\nSECTION\n2\nHEADER\n9\n$ACADVER\n1\nAC1009\n0\nENDSEC\n");
+        let _ = write!(
+            out,
+            "0\nSECTION\n2\nTABLES\n0\nTABLE\n2\nLAYER\n70\n{}\n",
+            LAYERS.len()
+        );
+        for (name, color) in LAYERS {
+            let _ = write!(
+                out,
+                "0\nLAYER\n2\n{}\n70\n0\n62\n{}\n6\nCONTINUOUS\n",
+                name, color
+            );
+        }
+        out.push_str("0\nENDTAB\n0\nENDSEC\n");
+        out.push_str("0\nSECTION\n2\nENTITIES\n");
+        out.push_str(&self.entities);
+        out.push_str("0\nENDSEC\n0\nEOF\n");
+        out
+    }
+}
+
+/// Export a 2-D drawing in centimetres for CAM import.
+///
+/// Two views share the X axis (distance from the embouchure centre):
+/// a longitudinal section centred on y = 0 (outer wall, bore, holes cut through the top wall)
+/// and a plan view below it with the hole and embouchure circles at finished diameter.
+/// Each hole's centreline runs through both views.
+pub fn export_dxf(flute: &Flute) -> String {
+    let mut dxf = Dxf::new();
+    let r_in = flute.bore_radius;
+    let r_out = flute.bore_radius + flute.wall_thickness;
+    let x0 = -head_extension(flute);
+    let x1 = flute.length;
+    let plan_y = -3.0 * r_out;
+
+    // Section view
+    for y in [r_out, -r_out] {
+        dxf.line("OUTER", x0, y, x1, y);
+    }
+    dxf.line("OUTER", x0, -r_out, x0, r_out);
+    dxf.line("OUTER", x1, -r_out, x1, r_out);
+    for y in [r_in, -r_in] {
+        dxf.line("BORE", x0, y, x1, y);
+    }
+
+    // Plan view outline
+    dxf.line("OUTER", x0, plan_y + r_out, x1, plan_y + r_out);
+    dxf.line("OUTER", x0, plan_y - r_out, x1, plan_y - r_out);
+    dxf.line("OUTER", x0, plan_y - r_out, x0, plan_y + r_out);
+    dxf.line("OUTER", x1, plan_y - r_out, x1, plan_y + r_out);
+
+    let openings = std::iter::once((0.0, flute.embouchure_hole_radius)).chain(
+        flute
+            .hole_order()
+            .into_iter()
+            .map(|i| (flute.holes[i].position, flute.holes[i].radius)),
+    );
+    for (x, r) in openings {
+        // Hole walls through the top of the section
+        dxf.line("HOLES", x - r, r_in, x - r, r_out);
+        dxf.line("HOLES", x + r, r_in, x + r, r_out);
+        dxf.circle("HOLES", x, plan_y, r);
+        dxf.line("CENTERLINES", x, plan_y - r_out - 0.5, x, r_out + 0.5);
+    }
+
+    dxf.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Hole;
+
+    #[test]
+    fn test_dxf_layers_and_entities() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(30.0, 0.3, true)).unwrap();
+        let dxf = export_dxf(&flute);
+
+        // R12 header: the version alone, no later-release units variable
+        assert!(dxf.starts_with("0\nSECTION\n2\nHEADER\n9\n$ACADVER\n1\nAC1009\n0\nENDSEC\n"));
+        assert!(dxf.ends_with("0\nEOF\n"));
+        for (name, _) in LAYERS {
+            assert!(dxf.contains(&format!("0\nLAYER\n2\n{}\n", name)));
+        }
+        // Embouchure + one hole
+        assert_eq!(dxf.matches("0\nCIRCLE\n").count(), 2);
+        assert_eq!(dxf.matches("8\nCENTERLINES\n").count(), 2);
+        assert!(dxf.contains("10\n30.0000\n20\n-4.0500\n30\n0.0\n40\n0.3000\n"));
+    }
+}
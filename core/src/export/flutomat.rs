@@ -0,0 +1,64 @@
+use crate::import::flutomat::{FlutomatDesign, FlutomatHole};
+use crate::physics::Flute;
+use crate::tuning;
+
+/// The design as Flutomat form values in millimetres, the format `import::flutomat` reads,
+/// with the frequencies the design actually plays. Flutomat models transverse flutes, so
+/// notch and duct geometry is left out and the embouchure diameter is written as is.
+pub fn export_flutomat_json(flute: &Flute, jet_velocity: f64) -> String {
+    serde_json::to_string_pretty(&flutomat_design(flute, jet_velocity)).unwrap_or_default()
+}
+
+pub fn flutomat_design(flute: &Flute, jet_velocity: f64) -> FlutomatDesign {
+    let chart = tuning::fingering_chart(flute, jet_velocity);
+    let order = flute.hole_order();
+    let played = |row: usize| {
+        chart
+            .get(row)
+            .map(|r| r.frequency)
+            .filter(|f| f.is_finite())
+    };
+    FlutomatDesign {
+        units: "mm".to_string(),
+        bore: flute.bore_radius * 20.0,
+        wall: flute.wall_thickness * 10.0,
+        embouchure: flute.embouchure_hole_radius * 20.0,
+        length: flute.length * 10.0,
+        end_frequency: played(0),
+        holes: order
+            .iter()
+            .enumerate()
+            .map(|(n, &i)| FlutomatHole {
+                diameter: flute.holes[i].radius * 20.0,
+                location: flute.holes[i].position * 10.0,
+                frequency: played(order.len() - n),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::flutomat::import_flutomat;
+    use crate::physics::Hole;
+
+    #[test]
+    fn test_flutomat_round_trip() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, true)).unwrap();
+        flute.holes.push(Hole::new(35.0, 0.3, true)).unwrap();
+        let json = export_flutomat_json(&flute, f64::NAN);
+        let doc = import_flutomat(&json).unwrap();
+        let back = doc.to_flute();
+        assert!((back.length - flute.length).abs() < 1e-9);
+        assert!((back.embouchure_hole_radius - flute.embouchure_hole_radius).abs() < 1e-9);
+        // Holes come back front to back
+        assert!((back.holes[0].position - 35.0).abs() < 1e-9);
+        assert!((back.holes[1].radius - 0.35).abs() < 1e-9);
+
+        let chart = tuning::fingering_chart(&flute, f64::NAN);
+        let played: Vec<f64> = chart.iter().map(|r| r.frequency).collect();
+        assert_eq!(doc.targets, played);
+    }
+}
@@ -0,0 +1,227 @@
+use crate::physics::Flute;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// How the tube blank is held on the machine
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DrillSetup {
+    /// Tube on a rotary 4th axis along X; Z0 on the rotation axis
+    Rotary,
+    /// Tube clamped in a V-block along X; Z0 on top of the tube
+    FlatJig,
+}
+
+/// Machine settings for `export_gcode`. Feeds in mm/min, distances in mm.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct GcodeOptions {
+    pub setup: DrillSetup,
+    pub spindle_rpm: f64,
+    pub feed_rate: f64,    // rapid-ish positioning feed between holes
+    pub plunge_rate: f64,  // drilling feed
+    pub peck_depth: f64,   // Q of the G83 cycle
+    pub safe_height: f64,  // clearance above the tube surface
+    pub breakthrough: f64, // extra depth past the bore wall
+}
+
+impl Default for GcodeOptions {
+    fn default() -> Self {
+        GcodeOptions {
+            setup: DrillSetup::FlatJig,
+            spindle_rpm: 3000.0,
+            feed_rate: 600.0,
+            plunge_rate: 60.0,
+            peck_depth: 1.0,
+            safe_height: 5.0,
+            breakthrough: 0.5,
+        }
+    }
+}
+
+struct DrillOp {
+    label: String,
+    x: f64,        // mm from the embouchure centre
+    diameter: f64, // mm
+    azimuth: f64,  // degrees around the tube
+}
+
+/// Peck-drilling program (G83) for the embouchure and every tone hole, in millimetres.
+/// Holes are grouped by bit diameter so each size needs one tool change.
+/// X0 is the embouchure centre (the labium edge of a duct flute or organ pipe, whose window
+/// is left to cut by hand); the caller touches off there.
+pub fn export_gcode(flute: &Flute, opts: &GcodeOptions) -> String {
+    let mm = |cm: f64| cm * 10.0;
+    let r_out = mm(flute.bore_radius + flute.wall_thickness);
+    let wall = mm(flute.wall_thickness);
+
+    // Surface height depends on the work-holding zero
+    let surface_z = match opts.setup {
+        DrillSetup::Rotary => r_out,
+        DrillSetup::FlatJig => 0.0,
+    };
+    let bottom_z = surface_z - wall - opts.breakthrough;
+    let retract_z = surface_z + opts.safe_height;
+
+    // Only a transverse flute's mouth is a round hole; notch flutes have none to drill
+    let mut ops = Vec::new();
+    if flute.instrument.has_embouchure_hole() {
+        ops.push(DrillOp {
+            label: "embouchure".to_string(),
+            x: 0.0,
+            diameter: mm(2.0 * flute.embouchure_hole_radius),
+            azimuth: 0.0,
+        });
+    }
+    for (n, i) in flute.hole_order().into_iter().enumerate() {
+        let hole = &flute.holes[i];
+        ops.push(DrillOp {
+            label: format!("hole {}", n + 1),
+            x: mm(hole.position),
+            diameter: mm(2.0 * hole.radius),
+            azimuth: hole.azimuth,
+        });
+    }
+    // Stable sort keeps axial order within each bit size
+    ops.sort_by(|a, b| {
+        a.diameter
+            .partial_cmp(&b.diameter)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut out = String::new();
+    let _ = writeln!(out, "(Flyte drilling program)");
+    let _ = writeln!(
+        out,
+        "(Setup: {}, X0 = embouchure centre, Z0 = {})",
+        match opts.setup {
+            DrillSetup::Rotary => "rotary 4th axis",
+            DrillSetup::FlatJig => "flat jig",
+        },
+        match opts.setup {
+            DrillSetup::Rotary => "rotation axis",
+            DrillSetup::FlatJig => "top of tube",
+        }
+    );
+    if let Some(duct) = flute.instrument.duct() {
+        let _ = writeln!(
+            out,
+            "(Window {:.2} x {:.2}mm from X{:.3} to X0.000 is not drilled: cut it by hand)",
+            mm(duct.windway_width),
+            mm(duct.window_length),
+            -mm(duct.window_length)
+        );
+    }
+    let _ = writeln!(out, "G21 G90 G17 G94");
+    let _ = writeln!(out, "G0 Z{:.3}", retract_z);
+
+    let mut tool = 0;
+    let mut current_diameter = f64::NAN;
+    for op in &ops {
+        if (op.diameter - current_diameter).abs() > 1e-6 || current_diameter.is_nan() {
+            if tool > 0 {
+                let _ = writeln!(out, "G80");
+                let _ = writeln!(out, "M5");
+                let _ = writeln!(out, "G0 Z{:.3}", retract_z);
+            }
+            tool += 1;
+            current_diameter = op.diameter;
+            let _ = writeln!(out, "T{} M6 (DRILL D={:.2}mm)", tool, op.diameter);
+            let _ = writeln!(out, "S{:.0} M3", opts.spindle_rpm);
+        }
+        let _ = writeln!(out, "({} D={:.2}mm)", op.label, op.diameter);
+        match opts.setup {
+            DrillSetup::Rotary => {
+                let _ = writeln!(
+                    out,
+                    "G1 X{:.3} A{:.3} F{:.0}",
+                    op.x, op.azimuth, opts.feed_rate
+                );
+            }
+            DrillSetup::FlatJig => {
+                if op.azimuth.abs() > 1e-6 {
+                    let _ = writeln!(out, "M0 (ROTATE TUBE TO {:.1} DEG)", op.azimuth);
+                }
+                let _ = writeln!(out, "G1 X{:.3} Y0.000 F{:.0}", op.x, opts.feed_rate);
+            }
+        }
+        let _ = writeln!(
+            out,
+            "G83 X{:.3} Z{:.3} R{:.3} Q{:.3} F{:.0}",
+            op.x, bottom_z, retract_z, opts.peck_depth, opts.plunge_rate
+        );
+    }
+
+    let _ = writeln!(out, "G80");
+    let _ = writeln!(out, "M5");
+    let _ = writeln!(out, "G0 Z{:.3}", retract_z);
+    let _ = writeln!(out, "M30");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{DuctGeometry, FluePipe, Hole, InstrumentType};
+
+    #[test]
+    fn test_gcode_groups_tools_and_pecks_every_hole() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for (pos, r) in [(40.0, 0.35), (30.0, 0.3), (35.0, 0.35)] {
+            flute.holes.push(Hole::new(pos, r, true)).unwrap();
+        }
+        let gcode = export_gcode(&flute, &GcodeOptions::default());
+
+        // 6 mm, 7 mm and 10 mm (embouchure) bits
+        assert_eq!(gcode.matches(" M6 ").count(), 3);
+        assert_eq!(gcode.matches("G83 ").count(), 4);
+        // 4 mm wall + 0.5 mm breakthrough below the tube top
+        assert!(gcode.contains("G83 X350.000 Z-4.500 R5.000 Q1.000 F60"));
+        // Same-size holes stay in axial order after grouping
+        let first = gcode.find("X350.000 Z").unwrap();
+        let second = gcode.find("X400.000 Z").unwrap();
+        assert!(first < second);
+        assert!(gcode.trim_end().ends_with("M30"));
+
+        let rotary = export_gcode(
+            &flute,
+            &GcodeOptions {
+                setup: DrillSetup::Rotary,
+                ..Default::default()
+            },
+        );
+        // Surface sits at the outer radius (13.5 mm) above the axis
+        assert!(rotary.contains("Z9.000 R18.500"));
+        assert!(rotary.contains("A0.000"));
+
+        flute.holes[1].azimuth = 180.0;
+        let rotary = export_gcode(
+            &flute,
+            &GcodeOptions {
+                setup: DrillSetup::Rotary,
+                ..Default::default()
+            },
+        );
+        assert!(rotary.contains("G1 X300.000 A180.000"));
+        let flat = export_gcode(&flute, &GcodeOptions::default());
+        assert!(flat.contains("M0 (ROTATE TUBE TO 180.0 DEG)"));
+    }
+
+    #[test]
+    fn test_gcode_leaves_the_duct_window_undrilled() {
+        let mut flute = Flute::new(30.0, 0.65, 0.3);
+        flute.instrument = InstrumentType::Duct(DuctGeometry::default());
+        flute.holes.push(Hole::new(20.0, 0.3, true)).unwrap();
+        let gcode = export_gcode(&flute, &GcodeOptions::default());
+        // The 6 mm tone hole alone, no 10 mm embouchure
+        assert_eq!(gcode.matches(" M6 ").count(), 1);
+        assert_eq!(gcode.matches("G83 ").count(), 1);
+        assert!(!gcode.contains("embouchure D="));
+        assert!(gcode.contains("(Window 10.00 x 5.00mm from X-5.000 to X0.000"));
+
+        flute.instrument = InstrumentType::OrganPipe(FluePipe::default());
+        let gcode = export_gcode(&flute, &GcodeOptions::default());
+        assert_eq!(gcode.matches("G83 ").count(), 1);
+        assert!(gcode.contains("(Window 16.00 x 4.00mm"));
+    }
+}
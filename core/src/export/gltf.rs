@@ -0,0 +1,162 @@
+use crate::geometry::Mesh;
+use serde_json::json;
+
+const GLB_MAGIC: u32 = 0x4654_6C67; // "glTF"
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+// glTF enums
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const TRIANGLES: u32 = 4;
+
+/// Binary glTF (GLB) with one node per mesh group, so a viewer can show or hide the cutters.
+/// glTF is always metres and Y-up, which is the model's own orientation.
+/// Normals are left out; viewers compute flat normals for primitives without them.
+pub fn export_glb(mesh: &Mesh) -> Vec<u8> {
+    let mut bin: Vec<u8> = Vec::new();
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for &(x, y, z) in mesh.vertices() {
+        // cm -> m
+        let p = [(x * 0.01) as f32, (y * 0.01) as f32, (z * 0.01) as f32];
+        for k in 0..3 {
+            min[k] = min[k].min(p[k]);
+            max[k] = max[k].max(p[k]);
+            bin.extend_from_slice(&p[k].to_le_bytes());
+        }
+    }
+
+    let mut buffer_views = vec![json!({
+        "buffer": 0,
+        "byteOffset": 0,
+        "byteLength": bin.len(),
+        "target": ARRAY_BUFFER,
+    })];
+    let mut accessors = vec![json!({
+        "bufferView": 0,
+        "componentType": FLOAT,
+        "count": mesh.vertices().len(),
+        "type": "VEC3",
+        "min": min,
+        "max": max,
+    })];
+    let mut meshes = Vec::new();
+    let mut nodes = Vec::new();
+
+    // Every group shares the position accessor and gets its own index list
+    for (name, tris) in mesh.group_triangles() {
+        let offset = bin.len();
+        for tri in &tris {
+            for &i in tri {
+                bin.extend_from_slice(&i.to_le_bytes());
+            }
+        }
+        buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": bin.len() - offset,
+            "target": ELEMENT_ARRAY_BUFFER,
+        }));
+        accessors.push(json!({
+            "bufferView": buffer_views.len() - 1,
+            "componentType": UNSIGNED_INT,
+            "count": 3 * tris.len(),
+            "type": "SCALAR",
+        }));
+        meshes.push(json!({
+            "name": name,
+            "primitives": [{
+                "attributes": { "POSITION": 0 },
+                "indices": accessors.len() - 1,
+                "mode": TRIANGLES,
+            }],
+        }));
+        nodes.push(json!({ "name": name, "mesh": meshes.len() - 1 }));
+    }
+
+    let doc = json!({
+        "asset": { "version": "2.0", "generator": "Flyte Architect" },
+        "scene": 0,
+        "scenes": [{ "name": "FluteProject", "nodes": (0..nodes.len()).collect::<Vec<_>>() }],
+        "nodes": nodes,
+        "meshes": meshes,
+        "accessors": accessors,
+        "bufferViews": buffer_views,
+        "buffers": [{ "byteLength": bin.len() }],
+    });
+
+    // Chunks are 4-byte aligned: JSON padded with spaces, binary with zeros
+    let mut json_bytes = doc.to_string().into_bytes();
+    while !json_bytes.len().is_multiple_of(4) {
+        json_bytes.push(b' ');
+    }
+    while !bin.len().is_multiple_of(4) {
+        bin.push(0);
+    }
+
+    let total = 12 + 8 + json_bytes.len() + 8 + bin.len();
+    let mut out = Vec::with_capacity(total);
+    out.extend_from_slice(&GLB_MAGIC.to_le_bytes());
+    out.extend_from_slice(&2u32.to_le_bytes());
+    out.extend_from_slice(&(total as u32).to_le_bytes());
+    out.extend_from_slice(&(json_bytes.len() as u32).to_le_bytes());
+    out.extend_from_slice(&CHUNK_JSON.to_le_bytes());
+    out.extend_from_slice(&json_bytes);
+    out.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+    out.extend_from_slice(&CHUNK_BIN.to_le_bytes());
+    out.extend_from_slice(&bin);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::generate_flute_mesh;
+    use crate::physics::{Flute, Hole};
+
+    fn u32_at(bytes: &[u8], at: usize) -> usize {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize
+    }
+
+    #[test]
+    fn test_glb_layout_and_nodes() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, true)).unwrap();
+        let mesh = generate_flute_mesh(&flute);
+        let glb = export_glb(&mesh);
+
+        assert_eq!(&glb[0..4], b"glTF");
+        assert_eq!(u32_at(&glb, 8), glb.len());
+        let json_len = u32_at(&glb, 12);
+        let doc: serde_json::Value = serde_json::from_slice(&glb[20..20 + json_len]).unwrap();
+
+        let names: Vec<&str> = doc["nodes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|n| n["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "TubeBody",
+                "HoleCutters",
+                "MouthHoleCutter",
+                "EmbouchureChimney"
+            ]
+        );
+        assert_eq!(doc["accessors"][0]["count"], mesh.vertices().len());
+
+        let bin_len = u32_at(&glb, 20 + json_len);
+        assert_eq!(
+            doc["buffers"][0]["byteLength"].as_u64().unwrap() as usize,
+            bin_len
+        );
+        // Tube spans -5 cm (head stub) to 60 cm, in metres
+        assert!((doc["accessors"][0]["min"][0].as_f64().unwrap() + 0.05).abs() < 1e-6);
+        assert!((doc["accessors"][0]["max"][0].as_f64().unwrap() - 0.6).abs() < 1e-6);
+    }
+}
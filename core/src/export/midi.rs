@@ -0,0 +1,131 @@
+use crate::physics::Flute;
+use crate::tuning::{self, ChartRow};
+
+/// Ticks per quarter note; each scale note lasts one quarter at 120 bpm
+const DIVISION: u16 = 480;
+const MICROS_PER_QUARTER: u32 = 500_000;
+/// General MIDI flute (program 74, zero-based)
+const FLUTE_PROGRAM: u8 = 73;
+/// Pitch-bend range set through RPN 0, in semitones. Deviations past it are clamped.
+const BEND_RANGE: u8 = 2;
+const VELOCITY: u8 = 90;
+
+/// Standard MIDI File (format 0) playing the fingering chart from the lowest note up. Each
+/// note is the nearest equal-tempered key with a pitch bend carrying the cents deviation,
+/// so a DAW plays back the design's real intonation.
+pub fn export_scale_midi(flute: &Flute, jet_velocity: f64) -> Vec<u8> {
+    scale_midi(&tuning::fingering_chart(flute, jet_velocity))
+}
+
+/// `export_scale_midi` for an already computed chart
+pub fn scale_midi(chart: &[ChartRow]) -> Vec<u8> {
+    let mut track = Vec::new();
+    let name = b"Flyte scale";
+    event(&mut track, 0, &[0xff, 0x03, name.len() as u8]);
+    track.extend_from_slice(name);
+    let tempo = MICROS_PER_QUARTER.to_be_bytes();
+    event(
+        &mut track,
+        0,
+        &[0xff, 0x51, 0x03, tempo[1], tempo[2], tempo[3]],
+    );
+    event(&mut track, 0, &[0xc0, FLUTE_PROGRAM]);
+    // RPN 0 (pitch-bend sensitivity) = BEND_RANGE semitones, then close the RPN
+    for (controller, value) in [
+        (101, 0),
+        (100, 0),
+        (6, BEND_RANGE),
+        (38, 0),
+        (101, 127),
+        (100, 127),
+    ] {
+        event(&mut track, 0, &[0xb0, controller, value]);
+    }
+
+    for row in chart {
+        if !row.frequency.is_finite() || row.note.target <= 0.0 {
+            continue;
+        }
+        let key = 69.0 + 12.0 * (row.note.target / 440.0).log2();
+        let key = key.round().clamp(0.0, 127.0) as u8;
+        let bend = 8192.0 + row.note.cents / (100.0 * BEND_RANGE as f64) * 8192.0;
+        let bend = bend.round().clamp(0.0, 16383.0) as u16;
+        event(
+            &mut track,
+            0,
+            &[0xe0, (bend & 0x7f) as u8, (bend >> 7) as u8],
+        );
+        event(&mut track, 0, &[0x90, key, VELOCITY]);
+        event(&mut track, DIVISION as u32, &[0x80, key, 0]);
+    }
+    event(&mut track, 0, &[0xe0, 0x00, 0x40]);
+    event(&mut track, 0, &[0xff, 0x2f, 0x00]);
+
+    let mut out = Vec::with_capacity(22 + track.len());
+    out.extend_from_slice(b"MThd");
+    out.extend_from_slice(&6u32.to_be_bytes());
+    out.extend_from_slice(&0u16.to_be_bytes()); // format 0
+    out.extend_from_slice(&1u16.to_be_bytes()); // one track
+    out.extend_from_slice(&DIVISION.to_be_bytes());
+    out.extend_from_slice(b"MTrk");
+    out.extend_from_slice(&(track.len() as u32).to_be_bytes());
+    out.extend_from_slice(&track);
+    out
+}
+
+fn event(track: &mut Vec<u8>, delta: u32, bytes: &[u8]) {
+    write_vlq(track, delta);
+    track.extend_from_slice(bytes);
+}
+
+/// MIDI variable-length quantity: 7 bits per byte, high bit set on all but the last
+fn write_vlq(out: &mut Vec<u8>, mut value: u32) {
+    let mut bytes = [0u8; 5];
+    let mut n = 0;
+    loop {
+        bytes[n] = (value & 0x7f) as u8;
+        n += 1;
+        value >>= 7;
+        if value == 0 {
+            break;
+        }
+    }
+    for (i, &byte) in bytes[..n].iter().enumerate().rev() {
+        out.push(byte | if i > 0 { 0x80 } else { 0 });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Hole;
+
+    #[test]
+    fn test_scale_midi_structure() {
+        let mut vlq = Vec::new();
+        write_vlq(&mut vlq, 0x3fff);
+        assert_eq!(vlq, [0xff, 0x7f]);
+
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for pos in [40.0, 35.0] {
+            flute.holes.push(Hole::new(pos, 0.35, true)).unwrap();
+        }
+        let chart = tuning::fingering_chart(&flute, f64::NAN);
+        let midi = scale_midi(&chart);
+        assert_eq!(&midi[..4], b"MThd");
+        assert_eq!(&midi[14..18], b"MTrk");
+        let len = u32::from_be_bytes(midi[18..22].try_into().unwrap()) as usize;
+        assert_eq!(midi.len(), 22 + len);
+        assert_eq!(&midi[midi.len() - 3..], [0xff, 0x2f, 0x00]);
+
+        // One note-on per chart row, bent by the row's cents
+        let note_ons: Vec<usize> = (22..midi.len() - 2)
+            .filter(|&i| midi[i] == 0x90 && midi[i + 2] == VELOCITY)
+            .collect();
+        assert_eq!(note_ons.len(), chart.len());
+        let first = note_ons[0];
+        let bend = midi[first - 3] as i32 | (midi[first - 2] as i32) << 7;
+        let cents = (bend - 8192) as f64 / 8192.0 * 200.0;
+        assert!((cents - chart[0].note.cents).abs() < 0.1);
+    }
+}
@@ -0,0 +1,18 @@
+//! File exports beyond the OBJ text. Most work from the `Flute` description rather than
+//! the mesh; `gltf`, `stl` and `threemf` package the generated mesh, `midi` plays the
+//! fingering chart and `wav` packages audio from `synth`. `csv` and `flutomat` write hole
+//! tables and designs other calculators and spreadsheets read.
+
+pub mod csv;
+pub mod dxf;
+pub mod flutomat;
+pub mod gcode;
+pub mod gltf;
+pub mod midi;
+pub mod pdf;
+pub mod profile;
+pub mod spec;
+pub mod stl;
+pub mod svg;
+pub mod threemf;
+pub mod wav;
@@ -0,0 +1,574 @@
+use crate::geometry::head_extension;
+use crate::physics::Flute;
+use crate::tuning::{fingering_chart, ChartRow};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::fmt::Write;
+
+// A3 landscape in PDF points
+const PAGE_W: f64 = 1190.55;
+const PAGE_H: f64 = 841.89;
+const MARGIN: f64 = 40.0;
+const PT_PER_CM: f64 = 72.0 / 2.54;
+const ROW_H: f64 = 13.0;
+
+/// Drawing commands for a single page, in points with the origin bottom-left
+struct Page {
+    ops: String,
+}
+
+impl Page {
+    fn new() -> Self {
+        Page {
+            ops: String::from("0.5 w\n"),
+        }
+    }
+
+    fn line(&mut self, x1: f64, y1: f64, x2: f64, y2: f64) {
+        let _ = writeln!(self.ops, "{:.2} {:.2} m {:.2} {:.2} l S", x1, y1, x2, y2);
+    }
+
+    fn rect(&mut self, x: f64, y: f64, w: f64, h: f64) {
+        let _ = writeln!(self.ops, "{:.2} {:.2} {:.2} {:.2} re S", x, y, w, h);
+    }
+
+    fn circle(&mut self, cx: f64, cy: f64, r: f64) {
+        // Four cubic Bezier quadrants; control points sit k along each end tangent
+        let k = 0.5523 * r;
+        let _ = write!(self.ops, "{:.2} {:.2} m", cx + r, cy);
+        for q in 0..4 {
+            let a0 = q as f64 * PI / 2.0;
+            let a1 = a0 + PI / 2.0;
+            let (x0, y0) = (cx + r * a0.cos(), cy + r * a0.sin());
+            let (x3, y3) = (cx + r * a1.cos(), cy + r * a1.sin());
+            let _ = write!(
+                self.ops,
+                " {:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c",
+                x0 - k * a0.sin(),
+                y0 + k * a0.cos(),
+                x3 + k * a1.sin(),
+                y3 - k * a1.cos(),
+                x3,
+                y3
+            );
+        }
+        self.ops.push_str(" S\n");
+    }
+
+    fn dashed(&mut self, on: bool) {
+        self.ops
+            .push_str(if on { "[4 2] 0 d\n" } else { "[] 0 d\n" });
+    }
+
+    fn text(&mut self, x: f64, y: f64, size: f64, s: &str) {
+        let _ = writeln!(
+            self.ops,
+            "BT /F1 {} Tf {:.2} {:.2} Td ({}) Tj ET",
+            size,
+            x,
+            y,
+            escape(s)
+        );
+    }
+
+    /// Text running bottom-to-top, for ordinate dimensions
+    fn text_vertical(&mut self, x: f64, y: f64, size: f64, s: &str) {
+        let _ = writeln!(
+            self.ops,
+            "BT /F1 {} Tf 0 1 -1 0 {:.2} {:.2} Tm ({}) Tj ET",
+            size,
+            x,
+            y,
+            escape(s)
+        );
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('(', "\\(")
+        .replace(')', "\\)")
+}
+
+/// Wrap page content streams into a complete PDF file (Helvetica, no compression)
+fn assemble(pages: &[String], width: f64, height: f64) -> Vec<u8> {
+    // 1 catalog, 2 page tree, 3 font, then a page object and its content stream per page
+    let kids: Vec<String> = (0..pages.len())
+        .map(|i| format!("{} 0 R", 4 + 2 * i))
+        .collect();
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+    ];
+    for (i, content) in pages.iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+             /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            width,
+            height,
+            5 + 2 * i
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut out = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (i, obj) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        let _ = write!(out, "{} 0 obj\n{}\nendobj\n", i + 1, obj);
+    }
+    let xref = out.len();
+    let _ = write!(out, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for off in offsets {
+        let _ = writeln!(out, "{:010} 00000 n ", off);
+    }
+    let _ = write!(
+        out,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    );
+    out.into_bytes()
+}
+
+/// "XXO..." pattern in player order (X = closed, O = open)
+fn fingering_pattern(flute: &Flute, fingering: u32) -> String {
+    flute
+        .hole_order()
+        .into_iter()
+        .map(|i| {
+            if i < 32 && fingering & (1 << i) != 0 {
+                'O'
+            } else {
+                'X'
+            }
+        })
+        .collect()
+}
+
+/// Printable A3 workshop drawing: dimensioned side view, 1:1 cross-section,
+/// hole table and the progressive fingering chart at the given jet velocity.
+/// Dimensions are in millimetres, measured from the embouchure centre.
+pub fn export_pdf_plan(flute: &Flute, jet_velocity: f64) -> Vec<u8> {
+    let mut page = Page::new();
+    let r_in = flute.bore_radius;
+    let r_out = flute.bore_radius + flute.wall_thickness;
+    let head = head_extension(flute);
+    let body_len = flute.length + head;
+    let mm = |cm: f64| cm * 10.0;
+
+    page.text(MARGIN, PAGE_H - MARGIN, 16.0, "Flyte flute plan");
+    page.text(
+        MARGIN,
+        PAGE_H - MARGIN - 18.0,
+        9.0,
+        &format!(
+            "Sounding length {:.1} mm   Body {:.1} mm   Bore {:.1} mm   OD {:.1} mm   Cork {:.1} mm",
+            mm(flute.length),
+            mm(body_len),
+            mm(2.0 * r_in),
+            mm(2.0 * r_out),
+            mm(flute.cork_position)
+        ),
+    );
+
+    // --- Side view, scaled to the page width ---
+    let scale = ((PAGE_W - 2.0 * MARGIN) / body_len).min(PT_PER_CM);
+    let x_of = |pos: f64| MARGIN + (pos + head) * scale;
+    let axis_y = PAGE_H - 200.0;
+    page.text(
+        MARGIN,
+        axis_y + r_out * scale + 80.0,
+        10.0,
+        &format!("Side view (scale 1:{:.1})", PT_PER_CM / scale),
+    );
+    page.rect(
+        x_of(-head),
+        axis_y - r_out * scale,
+        body_len * scale,
+        2.0 * r_out * scale,
+    );
+    page.dashed(true);
+    page.line(
+        x_of(-head),
+        axis_y + r_in * scale,
+        x_of(flute.length),
+        axis_y + r_in * scale,
+    );
+    page.line(
+        x_of(-head),
+        axis_y - r_in * scale,
+        x_of(flute.length),
+        axis_y - r_in * scale,
+    );
+    page.dashed(false);
+
+    // Ordinate dimensions from the embouchure for every opening
+    let dim_y = axis_y + r_out * scale + 8.0;
+    let openings = std::iter::once((0.0, flute.embouchure_hole_radius)).chain(
+        flute
+            .hole_order()
+            .into_iter()
+            .map(|i| (flute.holes[i].position, flute.holes[i].radius)),
+    );
+    for (pos, r) in openings {
+        page.circle(x_of(pos), axis_y, r * scale);
+        page.line(x_of(pos), axis_y + r * scale, x_of(pos), dim_y + 4.0);
+        page.text_vertical(
+            x_of(pos) + 3.0,
+            dim_y + 6.0,
+            7.0,
+            &format!("{:.1}", mm(pos)),
+        );
+    }
+
+    // Overall length dimension under the tube
+    let len_y = axis_y - r_out * scale - 20.0;
+    page.line(x_of(-head), len_y, x_of(flute.length), len_y);
+    page.line(x_of(-head), len_y - 4.0, x_of(-head), len_y + 4.0);
+    page.line(
+        x_of(flute.length),
+        len_y - 4.0,
+        x_of(flute.length),
+        len_y + 4.0,
+    );
+    page.text(
+        (x_of(-head) + x_of(flute.length)) / 2.0 - 20.0,
+        len_y - 12.0,
+        8.0,
+        &format!("{:.1} mm", mm(body_len)),
+    );
+
+    // --- Cross-section, true scale ---
+    let cs_x = MARGIN + r_out * PT_PER_CM + 10.0;
+    let cs_y = PAGE_H - 420.0;
+    page.text(
+        MARGIN,
+        cs_y + r_out * PT_PER_CM + 20.0,
+        10.0,
+        "Cross-section (1:1)",
+    );
+    page.circle(cs_x, cs_y, r_out * PT_PER_CM);
+    page.circle(cs_x, cs_y, r_in * PT_PER_CM);
+    page.line(cs_x - r_in * PT_PER_CM, cs_y, cs_x + r_in * PT_PER_CM, cs_y);
+    let label_x = cs_x + r_out * PT_PER_CM + 10.0;
+    page.text(
+        label_x,
+        cs_y + 10.0,
+        8.0,
+        &format!("Bore {:.2} mm", mm(2.0 * r_in)),
+    );
+    page.text(label_x, cs_y, 8.0, &format!("OD {:.2} mm", mm(2.0 * r_out)));
+    page.text(
+        label_x,
+        cs_y - 10.0,
+        8.0,
+        &format!("Wall {:.2} mm", mm(flute.wall_thickness)),
+    );
+
+    // --- Hole table ---
+    let table_x = MARGIN + 260.0;
+    let mut y = PAGE_H - 330.0;
+    page.text(table_x, y, 10.0, "Hole table");
+    y -= ROW_H + 4.0;
+    let cols = [0.0, 30.0, 100.0, 170.0, 230.0, 290.0];
+    for (c, title) in cols.iter().zip([
+        "#",
+        "Position mm",
+        "Diameter mm",
+        "Angle deg",
+        "Undercut",
+        "Chimney mm",
+    ]) {
+        page.text(table_x + c, y, 8.0, title);
+    }
+    page.line(table_x, y - 3.0, table_x + 350.0, y - 3.0);
+    y -= ROW_H;
+    let emb = [
+        "E".to_string(),
+        "0.0".to_string(),
+        format!("{:.2}", mm(2.0 * flute.embouchure_hole_radius)),
+        "0".to_string(),
+        "-".to_string(),
+        format!("{:.2}", mm(flute.embouchure_chimney)),
+    ];
+    for (c, cell) in cols.iter().zip(emb.iter()) {
+        page.text(table_x + c, y, 8.0, cell);
+    }
+    for (n, i) in flute.hole_order().into_iter().enumerate() {
+        y -= ROW_H;
+        let hole = &flute.holes[i];
+        let row = [
+            format!("{}", n + 1),
+            format!("{:.1}", mm(hole.position)),
+            format!("{:.2}", mm(2.0 * hole.radius)),
+            format!("{:.0}", hole.azimuth),
+            // The acoustic model has no undercut yet; straight-drilled holes
+            "-".to_string(),
+            format!("{:.2}", mm(flute.wall_thickness)),
+        ];
+        for (c, cell) in cols.iter().zip(row.iter()) {
+            page.text(table_x + c, y, 8.0, cell);
+        }
+    }
+
+    // --- Tuning chart ---
+    let chart_x = table_x + 380.0;
+    let mut y = PAGE_H - 330.0;
+    page.text(
+        chart_x,
+        y,
+        10.0,
+        &format!("Tuning chart (jet {:.0} cm/s)", jet_velocity),
+    );
+    y -= ROW_H + 4.0;
+    let cols = [0.0, 110.0, 180.0, 230.0];
+    for (c, title) in cols.iter().zip(["Fingering", "Hz", "Note", "Cents"]) {
+        page.text(chart_x + c, y, 8.0, title);
+    }
+    page.line(chart_x, y - 3.0, chart_x + 280.0, y - 3.0);
+    let chart: Vec<ChartRow> = fingering_chart(flute, jet_velocity);
+    for row in &chart {
+        y -= ROW_H;
+        let cells = [
+            fingering_pattern(flute, row.fingering),
+            format!("{:.1}", row.frequency),
+            row.note.name.clone(),
+            format!("{:+.0}", row.note.cents),
+        ];
+        for (c, cell) in cols.iter().zip(cells.iter()) {
+            page.text(chart_x + c, y, 8.0, cell);
+        }
+    }
+
+    assemble(&[page.ops], PAGE_W, PAGE_H)
+}
+
+/// Home-printer paper for the wrap template
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PaperSize {
+    #[default]
+    A4,
+    Letter,
+}
+
+impl PaperSize {
+    /// Landscape width and height in points
+    fn landscape(self) -> (f64, f64) {
+        match self {
+            PaperSize::A4 => (841.89, 595.28),
+            PaperSize::Letter => (792.0, 612.0),
+        }
+    }
+}
+
+// Wrap template layout, in cm
+const TILE_MARGIN: f64 = 1.0; // unprintable edge most printers leave
+const TILE_OVERLAP: f64 = 1.0; // shared strip between neighbouring pages
+const TEMPLATE_BORDER: f64 = 1.0; // paper around the unrolled tube
+const TEMPLATE_RULER: f64 = 1.2;
+
+/// 1:1 wrap-around drilling template tiled over A4 or Letter pages (landscape).
+///
+/// The unrolled tube is laid out as in the SVG drill template: axial position left to right,
+/// circumference vertically with top-side holes on the centre line. Neighbouring pages
+/// overlap by 1 cm and share registration crosshairs on a grid, so the pages can be
+/// taped together by lining up the crosshairs. Each page carries a 5 cm bar for checking
+/// that the printer did not scale the page.
+pub fn export_paper_template(flute: &Flute, paper: PaperSize) -> Vec<u8> {
+    let (page_w, page_h) = paper.landscape();
+    let r_out = flute.bore_radius + flute.wall_thickness;
+    let circumference = 2.0 * PI * r_out;
+    let head = head_extension(flute);
+
+    // Template coordinates in cm, origin bottom-left of the sheet, y up
+    let sheet_w = flute.length + head + 2.0 * TEMPLATE_BORDER;
+    let sheet_h = circumference + TEMPLATE_RULER + 2.0 * TEMPLATE_BORDER;
+    let x_of = |pos: f64| TEMPLATE_BORDER + head + pos;
+    let bottom = TEMPLATE_BORDER;
+    let centre_y = bottom + circumference / 2.0;
+    let top = bottom + circumference;
+
+    // Everything on the sheet, drawn once in points and clipped per page
+    let mut sheet = Page::new();
+    let pt = |cm: f64| cm * PT_PER_CM;
+    sheet.rect(
+        pt(x_of(-head)),
+        pt(bottom),
+        pt(flute.length + head),
+        pt(circumference),
+    );
+    sheet.dashed(true);
+    sheet.line(
+        pt(x_of(-head)),
+        pt(centre_y),
+        pt(x_of(flute.length)),
+        pt(centre_y),
+    );
+    sheet.dashed(false);
+    let first_mm = (-head * 10.0).ceil() as i64;
+    let last_mm = (flute.length * 10.0).floor() as i64;
+    for mm in first_mm..=last_mm {
+        let x = pt(x_of(mm as f64 / 10.0));
+        let tick = if mm % 10 == 0 {
+            0.5
+        } else if mm % 5 == 0 {
+            0.35
+        } else {
+            0.2
+        };
+        sheet.line(x, pt(top), x, pt(top + tick));
+        if mm % 10 == 0 {
+            sheet.text(x - 3.0, pt(top + 0.6), 7.0, &format!("{}", mm / 10));
+        }
+    }
+
+    let mut marks = Vec::new();
+    if flute.instrument.has_embouchure_hole() {
+        marks.push((0.0, centre_y, flute.embouchure_hole_radius, "E".to_string()));
+    }
+    for (n, &i) in flute.hole_order().iter().enumerate() {
+        let hole = &flute.holes[i];
+        let offset = (hole.azimuth / 360.0).rem_euclid(1.0) * circumference;
+        let y = bottom + (circumference / 2.0 + offset) % circumference;
+        let label = format!(
+            "H{} {:.1} / D{:.1}mm",
+            n + 1,
+            hole.position,
+            hole.radius * 20.0
+        );
+        marks.push((hole.position, y, hole.radius, label));
+    }
+    for (pos, y, r, label) in &marks {
+        let (cx, cy) = (pt(x_of(*pos)), pt(*y));
+        let arm = pt(r + 0.2);
+        sheet.circle(cx, cy, pt(*r));
+        sheet.line(cx - arm, cy, cx + arm, cy);
+        sheet.line(cx, cy - arm, cx, cy + arm);
+        sheet.text(cx - 12.0, cy + arm + 2.0, 6.0, label);
+    }
+
+    // Tile grid: each page shows a `printable` window, stepping by `printable - overlap`
+    let printable = (
+        page_w / PT_PER_CM - 2.0 * TILE_MARGIN,
+        page_h / PT_PER_CM - 2.0 * TILE_MARGIN,
+    );
+    let step = (printable.0 - TILE_OVERLAP, printable.1 - TILE_OVERLAP);
+    let cols = ((sheet_w - TILE_OVERLAP) / step.0).ceil().max(1.0) as usize;
+    let rows = ((sheet_h - TILE_OVERLAP) / step.1).ceil().max(1.0) as usize;
+
+    // Crosshairs at every grid corner land inside the overlap of the pages that share it
+    for c in 0..=cols {
+        for r in 0..=rows {
+            let (x, y) = (
+                pt(c as f64 * step.0 + TILE_OVERLAP / 2.0),
+                pt(r as f64 * step.1 + TILE_OVERLAP / 2.0),
+            );
+            sheet.circle(x, y, 6.0);
+            sheet.line(x - 10.0, y, x + 10.0, y);
+            sheet.line(x, y - 10.0, x, y + 10.0);
+        }
+    }
+
+    let mut pages = Vec::new();
+    for r in 0..rows {
+        for c in 0..cols {
+            let mut page = Page::new();
+            let (ox, oy) = (pt(TILE_MARGIN), pt(TILE_MARGIN));
+            let (sx, sy) = (pt(c as f64 * step.0), pt(r as f64 * step.1));
+            // Clip to the printable window, then shift the sheet under it
+            let _ = writeln!(
+                page.ops,
+                "q {:.2} {:.2} {:.2} {:.2} re W n 1 0 0 1 {:.2} {:.2} cm",
+                ox,
+                oy,
+                pt(printable.0),
+                pt(printable.1),
+                ox - sx,
+                oy - sy
+            );
+            page.ops.push_str(&sheet.ops);
+            page.ops.push_str("Q\n");
+
+            page.text(
+                ox,
+                page_h - oy + 8.0,
+                8.0,
+                &format!(
+                    "Flyte wrap template - page {} of {} (row {}, column {}) - print at 100%",
+                    r * cols + c + 1,
+                    rows * cols,
+                    r + 1,
+                    c + 1
+                ),
+            );
+            // Scale check bar: must measure exactly 5 cm
+            page.line(ox, oy - 12.0, ox + pt(5.0), oy - 12.0);
+            page.line(ox, oy - 15.0, ox, oy - 9.0);
+            page.line(ox + pt(5.0), oy - 15.0, ox + pt(5.0), oy - 9.0);
+            page.text(ox + pt(5.0) + 4.0, oy - 15.0, 7.0, "5 cm");
+            pages.push(page.ops);
+        }
+    }
+    assemble(&pages, page_w, page_h)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Hole;
+
+    #[test]
+    fn test_pdf_structure() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(30.0, 0.3, true)).unwrap();
+        let pdf = String::from_utf8(export_pdf_plan(&flute, 2000.0)).unwrap();
+
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        // xref offsets must point at the objects
+        let xref_at: usize = pdf
+            .rsplit("startxref\n")
+            .next()
+            .and_then(|t| t.lines().next())
+            .and_then(|n| n.parse().ok())
+            .unwrap();
+        assert!(pdf[xref_at..].starts_with("xref\n"));
+        let first_obj: usize = pdf[xref_at..].lines().nth(3).unwrap()[..10]
+            .parse()
+            .unwrap();
+        assert!(pdf[first_obj..].starts_with("1 0 obj"));
+
+        assert!(pdf.contains("(300.0) Tj"));
+        // Two fingerings: closed and open
+        assert!(pdf.contains("(X) Tj"));
+        assert!(pdf.contains("(O) Tj"));
+    }
+
+    #[test]
+    fn test_paper_template_tiles() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(30.0, 0.3, true)).unwrap();
+        let pdf = String::from_utf8(export_paper_template(&flute, PaperSize::A4)).unwrap();
+
+        // 67 cm sheet over 26.7 cm steps of landscape A4: three pages in one row
+        assert!(pdf.contains("/Count 3 >>"));
+        assert_eq!(pdf.matches("/Type /Page /Parent").count(), 3);
+        assert!(pdf.contains("page 3 of 3 \\(row 1, column 3\\)"));
+        assert!(pdf.contains("(H1 30.0 / D6.0mm) Tj"));
+
+        let letter = String::from_utf8(export_paper_template(&flute, PaperSize::Letter)).unwrap();
+        assert!(letter.contains("/MediaBox [0 0 792 612]"));
+    }
+}
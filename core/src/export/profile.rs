@@ -0,0 +1,300 @@
+use super::dxf::Dxf;
+use crate::geometry::head_extension;
+use crate::physics::Flute;
+use serde::Serialize;
+use std::fmt::Write;
+
+const MARGIN: f64 = 1.0; // cm around the drawing
+const STROKE: f64 = 0.02; // cm
+
+/// Radius left for the finishing pass when none is given
+pub const DEFAULT_REAMER_ALLOWANCE: f64 = 0.02; // cm
+pub const DEFAULT_REAMER_STEP: f64 = 1.0; // cm
+const MIN_REAMER_STEP: f64 = 0.1; // cm
+
+/// A hole marked on the profile, numbered from the blowing end (0 = embouchure)
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ProfileMark {
+    pub number: usize,
+    pub position: f64,
+    pub radius: f64,
+}
+
+/// Half longitudinal section of the body in (x, radius) cm, x from the embouchure centre.
+/// `outer` walks head end -> foot end including both end faces; `inner` is the bore line.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub outer: Vec<(f64, f64)>,
+    pub inner: Vec<(f64, f64)>,
+    pub holes: Vec<ProfileMark>,
+}
+
+pub fn body_profile(flute: &Flute) -> Profile {
+    let r_in = flute.bore_radius;
+    let r_out = flute.bore_radius + flute.wall_thickness;
+    let x0 = -head_extension(flute);
+    let x1 = flute.length;
+
+    let mut holes = Vec::new();
+    if flute.instrument.has_embouchure_hole() {
+        holes.push(ProfileMark {
+            number: 0,
+            position: 0.0,
+            radius: flute.embouchure_hole_radius,
+        });
+    }
+    for (n, i) in flute.hole_order().into_iter().enumerate() {
+        holes.push(ProfileMark {
+            number: n + 1,
+            position: flute.holes[i].position,
+            radius: flute.holes[i].radius,
+        });
+    }
+
+    Profile {
+        outer: vec![(x0, r_in), (x0, r_out), (x1, r_out), (x1, r_in)],
+        inner: vec![(x0, r_in), (x1, r_in)],
+        holes,
+    }
+}
+
+/// One row of a reamer grinding table, all in cm
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ReamerStation {
+    /// From the head end of the bore, which is where the reamer's tip enters
+    pub distance: f64,
+    /// Finished bore radius here
+    pub bore_radius: f64,
+    /// Bore radius minus the finishing allowance
+    pub reamer_radius: f64,
+}
+
+/// Bore radius at `x`, interpolated along the profile's bore line
+fn radius_at(inner: &[(f64, f64)], x: f64) -> f64 {
+    for pair in inner.windows(2) {
+        let ((x0, r0), (x1, r1)) = (pair[0], pair[1]);
+        if x <= x1 {
+            let t = if x1 > x0 { (x - x0) / (x1 - x0) } else { 0.0 };
+            return r0 + (r1 - r0) * t.clamp(0.0, 1.0);
+        }
+    }
+    inner.last().map_or(0.0, |p| p.1)
+}
+
+/// Reamer radius every `step` cm along the bore (always including both ends). Steps under
+/// a millimetre, or NaN, are taken as a millimetre.
+pub fn reamer_table(flute: &Flute, allowance: f64, step: f64) -> Vec<ReamerStation> {
+    let step = step.max(MIN_REAMER_STEP);
+    let inner = body_profile(flute).inner;
+    let (start, end) = (inner[0].0, inner[inner.len() - 1].0);
+    let length = end - start;
+    let count = (length / step).ceil().max(1.0) as usize;
+    (0..=count)
+        .map(|k| {
+            let distance = (k as f64 * step).min(length);
+            let bore_radius = radius_at(&inner, start + distance);
+            ReamerStation {
+                distance,
+                bore_radius,
+                reamer_radius: (bore_radius - allowance).max(0.0),
+            }
+        })
+        .collect()
+}
+
+/// Reamer blade outline as a closed DXF polyline (cm, tip at x = 0, axis on y = 0), with the
+/// finished bore on the BORE layer for comparison
+pub fn reamer_dxf(flute: &Flute, allowance: f64) -> String {
+    let table = reamer_table(flute, allowance, DEFAULT_REAMER_STEP);
+    let mut outline: Vec<(f64, f64)> = table
+        .iter()
+        .map(|s| (s.distance, s.reamer_radius))
+        .collect();
+    outline.extend(table.iter().rev().map(|s| (s.distance, -s.reamer_radius)));
+    outline.push(outline[0]);
+
+    let mut dxf = Dxf::new();
+    dxf.polyline("OUTER", &outline);
+    for side in [1.0, -1.0] {
+        let bore: Vec<(f64, f64)> = table
+            .iter()
+            .map(|s| (s.distance, side * s.bore_radius))
+            .collect();
+        dxf.polyline("BORE", &bore);
+    }
+    let last = table.last().map_or(0.0, |s| s.distance);
+    dxf.line("CENTERLINES", 0.0, 0.0, last, 0.0);
+    dxf.finish()
+}
+
+/// The profile as a 1:1 SVG in centimetres, axis at the bottom, for printing a form-tool
+/// or duplicator template. Hole walls are drawn through the wall with their centre marked.
+pub fn profile_svg(flute: &Flute) -> String {
+    let profile = body_profile(flute);
+    let r_out = flute.bore_radius + flute.wall_thickness;
+    let x0 = -head_extension(flute);
+    let width = flute.length - x0 + 2.0 * MARGIN;
+    let height = r_out + 2.0 * MARGIN + 0.5;
+
+    let x_of = |x: f64| MARGIN + x - x0;
+    let axis = MARGIN + 0.5 + r_out;
+    let y_of = |r: f64| axis - r;
+    let points = |line: &[(f64, f64)]| {
+        line.iter()
+            .map(|&(x, r)| format!("{:.3},{:.3}", x_of(x), y_of(r)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w:.3}cm" height="{h:.3}cm" viewBox="0 0 {w:.3} {h:.3}">"#,
+        w = width,
+        h = height
+    );
+    let _ = writeln!(
+        out,
+        r#"<g fill="none" stroke="black" stroke-width="{}" font-family="sans-serif">"#,
+        STROKE
+    );
+    let _ = writeln!(
+        out,
+        r#"<polyline id="outer" points="{}"/>"#,
+        points(&profile.outer)
+    );
+    let _ = writeln!(
+        out,
+        r#"<polyline id="bore" points="{}" stroke="blue"/>"#,
+        points(&profile.inner)
+    );
+    let _ = writeln!(
+        out,
+        r#"<line id="axis" x1="{:.3}" y1="{y:.3}" x2="{:.3}" y2="{y:.3}" stroke-dasharray="0.4 0.2"/>"#,
+        x_of(x0),
+        x_of(flute.length),
+        y = axis
+    );
+    for hole in &profile.holes {
+        let label = if hole.number == 0 {
+            "E".to_string()
+        } else {
+            format!("H{}", hole.number)
+        };
+        let _ = writeln!(out, r#"<g id="mark-{}" stroke="red">"#, hole.number);
+        for x in [hole.position - hole.radius, hole.position + hole.radius] {
+            let _ = writeln!(
+                out,
+                r#"<line x1="{x:.3}" y1="{:.3}" x2="{x:.3}" y2="{:.3}"/>"#,
+                y_of(flute.bore_radius),
+                y_of(r_out),
+                x = x_of(x)
+            );
+        }
+        let _ = writeln!(
+            out,
+            r#"<line x1="{x:.3}" y1="{:.3}" x2="{x:.3}" y2="{:.3}" stroke-dasharray="0.1 0.1"/>"#,
+            y_of(r_out) - 0.3,
+            axis,
+            x = x_of(hole.position)
+        );
+        let _ = writeln!(
+            out,
+            r#"<text x="{:.3}" y="{:.3}" font-size="0.25" text-anchor="middle" fill="black" stroke="none">{}</text>"#,
+            x_of(hole.position),
+            y_of(r_out) - 0.4,
+            label
+        );
+        out.push_str("</g>\n");
+    }
+    out.push_str("</g>\n</svg>\n");
+    out
+}
+
+/// The profile as DXF polylines in centimetres with y = radius, axis on y = 0
+pub fn profile_dxf(flute: &Flute) -> String {
+    let profile = body_profile(flute);
+    let r_out = flute.bore_radius + flute.wall_thickness;
+    let mut dxf = Dxf::new();
+    dxf.polyline("OUTER", &profile.outer);
+    dxf.polyline("BORE", &profile.inner);
+    dxf.line(
+        "CENTERLINES",
+        -head_extension(flute),
+        0.0,
+        flute.length,
+        0.0,
+    );
+    for hole in &profile.holes {
+        for x in [hole.position - hole.radius, hole.position + hole.radius] {
+            dxf.line("HOLES", x, flute.bore_radius, x, r_out);
+        }
+        dxf.line(
+            "CENTERLINES",
+            hole.position,
+            0.0,
+            hole.position,
+            r_out + 0.3,
+        );
+    }
+    dxf.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Hole;
+
+    #[test]
+    fn test_profile_polylines() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, true)).unwrap();
+        flute.holes.push(Hole::new(30.0, 0.3, true)).unwrap();
+        let profile = body_profile(&flute);
+
+        assert_eq!(profile.outer.first(), Some(&(-5.0, 0.95)));
+        assert_eq!(profile.outer[2], (60.0, 1.35));
+        // Embouchure plus holes in playing order
+        let numbers: Vec<(usize, f64)> = profile
+            .holes
+            .iter()
+            .map(|h| (h.number, h.position))
+            .collect();
+        assert_eq!(numbers, vec![(0, 0.0), (1, 30.0), (2, 40.0)]);
+
+        let svg = profile_svg(&flute);
+        // 1 cm margin + 5 cm head stub: the foot end sits at x = 66; axis at y = 2.85
+        assert!(svg.contains(r#"points="1.000,1.900 1.000,1.500 66.000,1.500 66.000,1.900""#));
+        assert!(svg.contains(r#"<g id="mark-2" stroke="red">"#));
+
+        let dxf = profile_dxf(&flute);
+        assert_eq!(dxf.matches("0\nPOLYLINE\n").count(), 2);
+        assert_eq!(dxf.matches("0\nVERTEX\n").count(), 6);
+        assert!(dxf.contains("0\nVERTEX\n8\nOUTER\n10\n60.0000\n20\n1.3500\n"));
+    }
+
+    #[test]
+    fn test_reamer_table() {
+        let flute = Flute::new(60.0, 0.95, 0.4);
+        // 65 cm bore in 2 cm steps: 0, 2, ..., 64, then the end at 65
+        let table = reamer_table(&flute, 0.02, 2.0);
+        assert_eq!(table.len(), 34);
+        assert_eq!(table[0].distance, 0.0);
+        assert_eq!(table[33].distance, 65.0);
+        assert!(table
+            .iter()
+            .all(|s| s.bore_radius == 0.95 && (s.reamer_radius - 0.93).abs() < 1e-12));
+
+        // Too fine a step is taken as 1 mm and still reaches the far end
+        for step in [0.01, 0.0, f64::NAN] {
+            let table = reamer_table(&flute, 0.02, step);
+            assert_eq!(table.len(), 651);
+            assert_eq!(table[1].distance, 0.1);
+            assert_eq!(table.last().unwrap().distance, 65.0);
+        }
+
+        let dxf = reamer_dxf(&flute, 0.02);
+        assert!(dxf.contains("0\nVERTEX\n8\nOUTER\n10\n65.0000\n20\n-0.9300\n"));
+        assert_eq!(dxf.matches("0\nPOLYLINE\n").count(), 3);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::geometry::head_extension;
+use crate::physics::Flute;
+use serde::Serialize;
+
+/// Bumped whenever a field is renamed or removed; new fields may be added within a version
+pub const SPEC_VERSION: u32 = 1;
+pub const SPEC_FORMAT: &str = "flyte-manufacturing-spec";
+
+// Default workshop tolerances (mm), roughly what shifts a note by a couple of cents
+const TOL_LENGTH: f64 = 0.5;
+const TOL_BORE: f64 = 0.05;
+const TOL_WALL: f64 = 0.1;
+const TOL_HOLE_POSITION: f64 = 0.2;
+const TOL_HOLE_DIAMETER: f64 = 0.05;
+const TOL_AZIMUTH: f64 = 2.0; // degrees
+
+/// A nominal dimension with a symmetric (+/-) tolerance, in millimetres (degrees for angles)
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Dimension {
+    pub nominal: f64,
+    pub tolerance: f64,
+}
+
+fn dim(cm: f64, tolerance: f64) -> Dimension {
+    Dimension {
+        nominal: cm * 10.0,
+        tolerance,
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BodySpec {
+    pub total_length: Dimension,
+    pub sounding_length: Dimension,
+    pub head_extension: Dimension,
+    pub bore_diameter: Dimension,
+    pub outer_diameter: Dimension,
+    pub wall_thickness: Dimension,
+    pub cork_position: Dimension,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct EmbouchureSpec {
+    pub position: Dimension,
+    pub diameter: Dimension,
+    pub chimney_height: Dimension,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct HoleSpec {
+    /// 1-based, counted from the embouchure
+    pub number: usize,
+    /// Index into the design's hole list
+    pub index: usize,
+    pub position: Dimension,
+    pub diameter: Dimension,
+    pub depth: Dimension,
+    /// Degrees around the tube, 0 = top
+    pub azimuth: Dimension,
+}
+
+/// Every manufacturing dimension of a design. Positions are measured from the embouchure centre.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ManufacturingSpec {
+    pub format: &'static str,
+    pub version: u32,
+    pub units: &'static str,
+    pub body: BodySpec,
+    pub embouchure: EmbouchureSpec,
+    pub holes: Vec<HoleSpec>,
+}
+
+pub fn manufacturing_spec(flute: &Flute) -> ManufacturingSpec {
+    let holes = flute
+        .hole_order()
+        .into_iter()
+        .enumerate()
+        .map(|(n, index)| {
+            let hole = &flute.holes[index];
+            HoleSpec {
+                number: n + 1,
+                index,
+                position: dim(hole.position, TOL_HOLE_POSITION),
+                diameter: dim(2.0 * hole.radius, TOL_HOLE_DIAMETER),
+                depth: dim(flute.wall_thickness, TOL_WALL),
+                azimuth: Dimension {
+                    nominal: hole.azimuth,
+                    tolerance: TOL_AZIMUTH,
+                },
+            }
+        })
+        .collect();
+
+    let head = head_extension(flute);
+    ManufacturingSpec {
+        format: SPEC_FORMAT,
+        version: SPEC_VERSION,
+        units: "mm",
+        body: BodySpec {
+            total_length: dim(flute.length + head, TOL_LENGTH),
+            sounding_length: dim(flute.length, TOL_LENGTH),
+            head_extension: dim(head, TOL_LENGTH),
+            bore_diameter: dim(2.0 * flute.bore_radius, TOL_BORE),
+            outer_diameter: dim(2.0 * (flute.bore_radius + flute.wall_thickness), TOL_WALL),
+            wall_thickness: dim(flute.wall_thickness, TOL_WALL),
+            cork_position: dim(flute.cork_position, TOL_LENGTH),
+        },
+        embouchure: EmbouchureSpec {
+            position: dim(0.0, TOL_HOLE_POSITION),
+            diameter: dim(2.0 * flute.embouchure_hole_radius, TOL_HOLE_DIAMETER),
+            chimney_height: dim(flute.embouchure_chimney, TOL_WALL),
+        },
+        holes,
+    }
+}
+
+/// Pretty-printed JSON of `manufacturing_spec`
+pub fn export_spec_json(flute: &Flute) -> String {
+    serde_json::to_string_pretty(&manufacturing_spec(flute)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Hole;
+
+    #[test]
+    fn test_spec_json_shape() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, true)).unwrap();
+        flute.holes.push(Hole::new(30.0, 0.3, false)).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&export_spec_json(&flute)).unwrap();
+        assert_eq!(json["format"], SPEC_FORMAT);
+        assert_eq!(json["version"], SPEC_VERSION);
+        assert_eq!(json["units"], "mm");
+        assert_eq!(json["body"]["bore_diameter"]["nominal"], 19.0);
+        assert_eq!(json["holes"][0]["number"], 1);
+        assert_eq!(json["holes"][0]["index"], 1);
+        assert_eq!(json["holes"][0]["position"]["nominal"], 300.0);
+        assert_eq!(json["holes"][1]["diameter"]["tolerance"], TOL_HOLE_DIAMETER);
+    }
+}
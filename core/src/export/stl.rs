@@ -0,0 +1,112 @@
+use crate::geometry::{ExportUnits, Mesh, UpAxis};
+
+/// Binary STL of the printable groups of the mesh, in the chosen units and up axis. STL
+/// has no groups to tell a cutter from the body, so the cutters are left out; a slicer
+/// would print them as plugs in the holes.
+pub fn export_stl(mesh: &Mesh, units: ExportUnits, up_axis: UpAxis) -> Vec<u8> {
+    let points: Vec<[f32; 3]> = mesh
+        .vertices()
+        .iter()
+        .map(|&v| {
+            let (x, y, z) = up_axis.apply(units.scale(v));
+            [x as f32, y as f32, z as f32]
+        })
+        .collect();
+    let tris: Vec<[u32; 3]> = mesh
+        .group_triangles()
+        .into_iter()
+        .filter(|(name, _)| !Mesh::is_cutter(name))
+        .flat_map(|(_, tris)| tris)
+        .collect();
+
+    let mut out = Vec::with_capacity(84 + 50 * tris.len());
+    let mut header = format!(
+        "Flyte Architect binary STL, {}, {}-up",
+        units.label(),
+        up_axis.label()
+    )
+    .into_bytes();
+    header.resize(80, b' ');
+    out.extend_from_slice(&header);
+    out.extend_from_slice(&(tris.len() as u32).to_le_bytes());
+    for tri in &tris {
+        let [a, b, c] = tri.map(|i| points[i as usize]);
+        let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+        let n = [
+            u[1] * v[2] - u[2] * v[1],
+            u[2] * v[0] - u[0] * v[2],
+            u[0] * v[1] - u[1] * v[0],
+        ];
+        let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+        let n = if len > 0.0 {
+            n.map(|c| c / len)
+        } else {
+            [0.0; 3]
+        };
+        for p in [n, a, b, c] {
+            for k in p {
+                out.extend_from_slice(&k.to_le_bytes());
+            }
+        }
+        out.extend_from_slice(&0u16.to_le_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::generate_flute_mesh;
+    use crate::physics::{DuctGeometry, Flute, Hole, InstrumentType};
+
+    #[test]
+    fn test_binary_stl_layout() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, true)).unwrap();
+        let mesh = generate_flute_mesh(&flute);
+        let count: usize = mesh
+            .group_triangles()
+            .iter()
+            .filter(|(name, _)| !Mesh::is_cutter(name))
+            .map(|(_, t)| t.len())
+            .sum();
+        let stl = export_stl(&mesh, ExportUnits::Millimeters, UpAxis::Z);
+
+        assert!(stl.starts_with(b"Flyte Architect binary STL, mm, Z-up"));
+        assert_eq!(
+            u32::from_le_bytes(stl[80..84].try_into().unwrap()) as usize,
+            count
+        );
+        assert_eq!(stl.len(), 84 + 50 * count);
+        // Every facet normal is unit length (or zero for a degenerate sliver)
+        for facet in stl[84..].chunks(50) {
+            let n: Vec<f32> = facet[..12]
+                .chunks(4)
+                .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+                .collect();
+            let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+            assert!(len == 0.0 || (len - 1.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_stl_leaves_out_cutters() {
+        let mut flute = Flute::new(30.0, 0.65, 0.3);
+        flute.instrument = InstrumentType::Duct(DuctGeometry::default());
+        flute.holes.push(Hole::new(20.0, 0.3, true)).unwrap();
+        let mesh = generate_flute_mesh(&flute);
+        let groups = mesh.group_triangles();
+        let body: usize = groups
+            .iter()
+            .filter(|(name, _)| !Mesh::is_cutter(name))
+            .map(|(_, t)| t.len())
+            .sum();
+        assert!(groups.iter().any(|(name, _)| *name == "WindwayCutter"));
+        let stl = export_stl(&mesh, ExportUnits::Millimeters, UpAxis::Z);
+        assert_eq!(
+            u32::from_le_bytes(stl[80..84].try_into().unwrap()) as usize,
+            body
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use crate::geometry::head_extension;
+use crate::physics::Flute;
+use std::f64::consts::PI;
+use std::fmt::Write;
+
+const MARGIN: f64 = 1.0; // cm around the template
+const RULER_HEIGHT: f64 = 1.2; // cm reserved above the tube outline
+const STROKE: f64 = 0.02; // cm, hairline for cutting/drilling marks
+
+/// Generate a 1:1 wrap-around drilling template as SVG (user units are centimetres).
+///
+/// The unrolled tube runs left to right from the head end (behind the embouchure) to the foot,
+/// with the circumference vertical. Top-side holes (azimuth 0) sit on the centre line, the seam
+/// at the top/bottom edges, so thumb holes (azimuth 180) land on the seam.
+/// The ruler is measured from the embouchure centre, the same origin as hole positions.
+pub fn drill_template(flute: &Flute) -> String {
+    let outer_radius = flute.bore_radius + flute.wall_thickness;
+    let circumference = 2.0 * PI * outer_radius;
+    let head = head_extension(flute);
+    let body_len = flute.length + head;
+
+    let width = body_len + 2.0 * MARGIN;
+    let height = circumference + RULER_HEIGHT + 2.0 * MARGIN;
+
+    // Map axial position (cm from embouchure) to SVG x
+    let x_of = |pos: f64| MARGIN + head + pos;
+    let top = MARGIN + RULER_HEIGHT;
+    let center_y = top + circumference / 2.0;
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w:.3}cm" height="{h:.3}cm" viewBox="0 0 {w:.3} {h:.3}">"#,
+        w = width,
+        h = height
+    );
+    let _ = writeln!(
+        out,
+        r#"<g fill="none" stroke="black" stroke-width="{}" font-family="sans-serif">"#,
+        STROKE
+    );
+
+    // Unrolled tube outline and hole centre line
+    let _ = writeln!(
+        out,
+        r#"<rect id="outline" x="{:.3}" y="{:.3}" width="{:.3}" height="{:.3}"/>"#,
+        x_of(-head),
+        top,
+        body_len,
+        circumference
+    );
+    let _ = writeln!(
+        out,
+        r#"<line id="centerline" x1="{:.3}" y1="{y:.3}" x2="{:.3}" y2="{y:.3}" stroke-dasharray="0.4 0.2"/>"#,
+        x_of(-head),
+        x_of(flute.length),
+        y = center_y
+    );
+
+    // Axial ruler: mm ticks, longer every 5 mm, labelled every cm
+    out.push_str("<g id=\"ruler\">\n");
+    let first_mm = (-head * 10.0).ceil() as i64;
+    let last_mm = (flute.length * 10.0).floor() as i64;
+    for mm in first_mm..=last_mm {
+        let x = x_of(mm as f64 / 10.0);
+        let tick = if mm % 10 == 0 {
+            0.5
+        } else if mm % 5 == 0 {
+            0.35
+        } else {
+            0.2
+        };
+        let _ = writeln!(
+            out,
+            r#"<line x1="{x:.3}" y1="{:.3}" x2="{x:.3}" y2="{:.3}"/>"#,
+            top - tick,
+            top,
+            x = x
+        );
+        if mm % 10 == 0 {
+            let _ = writeln!(
+                out,
+                r#"<text x="{:.3}" y="{:.3}" font-size="0.3" text-anchor="middle" fill="black" stroke="none">{}</text>"#,
+                x,
+                top - 0.6,
+                mm / 10
+            );
+        }
+    }
+    out.push_str("</g>\n");
+
+    // Embouchure
+    draw_hole(
+        &mut out,
+        "embouchure",
+        x_of(0.0),
+        center_y,
+        flute.embouchure_hole_radius,
+        "E",
+    );
+
+    // Tone holes, numbered from the embouchure end as the player counts them
+    for (n, &i) in flute.hole_order().iter().enumerate() {
+        let hole = &flute.holes[i];
+        let label = format!(
+            "H{} {:.1} / ⌀{:.1}mm",
+            n + 1,
+            hole.position,
+            hole.radius * 20.0
+        );
+        // Azimuth moves the hole around the circumference, wrapping across the seam
+        let offset = (hole.azimuth / 360.0).rem_euclid(1.0) * circumference;
+        let y = top + (circumference / 2.0 + offset) % circumference;
+        draw_hole(
+            &mut out,
+            &format!("hole-{}", n + 1),
+            x_of(hole.position),
+            y,
+            hole.radius,
+            &label,
+        );
+    }
+
+    out.push_str("</g>\n</svg>\n");
+    out
+}
+
+/// Circle at the finished diameter plus a centre-punch crosshair and a label above it
+fn draw_hole(out: &mut String, id: &str, cx: f64, cy: f64, radius: f64, label: &str) {
+    let arm = radius + 0.2;
+    let _ = writeln!(out, r#"<g id="{}">"#, id);
+    let _ = writeln!(
+        out,
+        r#"<circle cx="{:.3}" cy="{:.3}" r="{:.3}"/>"#,
+        cx, cy, radius
+    );
+    let _ = writeln!(
+        out,
+        r#"<line x1="{:.3}" y1="{cy:.3}" x2="{:.3}" y2="{cy:.3}"/>"#,
+        cx - arm,
+        cx + arm,
+        cy = cy
+    );
+    let _ = writeln!(
+        out,
+        r#"<line x1="{cx:.3}" y1="{:.3}" x2="{cx:.3}" y2="{:.3}"/>"#,
+        cy - arm,
+        cy + arm,
+        cx = cx
+    );
+    let _ = writeln!(
+        out,
+        r#"<text x="{:.3}" y="{:.3}" font-size="0.25" text-anchor="middle" fill="black" stroke="none">{}</text>"#,
+        cx,
+        cy - arm - 0.1,
+        label
+    );
+    out.push_str("</g>\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Hole;
+
+    #[test]
+    fn test_drill_template_is_one_to_one() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, false)).unwrap();
+        flute.holes.push(Hole::new(30.0, 0.3, true)).unwrap();
+        let svg = drill_template(&flute);
+
+        // 65 cm body + 2 cm margin
+        assert!(svg.contains(r#"width="67.000cm""#));
+        // Closed holes still need drilling; numbering follows position
+        assert!(svg.contains(r#"<g id="hole-2">"#));
+        assert!(svg.contains("H1 30.0 / ⌀6.0mm"));
+        // Hole at 40 cm from the embouchure: 1 cm margin + 5 cm head stub + 40
+        assert!(svg.contains(r#"<circle cx="46.000""#));
+    }
+}
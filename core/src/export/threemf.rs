@@ -0,0 +1,192 @@
+use crate::geometry::{ExportUnits, Mesh, UpAxis};
+use std::fmt::Write;
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+ <Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+ <Default Extension="model" ContentType="application/vnd.ms-package.3dmanufacturing-3dmodel+xml"/>
+</Types>
+"#;
+
+const RELS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+ <Relationship Target="/3D/3dmodel.model" Id="rel0" Type="http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel"/>
+</Relationships>
+"#;
+
+/// 3MF package with one named object per printable mesh group, all placed on the build
+/// plate. The boolean cutters are left out: as objects they would print as plugs.
+/// 3MF is Z-up by definition, so the up axis is fixed; units follow the export setting.
+pub fn export_3mf(mesh: &Mesh, units: ExportUnits) -> Vec<u8> {
+    let unit = match units {
+        ExportUnits::Millimeters => "millimeter",
+        ExportUnits::Centimeters => "centimeter",
+        ExportUnits::Inches => "inch",
+    };
+    let mut model = String::new();
+    let _ = writeln!(model, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        model,
+        r#"<model unit="{}" xml:lang="en-US" xmlns="http://schemas.microsoft.com/3dmanufacturing/core/2015/02">"#,
+        unit
+    );
+    let _ = writeln!(
+        model,
+        r#" <metadata name="Application">Flyte Architect</metadata>"#
+    );
+    let _ = writeln!(model, " <resources>");
+
+    let mut groups = mesh.group_triangles();
+    groups.retain(|(name, _)| !Mesh::is_cutter(name));
+    for (id, (name, tris)) in groups.iter().enumerate() {
+        // Each object carries only the vertices its triangles use
+        let mut remap = vec![u32::MAX; mesh.vertices().len()];
+        let mut used = Vec::new();
+        for &i in tris.iter().flatten() {
+            if remap[i as usize] == u32::MAX {
+                remap[i as usize] = used.len() as u32;
+                used.push(i);
+            }
+        }
+        let _ = writeln!(
+            model,
+            r#"  <object id="{}" name="{}" type="model">"#,
+            id + 1,
+            name
+        );
+        let _ = writeln!(model, "   <mesh>\n    <vertices>");
+        for &i in &used {
+            let (x, y, z) = UpAxis::Z.apply(units.scale(mesh.vertices()[i as usize]));
+            let _ = writeln!(
+                model,
+                r#"     <vertex x="{:.4}" y="{:.4}" z="{:.4}"/>"#,
+                x, y, z
+            );
+        }
+        let _ = writeln!(model, "    </vertices>\n    <triangles>");
+        for tri in tris {
+            let [a, b, c] = tri.map(|i| remap[i as usize]);
+            let _ = writeln!(
+                model,
+                r#"     <triangle v1="{}" v2="{}" v3="{}"/>"#,
+                a, b, c
+            );
+        }
+        let _ = writeln!(model, "    </triangles>\n   </mesh>\n  </object>");
+    }
+    let _ = writeln!(model, " </resources>\n <build>");
+    for id in 1..=groups.len() {
+        let _ = writeln!(model, r#"  <item objectid="{}"/>"#, id);
+    }
+    let _ = writeln!(model, " </build>\n</model>");
+
+    store_zip(&[
+        ("[Content_Types].xml", CONTENT_TYPES.as_bytes()),
+        ("_rels/.rels", RELS.as_bytes()),
+        ("3D/3dmodel.model", model.as_bytes()),
+    ])
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Zip archive with every entry stored uncompressed, which 3MF readers accept
+fn store_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut central = Vec::new();
+    for &(name, data) in files {
+        let offset = out.len() as u32;
+        let crc = crc32(data);
+        let size = data.len() as u32;
+        let common = |buf: &mut Vec<u8>| {
+            buf.extend_from_slice(&20u16.to_le_bytes()); // version needed
+            buf.extend_from_slice(&0u16.to_le_bytes()); // flags
+            buf.extend_from_slice(&0u16.to_le_bytes()); // stored
+            buf.extend_from_slice(&0u16.to_le_bytes()); // mod time
+            buf.extend_from_slice(&0x21u16.to_le_bytes()); // mod date 1980-01-01
+            buf.extend_from_slice(&crc.to_le_bytes());
+            buf.extend_from_slice(&size.to_le_bytes());
+            buf.extend_from_slice(&size.to_le_bytes());
+            buf.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            buf.extend_from_slice(&0u16.to_le_bytes()); // extra length
+        };
+
+        out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        common(&mut out);
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(data);
+
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        common(&mut central);
+        central.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        central.extend_from_slice(&0u16.to_le_bytes()); // disk number
+        central.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+        central.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    let central_offset = out.len() as u32;
+    out.extend_from_slice(&central);
+    out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes());
+    out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    out.extend_from_slice(&central_offset.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::generate_flute_mesh;
+    use crate::physics::{DuctGeometry, Flute, Hole, InstrumentType};
+
+    #[test]
+    fn test_3mf_package() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, true)).unwrap();
+        let package = export_3mf(&generate_flute_mesh(&flute), ExportUnits::Millimeters);
+        assert!(package.starts_with(b"PK\x03\x04"));
+        // End of central directory lists the three parts
+        let end = package.len() - 22;
+        assert_eq!(&package[end..end + 4], b"PK\x05\x06");
+        assert_eq!(
+            u16::from_le_bytes([package[end + 10], package[end + 11]]),
+            3
+        );
+
+        let text = String::from_utf8_lossy(&package);
+        assert!(text.contains(r#"<model unit="millimeter""#));
+        assert!(text.contains(r#"name="TubeBody""#));
+        assert!(text.contains(r#"<item objectid="1"/>"#));
+
+        // A duct flute's windway, window and labium cutters stay out of the package
+        flute.instrument = InstrumentType::Duct(DuctGeometry::default());
+        let package = export_3mf(&generate_flute_mesh(&flute), ExportUnits::Millimeters);
+        let text = String::from_utf8_lossy(&package);
+        assert!(!text.contains("Cutter"));
+        assert_eq!(
+            text.matches("<object ").count(),
+            text.matches("<item ").count()
+        );
+    }
+}
@@ -0,0 +1,405 @@
+//! C API for embedding the engine natively (mobile apps, audio plugins). The header is
+//! generated into `core/include/flyte.h` when building with the `ffi` feature.
+//!
+//! Functions that can fail return a `FlyteStatus`; `flyte_last_error` then describes the
+//! failure on the calling thread. Buffers and strings handed out by the library must be
+//! released with `flyte_buffer_free` / `flyte_string_free`.
+
+use crate::document::DesignDocument;
+use crate::error::FlyteError;
+use crate::export;
+use crate::geometry::{self, ExportUnits, MeshOptions, UpAxis};
+use crate::physics::{Flute, Hole, Holes};
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+/// Result of a fallible call. Matches the `code` of `FlyteError`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlyteStatus {
+    Ok = 0,
+    NullPointer = 1,
+    InvalidGeometry = 2,
+    FailedCheck = 3,
+    IndexOutOfBounds = 4,
+    SolverDidNotConverge = 5,
+    InvalidInput = 6,
+    Serialization = 7,
+}
+
+/// Mesh file format for `flyte_engine_export_mesh`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlyteMeshFormat {
+    /// OBJ text, one group per part
+    Obj = 0,
+    /// Binary STL
+    Stl = 1,
+    ThreeMf = 2,
+    /// Binary glTF, metres, Y-up
+    Glb = 3,
+}
+
+/// Bytes owned by the library
+#[repr(C)]
+pub struct FlyteBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+/// Opaque handle to a design and its export settings
+pub struct FlyteEngine {
+    flute: Flute,
+    mesh_options: MeshOptions,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn fail(error: FlyteError) -> FlyteStatus {
+    let status = match error {
+        FlyteError::InvalidGeometry { .. } => FlyteStatus::InvalidGeometry,
+        FlyteError::FailedCheck { .. } => FlyteStatus::FailedCheck,
+        FlyteError::IndexOutOfBounds { .. } => FlyteStatus::IndexOutOfBounds,
+        FlyteError::SolverDidNotConverge { .. } => FlyteStatus::SolverDidNotConverge,
+        FlyteError::InvalidInput { .. } => FlyteStatus::InvalidInput,
+        FlyteError::Serialization { .. } => FlyteStatus::Serialization,
+    };
+    let message = CString::new(error.to_string()).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+    status
+}
+
+fn null_pointer() -> FlyteStatus {
+    LAST_ERROR.with(|e| *e.borrow_mut() = CString::new("null pointer argument").ok());
+    FlyteStatus::NullPointer
+}
+
+/// Message for the last failed call on this thread, or NULL. Valid until the next failing
+/// call on the same thread; do not free.
+#[no_mangle]
+pub extern "C" fn flyte_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |m| m.as_ptr()))
+}
+
+/// A new engine with no holes, or NULL if the dimensions (cm) are invalid
+#[no_mangle]
+pub extern "C" fn flyte_engine_new(
+    length: f64,
+    bore_radius: f64,
+    wall_thickness: f64,
+) -> *mut FlyteEngine {
+    let flute = Flute::new(length, bore_radius, wall_thickness);
+    if let Err(error) = flute.validate() {
+        fail(error);
+        return ptr::null_mut();
+    }
+    Box::into_raw(Box::new(FlyteEngine {
+        flute,
+        mesh_options: MeshOptions::default(),
+    }))
+}
+
+/// Load a design saved by `flyte_engine_to_json` or the web app. NULL on failure.
+///
+/// # Safety
+/// `json` must be NULL or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn flyte_engine_from_json(json: *const c_char) -> *mut FlyteEngine {
+    if json.is_null() {
+        null_pointer();
+        return ptr::null_mut();
+    }
+    let loaded = CStr::from_ptr(json)
+        .to_str()
+        .map_err(FlyteError::invalid_input)
+        .and_then(DesignDocument::from_json)
+        .map(|doc| doc.to_flute())
+        .and_then(|flute| flute.validate().map(|_| flute));
+    match loaded {
+        Ok(flute) => Box::into_raw(Box::new(FlyteEngine {
+            flute,
+            mesh_options: MeshOptions::default(),
+        })),
+        Err(error) => {
+            fail(error);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+/// `engine` must be NULL or a pointer from `flyte_engine_new` / `flyte_engine_from_json`
+/// that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn flyte_engine_free(engine: *mut FlyteEngine) {
+    if !engine.is_null() {
+        drop(Box::from_raw(engine));
+    }
+}
+
+/// Set the tube dimensions (cm). Pass NaN to keep a value.
+///
+/// # Safety
+/// `engine` must be a live engine pointer.
+#[no_mangle]
+pub unsafe extern "C" fn flyte_engine_set_geometry(
+    engine: *mut FlyteEngine,
+    length: f64,
+    bore_radius: f64,
+    wall_thickness: f64,
+) -> FlyteStatus {
+    let Some(engine) = engine.as_mut() else {
+        return null_pointer();
+    };
+    let mut candidate = engine.flute.clone();
+    for (value, field) in [
+        (length, &mut candidate.length),
+        (bore_radius, &mut candidate.bore_radius),
+        (wall_thickness, &mut candidate.wall_thickness),
+    ] {
+        if !value.is_nan() {
+            *field = value;
+        }
+    }
+    match candidate.validate() {
+        Ok(()) => {
+            engine.flute = candidate;
+            FlyteStatus::Ok
+        }
+        Err(error) => fail(error),
+    }
+}
+
+/// Replace every hole. `open` may be NULL for all open; otherwise nonzero = open.
+///
+/// # Safety
+/// `engine` must be a live engine pointer; `positions` and `radii` must point to `count`
+/// doubles and `open` (if not NULL) to `count` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn flyte_engine_set_holes(
+    engine: *mut FlyteEngine,
+    positions: *const f64,
+    radii: *const f64,
+    open: *const u8,
+    count: usize,
+) -> FlyteStatus {
+    let Some(engine) = engine.as_mut() else {
+        return null_pointer();
+    };
+    if count > 0 && (positions.is_null() || radii.is_null()) {
+        return null_pointer();
+    }
+    let mut candidate = engine.flute.clone();
+    let holes = (0..count)
+        .map(|i| {
+            let is_open = open.is_null() || *open.add(i) != 0;
+            Hole::new(*positions.add(i), *radii.add(i), is_open)
+        })
+        .collect();
+    // Checked as a whole below, so errors name the hole's index
+    candidate.holes = Holes::from_unchecked(holes);
+    match candidate.validate() {
+        Ok(()) => {
+            engine.flute = candidate;
+            FlyteStatus::Ok
+        }
+        Err(error) => fail(error),
+    }
+}
+
+/// Open or close one hole
+///
+/// # Safety
+/// `engine` must be a live engine pointer.
+#[no_mangle]
+pub unsafe extern "C" fn flyte_engine_set_hole_open(
+    engine: *mut FlyteEngine,
+    index: usize,
+    open: bool,
+) -> FlyteStatus {
+    let Some(engine) = engine.as_mut() else {
+        return null_pointer();
+    };
+    let len = engine.flute.holes.len();
+    match engine.flute.holes.get_mut(index) {
+        Some(hole) => {
+            hole.open = open;
+            FlyteStatus::Ok
+        }
+        None => fail(FlyteError::IndexOutOfBounds { index, len }),
+    }
+}
+
+/// Sounding pitch (Hz) of the current open/closed state into `out_hz`. NaN jet velocity
+/// uses the default.
+///
+/// # Safety
+/// `engine` must be a live engine pointer and `out_hz` writable.
+#[no_mangle]
+pub unsafe extern "C" fn flyte_engine_calculate_pitch(
+    engine: *mut FlyteEngine,
+    jet_velocity: f64,
+    out_hz: *mut f64,
+) -> FlyteStatus {
+    let (Some(engine), false) = (engine.as_mut(), out_hz.is_null()) else {
+        return null_pointer();
+    };
+    match engine.flute.checked_pitch(jet_velocity) {
+        Ok(pitch) => {
+            *out_hz = pitch;
+            FlyteStatus::Ok
+        }
+        Err(error) => fail(error),
+    }
+}
+
+/// Units and up axis used by `flyte_engine_export_mesh`: 0 = mm, 1 = cm, 2 = inches;
+/// `z_up` false = Y-up
+///
+/// # Safety
+/// `engine` must be a live engine pointer.
+#[no_mangle]
+pub unsafe extern "C" fn flyte_engine_set_export_units(
+    engine: *mut FlyteEngine,
+    units: u32,
+    z_up: bool,
+) -> FlyteStatus {
+    let Some(engine) = engine.as_mut() else {
+        return null_pointer();
+    };
+    engine.mesh_options.units = match units {
+        0 => ExportUnits::Millimeters,
+        1 => ExportUnits::Centimeters,
+        2 => ExportUnits::Inches,
+        _ => {
+            return fail(FlyteError::invalid_input(format!(
+                "unknown units {}",
+                units
+            )))
+        }
+    };
+    engine.mesh_options.up_axis = if z_up { UpAxis::Z } else { UpAxis::Y };
+    FlyteStatus::Ok
+}
+
+/// Build the export mesh in `format` into `out`, to be freed with `flyte_buffer_free`
+///
+/// # Safety
+/// `engine` must be a live engine pointer and `out` writable.
+#[no_mangle]
+pub unsafe extern "C" fn flyte_engine_export_mesh(
+    engine: *const FlyteEngine,
+    format: FlyteMeshFormat,
+    out: *mut FlyteBuffer,
+) -> FlyteStatus {
+    let (Some(engine), false) = (engine.as_ref(), out.is_null()) else {
+        return null_pointer();
+    };
+    let opts = &engine.mesh_options;
+    let mesh = geometry::generate_flute_mesh_with(&engine.flute, opts);
+    let bytes = match format {
+        FlyteMeshFormat::Obj => mesh.to_obj_string(opts.units, opts.up_axis).into_bytes(),
+        FlyteMeshFormat::Stl => export::stl::export_stl(&mesh, opts.units, opts.up_axis),
+        FlyteMeshFormat::ThreeMf => export::threemf::export_3mf(&mesh, opts.units),
+        FlyteMeshFormat::Glb => export::gltf::export_glb(&mesh),
+    };
+    let mut bytes = bytes.into_boxed_slice();
+    *out = FlyteBuffer {
+        data: bytes.as_mut_ptr(),
+        len: bytes.len(),
+    };
+    std::mem::forget(bytes);
+    FlyteStatus::Ok
+}
+
+/// # Safety
+/// `buffer` must come from this library and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn flyte_buffer_free(buffer: FlyteBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            buffer.data,
+            buffer.len,
+        )));
+    }
+}
+
+/// The design as a versioned JSON document, to be freed with `flyte_string_free`.
+/// NULL if `engine` is NULL.
+///
+/// # Safety
+/// `engine` must be NULL or a live engine pointer.
+#[no_mangle]
+pub unsafe extern "C" fn flyte_engine_to_json(engine: *const FlyteEngine) -> *mut c_char {
+    let Some(engine) = engine.as_ref() else {
+        null_pointer();
+        return ptr::null_mut();
+    };
+    let json = DesignDocument::new(&engine.flute).to_json();
+    CString::new(json).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// # Safety
+/// `s` must be NULL or a string returned by this library that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn flyte_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_c_api_round_trip() {
+        unsafe {
+            assert!(flyte_engine_new(-1.0, 0.95, 0.4).is_null());
+            let message = CStr::from_ptr(flyte_last_error()).to_str().unwrap();
+            assert!(message.contains("length"));
+
+            let engine = flyte_engine_new(60.0, 0.95, 0.4);
+            let positions = [40.0, 35.0];
+            let radii = [0.35, 0.35];
+            let status =
+                flyte_engine_set_holes(engine, positions.as_ptr(), radii.as_ptr(), ptr::null(), 2);
+            assert_eq!(status, FlyteStatus::Ok);
+            assert_eq!(
+                flyte_engine_set_hole_open(engine, 5, false),
+                FlyteStatus::IndexOutOfBounds
+            );
+
+            let mut pitch = 0.0;
+            let status = flyte_engine_calculate_pitch(engine, f64::NAN, &mut pitch);
+            assert_eq!(status, FlyteStatus::Ok);
+            let mut expected = Flute::new(60.0, 0.95, 0.4);
+            expected.holes = Holes::try_from(vec![
+                Hole::new(40.0, 0.35, true),
+                Hole::new(35.0, 0.35, true),
+            ])
+            .unwrap();
+            assert_eq!(pitch, expected.calculate_pitch(f64::NAN));
+
+            let mut stl = FlyteBuffer {
+                data: ptr::null_mut(),
+                len: 0,
+            };
+            let status = flyte_engine_export_mesh(engine, FlyteMeshFormat::Stl, &mut stl);
+            assert_eq!(status, FlyteStatus::Ok);
+            assert!(stl.len > 84);
+            flyte_buffer_free(stl);
+
+            let json = flyte_engine_to_json(engine);
+            let copy = flyte_engine_from_json(json);
+            assert!(!copy.is_null());
+            assert_eq!((*copy).flute.holes, (*engine).flute.holes);
+            flyte_string_free(json);
+            flyte_engine_free(copy);
+            flyte_engine_free(engine);
+        }
+    }
+}
@@ -0,0 +1,522 @@
+//! The sound field inside the bore at one frequency: pressure along the tube over a
+//! period, for animating the standing wave as it breathes through the open holes, and the
+//! nodes and antinodes it implies for placing vents and tuning holes, how strongly each
+//! opening radiates and the far-field pattern those openings make together, and which part
+//! of the air column sets the input admittance.
+
+use crate::math;
+use crate::physics::{Flute, SPEED_OF_SOUND};
+use num_complex::Complex64;
+use serde::Serialize;
+use std::f64::consts::PI;
+
+/// Most points along the bore `standing_wave` samples
+pub const MAX_POINTS: usize = 2000;
+/// Most snapshots per period `standing_wave` returns
+pub const MAX_FRAMES: usize = 240;
+/// Pressure, relative to the loudest point of the bore, below which a vent hole hardly
+/// disturbs the note
+const VENT_LEVEL: f64 = 0.2;
+/// Relative pressure above which a small hole or a change to one moves the note most
+const TUNING_LEVEL: f64 = 0.8;
+/// Node and antinode candidates must be at least this far below / above half the peak, so
+/// the kinks at closed holes and the fading wave past the open ones do not count
+const EXTREMUM_LEVEL: f64 = 0.5;
+/// Most directions `directivity` evaluates
+pub const MAX_ANGLES: usize = 720;
+/// Floor of the directivity pattern, dB: the nulls of an ideal model are minus infinity
+const MIN_LEVEL: f64 = -60.0;
+
+/// Pressure along the bore at evenly spaced moments of one period
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct StandingWave {
+    pub frequency: f64,
+    /// Where the pressure is sampled, cm from the embouchure to the foot
+    pub positions: Vec<f64>,
+    /// `frames[t][i]` is the pressure at `positions[i]` a fraction t / frames.len() into the
+    /// period, scaled so the largest swing anywhere along the bore is 1
+    pub frames: Vec<Vec<f64>>,
+}
+
+/// `frames` snapshots of the pressure at `points` positions from the embouchure to the
+/// foot, for the design's current fingering sounding at `frequency` (normally one of its
+/// resonances). Each snapshot is the real part of the complex pressure profile turned by
+/// the phase of that moment, so nodes stay still and the antinodes swing.
+pub fn standing_wave(flute: &Flute, frequency: f64, points: usize, frames: usize) -> StandingWave {
+    let frames = frames.clamp(1, MAX_FRAMES);
+    let (positions, pressure) = profile(flute, frequency, points);
+    let frames = (0..frames)
+        .map(|t| {
+            let phase = 2.0 * PI * t as f64 / frames as f64;
+            let (sin, cos) = phase.sin_cos();
+            pressure.iter().map(|p| p.re * cos - p.im * sin).collect()
+        })
+        .collect();
+    StandingWave {
+        frequency,
+        positions,
+        frames,
+    }
+}
+
+/// A stretch of the tube, cm from the embouchure
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Where the pressure of one note stands still and where it swings hardest, and what that
+/// means for drilling
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NodeHints {
+    pub frequency: f64,
+    /// Pressure nodes inside the tube, cm from the embouchure. The node at the blowing end
+    /// sits just outside it, beyond the end correction, and is not listed.
+    pub nodes: Vec<f64>,
+    /// Pressure maxima, cm from the embouchure
+    pub antinodes: Vec<f64>,
+    /// Around each node: a small vent here leaves this note nearly untouched while
+    /// upsetting the modes that do swing here, e.g. an octave vent for the second mode
+    pub vent_regions: Vec<Region>,
+    /// Around each antinode: opening, enlarging or undercutting a hole here moves this
+    /// note the most
+    pub tuning_regions: Vec<Region>,
+}
+
+/// Nodes, antinodes and placement regions of the pressure at `frequency` for the design's
+/// current fingering, sampled every millimetre (up to `MAX_POINTS` samples)
+pub fn node_hints(flute: &Flute, frequency: f64) -> NodeHints {
+    let points = (flute.length * 10.0) as usize + 1;
+    let (positions, pressure) = profile(flute, frequency, points);
+    let level: Vec<f64> = pressure.iter().map(|&p| math::norm(p)).collect();
+    let n = level.len();
+    let interior = 1..n.saturating_sub(1);
+    let nodes: Vec<usize> = interior
+        .clone()
+        .filter(|&i| {
+            level[i] < EXTREMUM_LEVEL && level[i] < level[i - 1] && level[i] <= level[i + 1]
+        })
+        .collect();
+    let antinodes: Vec<usize> = interior
+        .filter(|&i| {
+            level[i] > EXTREMUM_LEVEL && level[i] > level[i - 1] && level[i] >= level[i + 1]
+        })
+        .collect();
+    // The run of samples around `i` for which `inside` holds
+    let region = |i: usize, inside: &dyn Fn(f64) -> bool| {
+        let start = (0..i)
+            .rev()
+            .find(|&j| !inside(level[j]))
+            .map_or(0, |j| j + 1);
+        let end = (i..n).find(|&j| !inside(level[j])).map_or(n - 1, |j| j - 1);
+        Region {
+            start: positions[start],
+            end: positions[end],
+        }
+    };
+    NodeHints {
+        frequency,
+        vent_regions: nodes
+            .iter()
+            .map(|&i| region(i, &|l| l < VENT_LEVEL))
+            .filter(|r| r.end > r.start)
+            .collect(),
+        tuning_regions: antinodes
+            .iter()
+            .map(|&i| region(i, &|l| l > TUNING_LEVEL))
+            .collect(),
+        nodes: nodes.iter().map(|&i| positions[i]).collect(),
+        antinodes: antinodes.iter().map(|&i| positions[i]).collect(),
+    }
+}
+
+/// Which opening of the tube a `Source` is
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Opening {
+    /// The embouchure hole, window or open blowing end
+    Embouchure,
+    /// An open tone hole, by index into `holes`
+    Hole(usize),
+    Foot,
+}
+
+/// One opening radiating sound. Every opening is small next to the wavelength, so it
+/// radiates as a simple source whose far-field pressure is proportional to the volume flow
+/// through it.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Source {
+    pub opening: Opening,
+    /// cm from the embouchure
+    pub position: f64,
+    /// Flow magnitude, the strongest opening = 1
+    pub strength: f64,
+    /// The same in dB, 0 for the strongest opening
+    pub level: f64,
+    /// Radians, relative to the foot
+    pub phase: f64,
+}
+
+/// A virtual microphone at every opening of the current fingering sounding at `frequency`:
+/// the embouchure, each open hole front to back, then the foot. Shows which openings carry
+/// the sound, e.g. how much of a cross-fingered note still leaves by the foot.
+pub fn sources(flute: &Flute, frequency: f64) -> Vec<Source> {
+    let field = flute
+        .bore(&flute.hole_order_back_to_front())
+        .field(frequency);
+    let mut flows = vec![(Opening::Embouchure, 0.0, field.mouth_flow())];
+    flows.extend(
+        field
+            .hole_flows()
+            .iter()
+            .rev()
+            .map(|&(i, flow)| (Opening::Hole(i), flute.holes[i].position, flow)),
+    );
+    flows.push((Opening::Foot, flute.length, field.foot_flow()));
+    let loudest = flows
+        .iter()
+        .fold(0.0f64, |m, &(_, _, flow)| m.max(math::norm(flow)));
+    flows
+        .into_iter()
+        .map(|(opening, position, flow)| {
+            let strength = if loudest > 0.0 && loudest.is_finite() {
+                math::norm(flow) / loudest
+            } else {
+                0.0
+            };
+            Source {
+                opening,
+                position,
+                strength,
+                level: 20.0 * strength.log10(),
+                phase: math::arg(flow),
+            }
+        })
+        .collect()
+}
+
+/// Far-field level around the instrument, for a polar plot
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Directivity {
+    pub frequency: f64,
+    /// Degrees from the axis: 0 points straight out of the foot, 180 out past the
+    /// embouchure, 90 and 270 broadside
+    pub angles: Vec<f64>,
+    /// dB relative to the loudest direction, floored at -60
+    pub levels: Vec<f64>,
+}
+
+/// First-order far-field pattern of the note at `frequency`, at `points` evenly spaced
+/// angles (up to `MAX_ANGLES`): the `sources` taken as simple sources on the tube's axis,
+/// summed with the path difference each has to a distant listener. Ignores the body's
+/// shadow and the holes' rotation around the tube, so the pattern is symmetric about the
+/// axis.
+pub fn directivity(flute: &Flute, frequency: f64, points: usize) -> Directivity {
+    let points = points.clamp(4, MAX_ANGLES);
+    let k = 2.0 * PI * frequency / SPEED_OF_SOUND;
+    let sources = sources(flute, frequency);
+    let angles: Vec<f64> = (0..points)
+        .map(|i| 360.0 * i as f64 / points as f64)
+        .collect();
+    let amplitude: Vec<f64> = angles
+        .iter()
+        .map(|angle| {
+            let along = angle.to_radians().cos();
+            let total: Complex64 = sources
+                .iter()
+                .map(|s| Complex64::from_polar(s.strength, s.phase + k * s.position * along))
+                .sum();
+            math::norm(total)
+        })
+        .collect();
+    let loudest = amplitude.iter().fold(0.0f64, |m, &a| m.max(a));
+    let levels = amplitude
+        .iter()
+        .map(|&a| {
+            if loudest > 0.0 {
+                (20.0 * (a / loudest).log10()).max(MIN_LEVEL)
+            } else {
+                MIN_LEVEL
+            }
+        })
+        .collect();
+    Directivity {
+        frequency,
+        angles,
+        levels,
+    }
+}
+
+/// One part of the air column in an `AdmittanceBreakdown`
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Element {
+    /// The embouchure hole, window or open blowing end
+    Embouchure,
+    /// The closed stub behind the embouchure
+    Cork,
+    /// The bore between two holes or branches, cm from the embouchure
+    Bore { from: f64, to: f64 },
+    /// A tone hole, by index into `holes`, open or closed as the current fingering has it
+    Hole(usize),
+    /// A side resonator, by position
+    Resonator(f64),
+    /// An organ pipe's tuning slot
+    TuningSlot,
+    /// The open foot, or the stopper of a stopped pipe
+    Foot,
+}
+
+/// What one element adds to the input admittance, in cm^4 s/g
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Contribution {
+    pub element: Element,
+    pub conductance: f64,
+    pub susceptance: f64,
+    /// Its magnitude over the sum of every element's, 0-1
+    pub share: f64,
+}
+
+/// The input admittance at one frequency taken apart. The contributions sum exactly to
+/// the total; a note sounds where the total susceptance crosses zero, so the elements with
+/// the most susceptance of either sign are the ones that settle its pitch.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AdmittanceBreakdown {
+    pub frequency: f64,
+    pub conductance: f64,
+    pub susceptance: f64,
+    /// The embouchure and cork, then the bore and its branches front to back, then the foot
+    pub contributions: Vec<Contribution>,
+}
+
+/// The input admittance of the current fingering at `frequency`, split into the embouchure,
+/// cork stub, each length of bore, each hole and branch, and the foot: each part's flow
+/// over the pressure at the embouchure. Shows which element dominates a tuning problem.
+pub fn admittance_breakdown(flute: &Flute, frequency: f64) -> AdmittanceBreakdown {
+    let parts = flute
+        .bore(&flute.hole_order_back_to_front())
+        .admittances(frequency);
+    let mut terms = vec![(Element::Embouchure, parts.embouchure)];
+    terms.extend(parts.cork.map(|y| (Element::Cork, y)));
+    let mut segments = parts.segments.iter().rev();
+    let mut push_segment = |terms: &mut Vec<_>| {
+        if let Some(&(from, to, y)) = segments.next() {
+            if to > from {
+                terms.push((Element::Bore { from, to }, y));
+            }
+        }
+    };
+    push_segment(&mut terms);
+    for &(position, index, open, y) in parts.shunts.iter().rev() {
+        let element = match index {
+            Some(i) => Element::Hole(i),
+            None if open => Element::TuningSlot,
+            None => Element::Resonator(position),
+        };
+        terms.push((element, y));
+        push_segment(&mut terms);
+    }
+    terms.push((Element::Foot, parts.foot));
+    let total: Complex64 = terms.iter().map(|&(_, y)| y).sum();
+    let magnitude: f64 = terms.iter().map(|&(_, y)| math::norm(y)).sum();
+    let contributions = terms
+        .into_iter()
+        .map(|(element, y)| Contribution {
+            element,
+            conductance: y.re,
+            susceptance: y.im,
+            share: if magnitude > 0.0 && magnitude.is_finite() {
+                math::norm(y) / magnitude
+            } else {
+                0.0
+            },
+        })
+        .collect();
+    AdmittanceBreakdown {
+        frequency,
+        conductance: total.re,
+        susceptance: total.im,
+        contributions,
+    }
+}
+
+/// `points` evenly spaced positions from the embouchure to the foot and the complex
+/// pressure at each, scaled so the largest magnitude is 1
+fn profile(flute: &Flute, frequency: f64, points: usize) -> (Vec<f64>, Vec<Complex64>) {
+    let points = points.clamp(2, MAX_POINTS);
+    let field = flute
+        .bore(&flute.hole_order_back_to_front())
+        .field(frequency);
+    let positions: Vec<f64> = (0..points)
+        .map(|i| flute.length * i as f64 / (points - 1) as f64)
+        .collect();
+    let pressure: Vec<_> = positions.iter().map(|&x| field.at(x).0).collect();
+    let peak = pressure.iter().fold(0.0f64, |m, p| m.max(math::norm(*p)));
+    let scale = if peak > 0.0 && peak.is_finite() {
+        1.0 / peak
+    } else {
+        0.0
+    };
+    let pressure = pressure.into_iter().map(|p| p * scale).collect();
+    (positions, pressure)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Hole;
+
+    #[test]
+    fn test_standing_wave_breathes_through_the_open_hole() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, true)).unwrap();
+        let frequency = flute.calculate_pitch(f64::NAN);
+        let wave = standing_wave(&flute, frequency, 61, 8);
+        assert_eq!(wave.positions.len(), 61);
+        assert_eq!(wave.frames.len(), 8);
+        assert_eq!(wave.positions[60], 60.0);
+
+        let swing: Vec<f64> = (0..61)
+            .map(|i| wave.frames.iter().fold(0.0f64, |m, f| m.max(f[i].abs())))
+            .collect();
+        assert!(swing.iter().all(|&s| s <= 1.0 + 1e-12));
+        // Near-open at the embouchure, an antinode about halfway down the sounding length,
+        // and past the open hole a much weaker wave dying away to the open foot
+        let max = (0..61)
+            .max_by(|&a, &b| swing[a].total_cmp(&swing[b]))
+            .unwrap();
+        assert!((12..=28).contains(&max), "antinode at {} cm", max);
+        assert!(
+            swing[0] < 0.1 && swing[41] < 0.5 && swing[60] < 0.05,
+            "{:?}",
+            swing
+        );
+        // Half a period later the wave is the mirror image
+        for (a, b) in wave.frames[0].iter().zip(&wave.frames[4]) {
+            assert!((a + b).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_node_hints_place_the_octave_vent_mid_bore() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, false)).unwrap();
+        let fundamental = flute.mode_frequency(1);
+        let hints = node_hints(&flute, fundamental);
+        assert!(hints.nodes.is_empty() && hints.vent_regions.is_empty());
+        assert_eq!(hints.antinodes.len(), 1);
+
+        // The second mode's node halves the sounding length, where its antinode was
+        let second = flute.mode_frequency(2);
+        let hints = node_hints(&flute, second);
+        assert_eq!(hints.nodes.len(), 1);
+        assert_eq!(hints.antinodes.len(), 2);
+        let vent = hints.vent_regions[0];
+        assert!(vent.start < hints.nodes[0] && hints.nodes[0] < vent.end);
+        assert!((hints.nodes[0] - node_hints(&flute, fundamental).antinodes[0]).abs() < 1.0);
+        for (region, &antinode) in hints.tuning_regions.iter().zip(&hints.antinodes) {
+            assert!(region.start < antinode && antinode < region.end);
+            assert!(region.end < vent.start || region.start > vent.end);
+        }
+
+        let third = flute.mode_frequency(3);
+        let hints = node_hints(&flute, third);
+        assert_eq!((hints.nodes.len(), hints.antinodes.len()), (2, 3));
+    }
+
+    #[test]
+    fn test_sources_show_where_the_sound_leaves() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for pos in [40.0, 35.0, 30.0] {
+            flute.holes.push(Hole::new(pos, 0.35, false)).unwrap();
+        }
+        flute.holes[1].open = true;
+        let f = flute.calculate_pitch(f64::NAN);
+        let veiled = sources(&flute, f);
+        let openings: Vec<Opening> = veiled.iter().map(|s| s.opening).collect();
+        assert_eq!(
+            openings,
+            [Opening::Embouchure, Opening::Hole(1), Opening::Foot]
+        );
+        assert_eq!(veiled[2].position, 60.0);
+        assert!(veiled.iter().any(|s| s.strength == 1.0 && s.level == 0.0));
+        // The two ends of the sounding air column breathe together and carry the sound;
+        // a closed hole below the open one still lets a good part out of the foot
+        assert!(veiled[0].strength > 0.8 && veiled[1].strength > 0.8);
+        assert!(veiled[0].phase.abs() < 0.1 && veiled[1].phase.abs() < 0.1);
+        assert!(veiled[2].strength > 0.3 && veiled[2].strength < 0.8);
+
+        flute.holes[0].open = true;
+        let f = flute.calculate_pitch(f64::NAN);
+        let vented = sources(&flute, f);
+        assert_eq!(vented.len(), 4);
+        assert!(vented[3].strength < 0.5 * veiled[2].strength);
+    }
+
+    #[test]
+    fn test_directivity_of_a_breathing_tube() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        let f = flute.calculate_pitch(f64::NAN);
+        let pattern = directivity(&flute, f, 36);
+        assert_eq!(pattern.angles.len(), 36);
+        assert_eq!(pattern.angles[9], 90.0);
+        assert!(pattern
+            .levels
+            .iter()
+            .all(|&l| (MIN_LEVEL..=0.0).contains(&l)));
+        // Both ends breathe in phase about half a wavelength apart: loudest broadside,
+        // they largely cancel along the axis
+        assert!(pattern.levels[9] > -0.5 && pattern.levels[27] > -0.5);
+        assert!(pattern.levels[0] < -10.0 && pattern.levels[18] < -10.0);
+        for i in 1..36 {
+            assert!((pattern.levels[i] - pattern.levels[36 - i]).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_admittance_breakdown_sums_to_the_input_admittance() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for (pos, open) in [(40.0, false), (35.0, true)] {
+            flute.holes.push(Hole::new(pos, 0.35, open)).unwrap();
+        }
+        let frequency = flute.calculate_pitch(f64::NAN);
+        let breakdown = admittance_breakdown(&flute, frequency);
+        let z = flute
+            .bore(&flute.hole_order_back_to_front())
+            .impedance(frequency);
+        let y = 1.0 / z;
+        let total = Complex64::new(breakdown.conductance, breakdown.susceptance);
+        assert!(
+            math::norm(total - y) < 1e-9 * math::norm(y),
+            "{} vs {}",
+            total,
+            y
+        );
+
+        // Mouth, cork, bore down to the open hole, the hole, bore to the closed hole, the
+        // hole, bore to the foot, the foot
+        let elements: Vec<Element> = breakdown.contributions.iter().map(|c| c.element).collect();
+        assert_eq!(elements.len(), 8);
+        assert_eq!(elements[3], Element::Hole(1));
+        assert_eq!(
+            elements[4],
+            Element::Bore {
+                from: 35.0,
+                to: 40.0
+            }
+        );
+        let shares: f64 = breakdown.contributions.iter().map(|c| c.share).sum();
+        assert!((shares - 1.0).abs() < 1e-12);
+        // The open hole carries the note; the closed one past it hardly counts
+        let share = |e: Element| {
+            breakdown
+                .contributions
+                .iter()
+                .find(|c| c.element == e)
+                .unwrap()
+                .share
+        };
+        assert!(share(Element::Hole(1)) > 100.0 * share(Element::Hole(0)));
+    }
+}
@@ -1,6 +1,79 @@
-use crate::physics::Flute;
+use crate::font;
+use crate::materials::Material;
+use crate::physics::{
+    DuctGeometry, FluePipe, Flute, Hole, InstrumentType, NotchCut, NotchShape, MIN_LABIUM_CHAMFER,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::f64::consts::PI;
 
+/// The physical tube extends at least this far behind the embouchure centre (headjoint stub)
+const MIN_HEAD_EXTENSION: f64 = 5.0; // cm
+/// Room behind the cork face for the stopper itself
+const STOPPER_LENGTH: f64 = 2.0; // cm
+
+/// Vertices closer than this (cm) are reported as duplicates
+const WELD_TOLERANCE: f64 = 1e-6;
+
+/// Default slicer assumptions for `estimate_print` (PLA on a typical 0.4 mm nozzle)
+pub const DEFAULT_PRINT_DENSITY: f64 = Material::Pla.properties().density; // g/cm^3
+pub const DEFAULT_INFILL: f64 = 0.2;
+const PERIMETER_THICKNESS: f64 = 0.12; // cm, three 0.4 mm perimeters
+const VOLUMETRIC_FLOW: f64 = 0.008; // cm^3/s (8 mm^3/s)
+
+/// Volume and area of one face group, assuming it is a closed shell
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct GroupStats {
+    pub name: String,
+    pub volume: f64,       // cm^3
+    pub surface_area: f64, // cm^2
+    /// Axial position of the centre of volume (cm)
+    pub centroid_x: f64,
+}
+
+/// Material and print-time estimate for the finished (drilled) body
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PrintEstimate {
+    pub volume: f64,       // cm^3 of solid material
+    pub surface_area: f64, // cm^2
+    pub weight: f64,       // grams after infill
+    pub print_hours: f64,  // rough, flow-rate limited
+}
+
+/// Topology problems found by `Mesh::validate`.
+/// Each closed cutter is its own shell, so a clean export has one body shell plus one per cutter.
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct MeshReport {
+    /// True when every shell is closed, manifold and outward-facing
+    pub watertight: bool,
+    pub vertex_count: usize,
+    pub face_count: usize,
+    pub shell_count: usize,
+    /// Edges used by only one face (holes in the surface)
+    pub boundary_edges: usize,
+    /// Edges shared by more than two faces
+    pub non_manifold_edges: usize,
+    /// Edges walked in the same direction by both neighbouring faces (one face is flipped)
+    pub flipped_edges: usize,
+    /// Closed shells whose normals all point inwards
+    pub inverted_shells: usize,
+    pub duplicate_vertices: usize,
+    /// Faces with fewer than 3 vertices or indices outside the vertex list
+    pub degenerate_faces: usize,
+}
+
+impl MeshReport {
+    fn is_watertight(&self) -> bool {
+        self.boundary_edges == 0
+            && self.non_manifold_edges == 0
+            && self.flipped_edges == 0
+            && self.inverted_shells == 0
+            && self.duplicate_vertices == 0
+            && self.degenerate_faces == 0
+    }
+}
+
 pub struct Mesh {
     vertices: Vec<(f64, f64, f64)>,
     // Store faces per group for cleaner OBJ output: (Group Name, list of faces)
@@ -8,6 +81,12 @@ pub struct Mesh {
     current_group: String,
 }
 
+impl Default for Mesh {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Mesh {
     pub fn new() -> Self {
         Mesh {
@@ -36,12 +115,290 @@ impl Mesh {
         }
     }
 
-    pub fn to_obj_string(&self) -> String {
+    /// Vertex positions in cm; face indices are 1-based into this list
+    pub fn vertices(&self) -> &[(f64, f64, f64)] {
+        &self.vertices
+    }
+
+    /// Whether group `name` is a boolean cutter (tone holes, mouth, windway, ...) rather than
+    /// part of the body. Cutters are for CAD tools; print formats leave them out.
+    pub fn is_cutter(name: &str) -> bool {
+        name.ends_with("Cutter") || name.ends_with("Cutters")
+    }
+
+    /// Fan-triangulated faces of every non-empty group, as 0-based vertex indices.
+    /// All faces the generator emits are convex, so a fan from the first vertex is exact.
+    pub fn group_triangles(&self) -> Vec<(&str, Vec<[u32; 3]>)> {
+        self.groups
+            .iter()
+            .filter(|(_, faces)| !faces.is_empty())
+            .map(|(name, faces)| {
+                let mut tris = Vec::new();
+                for face in faces {
+                    for k in 1..face.len().saturating_sub(1) {
+                        tris.push([
+                            (face[0] - 1) as u32,
+                            (face[k] - 1) as u32,
+                            (face[k + 1] - 1) as u32,
+                        ]);
+                    }
+                }
+                (name.as_str(), tris)
+            })
+            .collect()
+    }
+
+    /// Flat arrays for a GPU buffer geometry: xyz positions (cm, Y-up), area-weighted
+    /// vertex normals, and triangle indices per group (0-based)
+    pub fn to_buffers(&self) -> MeshBuffers {
+        let groups: Vec<(String, Vec<u32>)> = self
+            .group_triangles()
+            .into_iter()
+            .map(|(name, tris)| (name.to_string(), tris.into_iter().flatten().collect()))
+            .collect();
+
+        // Unnormalised cross products weight each triangle by its area
+        let mut normals = vec![[0.0f64; 3]; self.vertices.len()];
+        for (_, indices) in &groups {
+            for tri in indices.chunks(3) {
+                let [a, b, c] = [0, 1, 2].map(|k| self.vertices[tri[k] as usize]);
+                let u = (b.0 - a.0, b.1 - a.1, b.2 - a.2);
+                let v = (c.0 - a.0, c.1 - a.1, c.2 - a.2);
+                let n = [
+                    u.1 * v.2 - u.2 * v.1,
+                    u.2 * v.0 - u.0 * v.2,
+                    u.0 * v.1 - u.1 * v.0,
+                ];
+                for &i in tri {
+                    for k in 0..3 {
+                        normals[i as usize][k] += n[k];
+                    }
+                }
+            }
+        }
+
+        MeshBuffers {
+            positions: self
+                .vertices
+                .iter()
+                .flat_map(|&(x, y, z)| [x as f32, y as f32, z as f32])
+                .collect(),
+            normals: normals
+                .iter()
+                .flat_map(|n| {
+                    let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+                    let len = if len > 0.0 { len } else { 1.0 };
+                    n.map(|c| (c / len) as f32)
+                })
+                .collect(),
+            groups,
+        }
+    }
+
+    /// Move every vertex by `offset` (cm), e.g. to lay several parts side by side
+    pub fn translate(&mut self, (dx, dy, dz): (f64, f64, f64)) {
+        for (x, y, z) in &mut self.vertices {
+            *x += dx;
+            *y += dy;
+            *z += dz;
+        }
+    }
+
+    /// Copy another mesh's vertices and non-empty groups onto the end of this one
+    pub fn append(&mut self, other: &Mesh) {
+        let offset = self.vertices.len();
+        self.vertices.extend_from_slice(&other.vertices);
+        for (name, faces) in other.groups.iter().filter(|(_, f)| !f.is_empty()) {
+            self.set_group(name);
+            for face in faces {
+                let shifted: Vec<usize> = face.iter().map(|i| i + offset).collect();
+                self.add_face(&shifted);
+            }
+        }
+    }
+
+    fn faces(&self) -> impl Iterator<Item = &Vec<usize>> {
+        self.groups.iter().flat_map(|(_, faces)| faces.iter())
+    }
+
+    /// Signed volume of the tetrahedron fan from the origin to a face (OBJ 1-based indices).
+    /// Summed over a closed, outward-facing shell this gives its enclosed volume.
+    fn face_signed_volume(&self, face: &[usize]) -> f64 {
+        let v = |i: usize| self.vertices[face[i] - 1];
+        let (ax, ay, az) = v(0);
+        let mut vol = 0.0;
+        for i in 1..face.len() - 1 {
+            let (bx, by, bz) = v(i);
+            let (cx, cy, cz) = v(i + 1);
+            vol += ax * (by * cz - bz * cy) - ay * (bx * cz - bz * cx) + az * (bx * cy - by * cx);
+        }
+        vol / 6.0
+    }
+
+    /// First moment about the YZ plane of the same tetrahedron fan (volume times centroid x)
+    fn face_moment_x(&self, face: &[usize]) -> f64 {
+        let v = |i: usize| self.vertices[face[i] - 1];
+        let (ax, ay, az) = v(0);
+        let mut moment = 0.0;
+        for i in 1..face.len() - 1 {
+            let (bx, by, bz) = v(i);
+            let (cx, cy, cz) = v(i + 1);
+            let vol = (ax * (by * cz - bz * cy) - ay * (bx * cz - bz * cx)
+                + az * (bx * cy - by * cx))
+                / 6.0;
+            // Tetrahedron centroid is the mean of its corners, one of which is the origin
+            moment += vol * (ax + bx + cx) / 4.0;
+        }
+        moment
+    }
+
+    fn face_area(&self, face: &[usize]) -> f64 {
+        let v = |i: usize| self.vertices[face[i] - 1];
+        let (ax, ay, az) = v(0);
+        let mut area = 0.0;
+        for i in 1..face.len() - 1 {
+            let (bx, by, bz) = v(i);
+            let (cx, cy, cz) = v(i + 1);
+            let (ux, uy, uz) = (bx - ax, by - ay, bz - az);
+            let (wx, wy, wz) = (cx - ax, cy - ay, cz - az);
+            let (nx, ny, nz) = (uy * wz - uz * wy, uz * wx - ux * wz, ux * wy - uy * wx);
+            area += (nx * nx + ny * ny + nz * nz).sqrt() / 2.0;
+        }
+        area
+    }
+
+    /// Enclosed volume and surface area per group (groups with the same name are merged)
+    pub fn group_stats(&self) -> Vec<GroupStats> {
+        let mut stats: Vec<GroupStats> = Vec::new();
+        for (name, faces) in &self.groups {
+            if faces.is_empty() {
+                continue;
+            }
+            let volume: f64 = faces.iter().map(|f| self.face_signed_volume(f)).sum();
+            let surface_area: f64 = faces.iter().map(|f| self.face_area(f)).sum();
+            let moment: f64 = faces.iter().map(|f| self.face_moment_x(f)).sum();
+            match stats.iter_mut().find(|g| &g.name == name) {
+                Some(g) => {
+                    // Hold the running moment in centroid_x until the end
+                    g.volume += volume;
+                    g.surface_area += surface_area;
+                    g.centroid_x += moment;
+                }
+                None => stats.push(GroupStats {
+                    name: name.clone(),
+                    volume,
+                    surface_area,
+                    centroid_x: moment,
+                }),
+            }
+        }
+        for g in &mut stats {
+            g.centroid_x = if g.volume.abs() > 1e-12 {
+                g.centroid_x / g.volume
+            } else {
+                0.0
+            };
+        }
+        stats
+    }
+
+    /// Check the mesh for problems that make slicers reject the file:
+    /// open boundaries, non-manifold edges, inconsistent winding and duplicate vertices.
+    pub fn validate(&self) -> MeshReport {
+        let mut report = MeshReport {
+            vertex_count: self.vertices.len(),
+            ..Default::default()
+        };
+
+        let mut welded: HashMap<(i64, i64, i64), usize> = HashMap::new();
+        for &(x, y, z) in &self.vertices {
+            let key = (
+                (x / WELD_TOLERANCE).round() as i64,
+                (y / WELD_TOLERANCE).round() as i64,
+                (z / WELD_TOLERANCE).round() as i64,
+            );
+            *welded.entry(key).or_insert(0) += 1;
+        }
+        report.duplicate_vertices = welded.values().map(|n| n - 1).sum();
+
+        // Union-find over vertices to split the mesh into shells
+        let mut parent: Vec<usize> = (0..=self.vertices.len()).collect();
+        fn find(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+
+        // Undirected edge (lo, hi) -> (uses as lo->hi, uses as hi->lo)
+        let mut edges: HashMap<(usize, usize), (u32, u32)> = HashMap::new();
+        let mut valid_faces = Vec::new();
+
+        for face in self.faces() {
+            report.face_count += 1;
+            if face.len() < 3 || face.iter().any(|&i| i == 0 || i > self.vertices.len()) {
+                report.degenerate_faces += 1;
+                continue;
+            }
+            valid_faces.push(face);
+            for i in 0..face.len() {
+                let a = face[i];
+                let b = face[(i + 1) % face.len()];
+                let ra = find(&mut parent, a);
+                let rb = find(&mut parent, b);
+                parent[ra] = rb;
+
+                let entry = edges.entry((a.min(b), a.max(b))).or_insert((0, 0));
+                if a < b {
+                    entry.0 += 1;
+                } else {
+                    entry.1 += 1;
+                }
+            }
+        }
+
+        let mut open_shells = Vec::new();
+        for (&(a, _), &(fwd, rev)) in &edges {
+            match fwd + rev {
+                1 => {
+                    report.boundary_edges += 1;
+                    open_shells.push(find(&mut parent, a));
+                }
+                2 if fwd != 1 => report.flipped_edges += 1,
+                2 => {}
+                _ => report.non_manifold_edges += 1,
+            }
+        }
+
+        let mut shell_volumes: HashMap<usize, f64> = HashMap::new();
+        for face in valid_faces {
+            let root = find(&mut parent, face[0]);
+            *shell_volumes.entry(root).or_insert(0.0) += self.face_signed_volume(face);
+        }
+        report.shell_count = shell_volumes.len();
+        report.inverted_shells = shell_volumes
+            .iter()
+            .filter(|(root, vol)| !open_shells.contains(root) && **vol < 0.0)
+            .count();
+
+        report.watertight = report.is_watertight();
+        report
+    }
+
+    /// OBJ text with the model (cm, Y-up) converted to the requested units and up axis
+    pub fn to_obj_string(&self, units: ExportUnits, up_axis: UpAxis) -> String {
         let mut out = String::new();
         out.push_str("# Flyte Architect Export\n");
+        out.push_str(&format!(
+            "# Units: {}, {}-up\n",
+            units.label(),
+            up_axis.label()
+        ));
         out.push_str("o FluteProject\n");
 
-        for (x, y, z) in &self.vertices {
+        for &v in &self.vertices {
+            let (x, y, z) = up_axis.apply(units.scale(v));
             out.push_str(&format!("v {:.4} {:.4} {:.4}\n", x, y, z));
         }
 
@@ -51,7 +408,7 @@ impl Mesh {
             }
             out.push_str(&format!("g {}\n", group_name));
             for face in faces {
-                out.push_str("f");
+                out.push('f');
                 for idx in face {
                     // OBJ is 1-indexed
                     out.push_str(&format!(" {}", idx));
@@ -64,163 +421,1809 @@ impl Mesh {
     }
 }
 
-pub fn generate_flute_mesh(flute: &Flute) -> Mesh {
-    let mut mesh = Mesh::new();
+/// Mesh as flat typed arrays, ready for a three.js `BufferGeometry`
+pub struct MeshBuffers {
+    pub positions: Vec<f32>,
+    pub normals: Vec<f32>,
+    /// Group name and its triangle indices into `positions`
+    pub groups: Vec<(String, Vec<u32>)>,
+}
 
-    // Helper function moved to local scope to act on mesh
-    fn add_ring(mesh: &mut Mesh, x: f64, r: f64, segments: usize) -> Vec<usize> {
-        let mut indices = Vec::new();
-        for i in 0..segments {
-            let theta = 2.0 * PI * (i as f64) / (segments as f64);
-            let y = r * theta.cos();
-            let z = r * theta.sin();
-            // add_vertex returns count which serves as 1-based index
-            indices.push(mesh.add_vertex(x, y, z));
-        }
-        indices
-    }
-
-    fn stitch_rings(mesh: &mut Mesh, r1: &[usize], r2: &[usize], flip: bool, segments: usize) {
-        for i in 0..segments {
-            let next = (i + 1) % segments;
-            let idx1 = r1[i];
-            let idx2 = r2[i];
-            let idx1_next = r1[next];
-            let idx2_next = r2[next];
-
-            if flip {
-                mesh.add_face(&[idx1, idx1_next, idx2_next, idx2]);
-            } else {
-                mesh.add_face(&[idx1, idx2, idx2_next, idx1_next]);
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The exported geometry is broken or cannot be made
+    Error,
+    /// Makeable, but likely to print or play badly
+    Warning,
+}
+
+/// A problem with the design itself, found before any mesh is built
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct GeometryIssue {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    /// Index into `holes`, when the issue belongs to a hole
+    pub hole: Option<usize>,
+}
+
+/// Mass and axial centre of one solid part of the finished instrument
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PartMass {
+    pub name: String,
+    pub mass: f64,   // grams
+    pub centre: f64, // cm from the embouchure centre
+    /// How far this part moves the balance point (cm, negative = towards the head).
+    /// None for the body itself.
+    pub shift: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BalanceReport {
+    pub mass: f64, // grams
+    /// Centre of gravity along the tube, cm from the embouchure centre
+    pub balance_point: f64,
+    pub parts: Vec<PartMass>,
+}
+
+/// Finished mass and balance point at a solid `density` (g/cm^3), from the export mesh.
+/// Holes (every one drilled, whatever the fingering) and the sunk joins of risers and rings
+/// are taken out analytically like in `estimate_print`; engravings are too small to matter
+/// and are ignored.
+pub fn estimate_balance(flute: &Flute, options: &MeshOptions, density: f64) -> BalanceReport {
+    let mesh = generate_flute_mesh_with(flute, options);
+    let stats = mesh.group_stats();
+    let r_outer = flute.bore_radius + flute.wall_thickness;
+    let wall = flute.wall_thickness;
+
+    // (name, volume, first moment) per solid part
+    let mut parts: Vec<(String, f64, f64)> = Vec::new();
+    let mut body = stats
+        .iter()
+        .find(|g| g.name == "TubeBody")
+        .map(|g| (g.volume, g.volume * g.centroid_x))
+        .unwrap_or((0.0, 0.0));
+    let mut openings: Vec<(f64, f64)> = flute
+        .holes
+        .iter()
+        .map(|h| (h.position, PI * h.radius.powi(2)))
+        .collect();
+    if flute.instrument.has_embouchure_hole() {
+        let area = PI * flute.embouchure_hole_radius.powi(2);
+        openings.push((0.0, area));
+    } else if let Some(duct) = flute.instrument.duct() {
+        // The window runs back from the labium edge at x = 0
+        let area = duct.windway_width * duct.window_length;
+        openings.push((-0.5 * duct.window_length, area));
+    }
+    for (x, area) in openings {
+        body.0 -= area * wall;
+        body.1 -= area * wall * x;
+    }
+    parts.push(("Body".to_string(), body.0, body.1));
+
+    for g in &stats {
+        let extra = if g.name == "EmbouchureChimney" {
+            let oval = flute.embouchure_oval.max(0.1);
+            let (a, b) = (
+                flute.embouchure_hole_radius * oval.sqrt(),
+                flute.embouchure_hole_radius / oval.sqrt(),
+            );
+            // Only the collar standing above the tube adds material
+            let above_wall = flute.embouchure_chimney - wall;
+            Some(PI * ((a + wall) * (b + wall) - a * b) * above_wall)
+        } else if let Some(n) = g.name.strip_prefix("DecorativeRing") {
+            let ring = n
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| options.rings.get(n - 1));
+            let sunk = ring.map_or(0.0, |ring| {
+                PI * (r_outer.powi(2) - (r_outer - 0.5 * wall).powi(2)) * ring.width
+            });
+            Some(g.volume - sunk)
+        } else if g.name == "FippleBlock" || g.name.starts_with("Emboss") {
+            Some(g.volume)
+        } else {
+            None
+        };
+        if let Some(volume) = extra {
+            parts.push((g.name.clone(), volume, volume * g.centroid_x));
+        }
+    }
+
+    let total_volume: f64 = parts.iter().map(|p| p.1).sum();
+    let total_moment: f64 = parts.iter().map(|p| p.2).sum();
+    let balance_point = total_moment / total_volume.max(1e-12);
+    BalanceReport {
+        mass: total_volume * density,
+        balance_point,
+        parts: parts
+            .iter()
+            .enumerate()
+            .map(|(i, (name, volume, moment))| {
+                let without = (total_moment - moment) / (total_volume - volume).max(1e-12);
+                PartMass {
+                    name: name.clone(),
+                    mass: volume * density,
+                    centre: moment / volume.max(1e-12),
+                    shift: (i > 0).then_some(balance_point - without),
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Walls below two 0.4 mm perimeters don't print reliably
+pub const MIN_PRINTABLE_WALL: f64 = 0.08; // cm
+/// Material left between neighbouring holes before it gets fragile
+pub const MIN_BRIDGE: f64 = 0.2; // cm
+
+/// How far the labium ramp runs along the outer surface past the edge, flagging a tip
+/// too fine to print or a chamfer that leaves no ramp
+fn labium_run(
+    issue: &mut impl FnMut(Severity, &'static str, String, Option<usize>),
+    flute: &Flute,
+    duct: &DuctGeometry,
+) -> f64 {
+    if duct.labium_chamfer.is_nan() || duct.labium_chamfer >= flute.wall_thickness {
+        issue(
+            Severity::Error,
+            "labium_chamfer",
+            format!(
+                "Labium chamfer of {:.2} mm leaves no ramp in the {:.1} mm wall",
+                duct.labium_chamfer * 10.0,
+                flute.wall_thickness * 10.0
+            ),
+            None,
+        );
+    } else if duct.labium_chamfer < MIN_LABIUM_CHAMFER {
+        issue(
+            Severity::Warning,
+            "sharp_labium",
+            format!(
+                "Labium edge of {:.2} mm is finer than a printer holds; it will come out \
+                 rounded by however much the printer manages. A chamfer of {:.1} mm prints \
+                 repeatably",
+                duct.labium_chamfer.max(0.0) * 10.0,
+                MIN_LABIUM_CHAMFER * 10.0
+            ),
+            None,
+        );
+    }
+    bevel_run(flute, duct.ramp_angle, duct.labium_chamfer)
+}
+
+/// Position on the unrolled outer surface: (axial cm, circumferential cm)
+fn unrolled(r_outer: f64, position: f64, azimuth: f64) -> (f64, f64) {
+    let mut turn = azimuth.rem_euclid(360.0);
+    if turn > 180.0 {
+        turn -= 360.0;
+    }
+    (position, r_outer * turn.to_radians())
+}
+
+/// Clearance between two circles on the unrolled surface; negative when they overlap
+fn gap(a: (f64, f64), b: (f64, f64), ra: f64, rb: f64) -> f64 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt() - ra - rb
+}
+
+/// Clearance between two tone holes on the outer surface (cm); negative when they overlap
+pub(crate) fn hole_gap(flute: &Flute, a: &Hole, b: &Hole) -> f64 {
+    let r_outer = flute.bore_radius + flute.wall_thickness;
+    gap(
+        unrolled(r_outer, a.position, a.azimuth),
+        unrolled(r_outer, b.position, b.azimuth),
+        a.radius,
+        b.radius,
+    )
+}
+
+/// Check a design for geometry the slicer or the workshop would reject: walls too thin to
+/// print, holes that would split the tube, holes that overlap each other or the blowing
+/// feature, and chimneys taller than the wall.
+pub fn check_geometry(flute: &Flute) -> Vec<GeometryIssue> {
+    let mut issues = Vec::new();
+    let mut issue = |severity, code, message: String, hole| {
+        issues.push(GeometryIssue {
+            severity,
+            code,
+            message,
+            hole,
+        })
+    };
+    let r_outer = flute.bore_radius + flute.wall_thickness;
+
+    if flute.wall_thickness < MIN_PRINTABLE_WALL {
+        issue(
+            Severity::Error,
+            "thin_wall",
+            format!(
+                "Wall of {:.1} mm is below the printable minimum of {:.1} mm",
+                flute.wall_thickness * 10.0,
+                MIN_PRINTABLE_WALL * 10.0
+            ),
+            None,
+        );
+    }
+
+    // Blowing feature as a circle on the unrolled outer surface at (0, top)
+    let blowing_radius = match &flute.instrument {
+        InstrumentType::Transverse => {
+            if flute.embouchure_chimney > flute.wall_thickness + 1e-9 {
+                issue(
+                    Severity::Warning,
+                    "raised_chimney",
+                    format!(
+                        "Embouchure chimney of {:.1} mm exceeds the {:.1} mm wall; a riser is added",
+                        flute.embouchure_chimney * 10.0,
+                        flute.wall_thickness * 10.0
+                    ),
+                    None,
+                );
             }
+            if flute.embouchure_hole_radius >= flute.bore_radius {
+                issue(
+                    Severity::Error,
+                    "hole_too_large",
+                    "Embouchure hole is as wide as the bore and would split the tube".to_string(),
+                    None,
+                );
+            }
+            flute.embouchure_hole_radius * flute.embouchure_oval.max(0.1).sqrt()
+        }
+        InstrumentType::Notch(notch) => {
+            notch.depth
+                + flute.wall_thickness / notch.bevel_angle.clamp(5.0, 90.0).to_radians().tan()
+        }
+        InstrumentType::Duct(duct) => labium_run(&mut issue, flute, duct),
+        InstrumentType::OrganPipe(pipe) => labium_run(&mut issue, flute, &pipe.duct()),
+    };
+
+    let unrolled = |position: f64, azimuth: f64| unrolled(r_outer, position, azimuth);
+
+    for (i, hole) in flute.holes.iter().enumerate() {
+        let at = format!("Hole at {:.1} cm", hole.position);
+        if hole.radius >= flute.bore_radius {
+            issue(
+                Severity::Error,
+                "hole_too_large",
+                format!("{} is as wide as the bore and would split the tube", at),
+                Some(i),
+            );
+        }
+        if hole.position - hole.radius < 0.0 || hole.position + hole.radius > flute.length {
+            issue(
+                Severity::Error,
+                "hole_outside_body",
+                format!("{} runs past the end of the tube", at),
+                Some(i),
+            );
+        }
+        let p = unrolled(hole.position, hole.azimuth);
+        if gap(p, (0.0, 0.0), hole.radius, blowing_radius) < 0.0 {
+            issue(
+                Severity::Error,
+                "overlaps_embouchure",
+                format!("{} overlaps the blowing edge", at),
+                Some(i),
+            );
+        }
+        for other in flute.holes.iter().skip(i + 1) {
+            let g = hole_gap(flute, hole, other);
+            if g < 0.0 {
+                issue(
+                    Severity::Error,
+                    "holes_overlap",
+                    format!("{} overlaps the hole at {:.1} cm", at, other.position),
+                    Some(i),
+                );
+            } else if g < MIN_BRIDGE {
+                issue(
+                    Severity::Warning,
+                    "thin_bridge",
+                    format!(
+                        "{} leaves only {:.1} mm to the hole at {:.1} cm",
+                        at,
+                        g * 10.0,
+                        other.position
+                    ),
+                    Some(i),
+                );
+            }
+        }
+    }
+    issues
+}
+
+/// Estimate solid volume, weight and print time of the drilled body.
+/// The tube comes from the mesh; tone holes are subtracted analytically since
+/// the mesh only carries them as boolean cutters. Every hole is drilled, whatever
+/// the fingering.
+pub fn estimate_print(flute: &Flute, density: f64, infill: f64) -> PrintEstimate {
+    let mesh = generate_flute_mesh(flute);
+    let body = mesh
+        .group_stats()
+        .into_iter()
+        .find(|g| g.name == "TubeBody")
+        .map(|g| (g.volume, g.surface_area))
+        .unwrap_or((0.0, 0.0));
+
+    let mut volume = body.0;
+    let mut surface_area = body.1;
+    for hole in &flute.holes {
+        let opening = PI * hole.radius.powi(2);
+        volume -= opening * flute.wall_thickness;
+        surface_area += 2.0 * PI * hole.radius * flute.wall_thickness - 2.0 * opening;
+    }
+    volume = volume.max(0.0);
+
+    // Perimeters are printed solid, the rest at the infill ratio
+    let infill = infill.clamp(0.0, 1.0);
+    let shell = (surface_area * PERIMETER_THICKNESS).min(volume);
+    let extruded = shell + (volume - shell) * infill;
+
+    PrintEstimate {
+        volume,
+        surface_area,
+        weight: extruded * density,
+        print_hours: extruded / VOLUMETRIC_FLOW / 3600.0,
+    }
+}
+
+/// How far the body extends behind the embouchure centre: far enough to hold the cork
+/// and stopper, and never shorter than the classic 5 cm headjoint stub
+pub fn head_extension(flute: &Flute) -> f64 {
+    match &flute.instrument {
+        InstrumentType::Transverse => {
+            (flute.cork_position + STOPPER_LENGTH).max(MIN_HEAD_EXTENSION)
+        }
+        // End-blown: the tube starts at the blowing edge
+        InstrumentType::Notch(_) => 0.0,
+        InstrumentType::Duct(duct) => duct.window_length + duct.windway_length,
+        InstrumentType::OrganPipe(pipe) => pipe.cut_up + pipe.languid_length,
+    }
+}
+
+fn add_ring(mesh: &mut Mesh, x: f64, r: f64, segments: usize) -> Vec<usize> {
+    let mut indices = Vec::new();
+    for i in 0..segments {
+        let theta = 2.0 * PI * (i as f64) / (segments as f64);
+        let y = r * theta.cos();
+        let z = r * theta.sin();
+        // add_vertex returns count which serves as 1-based index
+        indices.push(mesh.add_vertex(x, y, z));
+    }
+    indices
+}
+
+fn stitch_rings(mesh: &mut Mesh, r1: &[usize], r2: &[usize], flip: bool, segments: usize) {
+    for i in 0..segments {
+        let next = (i + 1) % segments;
+        let idx1 = r1[i];
+        let idx2 = r2[i];
+        let idx1_next = r1[next];
+        let idx2_next = r2[next];
+
+        if flip {
+            mesh.add_face(&[idx1, idx1_next, idx2_next, idx2]);
+        } else {
+            mesh.add_face(&[idx1, idx2, idx2_next, idx1_next]);
+        }
+    }
+}
+
+/// Closed elliptical prism pointing radially out of the tube axis (hole cutters, risers).
+/// `profile` lists (radial height, growth) rings from the inside out; each ring's semi-axes
+/// are `a + growth` and `b + growth`, which lets a cutter flare into a chamfer or fillet.
+struct RadialPrism {
+    x: f64, // axial centre
+    a: f64, // semi-axis along the tube
+    b: f64, // semi-axis across the tube
+    profile: Vec<(f64, f64)>,
+    azimuth: f64, // radians around X, 0 = +Y, positive towards +Z
+}
+
+fn add_radial_prism(mesh: &mut Mesh, prism: &RadialPrism, segments: usize) {
+    let (sin_az, cos_az) = prism.azimuth.sin_cos();
+    let rings: Vec<Vec<usize>> = prism
+        .profile
+        .iter()
+        .map(|&(r, grow)| {
+            (0..segments)
+                .map(|j| {
+                    let theta = 2.0 * PI * (j as f64) / (segments as f64);
+                    // Built along +Y, then rotated about the tube axis
+                    let y = r;
+                    let z = (prism.b + grow) * theta.sin();
+                    mesh.add_vertex(
+                        prism.x + (prism.a + grow) * theta.cos(),
+                        y * cos_az - z * sin_az,
+                        y * sin_az + z * cos_az,
+                    )
+                })
+                .collect()
+        })
+        .collect();
+
+    add_loft(mesh, &rings);
+}
+
+/// Stitch a stack of equal-length vertex rings bottom to top and cap both ends.
+/// Rings must wind from +X towards +Z when seen in their local frame (bottom cap faces -Y).
+fn add_loft(mesh: &mut Mesh, rings: &[Vec<usize>]) {
+    for pair in rings.windows(2) {
+        stitch_rings(mesh, &pair[0], &pair[1], false, pair[0].len());
+    }
+
+    // Caps (so it's a solid boolean object)
+    // Ring order runs X -> Z, which faces -Y: bottom cap as-is, top cap reversed
+    if let (Some(bot), Some(top)) = (rings.first(), rings.last()) {
+        mesh.add_face(bot);
+        let mut top_rev = top.clone();
+        top_rev.reverse();
+        mesh.add_face(&top_rev);
+    }
+}
+
+/// Prism along the tube axis from `x.0` to `x.1` with a convex (y, z) cross-section.
+/// The outline must wind from +Z towards +Y.
+pub(crate) fn add_axial_prism(mesh: &mut Mesh, outline: &[(f64, f64)], x: (f64, f64)) {
+    let rings: Vec<Vec<usize>> = [x.0, x.1]
+        .iter()
+        .map(|&x| {
+            outline
+                .iter()
+                .map(|&(y, z)| mesh.add_vertex(x, y, z))
+                .collect()
+        })
+        .collect();
+    add_loft(mesh, &rings);
+}
+
+/// How far along the tube a bevel at `bevel_angle` runs through the wall above a `flat`
+/// left standing at the bore
+fn bevel_run(flute: &Flute, bevel_angle: f64, flat: f64) -> f64 {
+    let bevel = bevel_angle.clamp(5.0, 90.0).to_radians();
+    (flute.wall_thickness - flat).max(0.0) / bevel.tan()
+}
+
+/// Cutter through the top of the wall whose (x, z) outline is pushed further along +X at
+/// the outer surface than at the bore, giving a bevelled edge at the foot side. The bevel
+/// starts `flat` above the bore, leaving a square-faced tip that high.
+/// `outline(extra)` returns the outline with its +X edge moved by `extra`.
+fn add_bevel_cutter<F>(mesh: &mut Mesh, flute: &Flute, bevel_angle: f64, flat: f64, outline: F)
+where
+    F: Fn(f64) -> Vec<(f64, f64)>,
+{
+    let r_inner = flute.bore_radius;
+    let r_outer = flute.bore_radius + flute.wall_thickness;
+    let flat = flat.clamp(0.0, flute.wall_thickness);
+    let run = bevel_run(flute, bevel_angle, flat);
+
+    let rings: Vec<Vec<usize>> = [
+        (r_inner - 0.5, 0.0),
+        (r_inner + flat, 0.0),
+        (r_outer, run),
+        (r_outer + 0.5, run),
+    ]
+    .iter()
+    .map(|&(y, extra)| {
+        outline(extra)
+            .into_iter()
+            .map(|(x, z)| mesh.add_vertex(x, y, z))
+            .collect()
+    })
+    .collect();
+    add_loft(mesh, &rings);
+}
+
+/// Box from `y.0` to `y.1` along +Y, `x` along the tube and `half_width` either side,
+/// then rotated `azimuth` radians about the tube axis
+fn add_radial_box(mesh: &mut Mesh, x: (f64, f64), half_width: f64, y: (f64, f64), azimuth: f64) {
+    let (sin_az, cos_az) = azimuth.sin_cos();
+    let outline = [
+        (x.0, -half_width),
+        (x.1, -half_width),
+        (x.1, half_width),
+        (x.0, half_width),
+    ];
+    let rings: Vec<Vec<usize>> = [y.0, y.1]
+        .iter()
+        .map(|&y| {
+            outline
+                .iter()
+                .map(|&(x, z)| mesh.add_vertex(x, y * cos_az - z * sin_az, y * sin_az + z * cos_az))
+                .collect()
+        })
+        .collect();
+    add_loft(mesh, &rings);
+}
+
+/// Outline (x, z) of a notch reaching `depth` into the wall from the blowing end,
+/// starting `overhang` past the end face so the cut is clean. Winds from +X towards +Z.
+fn notch_outline(notch: &NotchCut, depth: f64, overhang: f64, steps: usize) -> Vec<(f64, f64)> {
+    let hw = notch.width / 2.0;
+    match notch.shape {
+        NotchShape::U => {
+            // Straight sides into a semicircular bottom; shallow notches keep a flatter arc
+            let r = hw.min(depth);
+            let centre = depth - r;
+            let mut outline = vec![(-overhang, -hw), (centre, -hw)];
+            for k in 1..steps {
+                let t = -PI / 2.0 + PI * (k as f64) / (steps as f64);
+                outline.push((centre + r * t.cos(), hw * t.sin()));
+            }
+            outline.push((centre, hw));
+            outline.push((-overhang, hw));
+            outline
+        }
+        NotchShape::V => {
+            let spread = hw * (depth + overhang) / depth.max(1e-6);
+            vec![(-overhang, -spread), (depth, 0.0), (-overhang, spread)]
+        }
+    }
+}
+
+/// Shape of a softened edge
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeProfile {
+    #[default]
+    Sharp,
+    Chamfer,
+    Fillet,
+}
+
+/// Chamfer leg length or fillet radius (cm) applied to one kind of edge
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(default)]
+pub struct EdgeTreatment {
+    pub profile: EdgeProfile,
+    pub size: f64,
+}
+
+impl EdgeTreatment {
+    /// The size actually applied, or None for a sharp edge
+    fn effective_size(&self, max: f64) -> Option<f64> {
+        match self.profile {
+            EdgeProfile::Sharp => None,
+            _ if self.size.is_nan() || self.size <= 0.0 => None,
+            _ => Some(self.size.min(max)),
+        }
+    }
+
+    /// Replace the right-angle corner `p` (between neighbours `prev` and `next`) with
+    /// a chamfer or fillet of the given size, in the same 2-D profile coordinates
+    fn round_corner(
+        &self,
+        size: f64,
+        p: (f64, f64),
+        prev: (f64, f64),
+        next: (f64, f64),
+        steps: usize,
+    ) -> Vec<(f64, f64)> {
+        let unit = |to: (f64, f64)| {
+            let (dx, dy) = (to.0 - p.0, to.1 - p.1);
+            let len = (dx * dx + dy * dy).sqrt();
+            (dx / len, dy / len)
+        };
+        let (u, v) = (unit(prev), unit(next));
+        match self.profile {
+            EdgeProfile::Fillet => (0..=steps)
+                .map(|k| {
+                    let t = (k as f64) * PI / 2.0 / (steps as f64);
+                    // Arc centre sits `size` along both legs
+                    let (c, s) = (1.0 - t.cos(), 1.0 - t.sin());
+                    (
+                        p.0 + size * (u.0 * s + v.0 * c),
+                        p.1 + size * (u.1 * s + v.1 * c),
+                    )
+                })
+                .collect(),
+            _ => vec![
+                (p.0 + size * u.0, p.1 + size * u.1),
+                (p.0 + size * v.0, p.1 + size * v.1),
+            ],
+        }
+    }
+}
+
+/// Length unit written to mesh files. Slicers assume millimetres.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportUnits {
+    #[default]
+    Millimeters,
+    Centimeters,
+    Inches,
+}
+
+impl ExportUnits {
+    /// Convert a point from the model's centimetres
+    pub fn scale(self, (x, y, z): (f64, f64, f64)) -> (f64, f64, f64) {
+        let k = match self {
+            ExportUnits::Millimeters => 10.0,
+            ExportUnits::Centimeters => 1.0,
+            ExportUnits::Inches => 1.0 / 2.54,
+        };
+        (x * k, y * k, z * k)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportUnits::Millimeters => "mm",
+            ExportUnits::Centimeters => "cm",
+            ExportUnits::Inches => "in",
+        }
+    }
+}
+
+/// Up axis of the exported file. The model itself is Y-up (tone holes face +Y);
+/// Z-up suits slicers and most CAD packages.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum UpAxis {
+    #[default]
+    Y,
+    Z,
+}
+
+impl UpAxis {
+    /// Rotate a model-space point into the export frame (a proper rotation about X)
+    pub fn apply(self, (x, y, z): (f64, f64, f64)) -> (f64, f64, f64) {
+        match self {
+            UpAxis::Y => (x, y, z),
+            // 0.0 - z rather than -z so flat faces don't print as "-0.0000"
+            UpAxis::Z => (x, 0.0 - z, y),
         }
     }
 
+    pub fn label(self) -> &'static str {
+        match self {
+            UpAxis::Y => "Y",
+            UpAxis::Z => "Z",
+        }
+    }
+}
+
+/// Text engraved into (or raised from) the outer surface, reading along the tube from
+/// `position` towards the foot. Letters are 5x7 pixel blocks.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Engraving {
+    pub text: String,
+    pub position: f64, // cm, left edge of the first letter
+    pub azimuth: f64,  // degrees around the tube to the centre line of the text
+    pub height: f64,   // cm, letter height
+    pub depth: f64,    // cm, cut depth or emboss height
+    /// Raise the letters instead of cutting them in
+    pub emboss: bool,
+}
+
+impl Default for Engraving {
+    fn default() -> Self {
+        Engraving {
+            text: String::new(),
+            position: 0.0,
+            azimuth: 0.0,
+            height: 0.5,
+            depth: 0.05,
+            emboss: false,
+        }
+    }
+}
+
+/// Mesh resolution. Draft is for regenerating on every slider move; Fine for final prints.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Lod {
+    Draft,
+    #[default]
+    Standard,
+    Fine,
+}
+
+impl Lod {
+    /// Segments around the tube body and decorative rings
+    pub(crate) fn tube_segments(self) -> usize {
+        match self {
+            Lod::Draft => 24,
+            Lod::Standard => 64,
+            Lod::Fine => 128,
+        }
+    }
+
+    /// Segments around hole, mouth-hole and block outlines
+    fn hole_segments(self) -> usize {
+        match self {
+            Lod::Draft => 12,
+            Lod::Standard => 32,
+            Lod::Fine => 64,
+        }
+    }
+
+    /// Steps along notch and windway arcs
+    fn arc_steps(self) -> usize {
+        match self {
+            Lod::Draft => 4,
+            Lod::Standard => 8,
+            Lod::Fine => 16,
+        }
+    }
+
+    /// Steps along a fillet's quarter circle
+    fn fillet_steps(self) -> usize {
+        match self {
+            Lod::Draft => 1,
+            Lod::Standard => 4,
+            Lod::Fine => 8,
+        }
+    }
+}
+
+/// Export-only shaping that the acoustic model ignores
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct MeshOptions {
+    /// Inner and outer edges at both tube ends (and the edges of decorative rings)
+    pub tube_ends: EdgeTreatment,
+    /// Outer rim of every tone hole
+    pub hole_rims: EdgeTreatment,
+    /// Outer rim of the mouth hole (top of the chimney if there is a riser)
+    pub embouchure_edge: EdgeTreatment,
+    /// Cosmetic bands, each exported as its own `DecorativeRing<n>` group
+    pub rings: Vec<DecorativeRing>,
+    /// Units and up axis of the written file; geometry is always built in cm, Y-up
+    pub units: ExportUnits,
+    pub up_axis: UpAxis,
+    /// Resolution used for exports
+    pub lod: Lod,
+    /// Maker's marks, each exported as an `Engraving<n>` cutter or `Emboss<n>` solid
+    pub engravings: Vec<Engraving>,
+}
+
+/// A cosmetic band around the body (ring, ferrule, crown band)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DecorativeRing {
+    pub position: f64,       // cm from the embouchure to the ring's near edge
+    pub width: f64,          // cm along the tube
+    pub outer_diameter: f64, // cm
+}
+
+/// Closed hollow cylinder from `x.0` to `x.1` between radii `r.0` and `r.1`, its four
+/// circular edges softened by `edge`. The (x, r) cross-section is walked outer left -> right,
+/// down the right end, inner right -> left, up the left end, which revolves to outward quads.
+pub(crate) fn add_annulus(
+    mesh: &mut Mesh,
+    x: (f64, f64),
+    r: (f64, f64),
+    edge: &EdgeTreatment,
+    lod: Lod,
+) {
+    let segments = lod.tube_segments();
+    let corners = [(x.0, r.1), (x.1, r.1), (x.1, r.0), (x.0, r.0)];
+    let max_edge = 0.45 * (r.1 - r.0).min(x.1 - x.0);
+    let mut section = Vec::new();
+    for (i, &p) in corners.iter().enumerate() {
+        let prev = corners[(i + 3) % 4];
+        let next = corners[(i + 1) % 4];
+        match edge.effective_size(max_edge) {
+            Some(size) => {
+                section.extend(edge.round_corner(size, p, prev, next, lod.fillet_steps()))
+            }
+            None => section.push(p),
+        }
+    }
+    let rings: Vec<Vec<usize>> = section
+        .iter()
+        .map(|&(x, r)| add_ring(mesh, x, r, segments))
+        .collect();
+    for i in 0..rings.len() {
+        stitch_rings(
+            mesh,
+            &rings[i],
+            &rings[(i + 1) % rings.len()],
+            true,
+            segments,
+        );
+    }
+}
+
+/// Cutter profile for a hole whose material edge sits at radial height `surface`
+fn cutter_profile(
+    edge: &EdgeTreatment,
+    max: f64,
+    r_start: f64,
+    surface: f64,
+    r_end: f64,
+    lod: Lod,
+) -> Vec<(f64, f64)> {
+    match edge.effective_size(max) {
+        None => vec![(r_start, 0.0), (r_end, 0.0)],
+        Some(size) => {
+            // Profile coordinates here are (growth, height)
+            let corner = edge.round_corner(
+                size,
+                (0.0, surface),
+                (0.0, r_start),
+                (1.0, surface),
+                lod.fillet_steps(),
+            );
+            let mut profile = vec![(r_start, 0.0)];
+            profile.extend(corner.into_iter().map(|(grow, h)| (h, grow)));
+            profile.push((r_end, size));
+            profile
+        }
+    }
+}
+
+/// Mouth-hole cutter plus a riser when the chimney is taller than the wall
+fn add_embouchure(mesh: &mut Mesh, flute: &Flute, options: &MeshOptions, lod: Lod) {
+    let r_inner = flute.bore_radius;
+    let r_outer = flute.bore_radius + flute.wall_thickness;
+
+    // 3. Mouth Hole Cutter
+    mesh.set_group("MouthHoleCutter");
+
+    // The mouth hole sits at x=0, the acoustic start of the air column, with the cork
+    // `cork_position` behind it inside the head stub.
+    // An oval keeps the simulated area: axial semi-axis r*sqrt(oval), transverse r/sqrt(oval).
+    let oval = flute.embouchure_oval.max(0.1);
+    let m_a = flute.embouchure_hole_radius * oval.sqrt();
+    let m_b = flute.embouchure_hole_radius / oval.sqrt();
+    // The chimney is the full depth of the hole measured from the bore
+    let chimney_top = r_inner + flute.embouchure_chimney.max(flute.wall_thickness);
+    let mouth = RadialPrism {
+        x: 0.0,
+        a: m_a,
+        b: m_b,
+        profile: cutter_profile(
+            &options.embouchure_edge,
+            0.9 * flute.wall_thickness,
+            r_inner - 0.5,
+            chimney_top,
+            chimney_top + 0.5,
+            lod,
+        ),
+        azimuth: 0.0,
+    };
+    add_radial_prism(mesh, &mouth, lod.hole_segments());
+
+    // 4. Riser (lip plate) when the chimney is taller than the wall
+    if flute.embouchure_chimney > flute.wall_thickness {
+        mesh.set_group("EmbouchureChimney");
+        let rim = flute.wall_thickness;
+        // Base sits inside the wall: above the bore under the hole edge,
+        // below the outer surface at the riser's edge so the two solids fuse
+        let above_bore = (r_inner.powi(2) - m_b.powi(2)).max(0.0).sqrt();
+        let below_outer = (r_outer.powi(2) - (m_b + rim).powi(2)).max(0.0).sqrt();
+        let y_base = if below_outer > above_bore {
+            (above_bore + below_outer) / 2.0
+        } else {
+            above_bore
+        };
+        let riser = RadialPrism {
+            a: m_a + rim,
+            b: m_b + rim,
+            profile: vec![(y_base, 0.0), (chimney_top, 0.0)],
+            ..mouth
+        };
+        add_radial_prism(mesh, &riser, lod.hole_segments());
+    }
+}
+
+/// Notch cutter at the blowing end. The outside bevel makes the cut run further along
+/// the outer surface than the bore surface, leaving a sharp edge on the inside.
+fn add_notch(mesh: &mut Mesh, flute: &Flute, notch: &NotchCut, lod: Lod) {
+    mesh.set_group("NotchCutter");
+    let depth = notch.depth.max(0.05);
+    add_bevel_cutter(mesh, flute, notch.bevel_angle, 0.0, |extra| {
+        notch_outline(notch, depth + extra, 0.5, lod.arc_steps())
+    });
+}
+
+/// Block plug, windway, window and labium cutters of a duct flute. The block is a separate
+/// solid so it can be printed in place or on its own and glued in; the window and the
+/// labium ramp are separate cutters so the edge can be checked and tuned on its own.
+fn add_duct(mesh: &mut Mesh, flute: &Flute, duct: &DuctGeometry, lod: Lod) {
+    let r_inner = flute.bore_radius;
+    let x_head = -(duct.window_length + duct.windway_length);
+    let x_window = -duct.window_length;
+    // Keep the channel inside the bore so the cutter never touches the tube wall
+    let hw = (duct.windway_width / 2.0).min(0.95 * r_inner);
+    let floor = r_inner - duct.windway_height.clamp(0.01, r_inner);
+
+    mesh.set_group("FippleBlock");
+    let segments = lod.hole_segments();
+    let circle: Vec<(f64, f64)> = (0..segments)
+        .map(|i| {
+            let t = 2.0 * PI * (i as f64) / (segments as f64);
+            let r = r_inner - BLOCK_CLEARANCE;
+            (r * t.sin(), r * t.cos())
+        })
+        .collect();
+    add_axial_prism(mesh, &circle, (x_head, x_window));
+
+    // Channel cross-section: flat floor, straight sides, bore arc on top.
+    // A shallow channel is just the circular segment above the floor.
+    mesh.set_group("WindwayCutter");
+    let hw = hw.min((r_inner.powi(2) - floor.powi(2)).sqrt());
+    let side_top = (r_inner.powi(2) - hw.powi(2)).sqrt();
+    let mut channel = Vec::new();
+    if side_top - floor > 1e-4 {
+        channel.push((floor, hw));
+    }
+    let steps = lod.arc_steps();
+    for k in 0..=steps {
+        let z = hw - 2.0 * hw * (k as f64) / (steps as f64);
+        channel.push(((r_inner.powi(2) - z.powi(2)).sqrt(), z));
+    }
+    if side_top - floor > 1e-4 {
+        channel.push((floor, -hw));
+    }
+    add_axial_prism(mesh, &channel, (x_head - 0.5, x_window + 0.1));
+
+    // Square-sided window straight through the wall, block face to labium edge
+    mesh.set_group("WindowCutter");
+    let r_outer = r_inner + flute.wall_thickness;
+    add_radial_box(
+        mesh,
+        (x_window, 0.0),
+        hw,
+        (r_inner - 0.2, r_outer + 0.2),
+        0.0,
+    );
+
+    // Labium ramp at the blade angle over the chamfer flat, reaching back into the window
+    // so the two cutters overlap instead of sharing a face
+    mesh.set_group("LabiumCutter");
+    let x_start = 0.5 * x_window;
+    add_bevel_cutter(mesh, flute, duct.ramp_angle, duct.labium_chamfer, |extra| {
+        vec![(x_start, -hw), (extra, -hw), (extra, hw), (x_start, hw)]
+    });
+}
+
+const BLOCK_CLEARANCE: f64 = 0.01; // cm, glue gap between block and bore
+
+pub fn generate_flute_mesh(flute: &Flute) -> Mesh {
+    generate_flute_mesh_with(flute, &MeshOptions::default())
+}
+
+pub fn generate_flute_mesh_with(flute: &Flute, options: &MeshOptions) -> Mesh {
+    generate_flute_mesh_lod(flute, options, options.lod)
+}
+
+/// Same mesh at a chosen resolution, e.g. `Lod::Draft` while the user is dragging
+pub fn generate_flute_mesh_lod(flute: &Flute, options: &MeshOptions, lod: Lod) -> Mesh {
+    trace_span!(INFO, "generate_flute_mesh", holes = flute.holes.len(), ?lod);
+    let mut mesh = body_part(flute, options, lod);
+    for hole in &flute.holes {
+        mesh.append(&hole_part(flute, hole, options, lod));
+    }
+    mesh.append(&trim_part(flute, options, lod));
+    mesh
+}
+
+/// The tube itself, which no hole edit touches
+fn body_part(flute: &Flute, options: &MeshOptions, lod: Lod) -> Mesh {
+    let mut mesh = Mesh::new();
+
     // 1. Tube Body
     mesh.set_group("TubeBody");
 
-    let segments = 64;
     let length = flute.length;
     let r_inner = flute.bore_radius;
     let r_outer = flute.bore_radius + flute.wall_thickness;
+    let head = head_extension(flute);
+
+    add_annulus(
+        &mut mesh,
+        (-head, length),
+        (r_inner, r_outer),
+        &options.tube_ends,
+        lod,
+    );
+    mesh
+}
+
+/// One tone hole's cutter; empty for a closed hole
+fn hole_part(flute: &Flute, hole: &Hole, options: &MeshOptions, lod: Lod) -> Mesh {
+    let mut mesh = Mesh::new();
+    if !hole.open {
+        return mesh;
+    }
+    let r_inner = flute.bore_radius;
+    let r_outer = flute.bore_radius + flute.wall_thickness;
 
-    let ring_l_in = add_ring(&mut mesh, -5.0, r_inner, segments);
-    let ring_l_out = add_ring(&mut mesh, -5.0, r_outer, segments);
-    let ring_r_in = add_ring(&mut mesh, length, r_inner, segments);
-    let ring_r_out = add_ring(&mut mesh, length, r_outer, segments);
+    // 2. Hole Cutters (Cylinders)
+    mesh.set_group("HoleCutters");
 
-    // Outer Surface (facing out)
-    stitch_rings(&mut mesh, &ring_l_out, &ring_r_out, true, segments);
+    // Cutter length: needs to pass through the wall.
+    // Wall extends from r_inner to r_outer.
+    // Let's make cutter go from r_inner - 0.5 to r_outer + 0.5
+    let cutter = RadialPrism {
+        x: hole.position,
+        a: hole.radius,
+        b: hole.radius,
+        profile: cutter_profile(
+            &options.hole_rims,
+            0.9 * flute.wall_thickness,
+            r_inner - 0.5,
+            r_outer,
+            r_outer + 0.5,
+            lod,
+        ),
+        azimuth: hole.azimuth.to_radians(),
+    };
+    add_radial_prism(&mut mesh, &cutter, lod.hole_segments());
+    mesh
+}
 
-    // Inner Surface (facing in)
-    stitch_rings(&mut mesh, &ring_l_in, &ring_r_in, false, segments);
+/// A stopped pipe's cap over the top end, or the tuning slot cut down from an open top on
+/// the side away from the mouth
+fn add_pipe_top(mesh: &mut Mesh, flute: &Flute, pipe: &FluePipe, lod: Lod) {
+    let r_outer = flute.bore_radius + flute.wall_thickness;
+    if pipe.stopped {
+        // A little proud of the tube, so it reads as a cap and shares no edge with the end
+        mesh.set_group("PipeCap");
+        let r_cap = r_outer + 0.5 * flute.wall_thickness;
+        let segments = lod.tube_segments();
+        let circle: Vec<(f64, f64)> = (0..segments)
+            .map(|i| {
+                let t = 2.0 * PI * (i as f64) / (segments as f64);
+                (r_cap * t.sin(), r_cap * t.cos())
+            })
+            .collect();
+        add_axial_prism(
+            mesh,
+            &circle,
+            (flute.length, flute.length + flute.wall_thickness),
+        );
+    } else if let Some(slot) = pipe.tuning_slot {
+        mesh.set_group("TuningSlotCutter");
+        add_radial_box(
+            mesh,
+            (flute.length - slot.length, flute.length + 0.5),
+            slot.width / 2.0,
+            (flute.bore_radius - 0.1, r_outer + 0.1),
+            PI,
+        );
+    }
+}
 
-    // End Caps (Rim)
-    // Left Rim (x=0): connect Outer to Inner
-    stitch_rings(&mut mesh, &ring_l_out, &ring_l_in, false, segments); // Check normals... Outer is ccw?
-                                                                       // Left: Normal points -X.
-                                                                       // Ring L Out points in +Y. The quad 1->2->2'->1'
+/// Blowing feature and decorative rings
+fn trim_part(flute: &Flute, options: &MeshOptions, lod: Lod) -> Mesh {
+    let mut mesh = Mesh::new();
+    let r_outer = flute.bore_radius + flute.wall_thickness;
 
-    // Right Rim (x=L)
-    stitch_rings(&mut mesh, &ring_r_out, &ring_r_in, true, segments);
+    match &flute.instrument {
+        InstrumentType::Transverse => add_embouchure(&mut mesh, flute, options, lod),
+        InstrumentType::Notch(notch) => add_notch(&mut mesh, flute, notch, lod),
+        InstrumentType::Duct(duct) => add_duct(&mut mesh, flute, duct, lod),
+        InstrumentType::OrganPipe(pipe) => {
+            add_duct(&mut mesh, flute, &pipe.duct(), lod);
+            add_pipe_top(&mut mesh, flute, pipe, lod);
+        }
+    }
 
-    // 2. Hole Cutters (Cylinders)
-    mesh.set_group("HoleCutters");
+    // 5. Decorative rings: sunk halfway into the wall so the shells fuse when sliced
+    for (n, ring) in options.rings.iter().enumerate() {
+        let r_ring = ring.outer_diameter / 2.0;
+        if ring.width <= 0.0 || r_ring <= r_outer {
+            continue;
+        }
+        mesh.set_group(&format!("DecorativeRing{}", n + 1));
+        add_annulus(
+            &mut mesh,
+            (ring.position, ring.position + ring.width),
+            (r_outer - 0.5 * flute.wall_thickness, r_ring),
+            &options.tube_ends,
+            lod,
+        );
+    }
 
-    for hole in &flute.holes {
+    // 6. Engravings: one box per horizontal run of pixels, each row turned to its own
+    // azimuth so the letters wrap with the surface. Rows run top to bottom towards +Z.
+    for (n, engraving) in options.engravings.iter().enumerate() {
+        let runs = font::pixel_runs(&engraving.text);
+        if runs.is_empty() || engraving.height <= 0.0 || engraving.depth <= 0.0 {
+            continue;
+        }
+        let (group, y) = if engraving.emboss {
+            // Sunk into the wall so the letters fuse with the body
+            let y = (
+                r_outer - 0.5 * flute.wall_thickness,
+                r_outer + engraving.depth,
+            );
+            (format!("Emboss{}", n + 1), y)
+        } else {
+            let y = (r_outer - engraving.depth, r_outer + 0.2);
+            (format!("Engraving{}", n + 1), y)
+        };
+        mesh.set_group(&group);
+        let pixel = engraving.height / font::GLYPH_HEIGHT as f64;
+        let mid_row = (font::GLYPH_HEIGHT as f64 - 1.0) / 2.0;
+        for (row, first, last) in runs {
+            let azimuth = engraving.azimuth.to_radians() + (row as f64 - mid_row) * pixel / r_outer;
+            let x0 = engraving.position + first as f64 * pixel;
+            let x1 = engraving.position + (last + 1) as f64 * pixel;
+            add_radial_box(&mut mesh, (x0, x1), pixel / 2.0, y, azimuth);
+        }
+    }
+
+    mesh
+}
+
+/// The air inside the instrument as closed solids, for visualisation or import into an FEM
+/// acoustics package: the bore from the cork face (or block face) to the foot as `AirBore`,
+/// and one `AirChimney<n>` stub per open hole, reaching down into the bore so a boolean
+/// union gives the single air domain.
+pub fn generate_air_column_mesh(flute: &Flute, lod: Lod) -> Mesh {
+    let mut mesh = Mesh::new();
+    let r_inner = flute.bore_radius;
+    let r_outer = flute.bore_radius + flute.wall_thickness;
+
+    let start = match &flute.instrument {
+        InstrumentType::Transverse => -flute.cork_position,
+        InstrumentType::Notch(_) => 0.0,
+        InstrumentType::Duct(duct) => -duct.window_length,
+        InstrumentType::OrganPipe(pipe) => -pipe.cut_up,
+    };
+    mesh.set_group("AirBore");
+    let segments = lod.tube_segments();
+    let circle: Vec<(f64, f64)> = (0..segments)
+        .map(|i| {
+            let t = 2.0 * PI * (i as f64) / (segments as f64);
+            (r_inner * t.sin(), r_inner * t.cos())
+        })
+        .collect();
+    add_axial_prism(&mut mesh, &circle, (start, flute.length));
+
+    let mut stubs = Vec::new();
+    if flute.instrument.has_embouchure_hole() {
+        let oval = flute.embouchure_oval.max(0.1);
+        stubs.push(RadialPrism {
+            x: 0.0,
+            a: flute.embouchure_hole_radius * oval.sqrt(),
+            b: flute.embouchure_hole_radius / oval.sqrt(),
+            profile: vec![
+                (0.5 * r_inner, 0.0),
+                (
+                    r_inner + flute.embouchure_chimney.max(flute.wall_thickness),
+                    0.0,
+                ),
+            ],
+            azimuth: 0.0,
+        });
+    }
+    for &i in &flute.hole_order() {
+        let hole = &flute.holes[i];
         if !hole.open {
             continue;
         }
-        // Maybe don't export closed holes? Or export as separate group?
-        // Let's export all defined holes as cutters.
+        stubs.push(RadialPrism {
+            x: hole.position,
+            a: hole.radius,
+            b: hole.radius,
+            profile: vec![(0.5 * r_inner, 0.0), (r_outer, 0.0)],
+            azimuth: hole.azimuth.to_radians(),
+        });
+    }
+    for (n, stub) in stubs.iter().enumerate() {
+        mesh.set_group(&format!("AirChimney{}", n + 1));
+        add_radial_prism(&mut mesh, stub, lod.hole_segments());
+    }
+    mesh
+}
+
+/// Parts of the last generated mesh, so editing one hole only rebuilds that hole's cutter.
+/// The owner invalidates whatever its edits touch; anything missing is rebuilt on demand.
+#[derive(Default)]
+pub struct MeshCache {
+    lod: Option<Lod>,
+    body: Option<Mesh>,
+    holes: Vec<Option<Mesh>>,
+    trim: Option<Mesh>,
+}
+
+impl MeshCache {
+    /// Drop everything, e.g. after the tube or the mesh options change
+    pub fn invalidate(&mut self) {
+        *self = MeshCache::default();
+    }
+
+    pub fn invalidate_hole(&mut self, index: usize) {
+        if let Some(part) = self.holes.get_mut(index) {
+            *part = None;
+        }
+    }
+
+    /// Keep the other holes' parts when a hole is inserted at `index`
+    pub fn insert_hole(&mut self, index: usize) {
+        if index <= self.holes.len() {
+            self.holes.insert(index, None);
+        }
+    }
 
-        let h_segments = 32;
-        let h_r = hole.radius;
-        let h_x = hole.position;
+    /// Keep the other holes' parts when the hole at `index` is removed
+    pub fn remove_hole(&mut self, index: usize) {
+        if index < self.holes.len() {
+            self.holes.remove(index);
+        }
+    }
 
-        // Cutter length: needs to pass through the wall.
-        // Wall extends from r_inner to r_outer.
-        // Let's make cutter go from r_inner - 0.5 to r_outer + 0.5
-        let y_start = r_inner - 0.5;
-        let y_end = r_outer + 0.5;
+    /// Whether the next `mesh` call has anything to rebuild
+    pub fn is_dirty(&self) -> bool {
+        self.body.is_none() || self.trim.is_none() || self.holes.iter().any(Option::is_none)
+    }
 
-        // Ring Bottom
-        let mut ring_bot = Vec::new();
-        for j in 0..h_segments {
-            let theta = 2.0 * PI * (j as f64) / (h_segments as f64);
-            // Cylinder along Y axis
-            // base circle in XZ plane
-            let local_x = h_r * theta.cos();
-            let local_z = h_r * theta.sin();
+    /// Same result as `generate_flute_mesh_lod`, rebuilding only invalidated parts
+    pub fn mesh(&mut self, flute: &Flute, options: &MeshOptions, lod: Lod) -> Mesh {
+        trace_span!(DEBUG, "mesh_cache", ?lod, dirty = self.is_dirty());
+        if self.lod != Some(lod) {
+            self.invalidate();
+            self.lod = Some(lod);
+        }
+        if self.holes.len() != flute.holes.len() {
+            self.holes.clear();
+            self.holes.resize_with(flute.holes.len(), || None);
+        }
 
-            let vx = h_x + local_x;
-            let vy = y_start;
-            let vz = local_z;
+        let body = self
+            .body
+            .get_or_insert_with(|| body_part(flute, options, lod));
+        let mut mesh = Mesh::new();
+        mesh.append(body);
+        for (part, hole) in self.holes.iter_mut().zip(&flute.holes) {
+            mesh.append(part.get_or_insert_with(|| hole_part(flute, hole, options, lod)));
+        }
+        mesh.append(
+            self.trim
+                .get_or_insert_with(|| trim_part(flute, options, lod)),
+        );
+        mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Hole;
 
-            ring_bot.push(mesh.add_vertex(vx, vy, vz));
+    fn cube() -> Mesh {
+        let mut mesh = Mesh::new();
+        for &(x, y, z) in &[
+            (0.0, 0.0, 0.0),
+            (1.0, 0.0, 0.0),
+            (1.0, 1.0, 0.0),
+            (0.0, 1.0, 0.0),
+            (0.0, 0.0, 1.0),
+            (1.0, 0.0, 1.0),
+            (1.0, 1.0, 1.0),
+            (0.0, 1.0, 1.0),
+        ] {
+            mesh.add_vertex(x, y, z);
         }
+        mesh.add_face(&[1, 4, 3, 2]);
+        mesh.add_face(&[5, 6, 7, 8]);
+        mesh.add_face(&[1, 2, 6, 5]);
+        mesh.add_face(&[3, 4, 8, 7]);
+        mesh.add_face(&[2, 3, 7, 6]);
+        mesh.add_face(&[1, 5, 8, 4]);
+        mesh
+    }
+
+    #[test]
+    fn test_cube_is_watertight() {
+        let report = cube().validate();
+        assert!(report.watertight, "{:?}", report);
+        assert_eq!(report.shell_count, 1);
+    }
 
-        // Ring Top
-        let mut ring_top = Vec::new();
-        for j in 0..h_segments {
-            let theta = 2.0 * PI * (j as f64) / (h_segments as f64);
-            let local_x = h_r * theta.cos();
-            let local_z = h_r * theta.sin();
+    #[test]
+    fn test_validate_detects_defects() {
+        let mut open = cube();
+        open.groups[0].1.pop();
+        let report = open.validate();
+        assert_eq!(report.boundary_edges, 4);
 
-            let vx = h_x + local_x;
-            let vy = y_end;
-            let vz = local_z;
+        let mut flipped = cube();
+        flipped.groups[0].1[0].reverse();
+        let report = flipped.validate();
+        assert_eq!(report.flipped_edges, 4);
 
-            ring_top.push(mesh.add_vertex(vx, vy, vz));
+        let mut inverted = cube();
+        for face in &mut inverted.groups[0].1 {
+            face.reverse();
         }
+        assert_eq!(inverted.validate().inverted_shells, 1);
 
-        // Stitch Cutter
-        stitch_rings(&mut mesh, &ring_bot, &ring_top, false, h_segments);
+        let mut duplicated = cube();
+        duplicated.add_vertex(1.0, 1.0, 1.0);
+        assert_eq!(duplicated.validate().duplicate_vertices, 1);
+    }
 
-        // Caps for cutter (so it's a solid boolean object)
-        let mut bot_rev = ring_bot.clone();
-        bot_rev.reverse();
-        mesh.add_face(&bot_rev); // Basic n-gon cap
+    #[test]
+    fn test_obj_units_and_up_axis() {
+        let obj = cube().to_obj_string(ExportUnits::default(), UpAxis::default());
+        assert!(obj.contains("# Units: mm, Y-up"));
+        assert!(obj.contains("v 10.0000 10.0000 0.0000\n"));
 
-        let top_norm = ring_top.clone(); // top_norm need to be ccw from top?
-        mesh.add_face(&top_norm);
+        let obj = cube().to_obj_string(ExportUnits::Inches, UpAxis::Z);
+        // (1, 1, 0) cm: old +Y becomes +Z
+        assert!(obj.contains("v 0.3937 0.0000 0.3937\n"));
+        // (0, 0, 1) cm: old +Z becomes -Y
+        assert!(obj.contains("v 0.0000 -0.3937 0.0000\n"));
     }
 
-    // 3. Mouth Hole Cutter
-    mesh.set_group("MouthHoleCutter");
+    #[test]
+    fn test_check_geometry() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, true)).unwrap();
+        flute.holes.push(Hole::new(40.6, 0.35, true)).unwrap(); // overlaps the first
+        flute.holes.push(Hole::new(45.0, 0.35, true)).unwrap();
+        flute.holes.push(Hole::new(45.8, 0.35, true)).unwrap(); // 1 mm of material between them
+        flute.holes.push(Hole::new(0.4, 0.3, true)).unwrap(); // on the mouth hole
+        flute.holes.push(Hole::new(30.0, 1.0, true)).unwrap(); // wider than the bore
 
-    // Position the mouth hole at x=0, which corresponds to the acoustic start of the air column.
-    // The physical tube extends backwards to -5.0cm (headjoint/stopper) for visual realism.
-
-    let m_r = 0.4; // Approximate mouth radius
-    let m_x = 0.0; // At the "start"
-
-    // Cutter at mouth
-    let y_start = r_inner - 0.5;
-    let y_end = r_outer + 0.5;
-
-    let h_segments = 32;
-    // Ring Bottom
-    let mut ring_bot = Vec::new();
-    for j in 0..h_segments {
-        let theta = 2.0 * PI * (j as f64) / (h_segments as f64);
-        let local_x = m_r * theta.cos();
-        let local_z = m_r * theta.sin();
-        ring_bot.push(mesh.add_vertex(m_x + local_x, y_start, local_z));
-    }
-    let mut ring_top = Vec::new();
-    for j in 0..h_segments {
-        let theta = 2.0 * PI * (j as f64) / (h_segments as f64);
-        let local_x = m_r * theta.cos();
-        let local_z = m_r * theta.sin();
-        ring_top.push(mesh.add_vertex(m_x + local_x, y_end, local_z));
-    }
-    stitch_rings(&mut mesh, &ring_bot, &ring_top, false, h_segments);
-    let mut bot_rev = ring_bot.clone();
-    bot_rev.reverse();
-    mesh.add_face(&bot_rev);
-    let top_norm = ring_top.clone();
-    mesh.add_face(&top_norm);
+        let codes: Vec<(&str, Option<usize>)> = check_geometry(&flute)
+            .iter()
+            .map(|i| (i.code, i.hole))
+            .collect();
+        assert!(codes.contains(&("raised_chimney", None)));
+        assert!(codes.contains(&("holes_overlap", Some(0))));
+        assert!(codes.contains(&("thin_bridge", Some(2))));
+        assert!(codes.contains(&("overlaps_embouchure", Some(4))));
+        assert!(codes.contains(&("hole_too_large", Some(5))));
 
-    mesh
+        // Turning the neighbour to the thumb side clears the overlap
+        flute.holes[1].azimuth = 180.0;
+        let codes: Vec<&str> = check_geometry(&flute).iter().map(|i| i.code).collect();
+        assert!(!codes.contains(&"holes_overlap"));
+
+        let thin = Flute::new(60.0, 0.95, 0.05);
+        assert_eq!(check_geometry(&thin)[0].code, "thin_wall");
+    }
+
+    #[test]
+    fn test_cube_buffers() {
+        let buffers = cube().to_buffers();
+        assert_eq!(buffers.positions.len(), 24);
+        assert_eq!(buffers.normals.len(), 24);
+        assert_eq!(buffers.groups.len(), 1);
+        // Six quads, two triangles each
+        assert_eq!(buffers.groups[0].1.len(), 36);
+        // Every corner normal is unit length and points away from the centre
+        for (p, n) in buffers.positions.chunks(3).zip(buffers.normals.chunks(3)) {
+            let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+            assert!((len - 1.0).abs() < 1e-6);
+            let outward: f32 = (0..3).map(|k| (p[k] - 0.5) * n[k]).sum();
+            assert!(outward > 0.0, "{:?} {:?}", p, n);
+        }
+    }
+
+    #[test]
+    fn test_cube_stats() {
+        let stats = cube().group_stats();
+        assert_eq!(stats.len(), 1);
+        assert!((stats[0].volume - 1.0).abs() < 1e-12);
+        assert!((stats[0].surface_area - 6.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_print_estimate_matches_tube_volume() {
+        let flute = Flute::new(60.0, 0.95, 0.4);
+        let est = estimate_print(&flute, DEFAULT_PRINT_DENSITY, 1.0);
+        // Annulus over the full body, including the stub behind the embouchure
+        let exact = PI * (1.35f64.powi(2) - 0.95f64.powi(2)) * (60.0 + head_extension(&flute));
+        assert!((est.volume - exact).abs() / exact < 0.01, "{}", est.volume);
+        assert!((est.weight - exact * DEFAULT_PRINT_DENSITY).abs() / est.weight < 0.01);
+
+        let sparse = estimate_print(&flute, DEFAULT_PRINT_DENSITY, 0.0);
+        assert!(sparse.weight < est.weight);
+        assert!(sparse.print_hours < est.print_hours);
+
+        // Holes are drilled whether the fingering covers them or not
+        let mut drilled = flute.clone();
+        drilled.holes.push(Hole::new(40.0, 0.35, true)).unwrap();
+        let open = estimate_print(&drilled, DEFAULT_PRINT_DENSITY, 1.0);
+        assert!(open.volume < est.volume);
+        drilled.holes[0].open = false;
+        assert_eq!(estimate_print(&drilled, DEFAULT_PRINT_DENSITY, 1.0), open);
+    }
+
+    #[test]
+    fn test_mouth_cutter_follows_embouchure() {
+        let mut flute = Flute::new(60.0, 0.95, 0.5);
+        flute.embouchure_hole_radius = 0.55;
+        flute.embouchure_oval = 1.21;
+        flute.cork_position = 4.0;
+        let mesh = generate_flute_mesh(&flute);
+
+        // Wall covers the chimney: no riser
+        assert!(mesh
+            .groups
+            .iter()
+            .all(|(name, _)| name != "EmbouchureChimney"));
+        // Cork moved back: the stub grows to hold it
+        assert!((head_extension(&flute) - 6.0).abs() < 1e-12);
+
+        let xs: Vec<f64> = mesh.vertices.iter().map(|v| v.0).collect();
+        assert!(xs.iter().any(|&x| (x + 6.0).abs() < 1e-12));
+        // Oval: 0.55 * 1.1 along the axis
+        let mouth_start = mesh.vertices.len() - 64;
+        let max_x = mesh.vertices[mouth_start..]
+            .iter()
+            .map(|v| v.0)
+            .fold(f64::MIN, f64::max);
+        assert!((max_x - 0.605).abs() < 1e-9, "{}", max_x);
+    }
+
+    #[test]
+    fn test_hole_cutter_follows_azimuth() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        let mut thumb = Hole::new(20.0, 0.3, true);
+        thumb.azimuth = 180.0;
+        flute.holes.push(thumb).unwrap();
+        let mesh = generate_flute_mesh(&flute);
+
+        // Hole cutter vertices are the 64 after the body's four rings
+        let cutter = &mesh.vertices[4 * 64..4 * 64 + 64];
+        assert!(cutter.iter().all(|v| v.1 < -0.4), "cutter not underneath");
+        assert!(mesh.validate().watertight);
+    }
+
+    #[test]
+    fn test_edge_treatments_stay_watertight() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(30.0, 0.3, true)).unwrap();
+        let sharp = generate_flute_mesh(&flute);
+
+        for profile in [EdgeProfile::Chamfer, EdgeProfile::Fillet] {
+            let edge = EdgeTreatment { profile, size: 0.1 };
+            let options = MeshOptions {
+                tube_ends: edge,
+                hole_rims: edge,
+                embouchure_edge: edge,
+                ..Default::default()
+            };
+            let mesh = generate_flute_mesh_with(&flute, &options);
+            let report = mesh.validate();
+            assert!(report.watertight, "{:?} {:?}", profile, report);
+            assert!(mesh.vertices.len() > sharp.vertices.len());
+
+            // Softened ends remove material: the body gets lighter
+            let body = |m: &Mesh| {
+                m.group_stats()
+                    .into_iter()
+                    .find(|g| g.name == "TubeBody")
+                    .unwrap()
+                    .volume
+            };
+            assert!(body(&mesh) < body(&sharp));
+        }
+    }
+
+    #[test]
+    fn test_decorative_rings_are_separate_shells() {
+        let flute = Flute::new(60.0, 0.95, 0.4);
+        let options = MeshOptions {
+            rings: vec![
+                DecorativeRing {
+                    position: -4.0,
+                    width: 1.0,
+                    outer_diameter: 3.2,
+                },
+                DecorativeRing {
+                    position: 58.0,
+                    width: 2.0,
+                    outer_diameter: 3.0,
+                },
+                // Thinner than the body: skipped
+                DecorativeRing {
+                    position: 20.0,
+                    width: 1.0,
+                    outer_diameter: 2.0,
+                },
+            ],
+            ..Default::default()
+        };
+        let mesh = generate_flute_mesh_with(&flute, &options);
+        let report = mesh.validate();
+        assert!(report.watertight, "{:?}", report);
+
+        let stats = mesh.group_stats();
+        let ring = stats.iter().find(|g| g.name == "DecorativeRing2").unwrap();
+        let exact = PI * (1.5f64.powi(2) - 1.15f64.powi(2)) * 2.0;
+        assert!((ring.volume - exact).abs() / exact < 0.01);
+        assert!(stats.iter().all(|g| g.name != "DecorativeRing3"));
+    }
+
+    #[test]
+    fn test_notch_flute_mesh() {
+        for shape in [NotchShape::U, NotchShape::V] {
+            let mut quena = Flute::new(40.0, 0.9, 0.3);
+            quena.instrument = InstrumentType::Notch(NotchCut {
+                shape,
+                ..Default::default()
+            });
+            let mesh = generate_flute_mesh(&quena);
+            let report = mesh.validate();
+            assert!(report.watertight, "{:?} {:?}", shape, report);
+
+            let names: Vec<&str> = mesh.groups.iter().map(|(n, _)| n.as_str()).collect();
+            assert!(names.contains(&"NotchCutter"));
+            assert!(!names.contains(&"MouthHoleCutter"));
+            // Body starts at the blowing end
+            assert_eq!(head_extension(&quena), 0.0);
+            let min_body_x = mesh.vertices[..64]
+                .iter()
+                .map(|v| v.0)
+                .fold(f64::MAX, f64::min);
+            assert_eq!(min_body_x, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_duct_flute_mesh() {
+        let mut whistle = Flute::new(30.0, 0.8, 0.25);
+        whistle.instrument = InstrumentType::Duct(DuctGeometry::default());
+        let mesh = generate_flute_mesh(&whistle);
+        let report = mesh.validate();
+        assert!(report.watertight, "{:?}", report);
+        assert_eq!(report.inverted_shells, 0);
+
+        let names: Vec<&str> = mesh.groups.iter().map(|(n, _)| n.as_str()).collect();
+        for part in [
+            "FippleBlock",
+            "WindwayCutter",
+            "WindowCutter",
+            "LabiumCutter",
+        ] {
+            assert!(names.contains(&part), "{:?}", names);
+        }
+        assert!(!names.contains(&"MouthHoleCutter"));
+        assert_eq!(head_extension(&whistle), 4.5);
+
+        // The window is square-sided, block face to edge; the ramp starts above the chamfer
+        let group_vertices = |mesh: &Mesh, name: &str| {
+            let triangles = mesh.group_triangles();
+            let (_, faces) = triangles.iter().find(|(n, _)| *n == name).unwrap();
+            faces
+                .iter()
+                .flatten()
+                .map(|&i| mesh.vertices[i as usize])
+                .collect::<Vec<_>>()
+        };
+        assert!(group_vertices(&mesh, "WindowCutter")
+            .iter()
+            .all(|v| v.0 == -0.5 || v.0 == 0.0));
+        let chamfered = |chamfer: f64| {
+            let mut w = whistle.clone();
+            w.instrument = InstrumentType::Duct(DuctGeometry {
+                labium_chamfer: chamfer,
+                ..DuctGeometry::default()
+            });
+            w
+        };
+        let tip = |w: &Flute| {
+            group_vertices(&generate_flute_mesh(w), "LabiumCutter")
+                .iter()
+                .filter(|v| v.0 == 0.0 && v.1 < 0.8 + 0.25)
+                .map(|v| v.1)
+                .fold(f64::MIN, f64::max)
+        };
+        assert!((tip(&chamfered(0.05)) - 0.85).abs() < 1e-12);
+        let codes = |w: &Flute| {
+            check_geometry(w)
+                .into_iter()
+                .map(|i| i.code)
+                .collect::<Vec<_>>()
+        };
+        assert!(codes(&whistle).is_empty());
+        assert_eq!(codes(&chamfered(0.0)), ["sharp_labium"]);
+        assert_eq!(codes(&chamfered(0.3)), ["labium_chamfer"]);
+    }
+
+    #[test]
+    fn test_lod_meshes_share_shape() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, true)).unwrap();
+        let options = MeshOptions {
+            tube_ends: EdgeTreatment {
+                profile: EdgeProfile::Fillet,
+                size: 0.1,
+            },
+            ..Default::default()
+        };
+
+        let counts: Vec<usize> = [Lod::Draft, Lod::Standard, Lod::Fine]
+            .iter()
+            .map(|&lod| {
+                let mesh = generate_flute_mesh_lod(&flute, &options, lod);
+                let report = mesh.validate();
+                assert!(report.watertight, "{:?} {:?}", lod, report);
+                assert_eq!(report.shell_count, 4);
+                report.vertex_count
+            })
+            .collect();
+        assert!(
+            counts[0] < counts[1] && counts[1] < counts[2],
+            "{:?}",
+            counts
+        );
+        assert_eq!(
+            generate_flute_mesh_with(&flute, &options).vertices().len(),
+            counts[1]
+        );
+    }
+
+    #[test]
+    fn test_mesh_cache_rebuilds_only_dirty_parts() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for pos in [30.0, 35.0, 40.0] {
+            flute.holes.push(Hole::new(pos, 0.35, true)).unwrap();
+        }
+        let options = MeshOptions::default();
+        let fresh = |f: &Flute| generate_flute_mesh_lod(f, &options, Lod::Draft);
+
+        let mut cache = MeshCache::default();
+        assert!(cache.is_dirty());
+        let first = cache.mesh(&flute, &options, Lod::Draft);
+        assert!(!cache.is_dirty());
+        assert_eq!(first.vertices(), fresh(&flute).vertices());
+
+        flute.holes[1].position = 36.0;
+        flute.holes[2].open = false;
+        cache.invalidate_hole(1);
+        cache.invalidate_hole(2);
+        assert!(cache.is_dirty());
+        let edited = cache.mesh(&flute, &options, Lod::Draft);
+        let expected = fresh(&flute);
+        assert_eq!(edited.vertices(), expected.vertices());
+        assert_eq!(edited.groups, expected.groups);
+        assert!(edited.validate().watertight);
+
+        flute.holes.insert(0, Hole::new(25.0, 0.3, true)).unwrap();
+        cache.insert_hole(0);
+        flute.holes.remove(2);
+        cache.remove_hole(2);
+        // Only the new hole is rebuilt; the survivors keep their cached parts
+        assert_eq!(cache.holes.iter().filter(|h| h.is_none()).count(), 1);
+        let reshaped = cache.mesh(&flute, &options, Lod::Draft);
+        assert_eq!(reshaped.vertices(), fresh(&flute).vertices());
+    }
+
+    #[test]
+    fn test_engraving_and_emboss() {
+        // "L": a 7-pixel stem and a 5-pixel foot, drawn as 7 runs
+        assert_eq!(font::pixel_runs("L").len(), 7);
+        assert_eq!(font::pixel_runs("L")[6], (6, 0, 4));
+        // Second letter starts after a blank column
+        assert_eq!(font::pixel_runs("LL")[0], (0, 0, 0));
+        assert_eq!(font::pixel_runs("LL")[1], (0, 6, 6));
+
+        let flute = Flute::new(60.0, 0.95, 0.4);
+        let options = MeshOptions {
+            engravings: vec![
+                Engraving {
+                    text: "Flyte 440".to_string(),
+                    position: 45.0,
+                    ..Default::default()
+                },
+                Engraving {
+                    text: "A".to_string(),
+                    position: 50.0,
+                    azimuth: 180.0,
+                    emboss: true,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let mesh = generate_flute_mesh_with(&flute, &options);
+        let report = mesh.validate();
+        assert!(report.watertight, "{:?}", report);
+
+        let boxes = |name: &str| {
+            mesh.groups
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, faces)| faces.len() / 6)
+                .unwrap_or(0)
+        };
+        assert_eq!(boxes("Engraving1"), font::pixel_runs("Flyte 440").len());
+        assert_eq!(boxes("Emboss2"), font::pixel_runs("A").len());
+        // Embossed letters stand proud of the tube on the thumb side
+        let r_outer = 0.95 + 0.4;
+        assert!(mesh
+            .vertices()
+            .iter()
+            .any(|v| v.1 < -r_outer - 0.04 && v.0 > 50.0));
+    }
+
+    #[test]
+    fn test_air_column_mesh() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, true)).unwrap();
+        flute.holes.push(Hole::new(30.0, 0.3, false)).unwrap();
+        let mesh = generate_air_column_mesh(&flute, Lod::Fine);
+        let report = mesh.validate();
+        assert!(report.watertight, "{:?}", report);
+        // Bore plus the embouchure and the one open hole
+        assert_eq!(report.shell_count, 3);
+
+        let stats = mesh.group_stats();
+        let bore = stats.iter().find(|g| g.name == "AirBore").unwrap();
+        let exact = PI * 0.95f64.powi(2) * (60.0 + flute.cork_position);
+        assert!(
+            (bore.volume - exact).abs() / exact < 0.01,
+            "{}",
+            bore.volume
+        );
+        assert!(stats.iter().any(|g| g.name == "AirChimney2"));
+    }
+
+    #[test]
+    fn test_balance_point() {
+        let flute = Flute::new(60.0, 0.95, 0.4);
+        let options = MeshOptions::default();
+        let mut plain = flute.clone();
+        plain.embouchure_chimney = plain.wall_thickness;
+        let report = estimate_balance(&plain, &options, 1.0);
+        // Plain tube from -5 to 60 cm balances near its middle (the mouth hole nudges it)
+        assert!((report.balance_point - 27.5).abs() < 0.1, "{:?}", report);
+        let exact = PI * (1.35f64.powi(2) - 0.95f64.powi(2)) * 65.0;
+        assert!((report.mass - exact).abs() / exact < 0.01);
+
+        // A heavy ring at the head end pulls the balance towards the player
+        let ringed = MeshOptions {
+            rings: vec![DecorativeRing {
+                position: -5.0,
+                width: 2.0,
+                outer_diameter: 3.5,
+            }],
+            ..options.clone()
+        };
+        let report = estimate_balance(&flute, &ringed, 1.0);
+        let ring = report
+            .parts
+            .iter()
+            .find(|p| p.name == "DecorativeRing1")
+            .unwrap();
+        assert!(ring.shift.unwrap() < -1.0, "{:?}", report);
+        assert!((ring.centre + 4.0).abs() < 0.05);
+        assert!(report.parts[0].shift.is_none());
+
+        // The fingering doesn't move material
+        let mut drilled = flute.clone();
+        drilled.holes.push(Hole::new(50.0, 0.4, true)).unwrap();
+        let open = estimate_balance(&drilled, &options, 1.0);
+        assert!(open.mass < estimate_balance(&flute, &options, 1.0).mass);
+        drilled.holes[0].open = false;
+        assert_eq!(estimate_balance(&drilled, &options, 1.0), open);
+
+        // A duct flute's window comes out of the body instead of an embouchure hole
+        let mut whistle = Flute::new(30.0, 0.65, 0.3);
+        whistle.instrument = InstrumentType::Duct(DuctGeometry::default());
+        let tube = generate_flute_mesh_with(&whistle, &options)
+            .group_stats()
+            .into_iter()
+            .find(|g| g.name == "TubeBody")
+            .unwrap()
+            .volume;
+        let report = estimate_balance(&whistle, &options, 1.0);
+        assert_eq!(report.parts[0].name, "Body");
+        assert!((report.parts[0].mass - (tube - 1.0 * 0.5 * 0.3)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_generated_flute_mesh_is_watertight() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(30.0, 0.3, true)).unwrap();
+        let report = generate_flute_mesh(&flute).validate();
+        assert!(report.watertight, "{:?}", report);
+        // Body + hole cutter + mouth cutter + 5 mm chimney riser over the 4 mm wall
+        assert_eq!(report.shell_count, 4);
+    }
 }
@@ -0,0 +1,115 @@
+use crate::physics::Flute;
+use std::collections::VecDeque;
+
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/// Bounded undo/redo stacks of whole design states, each labelled with the edit that
+/// replaced it. Consecutive edits with the same label (a hole being dragged) share one step
+/// until `checkpoint` is called.
+#[derive(Debug, Clone)]
+pub struct History {
+    undo: VecDeque<(String, Flute)>,
+    redo: Vec<(String, Flute)>,
+    limit: usize,
+    open: bool,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::new(DEFAULT_HISTORY_LIMIT)
+    }
+}
+
+impl History {
+    pub fn new(limit: usize) -> Self {
+        History {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            limit: limit.max(1),
+            open: false,
+        }
+    }
+
+    /// Call before applying an edit, with the design as it is now
+    pub fn record(&mut self, description: &str, before: &Flute) {
+        self.redo.clear();
+        if self.open && self.undo.back().is_some_and(|(d, _)| d == description) {
+            return;
+        }
+        self.undo
+            .push_back((description.to_string(), before.clone()));
+        if self.undo.len() > self.limit {
+            self.undo.pop_front();
+        }
+        self.open = true;
+    }
+
+    /// End the current step, so the next edit starts a new one even with the same label
+    pub fn checkpoint(&mut self) {
+        self.open = false;
+    }
+
+    /// The design to restore and the label of the edit being undone
+    pub fn undo(&mut self, current: &Flute) -> Option<(String, Flute)> {
+        let (description, state) = self.undo.pop_back()?;
+        self.redo.push((description.clone(), current.clone()));
+        self.open = false;
+        Some((description, state))
+    }
+
+    pub fn redo(&mut self, current: &Flute) -> Option<(String, Flute)> {
+        let (description, state) = self.redo.pop()?;
+        self.undo.push_back((description.clone(), current.clone()));
+        self.open = false;
+        Some((description, state))
+    }
+
+    pub fn undo_description(&self) -> Option<&str> {
+        self.undo.back().map(|(d, _)| d.as_str())
+    }
+
+    pub fn redo_description(&self) -> Option<&str> {
+        self.redo.last().map(|(d, _)| d.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_undo_redo_and_coalescing() {
+        let mut history = History::new(2);
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+
+        history.record("Set dimensions", &flute);
+        flute.length = 61.0;
+        // A drag: many edits under one label, one undo step
+        history.checkpoint();
+        history.record("Drag", &flute);
+        flute.length = 62.0;
+        history.record("Drag", &flute);
+        flute.length = 63.0;
+        history.checkpoint();
+        history.record("Set cork", &flute);
+        flute.cork_position = 2.0;
+
+        // Limit of two: the first step fell off
+        assert_eq!(history.undo_description(), Some("Set cork"));
+        let (label, state) = history.undo(&flute).unwrap();
+        assert_eq!(label, "Set cork");
+        flute = state;
+        assert_eq!(flute.cork_position, 1.7);
+        let (_, state) = history.undo(&flute).unwrap();
+        flute = state;
+        assert_eq!(flute.length, 61.0);
+        assert!(history.undo(&flute).is_none());
+
+        assert_eq!(history.redo_description(), Some("Drag"));
+        let (_, state) = history.redo(&flute).unwrap();
+        assert_eq!(state.length, 63.0);
+        // A new edit drops the redo branch
+        history.record("Set cork", &state);
+        assert!(history.redo_description().is_none());
+    }
+}
@@ -0,0 +1,112 @@
+//! Flutomat, the JavaScript flute calculator. Flutomat keeps no files of its own; designs
+//! are passed around as its form values, which this reads as JSON:
+//!
+//! ```json
+//! {
+//!   "units": "mm",
+//!   "bore": 19.0,
+//!   "wall": 3.0,
+//!   "embouchure": 10.0,
+//!   "length": 580.0,
+//!   "endFrequency": 293.66,
+//!   "holes": [{ "diameter": 8.0, "location": 420.0, "frequency": 329.63 }]
+//! }
+//! ```
+//!
+//! `bore` and `embouchure` are diameters; `length` and each hole's `location` are measured
+//! from the embouchure centre, as in Flutomat's results table. `units` defaults to mm;
+//! frequencies are optional.
+
+use super::{targets, unit_scale};
+use crate::document::DesignDocument;
+use crate::error::FlyteError;
+use crate::physics::{Flute, Hole, Holes};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FlutomatDesign {
+    #[serde(default = "default_units")]
+    pub units: String,
+    pub bore: f64,
+    pub wall: f64,
+    pub embouchure: f64,
+    pub length: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_frequency: Option<f64>,
+    #[serde(default)]
+    pub holes: Vec<FlutomatHole>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FlutomatHole {
+    pub diameter: f64,
+    pub location: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency: Option<f64>,
+}
+
+fn default_units() -> String {
+    "mm".to_string()
+}
+
+/// Parse Flutomat JSON into a design document
+pub fn import_flutomat(json: &str) -> Result<DesignDocument, FlyteError> {
+    let design: FlutomatDesign = serde_json::from_str(json).map_err(FlyteError::invalid_input)?;
+    design.to_document()
+}
+
+impl FlutomatDesign {
+    pub fn to_document(&self) -> Result<DesignDocument, FlyteError> {
+        let k = unit_scale(&self.units)?;
+        let mut flute = Flute::new(self.length * k, self.bore * k / 2.0, self.wall * k);
+        flute.embouchure_hole_radius = self.embouchure * k / 2.0;
+        let holes: Vec<Hole> = self
+            .holes
+            .iter()
+            .map(|h| Hole::new(h.location * k, h.diameter * k / 2.0, true))
+            .collect();
+        flute.holes = Holes::try_from(holes)?;
+        let mut doc = DesignDocument::new(&flute);
+        let notes: Vec<(f64, Option<f64>)> = self
+            .holes
+            .iter()
+            .map(|h| (h.location, h.frequency))
+            .collect();
+        doc.targets = targets(self.end_frequency, &notes);
+        Ok(doc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_flutomat() {
+        let json = r#"{
+            "bore": 19, "wall": 3, "embouchure": 10, "length": 580, "endFrequency": 293.66,
+            "holes": [
+                { "diameter": 8, "location": 380, "frequency": 369.99 },
+                { "diameter": 9, "location": 420, "frequency": 329.63 }
+            ]
+        }"#;
+        let doc = import_flutomat(json).unwrap();
+        let flute = doc.to_flute();
+        assert!((flute.length - 58.0).abs() < 1e-12);
+        assert!((flute.bore_radius - 0.95).abs() < 1e-12);
+        assert!((flute.embouchure_hole_radius - 0.5).abs() < 1e-12);
+        assert!((flute.holes[1].radius - 0.45).abs() < 1e-12);
+        assert_eq!(doc.targets, vec![293.66, 329.63, 369.99]);
+        assert!(flute.validate().is_ok());
+
+        let inches = r#"{"units": "in", "bore": 0.75, "wall": 0.125, "embouchure": 0.4,
+            "length": 23, "holes": [{ "diameter": 0.3, "location": 16 }]}"#;
+        let doc = import_flutomat(inches).unwrap();
+        assert!((doc.to_flute().length - 58.42).abs() < 1e-9);
+        assert!(doc.targets.is_empty());
+
+        assert!(import_flutomat(r#"{"bore": 19}"#).is_err());
+        assert!(import_flutomat(&inches.replace("\"in\"", "\"furlong\"")).is_err());
+    }
+}
@@ -0,0 +1,32 @@
+//! Designs from other flute calculators. Each importer maps the tool's fields onto a
+//! `DesignDocument` in centimetres, with the tool's note frequencies carried over as targets
+//! so the design can be re-tuned here.
+
+pub mod flutomat;
+pub mod twcalc;
+
+use crate::error::FlyteError;
+
+/// Length unit of an imported file, as a factor to centimetres
+fn unit_scale(units: &str) -> Result<f64, FlyteError> {
+    match units.trim().to_ascii_lowercase().as_str() {
+        "mm" | "millimeters" | "millimetres" => Ok(0.1),
+        "cm" | "centimeters" | "centimetres" => Ok(1.0),
+        "in" | "inch" | "inches" | "\"" => Ok(2.54),
+        other => Err(FlyteError::invalid_input(format!(
+            "unknown length unit {:?}",
+            other
+        ))),
+    }
+}
+
+/// Targets lowest note first: the all-closed note, then one per hole from the foot up.
+/// Empty unless every note is known, since a partial list would pair with the wrong holes.
+fn targets(end: Option<f64>, holes: &[(f64, Option<f64>)]) -> Vec<f64> {
+    let mut by_position: Vec<&(f64, Option<f64>)> = holes.iter().collect();
+    by_position.sort_by(|a, b| b.0.total_cmp(&a.0));
+    std::iter::once(end)
+        .chain(by_position.iter().map(|&&(_, f)| f))
+        .collect::<Option<Vec<f64>>>()
+        .unwrap_or_default()
+}
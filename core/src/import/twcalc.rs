@@ -0,0 +1,130 @@
+//! TWCalc, the tin whistle calculator. Its saved calculations are `Name = value` lines,
+//! read here with names matched ignoring case, spaces and underscores:
+//!
+//! ```text
+//! [Whistle]
+//! Units = mm
+//! Bore = 13.0            ; diameter
+//! Wall = 1.5
+//! Length = 265.0         ; labium edge to the open end
+//! Window Length = 4.5
+//! Window Width = 8.0
+//! Windway Height = 1.2
+//! Block Length = 40.0
+//! End Frequency = 587.33
+//! Hole1 Diameter = 7.0
+//! Hole1 Distance = 190.0 ; from the labium edge
+//! Hole1 Frequency = 659.26
+//! ```
+//!
+//! `;` and `#` start comments and `[section]` headers are ignored. Units default to mm;
+//! window and windway fields fall back to the `DuctGeometry` defaults.
+
+use super::{targets, unit_scale};
+use crate::document::DesignDocument;
+use crate::error::FlyteError;
+use crate::physics::{DuctGeometry, Flute, Hole, InstrumentType};
+use std::collections::BTreeMap;
+
+/// Parse a TWCalc calculation into a duct-flute design document
+pub fn import_twcalc(text: &str) -> Result<DesignDocument, FlyteError> {
+    let mut fields = BTreeMap::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.split([';', '#']).next().unwrap_or("").trim();
+        if line.is_empty() || line.starts_with('[') {
+            continue;
+        }
+        let (name, value) = line.split_once('=').ok_or_else(|| {
+            FlyteError::invalid_input(format!("line {}: expected Name = value", n + 1))
+        })?;
+        let name: String = name
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        fields.insert(name, value.trim().to_string());
+    }
+
+    let k = unit_scale(fields.get("units").map_or("mm", String::as_str))?;
+    let number = |name: &str| -> Result<Option<f64>, FlyteError> {
+        fields
+            .get(name)
+            .map(|v| {
+                v.parse().map_err(|_| {
+                    FlyteError::invalid_input(format!("{} is not a number: {:?}", name, v))
+                })
+            })
+            .transpose()
+    };
+    let required = |name: &str| {
+        number(name)?.ok_or_else(|| FlyteError::invalid_input(format!("missing {}", name)))
+    };
+
+    let mut flute = Flute::new(
+        required("length")? * k,
+        required("bore")? * k / 2.0,
+        required("wall")? * k,
+    );
+    let defaults = DuctGeometry::default();
+    flute.instrument = InstrumentType::Duct(DuctGeometry {
+        windway_length: number("blocklength")?.map_or(defaults.windway_length, |v| v * k),
+        windway_width: number("windowwidth")?.map_or(defaults.windway_width, |v| v * k),
+        windway_height: number("windwayheight")?.map_or(defaults.windway_height, |v| v * k),
+        window_length: number("windowlength")?.map_or(defaults.window_length, |v| v * k),
+        ..defaults
+    });
+
+    let mut notes = Vec::new();
+    for i in 1.. {
+        let Some(diameter) = number(&format!("hole{}diameter", i))? else {
+            break;
+        };
+        let distance = required(&format!("hole{}distance", i))?;
+        flute
+            .holes
+            .push(Hole::new(distance * k, diameter * k / 2.0, true))?;
+        notes.push((distance, number(&format!("hole{}frequency", i))?));
+    }
+
+    let mut doc = DesignDocument::new(&flute);
+    doc.targets = targets(number("endfrequency")?, &notes);
+    Ok(doc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_twcalc() {
+        let text = "[Whistle]\n\
+            Units = mm\n\
+            Bore = 13 ; diameter\n\
+            Wall = 1.5\n\
+            Length = 265\n\
+            Window_Length = 4.5\n\
+            End Frequency = 587.33\n\
+            Hole1 Diameter = 7\n\
+            Hole1 Distance = 190\n\
+            Hole1 Frequency = 659.26\n\
+            hole2diameter = 6.5\n\
+            HOLE2 DISTANCE = 165\n\
+            Hole2 Frequency = 739.99\n";
+        let doc = import_twcalc(text).unwrap();
+        let flute = doc.to_flute();
+        assert!((flute.length - 26.5).abs() < 1e-12);
+        assert!((flute.bore_radius - 0.65).abs() < 1e-12);
+        assert_eq!(flute.holes.len(), 2);
+        assert!((flute.holes[1].position - 16.5).abs() < 1e-12);
+        let InstrumentType::Duct(duct) = flute.instrument else {
+            panic!("expected a duct flute");
+        };
+        assert!((duct.window_length - 0.45).abs() < 1e-12);
+        assert_eq!(duct.windway_width, DuctGeometry::default().windway_width);
+        assert_eq!(doc.targets, vec![587.33, 659.26, 739.99]);
+
+        assert!(import_twcalc("Bore = 13\nWall = 1.5").is_err());
+        assert!(import_twcalc("Length = long").is_err());
+        assert!(import_twcalc("just some text").is_err());
+    }
+}
@@ -0,0 +1,316 @@
+#[cfg(feature = "optimize")]
+use crate::ergonomics::{OneHandedLayout, OneHandedOptions, OneHandedSearch};
+use crate::physics::Flute;
+use crate::tuning::{self, ChartRow};
+use serde::{Deserialize, Serialize};
+
+/// A long computation to run in slices, e.g. `{ kind: "spectrum", f0: 100, f1: 3000, n: 2000 }`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobSpec {
+    /// `impedance_spectrum`; one step per frequency
+    Spectrum { f0: f64, f1: f64, n: usize },
+    /// `fingering_chart`; one step per fingering
+    FingeringChart { jet_velocity: f64 },
+    /// `one_handed_layout`; one step per foot-hole candidate
+    #[cfg(feature = "optimize")]
+    OneHandedLayout {
+        #[serde(default)]
+        options: OneHandedOptions,
+    },
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct JobProgress {
+    pub done: usize,
+    pub total: usize,
+    pub finished: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "kind", content = "result", rename_all = "snake_case")]
+pub enum JobResult {
+    /// Interleaved (freq, |Z|, phase), as from `impedance_spectrum`
+    Spectrum(Vec<f64>),
+    FingeringChart(Vec<ChartRow>),
+    #[cfg(feature = "optimize")]
+    OneHandedLayout(OneHandedLayout),
+}
+
+#[derive(Debug, Clone)]
+enum State {
+    Spectrum {
+        f0: f64,
+        step: f64,
+        n: usize,
+        out: Vec<f64>,
+    },
+    Chart {
+        fingerings: Vec<u32>,
+        jet_velocity: f64,
+        rows: Vec<ChartRow>,
+    },
+    #[cfg(feature = "optimize")]
+    Layout(Box<OneHandedSearch>),
+}
+
+/// A resumable computation on a snapshot of the design, so edits made between polls do not
+/// change a half-finished result. Each `poll` does at most the requested number of steps.
+#[derive(Debug, Clone)]
+pub struct Job {
+    flute: Flute,
+    state: State,
+}
+
+impl Job {
+    pub fn new(flute: &Flute, spec: &JobSpec) -> Self {
+        Job::shard(flute, spec, 0, 1)
+    }
+
+    /// Part `index` of `count` of the job: a contiguous share of its frequencies,
+    /// fingerings or layout candidates, so a pool of workers (e.g. Web Workers, each with
+    /// its own engine) can split it. `merge` puts the shards' results back together.
+    pub fn shard(flute: &Flute, spec: &JobSpec, index: usize, count: usize) -> Self {
+        let count = count.max(1);
+        let index = index.min(count);
+        let share = |total: usize| total * index / count..total * (index + 1).min(count) / count;
+        let state = match *spec {
+            JobSpec::Spectrum { f0, f1, n } => {
+                let step = if n > 1 {
+                    (f1 - f0) / (n - 1) as f64
+                } else {
+                    0.0
+                };
+                let range = share(n);
+                State::Spectrum {
+                    f0: f0 + step * range.start as f64,
+                    step,
+                    n: range.len(),
+                    out: Vec::with_capacity(3 * range.len()),
+                }
+            }
+            JobSpec::FingeringChart { jet_velocity } => {
+                let mut fingerings = tuning::progressive_fingerings(flute);
+                let range = share(fingerings.len());
+                fingerings.truncate(range.end);
+                fingerings.drain(..range.start);
+                State::Chart {
+                    fingerings,
+                    jet_velocity,
+                    rows: Vec::new(),
+                }
+            }
+            #[cfg(feature = "optimize")]
+            JobSpec::OneHandedLayout { ref options } => {
+                let search = OneHandedSearch::new(flute, options);
+                let range = share(search.total_steps());
+                State::Layout(Box::new(search.restrict(range)))
+            }
+        };
+        Job {
+            flute: flute.clone(),
+            state,
+        }
+    }
+
+    pub fn progress(&self) -> JobProgress {
+        let (done, total) = match &self.state {
+            State::Spectrum { n, out, .. } => (out.len() / 3, *n),
+            State::Chart {
+                fingerings, rows, ..
+            } => (rows.len(), fingerings.len()),
+            #[cfg(feature = "optimize")]
+            State::Layout(search) => (search.steps_done(), search.total_steps()),
+        };
+        JobProgress {
+            done,
+            total,
+            finished: done >= total,
+        }
+    }
+
+    pub fn poll(&mut self, n_steps: usize) -> JobProgress {
+        trace_span!(DEBUG, "job_poll", n_steps);
+        let flute = &self.flute;
+        match &mut self.state {
+            State::Spectrum { f0, step, n, out } => {
+                let start = out.len() / 3;
+                let count = n_steps.min(*n - start);
+                if count > 0 {
+                    let first = *f0 + *step * start as f64;
+                    let last = *f0 + *step * (start + count - 1) as f64;
+                    out.extend(flute.impedance_spectrum(first, last, count));
+                }
+            }
+            State::Chart {
+                fingerings,
+                jet_velocity,
+                rows,
+            } => {
+                let start = rows.len();
+                let end = (start + n_steps).min(fingerings.len());
+                let pitches =
+                    tuning::pitches_for_fingerings(flute, &fingerings[start..end], *jet_velocity);
+                for (&fingering, frequency) in fingerings[start..end].iter().zip(pitches) {
+                    rows.push(ChartRow {
+                        fingering,
+                        frequency,
+                        note: tuning::note_info(frequency),
+                    });
+                }
+            }
+            #[cfg(feature = "optimize")]
+            State::Layout(search) => {
+                for _ in 0..n_steps {
+                    search.step();
+                }
+            }
+        }
+        self.progress()
+    }
+
+    /// The result so far; complete once `progress().finished`
+    pub fn result(&self) -> JobResult {
+        match &self.state {
+            State::Spectrum { out, .. } => JobResult::Spectrum(out.clone()),
+            State::Chart { rows, .. } => JobResult::FingeringChart(rows.clone()),
+            #[cfg(feature = "optimize")]
+            State::Layout(search) => JobResult::OneHandedLayout((**search).clone().finish()),
+        }
+    }
+}
+
+impl JobResult {
+    /// Combine the results of a job's shards, in shard order: spectra and charts are joined,
+    /// and the best layout wins. `None` when the kinds differ.
+    pub fn merge(results: Vec<JobResult>) -> Option<JobResult> {
+        let mut results = results.into_iter();
+        let mut merged = results.next()?;
+        for result in results {
+            match (&mut merged, result) {
+                (JobResult::Spectrum(all), JobResult::Spectrum(part)) => all.extend(part),
+                (JobResult::FingeringChart(all), JobResult::FingeringChart(part)) => {
+                    all.extend(part)
+                }
+                #[cfg(feature = "optimize")]
+                (JobResult::OneHandedLayout(best), JobResult::OneHandedLayout(layout)) => {
+                    if layout.error() < best.error() {
+                        *best = layout;
+                    }
+                }
+                _ => return None,
+            }
+        }
+        Some(merged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Hole;
+
+    #[test]
+    fn test_jobs_match_blocking_calls() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for pos in [40.0, 30.0, 35.0] {
+            flute.holes.push(Hole::new(pos, 0.35, true)).unwrap();
+        }
+
+        let mut job = Job::new(
+            &flute,
+            &JobSpec::Spectrum {
+                f0: 200.0,
+                f1: 400.0,
+                n: 25,
+            },
+        );
+        let progress = job.poll(10);
+        assert_eq!((progress.done, progress.total), (10, 25));
+        assert!(!progress.finished);
+        while !job.poll(10).finished {}
+        let JobResult::Spectrum(spectrum) = job.result() else {
+            panic!("wrong result kind");
+        };
+        let expected = flute.impedance_spectrum(200.0, 400.0, 25);
+        assert_eq!(spectrum.len(), expected.len());
+        for (a, b) in spectrum.iter().zip(&expected) {
+            assert!((a - b).abs() < 1e-9 * b.abs().max(1.0));
+        }
+
+        let mut job = Job::new(&flute, &JobSpec::FingeringChart { jet_velocity: 0.0 });
+        // Edits after the start do not leak into the running job
+        flute.holes.clear();
+        assert!(job.poll(100).finished);
+        let JobResult::FingeringChart(rows) = job.result() else {
+            panic!("wrong result kind");
+        };
+        assert_eq!(rows.len(), 4);
+    }
+
+    #[test]
+    fn test_merged_shards_match_whole_job() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for pos in [40.0, 30.0, 35.0] {
+            flute.holes.push(Hole::new(pos, 0.35, true)).unwrap();
+        }
+        let run = |mut job: Job| {
+            while !job.poll(7).finished {}
+            job.result()
+        };
+        let sharded = |spec: &JobSpec, count: usize| {
+            let parts = (0..count)
+                .map(|i| run(Job::shard(&flute, spec, i, count)))
+                .collect();
+            JobResult::merge(parts).unwrap()
+        };
+
+        let spec = JobSpec::Spectrum {
+            f0: 200.0,
+            f1: 400.0,
+            n: 25,
+        };
+        let (JobResult::Spectrum(whole), JobResult::Spectrum(parts)) =
+            (run(Job::new(&flute, &spec)), sharded(&spec, 3))
+        else {
+            panic!("wrong result kind");
+        };
+        assert_eq!(whole.len(), parts.len());
+        for (a, b) in whole.iter().zip(&parts) {
+            assert!((a - b).abs() < 1e-9 * b.abs().max(1.0));
+        }
+
+        // More shards than fingerings leaves some empty
+        let spec = JobSpec::FingeringChart { jet_velocity: 0.0 };
+        let (JobResult::FingeringChart(whole), JobResult::FingeringChart(parts)) =
+            (run(Job::new(&flute, &spec)), sharded(&spec, 6))
+        else {
+            panic!("wrong result kind");
+        };
+        assert_eq!(whole, parts);
+
+        #[cfg(feature = "optimize")]
+        {
+            let spec = JobSpec::OneHandedLayout {
+                options: OneHandedOptions {
+                    holes: 2,
+                    ..OneHandedOptions::default()
+                },
+            };
+            let (JobResult::OneHandedLayout(whole), JobResult::OneHandedLayout(parts)) =
+                (run(Job::new(&flute, &spec)), sharded(&spec, 2))
+            else {
+                panic!("wrong result kind");
+            };
+            assert_eq!(whole.holes, parts.holes);
+            assert_eq!(whole.notes, parts.notes);
+        }
+
+        let mixed = vec![
+            JobResult::Spectrum(Vec::new()),
+            JobResult::FingeringChart(parts),
+        ];
+        assert!(JobResult::merge(mixed).is_none());
+        assert!(JobResult::merge(Vec::new()).is_none());
+    }
+}
@@ -1,154 +1,76 @@
-mod geometry;
-mod physics;
-use physics::{Flute, Hole};
-use wasm_bindgen::prelude::*;
-
-#[wasm_bindgen]
-pub struct FluteEngine {
-    inner: Flute,
+//! Acoustic simulation and geometry for flute design.
+//!
+//! The physics, geometry and export modules are plain Rust. The `wasm` feature (on by
+//! default) adds the `FluteEngine` bindings used by the web app; depend on the crate with
+//! `default-features = false` to leave out wasm-bindgen and the panic hook. The `export`,
+//! `optimize` and `synth` features (also on by default) hold the file exporters, the layout
+//! search and presets, and audio rendering; a pitch-only web build can drop them. The `python`
+//! feature builds the `flyte` Python module instead (see `core/pyproject.toml`), and `ffi`
+//! adds a C API with a generated header in `core/include/flyte.h`. `tracing` instruments
+//! the solver, optimizers and mesh generation with `tracing` spans.
+
+/// Enter a `tracing` span at `$level` for the rest of the enclosing block. Compiles to
+/// nothing without the `tracing` feature, so arguments must not have side effects.
+macro_rules! trace_span {
+    ($level:ident, $($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::span!(tracing::Level::$level, $($arg)*).entered();
+    };
 }
 
-#[wasm_bindgen]
-impl FluteEngine {
-    #[wasm_bindgen(constructor)]
-    pub fn new(length: f64, bore_radius: f64, wall_thickness: f64) -> FluteEngine {
-        console_error_panic_hook::set_once();
-        FluteEngine {
-            inner: Flute::new(length, bore_radius, wall_thickness),
-        }
-    }
-
-    pub fn set_holes(
-        &mut self,
-        positions: &[f64],
-        radii: &[f64],
-        open: &[u8],
-    ) -> Result<(), JsValue> {
-        if positions.len() != radii.len() || positions.len() != open.len() {
-            return Err(JsValue::from_str("Arrays must have the same length"));
-        }
-
-        // Reuse existing capacity if possible
-        if self.inner.holes.capacity() < positions.len() {
-            self.inner
-                .holes
-                .reserve(positions.len() - self.inner.holes.len());
-        }
-
-        // Resize vector to match new length (either growing or shrinking)
-        // We can't use resize_with easily because we need to fill with specific data,
-        // so we'll just clear and push if we want to be safe, OR overwrite.
-        // A safer "reuse" pattern for strict typing: use strict indexing for update if len matches.
-
-        // Simplest safe approach that reuses allocation:
-        self.inner.holes.clear();
-
-        for i in 0..positions.len() {
-            let mut pos = positions[i];
-            if pos.is_nan() {
-                pos = 0.0;
-            }
-
-            let mut rad = radii[i];
-            if rad.is_nan() {
-                rad = 0.1;
-            }
-
-            self.inner.holes.push(Hole {
-                position: pos,
-                radius: rad,
-                open: open[i] != 0,
-            });
-        }
-        Ok(())
-    }
-
-    pub fn update_hole(
-        &mut self,
-        index: usize,
-        position: f64,
-        radius: f64,
-        open: bool,
-    ) -> Result<(), JsValue> {
-        if index >= self.inner.holes.len() {
-            return Err(JsValue::from_str("Hole index out of bounds"));
-        }
-
-        self.inner.holes[index].position = if position.is_nan() { 0.0 } else { position };
-        self.inner.holes[index].radius = if radius.is_nan() { 0.1 } else { radius };
-        self.inner.holes[index].open = open;
-
-        Ok(())
-    }
-
-    /// Calculate pitch using TMM and Resonance search
-    /// Uses a smart guess based on the first open hole to ensure we find the fundamental
-    /// Calculate pitch using TMM and Resonance search
-    /// Uses a smart guess based on the first open hole to ensure we find the fundamental
-    pub fn set_physics_params(&mut self, length: f64, bore_radius: f64, wall_thickness: f64) {
-        if !length.is_nan() && length > 0.0 {
-            self.inner.length = length;
-        }
-        if !bore_radius.is_nan() && bore_radius > 0.0 {
-            self.inner.bore_radius = bore_radius;
-        }
-        if !wall_thickness.is_nan() && wall_thickness > 0.0 {
-            self.inner.wall_thickness = wall_thickness;
-        }
-    }
-
-    /// Calculate pitch using TMM and Resonance search
-    /// Uses a smart guess based on the first open hole to ensure we find the fundamental
-    pub fn calculate_pitch(&mut self, jet_velocity: f64) -> f64 {
-        // Find the effective length based on the first open hole (closest to embouchure, pos 0)
-        // Holes are sorted by position in find_resonance, but here we just need a scan.
-        // We want the hole with the smallest position that is open.
-
-        let mut shortest_len = self.inner.length;
-
-        for hole in &self.inner.holes {
-            if hole.open && hole.position < shortest_len {
-                shortest_len = hole.position;
-            }
-        }
-
-        // Simple end correction approximation (0.61 * r) - crude but helps
-        let effective_len = shortest_len + 0.61 * self.inner.bore_radius;
-
-        // Fundamental of open-open pipe: f = c / 2L
-        let fundamental_guess = 34500.0 / (2.0 * effective_len);
-
-        // Aerodynamic Preferred Frequency (f_pref)
-        // f_pref ~ 0.2 * U / l
-        // U = jet_velocity (cm/s)
-        // l = cutting distance (approx diameter of embouchure hole or similar)
-        // Let's approximate cutting distance as 2 * embouchure_radius (~1.0 cm typically)
-
-        let guess_freq = if jet_velocity > 10.0 {
-            let cutting_dist = 2.0 * self.inner.embouchure_hole_radius;
-            let f_pref = 0.2 * jet_velocity / cutting_dist;
-
-            // We want to find the resonance closest to this preferred frequency
-            // But we should be careful. If the preferred frequency is very far off, we might want to still bias towards the fundamental
-            // For now, let's just use f_pref as the guess if it's substantial.
-            f_pref
-        } else {
-            fundamental_guess
-        };
-
-        // If the calculated preferred frequency is wildly different (e.g. very low velocity),
-        // we might get 0 Hz. Fallback to fundamental if guess is too low.
-        let robust_guess = if guess_freq < 20.0 {
-            fundamental_guess
-        } else {
-            guess_freq
-        };
-
-        self.inner.find_resonance(robust_guess)
-    }
-
-    pub fn export_obj(&self) -> String {
-        let mesh = geometry::generate_flute_mesh(&self.inner);
-        mesh.to_obj_string()
-    }
+/// Emit a `tracing` event at `$level` (`warn`, `debug`, ...) when the feature is on
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)*);
+    };
 }
+
+pub mod air;
+mod builder;
+#[cfg(feature = "optimize")]
+pub mod calibration;
+pub mod document;
+pub mod dynamics;
+pub mod ergonomics;
+mod error;
+#[cfg(feature = "export")]
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod field;
+mod font;
+pub mod geometry;
+pub mod history;
+pub mod import;
+pub mod jobs;
+pub mod materials;
+mod math;
+pub mod moisture;
+pub mod organ;
+pub mod panflute;
+pub mod physics;
+#[cfg(feature = "optimize")]
+pub mod presets;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "synth")]
+pub mod synth;
+pub mod tolerance;
+pub mod transfer;
+#[cfg(feature = "optimize")]
+pub mod transpose;
+pub mod tuning;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "optimize")]
+pub mod wizard;
+
+pub use builder::FluteBuilder;
+pub use error::FlyteError;
+pub use physics::{
+    Calibration, DuctGeometry, FluePipe, Flute, Hole, HoleId, Holes, InstrumentType, NotchCut,
+    NotchShape, SideResonator, SolverConfig, TuningSlot,
+};
+#[cfg(feature = "wasm")]
+pub use wasm::{FluteEngine, MeshBuffers};
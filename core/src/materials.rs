@@ -0,0 +1,256 @@
+//! Physical properties of the materials flutes are made from, in one place: density for
+//! weight and balance, stiffness and damping for the wall, thermal behaviour and how the
+//! wood moves with moisture. Figures are typical handbook and datasheet values; a given
+//! filament brand or billet can be off by 10-20 %.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Material {
+    #[default]
+    Pla,
+    Petg,
+    /// Standard SLA/DLP photopolymer
+    Resin,
+    Abs,
+    Boxwood,
+    /// African blackwood (grenadilla)
+    Blackwood,
+    Cocobolo,
+    /// Hard maple
+    Maple,
+    Bamboo,
+    /// Sterling silver tube
+    Silver,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Properties {
+    pub name: &'static str,
+    pub density: f64, // g/cm^3
+    /// Along the grain for woods (GPa)
+    pub youngs_modulus: f64,
+    /// Longitudinal (bar) wave speed along the tube (cm/s)
+    pub speed_of_sound: f64,
+    /// Internal damping of wall vibration (tan delta)
+    pub loss_factor: f64,
+    pub thermal_expansion: f64,    // 1/K, along the tube
+    pub thermal_conductivity: f64, // W/(m K)
+    /// Temperature the body starts to soften or deform at (°C); None when it is far above
+    /// anything a player will reach
+    pub softening_temperature: Option<f64>,
+    /// Fractional change per percentage point of moisture content: (radial, tangential,
+    /// longitudinal). Zero for materials that don't take up water.
+    pub swelling: (f64, f64, f64),
+    /// Can be made on a hobby 3D printer
+    pub printable: bool,
+    pub notes: &'static str,
+}
+
+/// One row of `Material::catalogue`
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MaterialInfo {
+    pub id: Material,
+    #[serde(flatten)]
+    pub properties: &'static Properties,
+}
+
+const NO_SWELLING: (f64, f64, f64) = (0.0, 0.0, 0.0);
+
+const TABLE: [Properties; 10] = [
+    Properties {
+        name: "PLA",
+        density: 1.24,
+        youngs_modulus: 3.5,
+        speed_of_sound: 168_000.0,
+        loss_factor: 0.02,
+        thermal_expansion: 68e-6,
+        thermal_conductivity: 0.13,
+        softening_temperature: Some(55.0),
+        swelling: NO_SWELLING,
+        printable: true,
+        notes: "Easiest to print and dimensionally accurate. Softens around 55-60 °C: don't \
+                leave it in a hot car, and warm breath on a thin wall can creep over months.",
+    },
+    Properties {
+        name: "PETG",
+        density: 1.27,
+        youngs_modulus: 2.1,
+        speed_of_sound: 129_000.0,
+        loss_factor: 0.02,
+        thermal_expansion: 60e-6,
+        thermal_conductivity: 0.20,
+        softening_temperature: Some(75.0),
+        swelling: NO_SWELLING,
+        printable: true,
+        notes: "Tougher and more heat tolerant than PLA and food-contact friendly. Strings \
+                more, so small holes and the embouchure edge need cleaning up.",
+    },
+    Properties {
+        name: "Resin",
+        density: 1.18,
+        youngs_modulus: 2.5,
+        speed_of_sound: 146_000.0,
+        loss_factor: 0.03,
+        thermal_expansion: 80e-6,
+        thermal_conductivity: 0.20,
+        softening_temperature: Some(60.0),
+        swelling: NO_SWELLING,
+        printable: true,
+        notes: "Finest surface and sharpest edges, ideal for labia and heads. Brittle; fully \
+                wash and post-cure before it goes near a mouth.",
+    },
+    Properties {
+        name: "ABS",
+        density: 1.04,
+        youngs_modulus: 2.2,
+        speed_of_sound: 145_000.0,
+        loss_factor: 0.02,
+        thermal_expansion: 90e-6,
+        thermal_conductivity: 0.17,
+        softening_temperature: Some(95.0),
+        swelling: NO_SWELLING,
+        printable: true,
+        notes: "Light and heat tolerant, can be vapour-smoothed. Warps without an enclosure, \
+                which bends long bodies.",
+    },
+    Properties {
+        name: "Boxwood",
+        density: 0.95,
+        youngs_modulus: 17.0,
+        speed_of_sound: 423_000.0,
+        loss_factor: 0.008,
+        thermal_expansion: 4e-6,
+        thermal_conductivity: 0.20,
+        softening_temperature: None,
+        swelling: (0.0023, 0.0036, 0.0001),
+        printable: false,
+        notes: "Traditional for baroque and renaissance flutes. Moves noticeably with \
+                humidity and can crack if played cold and dry; oil the bore.",
+    },
+    Properties {
+        name: "African blackwood",
+        density: 1.27,
+        youngs_modulus: 18.0,
+        speed_of_sound: 376_000.0,
+        loss_factor: 0.005,
+        thermal_expansion: 4e-6,
+        thermal_conductivity: 0.25,
+        softening_temperature: None,
+        swelling: (0.0010, 0.0016, 0.0001),
+        printable: false,
+        notes: "Dense, oily and very stable; the usual choice for Irish and modern wooden \
+                flutes. Still prone to cracking when blown in cold.",
+    },
+    Properties {
+        name: "Cocobolo",
+        density: 1.10,
+        youngs_modulus: 18.7,
+        speed_of_sound: 412_000.0,
+        loss_factor: 0.006,
+        thermal_expansion: 4e-6,
+        thermal_conductivity: 0.22,
+        softening_temperature: None,
+        swelling: (0.0010, 0.0015, 0.0001),
+        printable: false,
+        notes: "Stable and oily like blackwood. The dust and sometimes the finished wood \
+                sensitise skin.",
+    },
+    Properties {
+        name: "Maple",
+        density: 0.70,
+        youngs_modulus: 12.6,
+        speed_of_sound: 424_000.0,
+        loss_factor: 0.008,
+        thermal_expansion: 4e-6,
+        thermal_conductivity: 0.16,
+        softening_temperature: None,
+        swelling: (0.0035, 0.0047, 0.0001),
+        printable: false,
+        notes: "Cheap, light and easy to turn, but moves a lot with humidity; seal the bore \
+                well.",
+    },
+    Properties {
+        name: "Bamboo",
+        density: 0.70,
+        youngs_modulus: 17.0,
+        speed_of_sound: 493_000.0,
+        loss_factor: 0.01,
+        thermal_expansion: 5e-6,
+        thermal_conductivity: 0.18,
+        softening_temperature: None,
+        swelling: (0.0015, 0.0022, 0.0002),
+        printable: false,
+        notes: "Comes as a ready-made tube, so bore and wall are what the cane gives; node \
+                walls must be cleared. Splits along the grain if it dries quickly.",
+    },
+    Properties {
+        name: "Silver",
+        density: 10.36,
+        youngs_modulus: 74.0,
+        speed_of_sound: 267_000.0,
+        loss_factor: 0.0005,
+        thermal_expansion: 19.5e-6,
+        thermal_conductivity: 360.0,
+        softening_temperature: None,
+        swelling: NO_SWELLING,
+        printable: false,
+        notes: "Drawn thin-wall tube for concert flutes; tone holes are pulled or soldered \
+                rather than drilled through a thick wall. Conducts heat away from the breath \
+                fast, so it plays flat until warm.",
+    },
+];
+
+impl Material {
+    pub const ALL: [Material; 10] = [
+        Material::Pla,
+        Material::Petg,
+        Material::Resin,
+        Material::Abs,
+        Material::Boxwood,
+        Material::Blackwood,
+        Material::Cocobolo,
+        Material::Maple,
+        Material::Bamboo,
+        Material::Silver,
+    ];
+
+    pub const fn properties(self) -> &'static Properties {
+        &TABLE[self as usize]
+    }
+
+    /// Every material with its properties, for pickers and reports
+    pub fn catalogue() -> Vec<MaterialInfo> {
+        Material::ALL
+            .iter()
+            .map(|&id| MaterialInfo {
+                id,
+                properties: id.properties(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_material_table_is_consistent() {
+        for material in Material::ALL {
+            let p = material.properties();
+            // The listed wave speed is the bar speed sqrt(E / rho) (GPa -> dyn/cm^2)
+            let bar = (p.youngs_modulus * 1e10 / p.density).sqrt();
+            assert!((p.speed_of_sound - bar).abs() / bar < 0.02, "{}", p.name);
+            // Only the woods move with moisture, and swell least along the grain
+            let (radial, tangential, longitudinal) = p.swelling;
+            assert!(longitudinal <= radial && radial <= tangential, "{}", p.name);
+            assert_eq!(p.printable, p.softening_temperature.is_some(), "{}", p.name);
+        }
+        assert_eq!(
+            serde_json::to_string(&Material::Blackwood).unwrap(),
+            "\"blackwood\""
+        );
+    }
+}
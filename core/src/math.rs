@@ -0,0 +1,151 @@
+//! The transcendental functions the solver needs. Platform maths libraries differ in the
+//! last bit of `sin`, `hypot` and friends, so with the `deterministic` feature these come
+//! from the pure-Rust `libm` port instead and a design solves to bit-identical pitches on
+//! wasm, x86 and ARM. Arithmetic and `sqrt` are exactly rounded IEEE operations either way.
+//! The `_f32` functions serve the single-precision preview path.
+
+use num_complex::{Complex32, Complex64};
+
+/// Sine, for weighting a standing wave along the bore
+#[cfg(feature = "deterministic")]
+pub(crate) use libm::sin;
+#[cfg(feature = "deterministic")]
+use libm::{atan2, cos, cosf, expm1, expm1f, hypot, hypotf, log, sinf};
+
+#[cfg(not(feature = "deterministic"))]
+mod platform {
+    pub fn atan2(y: f64, x: f64) -> f64 {
+        y.atan2(x)
+    }
+    pub fn cos(x: f64) -> f64 {
+        x.cos()
+    }
+    pub fn expm1(x: f64) -> f64 {
+        x.exp_m1()
+    }
+    pub fn hypot(x: f64, y: f64) -> f64 {
+        x.hypot(y)
+    }
+    pub fn log(x: f64) -> f64 {
+        x.ln()
+    }
+    pub fn sin(x: f64) -> f64 {
+        x.sin()
+    }
+    pub fn cosf(x: f32) -> f32 {
+        x.cos()
+    }
+    pub fn expm1f(x: f32) -> f32 {
+        x.exp_m1()
+    }
+    pub fn hypotf(x: f32, y: f32) -> f32 {
+        x.hypot(y)
+    }
+    pub fn sinf(x: f32) -> f32 {
+        x.sin()
+    }
+}
+/// Sine, for weighting a standing wave along the bore
+#[cfg(not(feature = "deterministic"))]
+pub(crate) use platform::sin;
+#[cfg(not(feature = "deterministic"))]
+use platform::{atan2, cos, cosf, expm1, expm1f, hypot, hypotf, log, sinf};
+
+/// Natural logarithm
+pub(crate) fn ln(x: f64) -> f64 {
+    log(x)
+}
+
+/// `|z|`
+pub(crate) fn norm(z: Complex64) -> f64 {
+    hypot(z.re, z.im)
+}
+
+/// Principal argument of `z` in -pi..pi
+pub(crate) fn arg(z: Complex64) -> f64 {
+    atan2(z.im, z.re)
+}
+
+/// Complex tangent in the exponential form tan z = +-i (1 - w) / (1 + w), where w is
+/// e^(2iz) or e^(-2iz), whichever has |w| <= 1, so nothing overflows however lossy or long
+/// the segment. The halves 1 +- w are built from cos^2, sin^2 and expm1 rather than by
+/// subtraction, which keeps full relative precision next to the poles, where the textbook
+/// (sin 2a + i sinh 2b) / (cos 2a + cosh 2b) cancels to nothing.
+pub(crate) fn tan(z: Complex64) -> Complex64 {
+    // w = e^(2 sign(b) i z) = e^(-2|b|) (cos 2a + i sign(b) sin 2a)
+    let (s, c) = (sin(z.re), cos(z.re));
+    let sign = if z.im < 0.0 { -1.0 } else { 1.0 };
+    let m = expm1(-2.0 * z.im.abs());
+    let (cos_2a, sin_2a) = (c * c - s * s, 2.0 * s * c);
+    let w_im = sign * (1.0 + m) * sin_2a;
+    let one_plus = Complex64::new(2.0 * c * c + m * cos_2a, w_im);
+    let one_minus = Complex64::new(2.0 * s * s - m * cos_2a, -w_im);
+    // tan z = i sign(b) (1 - w) / (1 + w)
+    Complex64::new(0.0, sign) * one_minus / one_plus
+}
+
+/// Complex cosine, cos a cosh b - i sin a sinh b, for walking the pressure along a segment.
+/// Overflows past |b| ~ 710, far beyond any bore.
+pub(crate) fn complex_cos(z: Complex64) -> Complex64 {
+    let (up, down) = (expm1(z.im), expm1(-z.im));
+    let cosh = 1.0 + 0.5 * (up + down);
+    let sinh = 0.5 * (up - down);
+    Complex64::new(cos(z.re) * cosh, -sin(z.re) * sinh)
+}
+
+/// `norm` in single precision
+pub(crate) fn norm_f32(z: Complex32) -> f32 {
+    hypotf(z.re, z.im)
+}
+
+/// `tan` in single precision
+pub(crate) fn tan_f32(z: Complex32) -> Complex32 {
+    let (s, c) = (sinf(z.re), cosf(z.re));
+    let sign = if z.im < 0.0 { -1.0 } else { 1.0 };
+    let m = expm1f(-2.0 * z.im.abs());
+    let (cos_2a, sin_2a) = (c * c - s * s, 2.0 * s * c);
+    let w_im = sign * (1.0 + m) * sin_2a;
+    let one_plus = Complex32::new(2.0 * c * c + m * cos_2a, w_im);
+    let one_minus = Complex32::new(2.0 * s * s - m * cos_2a, -w_im);
+    Complex32::new(0.0, sign) * one_minus / one_plus
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_num_complex() {
+        for z in [
+            Complex64::new(0.3, -0.01),
+            Complex64::new(2.9, -0.2),
+            Complex64::new(-1.2, 0.5),
+        ] {
+            assert!((tan(z) - z.tan()).norm() <= 1e-12 * z.tan().norm());
+            assert!((norm(z) - z.norm()).abs() <= 1e-15 * z.norm());
+            assert!((arg(z) - z.arg()).abs() <= 1e-15);
+            assert!((complex_cos(z) - z.cos()).norm() <= 1e-15 * z.cos().norm());
+        }
+        assert!((ln(440.0) - 440f64.ln()).abs() < 1e-15);
+    }
+
+    #[test]
+    fn test_tan_is_stable_near_poles_and_for_long_lossy_lines() {
+        // tan(pi/2 + d + ie) ~ -1 / (d + ie) for small d, e
+        for (d, e) in [(1e-9, -1e-9), (-3e-10, -2e-11), (2e-8, 4e-9)] {
+            let z = Complex64::new(std::f64::consts::FRAC_PI_2 + d, e);
+            let expected = -1.0 / Complex64::new(d, e);
+            assert!(
+                (tan(z) - expected).norm() <= 1e-6 * expected.norm(),
+                "{}: {} vs {}",
+                z,
+                tan(z),
+                expected
+            );
+        }
+        // Far down a lossy line tan -> -i; sinh and cosh alone would overflow
+        let z = Complex64::new(1e3, -800.0);
+        assert!((tan(z) - Complex64::new(0.0, -1.0)).norm() < 1e-15);
+        assert!((tan_f32(Complex32::new(50.0, -80.0)) - Complex32::new(0.0, -1.0)).norm() < 1e-6);
+    }
+}
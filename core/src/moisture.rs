@@ -0,0 +1,158 @@
+//! Seasonal drift of wooden instruments: how far the bore, holes and length move as the
+//! wood takes up or gives off moisture, and what that does to every note.
+
+use crate::materials::Material;
+use crate::physics::Flute;
+use crate::tuning::{self, ComparisonRow};
+use serde::{Deserialize, Serialize};
+
+/// The humidity swing to predict, and the wood the design is turned from. Materials that
+/// don't take up water (plastics, silver) show no drift.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct DriftOptions {
+    pub material: Material,
+    /// Relative humidity the instrument was made and tuned at, %
+    pub from_humidity: f64,
+    /// Relative humidity to predict for, %
+    pub to_humidity: f64,
+    /// °C
+    pub temperature: f64,
+    /// NaN uses the solver default
+    pub jet_velocity: f64,
+}
+
+impl Default for DriftOptions {
+    fn default() -> Self {
+        // A heated winter workshop to a humid summer
+        DriftOptions {
+            material: Material::Boxwood,
+            from_humidity: 35.0,
+            to_humidity: 75.0,
+            temperature: 20.0,
+            jet_velocity: f64::NAN,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Drift {
+    /// Change in the wood's equilibrium moisture content, percentage points
+    pub moisture_change: f64,
+    /// Fractional change of the bore and wall across the grain
+    pub bore_change: f64,
+    /// Fractional change of length and hole positions along the grain
+    pub length_change: f64,
+    /// Fractional change of the tone-hole diameters
+    pub hole_change: f64,
+    /// Every progressive fingering: `a` as made, `b` after the change, `cents` b from a
+    pub notes: Vec<ComparisonRow>,
+}
+
+/// Moisture content (%) wood settles at in air of `humidity` % at `temperature` °C, from
+/// the Hailwood-Horrobin sorption fit used by the Wood Handbook
+pub fn equilibrium_moisture(humidity: f64, temperature: f64) -> f64 {
+    let t = temperature;
+    let h = (humidity / 100.0).clamp(0.0, 0.98);
+    let w = 330.0 + 0.452 * t + 0.00415 * t * t;
+    let k = 0.791 + 4.63e-4 * t - 8.44e-7 * t * t;
+    let k1 = 6.34 + 7.75e-4 * t - 9.35e-5 * t * t;
+    let k2 = 1.09 + 2.84e-2 * t - 9.04e-5 * t * t;
+    let kh = k * h;
+    1800.0 / w
+        * (kh / (1.0 - kh)
+            + (k1 * kh + 2.0 * k1 * k2 * kh * kh) / (1.0 + k1 * kh + k1 * k2 * kh * kh))
+}
+
+/// `flute` after its wood gains `moisture_change` percentage points of moisture (negative
+/// dries it). The bore is drilled along the grain, so its diameter and the wall move with
+/// the mean of the radial and tangential swelling and the length and hole positions with the
+/// small longitudinal one; a hole drilled through the wall spans the tangential and
+/// longitudinal directions.
+pub fn swollen(flute: &Flute, material: Material, moisture_change: f64) -> Flute {
+    let (bore, length, hole) = changes(material, moisture_change);
+    let mut made = flute.clone();
+    made.length *= 1.0 + length;
+    made.cork_position *= 1.0 + length;
+    made.bore_radius *= 1.0 + bore;
+    made.wall_thickness *= 1.0 + bore;
+    made.embouchure_hole_radius *= 1.0 + hole;
+    for h in &mut made.holes {
+        h.position *= 1.0 + length;
+        h.radius *= 1.0 + hole;
+    }
+    for r in &mut made.resonators {
+        r.position *= 1.0 + length;
+    }
+    made
+}
+
+/// Fractional (bore, length, hole) changes for `moisture_change` points of moisture
+fn changes(material: Material, moisture_change: f64) -> (f64, f64, f64) {
+    let (radial, tangential, longitudinal) = material.properties().swelling;
+    (
+        0.5 * (radial + tangential) * moisture_change,
+        longitudinal * moisture_change,
+        0.5 * (tangential + longitudinal) * moisture_change,
+    )
+}
+
+/// How far each note moves when the humidity goes from `opts.from_humidity` to
+/// `opts.to_humidity`. Swelling narrows nothing and widens everything, so notes mostly
+/// flatten slightly as the bore and holes grow; a maker tuning in dry air can aim sharp.
+pub fn moisture_drift(flute: &Flute, opts: &DriftOptions) -> Drift {
+    let moisture_change = equilibrium_moisture(opts.to_humidity, opts.temperature)
+        - equilibrium_moisture(opts.from_humidity, opts.temperature);
+    let (bore_change, length_change, hole_change) = changes(opts.material, moisture_change);
+    let drifted = swollen(flute, opts.material, moisture_change);
+    Drift {
+        moisture_change,
+        bore_change,
+        length_change,
+        hole_change,
+        notes: tuning::compare_designs(flute, &drifted, opts.jet_velocity),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Hole;
+
+    #[test]
+    fn test_moisture_drift() {
+        // Wood Handbook: about 12 % at 65 % RH, 20 °C
+        assert!((equilibrium_moisture(65.0, 20.0) - 12.0).abs() < 0.7);
+        assert!(equilibrium_moisture(30.0, 20.0) < equilibrium_moisture(80.0, 20.0));
+
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for pos in [40.0, 35.0] {
+            flute.holes.push(Hole::new(pos, 0.35, true)).unwrap();
+        }
+        let drift = moisture_drift(&flute, &DriftOptions::default());
+        assert!(drift.moisture_change > 5.0);
+        assert!(drift.bore_change > drift.hole_change && drift.hole_change > drift.length_change);
+        assert_eq!(drift.notes.len(), 3);
+
+        // Blackwood is far steadier than boxwood
+        let blackwood = moisture_drift(
+            &flute,
+            &DriftOptions {
+                material: Material::Blackwood,
+                ..DriftOptions::default()
+            },
+        );
+        for (box_note, black_note) in drift.notes.iter().zip(&blackwood.notes) {
+            assert!(black_note.cents.abs() < box_note.cents.abs());
+        }
+        // No swing, no drift
+        let still = DriftOptions {
+            to_humidity: 35.0,
+            ..DriftOptions::default()
+        };
+        assert!(moisture_drift(&flute, &still)
+            .notes
+            .iter()
+            .all(|n| n.cents == 0.0));
+    }
+}
@@ -0,0 +1,196 @@
+//! Flue organ pipes: a starting pipe from the builder's usual proportions, and the voicing
+//! figures they work with. The pipe itself is a `Flute` with an `InstrumentType::OrganPipe`
+//! mouth, so the pitch comes from the same duct model as a whistle's.
+
+use crate::ergonomics::{fit, pitch_near};
+use crate::error::FlyteError;
+use crate::physics::{FluePipe, Flute, InstrumentType, AIR_DENSITY, SPEED_OF_SOUND};
+use crate::tuning;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// Töpfer's normal scale: 155.5 mm across at 8' C (65.41 Hz), halving every 16 semitones
+const NORMAL_DIAMETER: f64 = 15.55; // cm
+const NORMAL_PITCH: f64 = 65.406;
+const HALVING_SEMITONES: f64 = 16.0;
+/// Edge-tone rule the pitch guess also uses: the jet prefers f ~ 0.2 U / cut-up
+const STROUHAL: f64 = 0.2;
+/// Pascals per millimetre of water column, the organ builder's wind gauge
+const PA_PER_MM_WATER: f64 = 9.806_65;
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct PipeOptions {
+    pub stopped: bool,
+    /// Semitones wider (+) or narrower (-) than the normal scale: principals sit near 0,
+    /// flutes a few wider, strings well narrower
+    pub scale: f64,
+    /// Mouth width over the pipe circumference; 1/4 is the classic "quarter mouth"
+    pub mouth_ratio: f64,
+    /// Cut-up over mouth width
+    pub cut_up_ratio: f64,
+    pub wall_thickness: f64, // cm
+}
+
+impl Default for PipeOptions {
+    fn default() -> Self {
+        PipeOptions {
+            stopped: false,
+            scale: 0.0,
+            mouth_ratio: 0.25,
+            cut_up_ratio: 0.25,
+            wall_thickness: 0.15,
+        }
+    }
+}
+
+/// Voicing figures for a pipe's mouth at its sounding pitch
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PipeReport {
+    pub frequency: f64,
+    pub note: String,
+    pub stopped: bool,
+    /// Cut-up over mouth width
+    pub cut_up_ratio: f64,
+    /// Mouth width over circumference
+    pub mouth_ratio: f64,
+    /// Semitones wider (+) or narrower (-) than Töpfer's normal scale at this pitch
+    pub scale: f64,
+    /// Jet speed the mouth is voiced for at this pitch (cm/s), from f = 0.2 U / cut-up
+    pub jet_velocity: f64,
+    /// Foot pressure that gives that jet, mm of water column
+    pub wind_pressure: f64,
+}
+
+/// Diameter (cm) of the normal-scale pipe sounding `frequency`
+pub fn normal_diameter(frequency: f64) -> f64 {
+    let semitones = 12.0 * (frequency / NORMAL_PITCH).log2();
+    NORMAL_DIAMETER * 2f64.powf(-semitones / HALVING_SEMITONES)
+}
+
+/// A pipe sounding `frequency`: the bore from the normal scale moved by `opts.scale`, the
+/// mouth from the two ratios, then the body cut to pitch
+pub fn design_pipe(frequency: f64, opts: &PipeOptions) -> Result<Flute, FlyteError> {
+    if !frequency.is_finite() || frequency <= 0.0 {
+        return Err(FlyteError::invalid_input(format!(
+            "pipe pitch must be positive, got {}",
+            frequency
+        )));
+    }
+    if !(opts.mouth_ratio > 0.0 && opts.mouth_ratio < 1.0 / PI) {
+        return Err(FlyteError::invalid_input(format!(
+            "mouth_ratio must be between 0 and 1/pi (a mouth as wide as the pipe), got {}",
+            opts.mouth_ratio
+        )));
+    }
+    // Scale offsets count semitones down the normal scale, so wider is lower
+    let diameter = normal_diameter(frequency * 2f64.powf(-opts.scale / 12.0));
+    let radius = diameter / 2.0;
+    let mouth_width = opts.mouth_ratio * PI * diameter;
+    let pipe = FluePipe {
+        mouth_width,
+        cut_up: opts.cut_up_ratio * mouth_width,
+        flue_depth: (0.03 * diameter).max(0.03),
+        languid_length: radius,
+        stopped: opts.stopped,
+        ..FluePipe::default()
+    };
+    let ends = if opts.stopped { 4.0 } else { 2.0 };
+    let guess = SPEED_OF_SOUND / (ends * frequency);
+    let mut flute = Flute::new(guess, radius, opts.wall_thickness);
+    flute.instrument = InstrumentType::OrganPipe(pipe);
+    flute.validate()?;
+    flute.length = fit((0.5 * guess, 1.5 * guess), frequency, false, |length| {
+        flute.length = length;
+        pitch_near(&flute, 0, frequency)
+    });
+    Ok(flute)
+}
+
+/// Cut-up ratio, scale and voicing wind for an organ-pipe design. Errors for other
+/// instrument types.
+pub fn pipe_report(flute: &Flute, jet_velocity: f64) -> Result<PipeReport, FlyteError> {
+    let InstrumentType::OrganPipe(pipe) = &flute.instrument else {
+        return Err(FlyteError::invalid_input("the design is not an organ pipe"));
+    };
+    flute.validate()?;
+    let frequency = flute.clone().calculate_pitch(jet_velocity);
+    let diameter = 2.0 * flute.bore_radius;
+    let jet = frequency * pipe.cut_up / STROUHAL;
+    // 0.5 rho U^2 in dyn/cm^2, a tenth of a pascal
+    let pressure = 0.5 * AIR_DENSITY * jet.powi(2) / 10.0;
+    Ok(PipeReport {
+        frequency,
+        note: tuning::note_info(frequency).name,
+        stopped: pipe.stopped,
+        cut_up_ratio: pipe.cut_up_ratio(),
+        mouth_ratio: pipe.mouth_width / (PI * diameter),
+        scale: HALVING_SEMITONES * (diameter / normal_diameter(frequency)).log2(),
+        jet_velocity: jet,
+        wind_pressure: pressure / PA_PER_MM_WATER,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry;
+    use crate::physics::TuningSlot;
+
+    #[test]
+    fn test_organ_pipes() {
+        // Principal and stopped flute on C5
+        let c5 = 523.25;
+        let open = design_pipe(c5, &PipeOptions::default()).unwrap();
+        let stopped = design_pipe(
+            c5,
+            &PipeOptions {
+                stopped: true,
+                ..PipeOptions::default()
+            },
+        )
+        .unwrap();
+        let report = pipe_report(&open, f64::NAN).unwrap();
+        assert!((1200.0 * (report.frequency / c5).log2()).abs() < 2.0);
+        assert_eq!(report.note, "C5");
+        assert!((report.cut_up_ratio - 0.25).abs() < 1e-12);
+        assert!((report.mouth_ratio - 0.25).abs() < 1e-12);
+        assert!(report.scale.abs() < 0.05);
+        // A few tens of mm of water, as small flue pipes are voiced on
+        assert!(report.wind_pressure > 2.0 && report.wind_pressure < 100.0);
+
+        // Stopping the top halves the body for the same note
+        let stopped_report = pipe_report(&stopped, f64::NAN).unwrap();
+        assert!((1200.0 * (stopped_report.frequency / c5).log2()).abs() < 2.0);
+        let ratio = stopped.length / open.length;
+        assert!(ratio > 0.4 && ratio < 0.6, "{}", ratio);
+
+        // Opening a tuning slot sharpens the open pipe; a stopped pipe can't have one
+        let mut slotted = open.clone();
+        let slot = TuningSlot {
+            length: 1.0,
+            width: 0.5,
+        };
+        if let InstrumentType::OrganPipe(pipe) = &mut slotted.instrument {
+            pipe.tuning_slot = Some(slot);
+        }
+        assert!(pipe_report(&slotted, f64::NAN).unwrap().frequency > report.frequency);
+        let mut bad = stopped.clone();
+        if let InstrumentType::OrganPipe(pipe) = &mut bad.instrument {
+            pipe.tuning_slot = Some(slot);
+        }
+        assert!(bad.validate().is_err());
+        assert!(pipe_report(&Flute::new(60.0, 0.95, 0.4), f64::NAN).is_err());
+
+        // The mouth is the duct flute's; the cap and slot are cut or added at the top
+        for (pipe, part) in [(&stopped, "PipeCap"), (&slotted, "TuningSlotCutter")] {
+            let mesh = geometry::generate_flute_mesh(pipe);
+            assert!(mesh.validate().watertight, "{}", part);
+            let groups = mesh.group_triangles();
+            let names: Vec<&str> = groups.iter().map(|(n, _)| *n).collect();
+            for part in ["FippleBlock", "WindowCutter", part] {
+                assert!(names.contains(&part), "{:?}", names);
+            }
+        }
+    }
+}
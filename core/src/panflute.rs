@@ -0,0 +1,331 @@
+//! Pan-flute sets: one stopped pipe per note, all cut from the same tube stock. Each pipe's
+//! air column is solved with the stopped-bore model (the cork-cavity stub run the whole
+//! length, radiating from the open end like the foot), so the open-end correction and wall
+//! losses are in the lengths. The player's lip shades part of the opening, which the model
+//! leaves out, so real pipes may want a final trim from a slightly long cut. The set comes with a cut list for sawing the pipes from
+//! stock and a printable raft mesh.
+
+use crate::error::FlyteError;
+use crate::geometry::{add_annulus, add_axial_prism, Mesh, MeshOptions};
+use crate::physics::{Flute, SPEED_OF_SOUND};
+use crate::transfer::Bore;
+use crate::tuning::{self, NoteInfo};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// Length refinements; the end correction barely changes between them
+const LENGTH_PASSES: usize = 8;
+/// The resonance is searched this factor either side of the quarter-wave guess
+const SEARCH_WINDOW: f64 = 1.5;
+/// Shortest air column worth making, in bore diameters
+const MIN_ASPECT: f64 = 2.0;
+/// The binding bars sit this far below the blowing rim (cm) and are this wide
+const BINDING_FROM: f64 = 1.5;
+const BINDING_WIDTH: f64 = 1.5;
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct PanFluteOptions {
+    /// One pipe per note (Hz), in any order; the set comes out lowest first
+    pub notes: Vec<f64>,
+    /// Of the tube stock, cm
+    pub bore_radius: f64,
+    pub wall_thickness: f64,
+    /// Solid plug (or printed base) below each air column, cm
+    pub stopper: f64,
+    /// Length of the stock tubes; NaN or 0 lists the cuts without packing them
+    pub stock_length: f64,
+    /// Lost to each saw cut, cm
+    pub kerf: f64,
+    /// Gap between neighbouring pipes in the raft mesh, cm
+    pub spacing: f64,
+}
+
+impl Default for PanFluteOptions {
+    fn default() -> Self {
+        // Eight pipes in C major from C5 on 12 mm bore tube
+        PanFluteOptions {
+            notes: vec![
+                523.25, 587.33, 659.26, 698.46, 783.99, 880.0, 987.77, 1046.5,
+            ],
+            bore_radius: 0.6,
+            wall_thickness: 0.15,
+            stopper: 1.0,
+            stock_length: f64::NAN,
+            kerf: 0.2,
+            spacing: 0.1,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Pipe {
+    pub target: f64,
+    pub note: NoteInfo,
+    /// Blowing rim to stopper face: the air column, cm
+    pub air_length: f64,
+    /// Air column plus stopper: what to saw off the stock
+    pub cut_length: f64,
+    /// What the model says the cut pipe plays
+    pub frequency: f64,
+    pub cents: f64,
+}
+
+/// One stock tube and the pipes sawn from it
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct StockPiece {
+    /// Indices into `PanFlute::pipes`, longest first
+    pub pipes: Vec<usize>,
+    /// Left over after the cuts and kerfs, cm
+    pub offcut: f64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PanFlute {
+    pub bore_radius: f64,
+    pub wall_thickness: f64,
+    /// Lowest first
+    pub pipes: Vec<Pipe>,
+    /// Empty when no stock length was given
+    pub cut_list: Vec<StockPiece>,
+    /// Centre-to-centre distance of the pipes in the raft, cm
+    pub pitch: f64,
+}
+
+/// Pipe lengths for every note, packed into stock lengths when `opts.stock_length` is set.
+/// Rejects non-positive notes or stock dimensions, pipes too short for their bore and pipes
+/// longer than the stock.
+pub fn design_pan_flute(opts: &PanFluteOptions) -> Result<PanFlute, FlyteError> {
+    trace_span!(INFO, "design_pan_flute", pipes = opts.notes.len());
+    if opts.notes.is_empty() {
+        return Err(FlyteError::invalid_input(
+            "a pan flute needs at least one note",
+        ));
+    }
+    for (field, value) in [
+        ("bore_radius", opts.bore_radius),
+        ("wall_thickness", opts.wall_thickness),
+    ] {
+        if !value.is_finite() || value <= 0.0 {
+            return Err(FlyteError::invalid_geometry(
+                field,
+                value,
+                "must be positive",
+            ));
+        }
+    }
+    let stopper = opts.stopper.max(0.0);
+    let mut notes = opts.notes.clone();
+    notes.sort_by(|a, b| a.total_cmp(b));
+
+    let mut pipes = Vec::with_capacity(notes.len());
+    for target in notes {
+        if !target.is_finite() || target <= 0.0 {
+            return Err(FlyteError::invalid_input(format!(
+                "pipe notes must be positive frequencies, got {}",
+                target
+            )));
+        }
+        let (air_length, frequency) = fit_pipe(opts.bore_radius, opts.wall_thickness, target);
+        if !frequency.is_finite() || air_length < MIN_ASPECT * 2.0 * opts.bore_radius {
+            return Err(FlyteError::invalid_input(format!(
+                "a {:.2} Hz pipe is too short for a {:.1} mm bore",
+                target,
+                20.0 * opts.bore_radius
+            )));
+        }
+        pipes.push(Pipe {
+            target,
+            note: tuning::note_info(target),
+            air_length,
+            cut_length: air_length + stopper,
+            frequency,
+            cents: 1200.0 * (frequency / target).log2(),
+        });
+    }
+
+    let cut_list = if opts.stock_length.is_finite() && opts.stock_length > 0.0 {
+        cut_list(&pipes, opts.stock_length, opts.kerf.max(0.0))?
+    } else {
+        Vec::new()
+    };
+    Ok(PanFlute {
+        bore_radius: opts.bore_radius,
+        wall_thickness: opts.wall_thickness,
+        pipes,
+        cut_list,
+        pitch: 2.0 * (opts.bore_radius + opts.wall_thickness) + opts.spacing.max(0.0),
+    })
+}
+
+/// Air column length for `target` and the pitch the model gives it. Starts from a quarter
+/// wavelength and moves the length by however far the solved pitch puts the effective
+/// length (mouth correction and losses included) from the target's.
+fn fit_pipe(bore_radius: f64, wall_thickness: f64, target: f64) -> (f64, f64) {
+    let quarter = |f: f64| SPEED_OF_SOUND / (4.0 * f);
+    let mut length = quarter(target);
+    for _ in 0..LENGTH_PASSES {
+        let frequency = stopped_pitch(&pipe_bore(length, bore_radius, wall_thickness), target);
+        if !frequency.is_finite() {
+            break;
+        }
+        let correction = quarter(frequency) - length;
+        length = quarter(target) - correction;
+    }
+    let frequency = stopped_pitch(&pipe_bore(length, bore_radius, wall_thickness), target);
+    (length, frequency)
+}
+
+/// The model of one pipe: a plain tube without holes
+fn pipe_bore(length: f64, bore_radius: f64, wall_thickness: f64) -> Bore {
+    Flute::new(length, bore_radius, wall_thickness).bore(&[])
+}
+
+/// Lowest resonance of a stopped pipe near `guess`: the first upward zero crossing of
+/// Im(Z) within `SEARCH_WINDOW`, bisected. NaN when there is none.
+fn stopped_pitch(bore: &Bore, guess: f64) -> f64 {
+    let im = |f: f64| bore.stopped_impedance(f).im;
+    let mut lo = guess / SEARCH_WINDOW;
+    while lo < guess * SEARCH_WINDOW {
+        let hi = lo * 1.01;
+        if im(lo) < 0.0 && im(hi) >= 0.0 {
+            let (mut lo, mut hi) = (lo, hi);
+            for _ in 0..40 {
+                let mid = 0.5 * (lo + hi);
+                if im(mid) < 0.0 {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                }
+            }
+            return 0.5 * (lo + hi);
+        }
+        lo = hi;
+    }
+    f64::NAN
+}
+
+/// First-fit decreasing: each pipe, longest first, goes on the first stock tube with room
+/// for it and a kerf
+fn cut_list(pipes: &[Pipe], stock: f64, kerf: f64) -> Result<Vec<StockPiece>, FlyteError> {
+    let mut order: Vec<usize> = (0..pipes.len()).collect();
+    order.sort_by(|&a, &b| pipes[b].cut_length.total_cmp(&pipes[a].cut_length));
+    let mut pieces: Vec<StockPiece> = Vec::new();
+    for i in order {
+        let need = pipes[i].cut_length;
+        if need > stock {
+            return Err(FlyteError::invalid_input(format!(
+                "the {} pipe needs {:.1} cm, longer than the {:.1} cm stock",
+                pipes[i].note.name, need, stock
+            )));
+        }
+        // A cut at the end of the tube needs no kerf
+        let fits = |piece: &StockPiece| piece.offcut >= need + kerf || piece.offcut == need;
+        match pieces.iter_mut().find(|piece| fits(piece)) {
+            Some(piece) => {
+                piece.offcut = (piece.offcut - need - kerf).max(0.0);
+                piece.pipes.push(i);
+            }
+            None => pieces.push(StockPiece {
+                pipes: vec![i],
+                offcut: (stock - need - kerf).max(0.0),
+            }),
+        }
+    }
+    Ok(pieces)
+}
+
+/// The set as one printable part: the pipes side by side along +Z with their blowing rims
+/// at x = 0, a solid `Stopper<n>` at the bottom of each, and `Binding` bars across the top
+/// and bottom of the tubes just below the rims, clear of the bores.
+pub fn pan_flute_mesh(set: &PanFlute, options: &MeshOptions) -> Mesh {
+    let mut mesh = Mesh::new();
+    let r_inner = set.bore_radius;
+    let r_outer = set.bore_radius + set.wall_thickness;
+    let segments = options.lod.tube_segments();
+    // Sunk halfway into the wall so the shells fuse when sliced
+    let r_plug = r_inner + 0.5 * set.wall_thickness;
+    let circle: Vec<(f64, f64)> = (0..segments)
+        .map(|i| {
+            let t = 2.0 * PI * (i as f64) / (segments as f64);
+            (r_plug * t.sin(), r_plug * t.cos())
+        })
+        .collect();
+    for (n, pipe) in set.pipes.iter().enumerate() {
+        let mut part = Mesh::new();
+        part.set_group(&format!("Pipe{}", n + 1));
+        add_annulus(
+            &mut part,
+            (0.0, pipe.cut_length),
+            (r_inner, r_outer),
+            &options.tube_ends,
+            options.lod,
+        );
+        if pipe.cut_length > pipe.air_length {
+            part.set_group(&format!("Stopper{}", n + 1));
+            add_axial_prism(&mut part, &circle, (pipe.air_length, pipe.cut_length));
+        }
+        part.translate((0.0, 0.0, n as f64 * set.pitch));
+        mesh.append(&part);
+    }
+
+    if set.pipes.len() > 1 {
+        mesh.set_group("Binding");
+        let z = (-r_outer, (set.pipes.len() - 1) as f64 * set.pitch + r_outer);
+        let x = (BINDING_FROM, BINDING_FROM + BINDING_WIDTH);
+        let thickness = set.wall_thickness.max(0.2);
+        for y in [
+            (r_plug, r_outer + thickness),
+            (-r_outer - thickness, -r_plug),
+        ] {
+            let outline = [(y.0, z.1), (y.1, z.1), (y.1, z.0), (y.0, z.0)];
+            add_axial_prism(&mut mesh, &outline, x);
+        }
+    }
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pan_flute_set() {
+        let opts = PanFluteOptions {
+            stock_length: 30.0,
+            ..PanFluteOptions::default()
+        };
+        let set = design_pan_flute(&opts).unwrap();
+        assert_eq!(set.pipes.len(), 8);
+        for pipe in &set.pipes {
+            assert!(pipe.cents.abs() < 1.0, "{:?}", pipe);
+            // Shorter than a bare quarter wave by the unflanged end correction, 0.61 r
+            let quarter = SPEED_OF_SOUND / (4.0 * pipe.target);
+            let correction = quarter - pipe.air_length;
+            assert!((correction / (0.61 * opts.bore_radius) - 1.0).abs() < 0.05);
+        }
+        assert!(set
+            .pipes
+            .windows(2)
+            .all(|p| p[0].air_length > p[1].air_length));
+        assert_eq!(set.pipes[0].note.name, "C5");
+
+        // Every pipe is cut exactly once and no stock tube is overfilled
+        let mut cut: Vec<usize> = set.cut_list.iter().flat_map(|s| s.pipes.clone()).collect();
+        cut.sort();
+        assert_eq!(cut, (0..8).collect::<Vec<_>>());
+        for piece in &set.cut_list {
+            let used: f64 = piece.pipes.iter().map(|&i| set.pipes[i].cut_length).sum();
+            assert!(used <= opts.stock_length);
+        }
+
+        let report = pan_flute_mesh(&set, &MeshOptions::default()).validate();
+        assert!(report.watertight, "{:?}", report);
+
+        let short = PanFluteOptions {
+            stock_length: 5.0,
+            ..PanFluteOptions::default()
+        };
+        assert!(design_pan_flute(&short).is_err());
+    }
+}
@@ -1,30 +1,775 @@
+use crate::error::FlyteError;
+use crate::geometry;
+use crate::math;
+use crate::transfer::Bore;
+use crate::tuning;
 use num_complex::Complex64;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::f64::consts::PI;
+use std::borrow::Cow;
 
-const SPEED_OF_SOUND: f64 = 34500.0; // cm/s
-const AIR_DENSITY: f64 = 0.0012; // g/cm^3
+pub(crate) const SPEED_OF_SOUND: f64 = 34500.0; // cm/s
+pub(crate) const AIR_DENSITY: f64 = 0.0012; // g/cm^3
+pub(crate) const AIR_VISCOSITY: f64 = 1.85e-4; // poise (g/(cm s))
+/// How far `track_resonance` looks from the previous pitch: three semitones either side,
+/// well inside the gap to the next mode
+const TRACKING_WINDOW: f64 = 1.189_207_115_002_721; // 2^(3/12)
+/// Extra points sampled across a near-miss in the resonance scan
+const DIP_SAMPLES: usize = 16;
+/// Most points `adaptive_spectrum` will return
+pub const MAX_ADAPTIVE_POINTS: usize = 100_000;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Flute {
     pub length: f64,         // Total length cm
     pub bore_radius: f64,    // cm
     pub wall_thickness: f64, // cm
-    pub holes: Vec<Hole>,
+    pub holes: Holes,
     // New fields for higher accuracy
-    #[serde(default)]
+    #[serde(default = "default_cork_position")]
     pub cork_position: f64, // Distance from embouchure center to cork (cm). Default ~1.7
-    #[serde(default)]
+    #[serde(default = "default_embouchure_hole_radius")]
     pub embouchure_hole_radius: f64, // cm. Default ~0.5
-    #[serde(default)]
+    #[serde(default = "default_embouchure_chimney")]
     pub embouchure_chimney: f64, // Height of chimney (lip plate) cm. Default ~0.5
+    #[serde(default = "default_embouchure_oval")]
+    pub embouchure_oval: f64, // Axial / transverse aspect of the hole at equal area. 1.0 = round
+    #[serde(default)]
+    pub instrument: InstrumentType,
+    /// Out-of-roundness of the bore, (widest - narrowest) / mean diameter in percent.
+    /// `bore_radius` is then the radius of equal area. For a bore that varies along the
+    /// length use its average: the wall losses it changes add up along the tube.
+    #[serde(default, skip_serializing_if = "is_round")]
+    pub bore_ovality: f64,
+    /// Closed side cavities branching off the bore; always in the network, whatever the
+    /// fingering
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resonators: Vec<SideResonator>,
+    /// How the pitch solvers search; the defaults suit anything from a soprano recorder to
+    /// an alto flute
+    #[serde(default, skip_serializing_if = "SolverConfig::is_default")]
+    pub solver: SolverConfig,
+    /// End corrections and loss the model is tuned with; the defaults are the textbook ones
+    #[serde(default, skip_serializing_if = "Calibration::is_default")]
+    pub calibration: Calibration,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Floating-point width of the per-frequency maths. `F32` is for live previews on slow
+/// devices (tracking a pitch or redrawing a spectrum while a slider moves); fresh solves,
+/// charts, reports and optimisation always run in `F64`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Precision {
+    #[default]
+    F64,
+    F32,
+}
+
+/// Limits of the pitch searches. Widen `search_bounds` for very high or very low
+/// instruments: the secant search starts inside them and never steps outside, so a
+/// piccolo's top notes or a contrabass's bottom ones need room.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct SolverConfig {
+    /// Most secant steps `find_resonance` takes
+    pub max_iterations: usize,
+    /// Hz; the secant search stops once a step moves the pitch less than this
+    pub frequency_tolerance: f64,
+    /// Lowest and highest frequency (Hz) a search may step to. The lower bound is also
+    /// where `describe_resonance` starts counting modes.
+    pub search_bounds: (f64, f64),
+    /// Ratio between neighbouring frequencies of the bracketing scans that count modes and
+    /// track a pitch; `resonance_near` steps twice as far. 1.005 is about 9 cents.
+    pub scan_resolution: f64,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        SolverConfig {
+            max_iterations: 20,
+            frequency_tolerance: 0.01,
+            search_bounds: (20.0, 5000.0),
+            scan_resolution: 1.005,
+        }
+    }
+}
+
+impl SolverConfig {
+    pub(crate) fn is_default(&self) -> bool {
+        *self == SolverConfig::default()
+    }
+
+    /// Reject settings the searches can't run with: no iterations, a tolerance or lower
+    /// bound that isn't positive, bounds the wrong way round and scan steps of 1 or less
+    pub fn validate(&self) -> Result<(), FlyteError> {
+        let (lo, hi) = self.search_bounds;
+        if self.max_iterations == 0 {
+            return Err(FlyteError::invalid_input(
+                "solver max_iterations must be at least 1",
+            ));
+        }
+        if !self.frequency_tolerance.is_finite() || self.frequency_tolerance <= 0.0 {
+            return Err(FlyteError::invalid_input(format!(
+                "solver frequency_tolerance must be positive, got {}",
+                self.frequency_tolerance
+            )));
+        }
+        if !(lo > 0.0 && lo < hi && hi.is_finite()) {
+            return Err(FlyteError::invalid_input(format!(
+                "solver search_bounds must be 0 < low < high, got ({}, {})",
+                lo, hi
+            )));
+        }
+        if !(self.scan_resolution > 1.0 && self.scan_resolution.is_finite()) {
+            return Err(FlyteError::invalid_input(format!(
+                "solver scan_resolution must be above 1, got {}",
+                self.scan_resolution
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The model's empirical constants. The defaults are the textbook values; a maker whose
+/// instruments come out consistently off (a family with thick walls, undercut holes or an
+/// unusual embouchure) can fit their own with `calibration::fit_calibration`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct Calibration {
+    /// Radiation end correction of an open unflanged end over its radius: the foot, and
+    /// the blowing end of a notch flute
+    pub open_end_correction: f64,
+    /// A tone hole's end corrections over its radius, added to the wall thickness for the
+    /// chimney's effective length; also used for tuning slots and duct windows
+    pub hole_end_correction: f64,
+    /// The same for the embouchure hole, added to the lip-plate chimney; the player's lip
+    /// covering part of the hole shows up here
+    pub embouchure_end_correction: f64,
+    /// Multiplier on the bore's viscothermal wall loss
+    pub loss_factor: f64,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Calibration {
+            open_end_correction: 0.61,
+            hole_end_correction: 1.5,
+            embouchure_end_correction: 1.5,
+            loss_factor: 1.0,
+        }
+    }
+}
+
+impl Calibration {
+    pub(crate) fn is_default(&self) -> bool {
+        *self == Calibration::default()
+    }
+
+    /// Reject constants that aren't positive and finite
+    pub fn validate(&self) -> Result<(), FlyteError> {
+        let values = [
+            ("open_end_correction", self.open_end_correction),
+            ("hole_end_correction", self.hole_end_correction),
+            ("embouchure_end_correction", self.embouchure_end_correction),
+            ("loss_factor", self.loss_factor),
+        ];
+        for (field, value) in values {
+            if !value.is_finite() || value <= 0.0 {
+                return Err(FlyteError::invalid_input(format!(
+                    "calibration {} must be positive, got {}",
+                    field, value
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Numerical trouble met during a solve, so a frozen or wild pitch readout comes with a
+/// reason. Returned next to the pitch by `calculate_pitch_diagnosed`.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Diagnostics {
+    /// Impedance evaluations that came out NaN or infinite; a large finite stand-in was used
+    /// for each, so the pitch may be meaningless
+    pub non_finite_impedance: u32,
+}
+
+impl Diagnostics {
+    /// Nothing went wrong
+    pub fn is_clean(&self) -> bool {
+        self.non_finite_impedance == 0
+    }
+}
+
+/// Everything the UI shows about one solved pitch, from `calculate_pitch_detailed`
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResonanceResult {
+    pub frequency: f64,
+    /// Nearest equal-tempered note, e.g. "D4", or "--" below 20 Hz
+    pub note: String,
+    /// From that note, positive = sharp
+    pub cents: f64,
+    /// The search ended on an impedance minimum, as `checked_pitch` requires
+    pub converged: bool,
+    /// 1 for the fingering's lowest resonance, 2 for the next (the octave on a flute) and
+    /// so on; 0 when not converged
+    pub mode: usize,
+    /// Quality factor of the minimum, f over its -3 dB bandwidth; NaN when not converged
+    pub q: f64,
+    pub diagnostics: Diagnostics,
+}
+
+/// What a hole edit does with a position off the tube or a radius out of range. `Clamp`
+/// pulls the value back to the nearest one `validate` accepts, so a hole dragged past the
+/// foot stops there; `Reject` returns the `validate` error, as a file import should.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RangePolicy {
+    #[default]
+    Clamp,
+    Reject,
+}
+
+/// Smallest hole radius `RangePolicy::Clamp` leaves, cm
+pub const MIN_HOLE_RADIUS: f64 = 0.05;
+/// Largest hole radius `RangePolicy::Clamp` leaves, as a fraction of the bore radius
+const MAX_HOLE_FRACTION: f64 = 0.95;
+
+/// Settings for `adaptive_spectrum`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct AdaptiveSampling {
+    /// Evenly spaced points to start from
+    pub initial_points: usize,
+    /// Stop refining at this many points, capped at `MAX_ADAPTIVE_POINTS`
+    pub max_points: usize,
+    /// Bisect wherever neighbouring points differ by more than this in phase (radians)...
+    pub max_phase_step: f64,
+    /// ...or in level (dB)
+    pub max_level_step: f64,
+    /// Narrowest interval worth bisecting, Hz
+    pub resolution: f64,
+}
+
+impl Default for AdaptiveSampling {
+    fn default() -> Self {
+        AdaptiveSampling {
+            initial_points: 64,
+            max_points: 4000,
+            max_phase_step: 0.2,
+            max_level_step: 1.0,
+            resolution: 0.05,
+        }
+    }
+}
+
+/// How the instrument is blown. Transverse flutes use the embouchure/cork fields;
+/// end-blown types replace them with geometry at the open blowing end (x = 0).
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Default)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum InstrumentType {
+    #[default]
+    Transverse,
+    /// Quena / shakuhachi: open end with a cut notch as the edge
+    Notch(NotchCut),
+    /// Whistle / recorder / NAF: a block-formed windway blowing across a window onto a labium
+    Duct(DuctGeometry),
+    /// Flue organ pipe: the duct model with organ-builder dimensions, an open or stopped top
+    /// and an optional tuning slot
+    OrganPipe(FluePipe),
+}
+
+impl InstrumentType {
+    /// Whether a stopped cork cavity sits behind the embouchure
+    pub fn has_cork(&self) -> bool {
+        matches!(self, InstrumentType::Transverse)
+    }
+
+    /// Whether the player blows across a round embouchure hole at x = 0. Duct flutes and
+    /// organ pipes have a rectangular window there instead, notch flutes no mouth hole.
+    pub fn has_embouchure_hole(&self) -> bool {
+        matches!(self, InstrumentType::Transverse)
+    }
+
+    /// The windway and window of a duct flute or organ pipe
+    pub fn duct(&self) -> Option<DuctGeometry> {
+        match self {
+            InstrumentType::Duct(duct) => Some(*duct),
+            InstrumentType::OrganPipe(pipe) => Some(pipe.duct()),
+            InstrumentType::Transverse | InstrumentType::Notch(_) => None,
+        }
+    }
+
+    /// Whether the far end (x = length) is closed
+    pub fn is_stopped(&self) -> bool {
+        matches!(self, InstrumentType::OrganPipe(pipe) if pipe.stopped)
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NotchShape {
+    /// Quena-style rounded notch
+    #[default]
+    U,
+    /// Pointed notch
+    V,
+}
+
+/// Notch cut into the top of the wall at the blowing end (cm, degrees)
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct NotchCut {
+    pub shape: NotchShape,
+    pub width: f64, // across the tube at the end face
+    pub depth: f64, // along the tube, measured on the bore surface
+    /// Angle of the outside bevel to the tube axis; 90 = square cut, smaller = sharper edge
+    pub bevel_angle: f64,
+}
+
+impl Default for NotchCut {
+    fn default() -> Self {
+        NotchCut {
+            shape: NotchShape::U,
+            width: 1.0,
+            depth: 0.6,
+            bevel_angle: 30.0,
+        }
+    }
+}
+
+/// Windway and window of a duct flute (cm, degrees). The labium edge sits at x = 0 and the
+/// window runs back towards the head; the block fills the bore behind it.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct DuctGeometry {
+    pub windway_length: f64, // block length, head end to window
+    pub windway_width: f64,  // also the window width
+    pub windway_height: f64, // channel depth below the bore top
+    pub window_length: f64,  // block face to labium edge
+    /// Blade angle: the labium ramp to the tube axis
+    pub ramp_angle: f64,
+    /// Flat left across the tip of the labium, measured up from the bore. A knife edge
+    /// doesn't print repeatably; a small deliberate flat does.
+    pub labium_chamfer: f64,
+}
+
+/// Thinnest labium tip a hobby printer holds to shape, about half a 0.4 mm nozzle line
+pub const MIN_LABIUM_CHAMFER: f64 = 0.02; // cm
+
+impl Default for DuctGeometry {
+    fn default() -> Self {
+        DuctGeometry {
+            windway_length: 4.0,
+            windway_width: 1.0,
+            windway_height: 0.15,
+            window_length: 0.5,
+            ramp_angle: 20.0,
+            labium_chamfer: MIN_LABIUM_CHAMFER,
+        }
+    }
+}
+
+/// Mouth and top of a flue organ pipe (cm, degrees). The upper lip sits at x = 0, the flue
+/// and languid below it, and the body runs up to x = length.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct FluePipe {
+    pub mouth_width: f64,    // across the flattened mouth
+    pub cut_up: f64,         // flue exit to upper lip
+    pub flue_depth: f64,     // gap between languid and lower lip
+    pub languid_length: f64, // flue channel, languid face to mouth
+    /// Angle of the upper lip's bevel to the pipe axis
+    pub lip_angle: f64,
+    /// Capped or stoppered top; sounds about an octave below an open pipe
+    pub stopped: bool,
+    /// Slot cut down from the open top and closed off by a rolled tongue to tune
+    pub tuning_slot: Option<TuningSlot>,
+}
+
+impl Default for FluePipe {
+    fn default() -> Self {
+        // A quarter-circumference mouth on a 2 cm pipe, cut up a quarter of its width
+        FluePipe {
+            mouth_width: 1.6,
+            cut_up: 0.4,
+            flue_depth: 0.06,
+            languid_length: 1.0,
+            lip_angle: 20.0,
+            stopped: false,
+            tuning_slot: None,
+        }
+    }
+}
+
+impl FluePipe {
+    /// Mouth height over width, the organ builder's main voicing figure: about 1/4 for
+    /// principals, higher for flutes, lower for strings
+    pub fn cut_up_ratio(&self) -> f64 {
+        self.cut_up / self.mouth_width
+    }
+
+    /// The same mouth in duct-flute terms, which the acoustics and mesh are built from
+    pub fn duct(&self) -> DuctGeometry {
+        DuctGeometry {
+            windway_length: self.languid_length,
+            windway_width: self.mouth_width,
+            windway_height: self.flue_depth,
+            window_length: self.cut_up,
+            ramp_angle: self.lip_angle,
+            ..DuctGeometry::default()
+        }
+    }
+}
+
+/// Open slot in the pipe wall running down from the top (cm). The open part acts as a
+/// side hole just below the end, raising the pitch as it is uncovered.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+pub struct TuningSlot {
+    pub length: f64, // open part, from the top down
+    pub width: f64,
+}
+
+// Designs saved before a field existed load with the `Flute::new` value
+pub(crate) fn default_cork_position() -> f64 {
+    1.7
+}
+
+pub(crate) fn default_embouchure_hole_radius() -> f64 {
+    0.5
+}
+
+pub(crate) fn default_embouchure_chimney() -> f64 {
+    0.5
+}
+
+pub(crate) fn default_embouchure_oval() -> f64 {
+    1.0
+}
+
+pub(crate) fn default_open() -> bool {
+    true
+}
+
+pub(crate) fn is_round(ovality: &f64) -> bool {
+    *ovality == 0.0
+}
+
+/// Largest `bore_ovality` the equal-area ellipse model is used for, percent
+pub const MAX_OVALITY: f64 = 50.0;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
 pub struct Hole {
     pub position: f64, // Distance from embouchure (cm)
     pub radius: f64,   // cm
+    #[serde(default = "default_open")]
     pub open: bool,
+    #[serde(default)]
+    pub azimuth: f64, // Degrees around the tube axis, 0 = top, 180 = thumb side. Geometry only
+}
+
+impl Hole {
+    pub fn new(position: f64, radius: f64, open: bool) -> Self {
+        Hole {
+            position,
+            radius,
+            open,
+            azimuth: 0.0,
+        }
+    }
+
+    /// Reject values no tube could take: a non-finite field, a negative position or a
+    /// non-positive radius. Fit against the bore is `Flute::validate`'s job.
+    pub fn check(&self) -> Result<(), FlyteError> {
+        if !self.position.is_finite() || self.position < 0.0 {
+            return Err(FlyteError::invalid_geometry(
+                "hole.position",
+                self.position,
+                "must be finite and not negative",
+            ));
+        }
+        if !self.radius.is_finite() || self.radius <= 0.0 {
+            return Err(FlyteError::invalid_geometry(
+                "hole.radius",
+                self.radius,
+                "must be positive",
+            ));
+        }
+        if !self.azimuth.is_finite() {
+            return Err(FlyteError::invalid_geometry(
+                "hole.azimuth",
+                self.azimuth,
+                "must be finite",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Identity of a hole within its `Holes`, unchanged by edits, moves past other holes and
+/// insertions or removals elsewhere. Ids are not saved with a design; a loaded one numbers
+/// its holes afresh.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(transparent)]
+pub struct HoleId(u32);
+
+impl HoleId {
+    pub fn get(self) -> u32 {
+        self.0
+    }
+}
+
+/// The finger holes of a design, in the order the user made them: indices are what the UI
+/// shows and the fingering bits count, whatever the positions. Alongside it keeps a stable
+/// id per hole and the back-to-front order the impedance calculation walks, checked
+/// against the positions before use so edits through `IndexMut` or `iter_mut` never see a
+/// stale order. Saved as a plain array of holes.
+///
+/// Every hole that comes in through `push`, `insert`, `TryFrom` or deserialization passes
+/// `Hole::check`. A collection doesn't know its tube, so the checks against the bore
+/// (on the tube, narrower than the bore, clear of the other holes) are `Flute::add_hole`,
+/// `insert_hole` and `set_hole`'s. Mutable access (`IndexMut`, `get_mut`, `iter_mut`) and
+/// `from_unchecked` skip every check; run `Flute::validate` after edits made that way.
+#[derive(Debug, Clone, Default)]
+pub struct Holes {
+    items: Vec<Hole>,
+    ids: Vec<HoleId>,
+    next_id: u32,
+    back_to_front: Vec<usize>,
+}
+
+impl Holes {
+    pub fn new() -> Self {
+        Holes::default()
+    }
+
+    fn issue_id(&mut self) -> HoleId {
+        let id = HoleId(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    /// A whole new set of holes with fresh ids, taken as they are: for holes that are about
+    /// to be validated with their design, like a loaded document's. `TryFrom` checks them.
+    pub fn from_unchecked(items: Vec<Hole>) -> Self {
+        let count = items.len() as u32;
+        Holes {
+            items,
+            ids: (0..count).map(HoleId).collect(),
+            next_id: count,
+            back_to_front: Vec::new(),
+        }
+    }
+
+    /// Add `hole` after the others, returning its id, or an error if `Hole::check` fails
+    pub fn push(&mut self, hole: Hole) -> Result<HoleId, FlyteError> {
+        self.insert(self.items.len(), hole)
+    }
+
+    /// Put `hole` at `index`, shifting later holes up by one. Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, hole: Hole) -> Result<HoleId, FlyteError> {
+        hole.check()?;
+        let id = self.issue_id();
+        self.items.insert(index, hole);
+        self.ids.insert(index, id);
+        Ok(id)
+    }
+
+    /// Take out the hole at `index`, shifting later holes down by one. Panics if out of
+    /// range.
+    pub fn remove(&mut self, index: usize) -> Hole {
+        self.ids.remove(index);
+        self.items.remove(index)
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.ids.clear();
+        self.back_to_front.clear();
+    }
+
+    /// Exchange two holes' places in the user order; each keeps its id
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.items.swap(a, b);
+        self.ids.swap(a, b);
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.items.reserve(additional);
+        self.ids.reserve(additional);
+    }
+
+    /// Unchecked, like `IndexMut`; `Flute::set_hole` checks the replacement
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Hole> {
+        self.items.get_mut(index)
+    }
+
+    /// Unchecked, like `IndexMut`
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Hole> {
+        self.items.iter_mut()
+    }
+
+    /// Ids in user order, one per hole
+    pub fn ids(&self) -> &[HoleId] {
+        &self.ids
+    }
+
+    /// Where the hole with `id` currently sits in user order
+    pub fn index_of(&self, id: HoleId) -> Option<usize> {
+        self.ids.iter().position(|&i| i == id)
+    }
+
+    /// Hole indices ordered front to back (embouchure to foot), as a player numbers them
+    pub fn front_to_back(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.items.len()).collect();
+        order.sort_by(|&a, &b| {
+            self.items[a]
+                .position
+                .partial_cmp(&self.items[b].position)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        order
+    }
+
+    /// Whether `order` lists every hole back to front, ties in index order, exactly as a
+    /// fresh stable sort would
+    fn is_back_to_front(&self, order: &[usize]) -> bool {
+        order.len() == self.items.len()
+            && order.windows(2).all(|pair| {
+                let (a, b) = (&self.items[pair[0]], &self.items[pair[1]]);
+                a.position > b.position || (a.position == b.position && pair[0] < pair[1])
+            })
+    }
+
+    fn sort_back_to_front(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.items.len()).collect();
+        order.sort_by(|&a, &b| {
+            self.items[b]
+                .position
+                .partial_cmp(&self.items[a].position)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        order
+    }
+
+    /// The cached back-to-front order, or a freshly sorted one when holes have moved past
+    /// each other or been added or removed since the last refresh
+    pub(crate) fn back_to_front(&self) -> Cow<'_, [usize]> {
+        if self.is_back_to_front(&self.back_to_front) {
+            Cow::Borrowed(&self.back_to_front)
+        } else {
+            Cow::Owned(self.sort_back_to_front())
+        }
+    }
+
+    /// Re-sort the cached order if it no longer matches the positions
+    pub(crate) fn refresh_order(&mut self) -> &[usize] {
+        if !self.is_back_to_front(&self.back_to_front) {
+            self.back_to_front = self.sort_back_to_front();
+        }
+        &self.back_to_front
+    }
+}
+
+impl std::ops::Deref for Holes {
+    type Target = [Hole];
+
+    fn deref(&self) -> &[Hole] {
+        &self.items
+    }
+}
+
+impl std::ops::Index<usize> for Holes {
+    type Output = Hole;
+
+    fn index(&self, index: usize) -> &Hole {
+        &self.items[index]
+    }
+}
+
+/// Unchecked: the hole can be given any values. `Flute::set_hole` checks the replacement.
+impl std::ops::IndexMut<usize> for Holes {
+    fn index_mut(&mut self, index: usize) -> &mut Hole {
+        &mut self.items[index]
+    }
+}
+
+impl<'a> IntoIterator for &'a Holes {
+    type Item = &'a Hole;
+    type IntoIter = std::slice::Iter<'a, Hole>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Holes {
+    type Item = &'a mut Hole;
+    type IntoIter = std::slice::IterMut<'a, Hole>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter_mut()
+    }
+}
+
+/// A whole new set of holes with fresh ids, each passing `Hole::check`
+impl TryFrom<Vec<Hole>> for Holes {
+    type Error = FlyteError;
+
+    fn try_from(items: Vec<Hole>) -> Result<Self, FlyteError> {
+        for hole in &items {
+            hole.check()?;
+        }
+        Ok(Holes::from_unchecked(items))
+    }
+}
+
+impl From<Holes> for Vec<Hole> {
+    fn from(holes: Holes) -> Self {
+        holes.items
+    }
+}
+
+/// Same holes in the same order; ids are identity, not content, and don't count
+impl PartialEq for Holes {
+    fn eq(&self, other: &Self) -> bool {
+        self.items == other.items
+    }
+}
+
+impl Serialize for Holes {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.items.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Holes {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let items = Vec::<Hole>::deserialize(deserializer)?;
+        Holes::try_from(items).map_err(serde::de::Error::custom)
+    }
+}
+
+/// A Helmholtz resonator on the side of the bore: a neck through the wall into a closed
+/// cavity. It pulls the notes near its own resonance, sqrt(A / (V l)) c / 2 pi, and does
+/// little elsewhere. Acoustics only; the mesh does not draw it.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct SideResonator {
+    pub position: f64,    // Distance from embouchure (cm)
+    pub neck_radius: f64, // cm
+    pub neck_length: f64, // cm, wall included
+    pub volume: f64,      // cm^3
+}
+
+impl SideResonator {
+    pub fn new(position: f64, neck_radius: f64, neck_length: f64, volume: f64) -> Self {
+        SideResonator {
+            position,
+            neck_radius,
+            neck_length,
+            volume,
+        }
+    }
 }
 
 impl Flute {
@@ -33,197 +778,426 @@ impl Flute {
             length,
             bore_radius,
             wall_thickness,
-            holes: Vec::new(),
-            cork_position: 1.7,
-            embouchure_hole_radius: 0.5,
-            embouchure_chimney: 0.5,
-        }
-    }
-    /// Calculate input impedance at the embouchure for a given frequency
-    /// Assumes holes are already sorted back-to-front by find_resonance
-    fn impedance_at(&self, freq: f64, holes: &[Hole]) -> Complex64 {
-        let omega = 2.0 * PI * freq;
-
-        // Viscothermal losses
-        // Alpha approx 1.2e-5 * sqrt(f) / radius_cm (N.B. check units, standard is per meter)
-        // Let's use a standard approximation for wide tubes:
-        // k = w/c - j * alpha
-        let alpha = (1.2e-5 * freq.sqrt()) / self.bore_radius;
-        let real_k = omega / SPEED_OF_SOUND;
-        // Complex wavenumber k
-        let k = Complex64::new(real_k, -alpha);
-
-        // Z_c = rho * c / Area
-        let bore_area = PI * self.bore_radius.powi(2);
-        let z_c_val = (AIR_DENSITY * SPEED_OF_SOUND) / bore_area;
-        let z_char = Complex64::new(z_c_val, 0.0);
-
-        // 1. Start at the foot (end of tube) with Radiation Impedance
-        // Z_rad for unflanged pipe approx:
-        // ka = k * r
-        // Z_rad = Z_c * (0.25*(ka)^2 + j*0.61*ka)
-        let ka = real_k * self.bore_radius;
-        let z_rad_foot = z_char * Complex64::new(0.25 * ka.powi(2), 0.61 * ka);
-
-        // Load at the end is the radiation impedance
-        let mut z_in = z_rad_foot;
-
-        // Iterate backwards from end of tube to embouchure
-        // Note: self.length is typically "embouchure to foot" physical length.
-        let mut current_pos = self.length;
-
-        // Iterate over holes (which we assume are sorted back-to-front)
-        for hole in holes {
-            // A. Transmission line from current_pos back to hole.position
-            let dist = current_pos - hole.position;
-            if dist > 0.0 {
-                z_in = transmission_line_impedance(z_in, z_char, k, dist);
+            holes: Holes::new(),
+            cork_position: default_cork_position(),
+            embouchure_hole_radius: default_embouchure_hole_radius(),
+            embouchure_chimney: default_embouchure_chimney(),
+            embouchure_oval: default_embouchure_oval(),
+            instrument: InstrumentType::Transverse,
+            bore_ovality: 0.0,
+            resonators: Vec::new(),
+            solver: SolverConfig::default(),
+            calibration: Calibration::default(),
+        }
+    }
+
+    /// Reject designs the acoustic model cannot give meaningful numbers for: non-finite
+    /// values, a non-positive length, bore, wall or hole radius, a negative chimney, a bore
+    /// ovality outside 0-50 %, a cork past the foot, holes off the tube or as wide as the
+    /// bore, holes that overlap, resonators off the tube, wider than the bore or without a
+    /// neck or cavity, unusable `solver` settings and non-positive `calibration` constants
+    pub fn validate(&self) -> Result<(), FlyteError> {
+        let positive = [
+            ("length", self.length),
+            ("bore_radius", self.bore_radius),
+            ("wall_thickness", self.wall_thickness),
+            ("cork_position", self.cork_position),
+            ("embouchure_hole_radius", self.embouchure_hole_radius),
+            ("embouchure_oval", self.embouchure_oval),
+        ];
+        for (field, value) in positive {
+            if !value.is_finite() || value <= 0.0 {
+                return Err(FlyteError::invalid_geometry(
+                    field,
+                    value,
+                    "must be positive",
+                ));
             }
-            current_pos = hole.position;
-
-            // B. Shunt impedance of the hole
-            // For open hole, we also use a radiation impedance model if possible,
-            // but the basic inertance model with end correction is robust enough for now.
-            // We can add a resistance term to z_hole for radiation damping?
-            // Z_hole_rad = (rho * c / A_hole) * (0.25 (ka_hole)^2)  (Resistance part)
-
-            let hole_area = PI * hole.radius.powi(2);
-            let mut z_hole = hole_impedance(hole.radius, self.wall_thickness, real_k);
-
-            // Add radiation resistance to open hole
-            if hole.open {
-                let ka_hole = real_k * hole.radius;
-                let hole_rad_res =
-                    ((AIR_DENSITY * SPEED_OF_SOUND) / hole_area) * 0.25 * ka_hole.powi(2);
-                z_hole = z_hole + Complex64::new(hole_rad_res, 0.0);
+        }
+        if !self.embouchure_chimney.is_finite() || self.embouchure_chimney < 0.0 {
+            return Err(FlyteError::invalid_geometry(
+                "embouchure_chimney",
+                self.embouchure_chimney,
+                "must not be negative",
+            ));
+        }
+        if !(0.0..MAX_OVALITY).contains(&self.bore_ovality) {
+            return Err(FlyteError::invalid_geometry(
+                "bore_ovality",
+                self.bore_ovality,
+                "must be at least 0 and below 50 %",
+            ));
+        }
+        if self.instrument.has_cork() && self.cork_position >= self.length {
+            return Err(FlyteError::invalid_geometry(
+                "cork_position",
+                self.cork_position,
+                "must be shorter than the tube",
+            ));
+        }
+        if let InstrumentType::OrganPipe(FluePipe {
+            stopped,
+            tuning_slot: Some(slot),
+            ..
+        }) = &self.instrument
+        {
+            if *stopped {
+                return Err(FlyteError::invalid_input(
+                    "a stopped pipe has no open top to cut a tuning slot into",
+                ));
+            }
+            for (field, value) in [("length", slot.length), ("width", slot.width)] {
+                if !value.is_finite() || value <= 0.0 {
+                    return Err(FlyteError::invalid_geometry(
+                        &format!("tuning_slot.{}", field),
+                        value,
+                        "must be positive",
+                    ));
+                }
+            }
+            if slot.length >= 0.5 * self.length {
+                return Err(FlyteError::invalid_geometry(
+                    "tuning_slot.length",
+                    slot.length,
+                    "must be under half the pipe length",
+                ));
             }
+        }
+        for (i, hole) in self.holes.iter().enumerate() {
+            self.check_hole_range(i, hole)?;
+        }
+        for (i, resonator) in self.resonators.iter().enumerate() {
+            if !resonator.position.is_finite()
+                || resonator.position < 0.0
+                || resonator.position > self.length
+            {
+                return Err(FlyteError::invalid_geometry(
+                    &format!("resonators[{}].position", i),
+                    resonator.position,
+                    "must lie on the tube, between 0 and the length",
+                ));
+            }
+            let positive = [
+                ("neck_radius", resonator.neck_radius),
+                ("neck_length", resonator.neck_length),
+                ("volume", resonator.volume),
+            ];
+            for (field, value) in positive {
+                if !value.is_finite() || value <= 0.0 {
+                    return Err(FlyteError::invalid_geometry(
+                        &format!("resonators[{}].{}", i, field),
+                        value,
+                        "must be positive",
+                    ));
+                }
+            }
+            if resonator.neck_radius >= self.bore_radius {
+                return Err(FlyteError::invalid_geometry(
+                    &format!("resonators[{}].neck_radius", i),
+                    resonator.neck_radius,
+                    "must be smaller than the bore radius",
+                ));
+            }
+        }
+        for (i, hole) in self.holes.iter().enumerate() {
+            self.check_hole_clear(i, hole, self.holes.iter().skip(i + 1))?;
+        }
+        self.solver.validate()?;
+        self.calibration.validate()
+    }
+
+    /// `validate`'s checks of one hole, numbered `index`, against the tube alone
+    fn check_hole_range(&self, index: usize, hole: &Hole) -> Result<(), FlyteError> {
+        if !hole.position.is_finite() || hole.position < 0.0 || hole.position > self.length {
+            return Err(FlyteError::invalid_geometry(
+                &format!("holes[{}].position", index),
+                hole.position,
+                "must lie on the tube, between 0 and the length",
+            ));
+        }
+        if !hole.radius.is_finite() || hole.radius <= 0.0 {
+            return Err(FlyteError::invalid_geometry(
+                &format!("holes[{}].radius", index),
+                hole.radius,
+                "must be positive",
+            ));
+        }
+        if hole.radius >= self.bore_radius {
+            return Err(FlyteError::invalid_geometry(
+                &format!("holes[{}].radius", index),
+                hole.radius,
+                "must be smaller than the bore radius",
+            ));
+        }
+        Ok(())
+    }
 
-            if hole.open {
-                // Open hole: Parallel connection
-                if z_hole.norm() < 1e-10 {
-                    z_in = Complex64::new(0.0, 0.0);
-                } else {
-                    z_in = (z_in * z_hole) / (z_in + z_hole);
+    /// `validate`'s overlap check of hole `index` against `others`
+    fn check_hole_clear<'a>(
+        &self,
+        index: usize,
+        hole: &Hole,
+        mut others: impl Iterator<Item = &'a Hole>,
+    ) -> Result<(), FlyteError> {
+        match others.find(|other| geometry::hole_gap(self, hole, other) < 0.0) {
+            Some(other) => Err(FlyteError::FailedCheck {
+                check: "holes_overlap".to_string(),
+                message: format!(
+                    "Hole at {:.1} cm overlaps the hole at {:.1} cm",
+                    hole.position, other.position
+                ),
+                hole: Some(index),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Add `hole` after the others if it fits this tube: `Hole::check`, on the tube,
+    /// narrower than the bore and clear of every other hole, as `validate` would have it.
+    /// Returns its id; on error the design is unchanged.
+    pub fn add_hole(&mut self, hole: Hole) -> Result<HoleId, FlyteError> {
+        self.insert_hole(self.holes.len(), hole)
+    }
+
+    /// `add_hole` at `index`, shifting later holes up by one. Panics if `index > len`.
+    pub fn insert_hole(&mut self, index: usize, hole: Hole) -> Result<HoleId, FlyteError> {
+        hole.check()?;
+        self.check_hole_range(index, &hole)?;
+        self.check_hole_clear(index, &hole, self.holes.iter())?;
+        self.holes.insert(index, hole)
+    }
+
+    /// Replace hole `index` under `add_hole`'s checks, keeping its id. Panics if out of
+    /// range.
+    pub fn set_hole(&mut self, index: usize, hole: Hole) -> Result<(), FlyteError> {
+        hole.check()?;
+        self.check_hole_range(index, &hole)?;
+        let others = self
+            .holes
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != index)
+            .map(|(_, other)| other);
+        self.check_hole_clear(index, &hole, others)?;
+        self.holes[index] = hole;
+        Ok(())
+    }
+
+    /// Position and radius for hole `index` under `policy`. NaN clamps to the tube's top and
+    /// a small radius; only the range checks of `validate` apply here, not overlaps.
+    pub fn fit_hole(
+        &self,
+        index: usize,
+        position: f64,
+        radius: f64,
+        policy: RangePolicy,
+    ) -> Result<(f64, f64), FlyteError> {
+        match policy {
+            RangePolicy::Clamp => {
+                let position = if position.is_nan() { 0.0 } else { position };
+                let radius = if radius.is_nan() { 0.1 } else { radius };
+                let largest = (MAX_HOLE_FRACTION * self.bore_radius).max(MIN_HOLE_RADIUS);
+                Ok((
+                    position.clamp(0.0, self.length.max(0.0)),
+                    radius.clamp(MIN_HOLE_RADIUS, largest),
+                ))
+            }
+            RangePolicy::Reject => {
+                if !(0.0..=self.length).contains(&position) {
+                    return Err(FlyteError::invalid_geometry(
+                        &format!("holes[{}].position", index),
+                        position,
+                        "must lie on the tube, between 0 and the length",
+                    ));
                 }
-            } else {
-                // Closed hole
-                // Calculate compliance as before...
-                let hole_area = PI * hole.radius.powi(2);
-                let eff_depth = self.wall_thickness + 1.5 * hole.radius; // Kept basic for now
-                let volume = hole_area * eff_depth;
-                let stiffness = (AIR_DENSITY * SPEED_OF_SOUND.powi(2)) / volume;
-                let z_closed = Complex64::new(0.0, -stiffness / omega);
-                z_in = (z_in * z_closed) / (z_in + z_closed);
+                if !radius.is_finite() || radius <= 0.0 || radius >= self.bore_radius {
+                    return Err(FlyteError::invalid_geometry(
+                        &format!("holes[{}].radius", index),
+                        radius,
+                        "must be positive and smaller than the bore radius",
+                    ));
+                }
+                Ok((position, radius))
             }
         }
+    }
 
-        // C. Final segment from first hole (or end) to embouchure (pos 0)
-        let dist = current_pos - 0.0;
-        if dist > 0.0 {
-            z_in = transmission_line_impedance(z_in, z_char, k, dist);
-        }
-
-        // --- EMBOUCHURE JOINT CORRECTION ---
-        // At pos=0, we have the "Main Bore" input impedance z_in.
-        // But we also have:
-        // 1. The Cork Cavity (a closed tube of length 'cork_position' upstream) => Shunt Z_cork
-        // 2. The Embouchure Hole (an inertance + radiation R leaking to outside) => Shunt Z_emb
-
-        // Z_cork (Closed stub)
-        // Z_cork = -j * Z_c * cot(k * L_cork)
-        // transmission_line_impedance with Load=Infinity?
-        // Easier: Z_input_closed_stub = Z_c / (j tan(kL)) = -j Z_c cot(kL)
-        let z_cork_stub = -Complex64::i() * z_char / (k * self.cork_position).tan();
-
-        // Z_emb (Embouchure hole impedance)
-        // Similar to a tone hole: inertance + radiation
-        // L = rho * t_eff / A
-        // t_eff ~ chimney + correction. Benade suggests "equivalent length" ~5cm?
-        // Let's use physical calculation:
-        let emb_area = PI * self.embouchure_hole_radius.powi(2);
-        // End corrections for embouchure hole (approximate)
-        let emb_t_eff = self.embouchure_chimney + 1.5 * self.embouchure_hole_radius;
-
-        // Radiation R for embouchure
-        let ka_emb = real_k * self.embouchure_hole_radius;
-        let emb_rad_res = ((AIR_DENSITY * SPEED_OF_SOUND) / emb_area) * 0.25 * ka_emb.powi(2);
-
-        let emb_inertance = (AIR_DENSITY * emb_t_eff) / emb_area;
-        let z_emb = Complex64::new(emb_rad_res, omega * emb_inertance);
-
-        // Total Impedance seen by the flow drive:
-        // Parallel of (Bore, Cork, EmbouchureHole)
-        // 1/Z_total = 1/Z_bore + 1/Z_cork + 1/Z_emb
-        // But wait! We look for resonance of the PIPE.
-        // The condition for resonance is Im(Y_total) = 0?
-        // Flutes play at minima of Input Impedance *of the bore*?
-        // No, the jet drives the whole system. The resonance frequencies are the poles of the admittance (zeros of impedance) seen by the jet.
-        // So we want Z_total to be minimal (Admittance maximal)?
-        // Actually, Benade states: "The playing frequency is close to the frequency where the sum of admittances of the main bore, the cork cavity, and the embouchure hole is zero." (Im(Y_sum) = 0).
-
-        let y_bore = if z_in.norm() < 1e-10 {
-            Complex64::new(1e10, 0.0)
-        } else {
-            1.0 / z_in
-        };
-        let y_cork = if z_cork_stub.norm() < 1e-10 {
-            Complex64::new(1e10, 0.0)
+    /// Hole indices ordered front to back (embouchure to foot), as a player numbers them
+    pub fn hole_order(&self) -> Vec<usize> {
+        self.holes.front_to_back()
+    }
+
+    /// The cached back-to-front order, or a freshly sorted one when holes have moved past
+    /// each other or been added or removed since the last solve
+    pub(crate) fn hole_order_back_to_front(&self) -> Cow<'_, [usize]> {
+        self.holes.back_to_front()
+    }
+
+    /// Calculate pitch using TMM and Resonance search
+    /// Starts from the lattice-corrected length of the current fingering so the search finds the fundamental
+    pub fn calculate_pitch(&mut self, jet_velocity: f64) -> f64 {
+        self.calculate_pitch_diagnosed(jet_velocity).0
+    }
+
+    /// `calculate_pitch` and what went wrong numerically on the way, if anything
+    pub fn calculate_pitch_diagnosed(&mut self, jet_velocity: f64) -> (f64, Diagnostics) {
+        trace_span!(
+            DEBUG,
+            "calculate_pitch",
+            holes = self.holes.len(),
+            jet_velocity
+        );
+        let guess = self.pitch_guess(jet_velocity);
+        self.find_resonance_diagnosed(guess)
+    }
+
+    /// Where `calculate_pitch` starts its search: the fundamental from an effective length
+    /// that allows for every open and closed hole (see `Bore::fundamental_estimate`), or
+    /// the jet's preferred frequency when a jet velocity is given
+    fn pitch_guess(&self, jet_velocity: f64) -> f64 {
+        let fundamental_guess = self
+            .bore(&self.hole_order_back_to_front())
+            .fundamental_estimate();
+
+        // Aerodynamic Preferred Frequency (f_pref)
+        // f_pref ~ 0.2 * U / l
+        // U = jet_velocity (cm/s)
+        // l = cutting distance (approx diameter of embouchure hole or similar)
+        // Let's approximate cutting distance as 2 * embouchure_radius (~1.0 cm typically)
+
+        let guess_freq = if jet_velocity > 10.0 {
+            let cutting_dist = 2.0 * self.embouchure_hole_radius;
+            let f_pref = 0.2 * jet_velocity / cutting_dist;
+
+            // We want to find the resonance closest to this preferred frequency
+            // But we should be careful. If the preferred frequency is very far off, we might want to still bias towards the fundamental
+            // For now, let's just use f_pref as the guess if it's substantial.
+            f_pref
         } else {
-            1.0 / z_cork_stub
+            fundamental_guess
         };
-        let y_emb = if z_emb.norm() < 1e-10 {
-            Complex64::new(1e10, 0.0)
+
+        // If the calculated preferred frequency is wildly different (e.g. very low velocity),
+        // we might get 0 Hz. Fallback to fundamental if guess is too low.
+        if guess_freq < self.solver.search_bounds.0 {
+            fundamental_guess
         } else {
-            1.0 / z_emb
-        };
+            guess_freq
+        }
+    }
 
-        let y_total = y_bore + y_cork + y_emb;
+    /// `calculate_pitch`, but an error instead of a meaningless number when the secant search
+    /// stops somewhere other than an impedance minimum
+    pub fn checked_pitch(&mut self, jet_velocity: f64) -> Result<f64, FlyteError> {
+        self.validate()?;
+        let (freq, diagnostics) = self.calculate_pitch_diagnosed(jet_velocity);
+        trace_span!(DEBUG, "checked_pitch", freq);
+        // find_resonance has just refreshed the order
+        let bore = self.bore(&self.holes.back_to_front);
+        if diagnostics.is_clean() && is_minimum(&bore, freq) {
+            Ok(freq)
+        } else {
+            trace_event!(warn, "pitch is not an impedance minimum");
+            Err(FlyteError::SolverDidNotConverge { frequency: freq })
+        }
+    }
 
-        // We return Z_total = 1/Y_total.
-        // If Y_total is large (resonance), Z_total is small.
-        // find_resonance looks for Z.im crossing 0.
-        // If Im(Y) = 0, then Im(1/Y) = -Im(Y)/|Y|^2 = 0. So checking Z.im is equivalent to checking Y.im (mostly).
+    /// `calculate_pitch` with the note, whether it converged, which mode it is and how
+    /// sharp the resonance is
+    pub fn calculate_pitch_detailed(&mut self, jet_velocity: f64) -> ResonanceResult {
+        let (frequency, diagnostics) = self.calculate_pitch_diagnosed(jet_velocity);
+        ResonanceResult {
+            diagnostics,
+            ..self.describe_resonance(frequency)
+        }
+    }
 
-        if y_total.norm() < 1e-10 {
-            Complex64::new(1e10, 1e10)
+    /// `ResonanceResult` for a frequency found some other way, e.g. by `resonance_near`.
+    /// The mode is counted from the upward zero crossings of Im(Z) between the lower search
+    /// bound and `frequency`; Q comes from the slope of Im(Z) over Re(Z) at the minimum, where
+    /// Z ~ R + jX'(f - f0) gives a -3 dB bandwidth of 2R / X'.
+    pub fn describe_resonance(&self, frequency: f64) -> ResonanceResult {
+        let bore = self.bore(&self.hole_order_back_to_front());
+        let note = tuning::note_info(frequency);
+        let converged = is_minimum(&bore, frequency);
+        let (mode, q) = if converged {
+            let step = self.solver.scan_resolution;
+            let mut scan = vec![self.solver.search_bounds.0];
+            while let Some(&f) = scan.last().filter(|&&f| f * step < frequency) {
+                scan.push(f * step);
+            }
+            let below = bore
+                .sweep(&scan)
+                .windows(2)
+                .filter(|z| z[0].im < 0.0 && z[1].im >= 0.0)
+                .count();
+            let h = frequency * 1e-4;
+            let slope =
+                (bore.impedance(frequency + h).im - bore.impedance(frequency - h).im) / (2.0 * h);
+            let resistance = bore.impedance(frequency).re;
+            let q = if resistance > 0.0 {
+                frequency * slope / (2.0 * resistance)
+            } else {
+                f64::NAN
+            };
+            (below + 1, q)
         } else {
-            1.0 / y_total
+            (0, f64::NAN)
+        };
+        ResonanceResult {
+            frequency,
+            note: note.name,
+            cents: note.cents,
+            converged,
+            mode,
+            q,
+            diagnostics: Diagnostics {
+                non_finite_impedance: bore.non_finite(),
+            },
         }
     }
 
     /// Find the resonance frequency closest to the target guess
     pub fn find_resonance(&mut self, guess_freq: f64) -> f64 {
-        // Clone holes to avoid modifying the actual state
-        let mut sorted_holes = self.holes.clone();
+        self.find_resonance_diagnosed(guess_freq).0
+    }
 
-        // Sort holes in-place (back-to-front) to avoid allocations
-        sorted_holes.sort_by(|a, b| {
-            b.position
-                .partial_cmp(&a.position)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
+    /// `find_resonance` and what went wrong numerically on the way, if anything
+    pub fn find_resonance_diagnosed(&mut self, guess_freq: f64) -> (f64, Diagnostics) {
+        trace_span!(DEBUG, "find_resonance", guess_freq);
+        // Only re-sorts when holes have moved past each other since the last solve
+        self.holes.refresh_order();
+        let bore = self.bore(&self.holes.back_to_front);
 
         // Secant method loop
         let _f0 = guess_freq * 0.8;
         let _f1 = guess_freq * 1.2;
 
+        let SolverConfig {
+            max_iterations,
+            frequency_tolerance,
+            search_bounds: (lo, hi),
+            ..
+        } = self.solver;
+        let guess_freq = guess_freq.clamp(lo, hi);
         let mut f_curr = guess_freq;
         let mut f_prev = guess_freq - 10.0;
 
-        for _ in 0..20 {
-            let z_curr = self.impedance_at(f_curr, &sorted_holes);
-            let z_prev = self.impedance_at(f_prev, &sorted_holes);
+        for _iteration in 0..max_iterations {
+            let z_curr = bore.impedance(f_curr);
+            let z_prev = bore.impedance(f_prev);
 
             let y_curr = z_curr.im;
             let y_prev = z_prev.im;
 
             if (y_curr - y_prev).abs() < 1e-6 {
+                trace_event!(debug, f_curr, "secant step stalled");
                 break;
             }
 
             let f_next = f_curr - y_curr * (f_curr - f_prev) / (y_curr - y_prev);
 
-            if f_next < 20.0 || f_next > 5000.0 {
+            if !(lo..=hi).contains(&f_next) {
                 f_prev = f_curr;
                 f_curr = (f_curr + guess_freq) / 2.0;
             } else {
@@ -231,48 +1205,274 @@ impl Flute {
                 f_curr = f_next;
             }
 
-            if (f_curr - f_prev).abs() < 0.01 {
+            if (f_curr - f_prev).abs() < frequency_tolerance {
+                trace_event!(debug, iterations = _iteration + 1, f_curr, "converged");
                 break;
             }
         }
 
-        f_curr
+        let diagnostics = Diagnostics {
+            non_finite_impedance: bore.non_finite(),
+        };
+        if !diagnostics.is_clean() {
+            trace_event!(
+                warn,
+                f_curr,
+                non_finite = diagnostics.non_finite_impedance,
+                "impedance was not finite"
+            );
+        }
+        (f_curr, diagnostics)
+    }
+
+    /// Input impedance at the embouchure over `n` evenly spaced frequencies from `f0` to `f1`,
+    /// interleaved as (freq, |Z|, phase in radians) triples
+    pub fn impedance_spectrum(&self, f0: f64, f1: f64, n: usize) -> Vec<f64> {
+        self.impedance_spectrum_with(f0, f1, n, Precision::F64)
     }
-}
 
-// Transmission Line Equation
-// Z_in = Zc * (Z_L + j Zc tan(kL)) / (Zc + j Z_L tan(kL))
-fn transmission_line_impedance(
-    z_load: Complex64,
-    z_char: Complex64,
-    k: Complex64,
-    len: f64,
-) -> Complex64 {
-    let kl = k * len;
-    let tan_kl = kl.tan();
-    let j_tan = Complex64::new(0.0, 1.0) * tan_kl;
+    /// `impedance_spectrum` computed at the given `precision`
+    pub fn impedance_spectrum_with(
+        &self,
+        f0: f64,
+        f1: f64,
+        n: usize,
+        precision: Precision,
+    ) -> Vec<f64> {
+        let bore = self.bore(&self.hole_order_back_to_front());
+        let step = if n > 1 {
+            (f1 - f0) / (n - 1) as f64
+        } else {
+            0.0
+        };
+        let freqs: Vec<f64> = (0..n).map(|i| f0 + step * i as f64).collect();
+        let impedances = match precision {
+            Precision::F64 => bore.sweep(&freqs),
+            Precision::F32 => freqs.iter().map(|&f| bore.impedance_f32(f)).collect(),
+        };
+        let mut out = Vec::with_capacity(3 * n);
+        for (freq, z) in freqs.iter().zip(impedances) {
+            out.extend_from_slice(&[*freq, math::norm(z), math::arg(z)]);
+        }
+        out
+    }
 
-    let numer = z_load + z_char * j_tan;
-    let denom = z_char + z_load * j_tan;
+    /// `impedance_spectrum` for each of `fingerings` (bit i = `holes[i]` open) over the same
+    /// grid, one triple list per fingering. The per-frequency terms shared by every
+    /// fingering are computed once, so this is much cheaper than a spectrum per fingering.
+    pub fn fingering_spectra(
+        &self,
+        fingerings: &[u32],
+        f0: f64,
+        f1: f64,
+        n: usize,
+    ) -> Vec<Vec<f64>> {
+        trace_span!(DEBUG, "fingering_spectra", fingerings = fingerings.len(), n);
+        let step = if n > 1 {
+            (f1 - f0) / (n - 1) as f64
+        } else {
+            0.0
+        };
+        let freqs: Vec<f64> = (0..n).map(|i| f0 + step * i as f64).collect();
+        self.bore(&self.hole_order_back_to_front())
+            .fingering_sweep(&freqs, fingerings)
+            .into_iter()
+            .map(|impedances| {
+                let mut out = Vec::with_capacity(3 * n);
+                for (freq, z) in freqs.iter().zip(impedances) {
+                    out.extend_from_slice(&[*freq, math::norm(z), math::arg(z)]);
+                }
+                out
+            })
+            .collect()
+    }
 
-    z_char * (numer / denom)
-}
+    /// (freq, |Z|, phase) triples from `f0` to `f1` Hz, sampled densely only where the
+    /// impedance changes quickly. A coarse even grid is bisected wherever neighbouring points
+    /// differ by more than `max_phase_step` or `max_level_step`, so narrow high-Q
+    /// resonances are resolved to `resolution` without paying for a fine grid everywhere.
+    pub fn adaptive_spectrum(&self, f0: f64, f1: f64, opts: &AdaptiveSampling) -> Vec<f64> {
+        trace_span!(DEBUG, "adaptive_spectrum", f0, f1);
+        if !f0.is_finite() || !f1.is_finite() {
+            return Vec::new();
+        }
+        let bore = self.bore(&self.hole_order_back_to_front());
+        let n = opts.initial_points.max(2);
+        let max_points = opts.max_points.clamp(n, MAX_ADAPTIVE_POINTS.max(n));
+        let mut freqs: Vec<f64> = (0..n)
+            .map(|i| f0 + (f1 - f0) * i as f64 / (n - 1) as f64)
+            .collect();
+        let mut impedances = bore.sweep(&freqs);
+        let db_per_neper = 20.0 / std::f64::consts::LN_10;
+        let split = |(fa, za): (f64, Complex64), (fb, zb): (f64, Complex64)| {
+            let level = db_per_neper * math::ln(math::norm(zb) / math::norm(za));
+            let phase = math::arg(zb) - math::arg(za);
+            (fb - fa).abs() > opts.resolution
+                && (level.abs() > opts.max_level_step || phase.abs() > opts.max_phase_step)
+        };
+        // A pass at a time, so each pass's new points go through one sweep
+        loop {
+            let budget = max_points - freqs.len();
+            let mids: Vec<(usize, f64)> = (0..freqs.len() - 1)
+                .filter(|&i| split((freqs[i], impedances[i]), (freqs[i + 1], impedances[i + 1])))
+                .map(|i| (i, 0.5 * (freqs[i] + freqs[i + 1])))
+                .take(budget)
+                .collect();
+            if mids.is_empty() {
+                break;
+            }
+            let mid_freqs: Vec<f64> = mids.iter().map(|&(_, f)| f).collect();
+            let mut mids = mids
+                .iter()
+                .zip(bore.sweep(&mid_freqs))
+                .map(|(&(i, f), z)| (i, f, z))
+                .peekable();
+            let mut merged = Vec::with_capacity(freqs.len() + mid_freqs.len());
+            for (i, point) in freqs.iter().copied().zip(impedances).enumerate() {
+                merged.push(point);
+                if let Some((_, f, z)) = mids.next_if(|&(at, _, _)| at == i) {
+                    merged.push((f, z));
+                }
+            }
+            (freqs, impedances) = merged.into_iter().unzip();
+        }
+        let mut out = Vec::with_capacity(3 * freqs.len());
+        for (freq, z) in freqs.into_iter().zip(impedances) {
+            out.extend_from_slice(&[freq, math::norm(z), math::arg(z)]);
+        }
+        out
+    }
+
+    /// Impedance minimum nearest `guess_freq`, found by bracketing rather than the secant
+    /// search: Im(Z) is scanned over half an octave either side for an upward zero crossing,
+    /// which is then bisected. Slower than `find_resonance` but cannot wander onto a
+    /// neighbouring mode or an impedance maximum. Falls back to `find_resonance` when the
+    /// window holds no minimum.
+    pub fn resonance_near(&self, guess_freq: f64) -> f64 {
+        trace_span!(DEBUG, "resonance_near", guess_freq);
+        let step = self.solver.scan_resolution.powi(2);
+        self.minimum_in_window(guess_freq, 2f64.sqrt(), step, Precision::F64)
+            .unwrap_or_else(|| {
+                trace_event!(
+                    debug,
+                    "no minimum in the window, falling back to the secant search"
+                );
+                self.clone().find_resonance(guess_freq)
+            })
+    }
+
+    /// Resonance `mode` of the current fingering (1 = lowest, as `ResonanceResult::mode`
+    /// counts): the default-jet pitch for the first, the minimum nearest `mode` times it
+    /// for the others
+    pub fn mode_frequency(&mut self, mode: usize) -> f64 {
+        let fundamental = self.calculate_pitch(f64::NAN);
+        if mode <= 1 {
+            fundamental
+        } else {
+            self.resonance_near(mode as f64 * fundamental)
+        }
+    }
 
-fn hole_impedance(radius: f64, wall_thickness: f64, k: f64) -> Complex64 {
-    // Z_hole = j * rho * omega * t_eff / A_hole
-    // t_eff = wall_thickness + 1.5 * radius (roughly)
+    /// Continuation step for interactive edits: the impedance minimum nearest `previous`, a
+    /// solution for a slightly different geometry, searched within three semitones so the
+    /// result stays on the same mode. `None` when the mode has moved out of reach (or
+    /// vanished), in which case the caller should solve from scratch.
+    pub fn track_resonance(&self, previous: f64) -> Option<f64> {
+        self.track_resonance_with(previous, Precision::F64)
+    }
 
-    let area = PI * radius.powi(2);
-    let t_eff = wall_thickness + 1.5 * radius; // Benade's end correction for hole
+    /// `track_resonance` computed at the given `precision`
+    pub fn track_resonance_with(&self, previous: f64, precision: Precision) -> Option<f64> {
+        if !previous.is_finite() || previous <= 0.0 {
+            return None;
+        }
+        self.minimum_in_window(
+            previous,
+            TRACKING_WINDOW,
+            self.solver.scan_resolution,
+            precision,
+        )
+    }
 
-    // Inertance L = (rho * t_eff) / Area
-    // Z = j * omega * L
+    /// Upward zero crossing of Im(Z) nearest `guess_freq` between `guess / ratio` and
+    /// `guess * ratio`, scanned in multiplicative `step`s and bisected
+    fn minimum_in_window(
+        &self,
+        guess_freq: f64,
+        ratio: f64,
+        step: f64,
+        precision: Precision,
+    ) -> Option<f64> {
+        let bore = self.bore(&self.hole_order_back_to_front());
+        let im = |f: f64| match precision {
+            Precision::F64 => bore.impedance(f).im,
+            Precision::F32 => bore.impedance_f32(f).im,
+        };
+        let im_all = |freqs: &[f64]| -> Vec<f64> {
+            match precision {
+                Precision::F64 => bore.sweep(freqs).into_iter().map(|z| z.im).collect(),
+                Precision::F32 => freqs.iter().map(|&f| im(f)).collect(),
+            }
+        };
 
-    // Note: omega is in k = omega/c => omega = k*c
-    let omega = k * SPEED_OF_SOUND;
+        // Scan points in one sweep, then bisect the crossings one at a time
+        let mut scan = vec![guess_freq / ratio];
+        while let Some(&lo) = scan.last().filter(|&&lo| lo < guess_freq * ratio) {
+            scan.push(lo * step);
+        }
+        let mut scanned = im_all(&scan);
+
+        // A narrow resonance can fit a minimum and the maximum next to it between two scan
+        // points, leaving Im(Z) dipping towards zero without crossing. Sample closer around
+        // every such dip, last first so earlier indices stay put.
+        let dips: Vec<usize> = (1..scanned.len().saturating_sub(1))
+            .filter(|&i| {
+                let (a, b, c) = (scanned[i - 1], scanned[i], scanned[i + 1]);
+                (a < 0.0) == (b < 0.0)
+                    && (b < 0.0) == (c < 0.0)
+                    && b.abs() < a.abs()
+                    && b.abs() < c.abs()
+            })
+            .collect();
+        for &i in dips.iter().rev() {
+            let (lo, hi) = (scan[i - 1], scan[i + 1]);
+            let fine: Vec<f64> = (1..DIP_SAMPLES)
+                .map(|j| lo + (hi - lo) * j as f64 / DIP_SAMPLES as f64)
+                .collect();
+            let values = im_all(&fine);
+            scan.splice(i..=i, fine);
+            scanned.splice(i..=i, values);
+        }
+
+        let mut best: Option<(f64, f64)> = None;
+        for (lo, y) in scan.windows(2).zip(scanned.windows(2)) {
+            let (lo, hi, y_lo, y_hi) = (lo[0], lo[1], y[0], y[1]);
+            if y_lo < 0.0 && y_hi >= 0.0 {
+                let (mut a, mut b) = (lo, hi);
+                for _ in 0..40 {
+                    let mid = 0.5 * (a + b);
+                    if im(mid) < 0.0 {
+                        a = mid;
+                    } else {
+                        b = mid;
+                    }
+                }
+                let f = 0.5 * (a + b);
+                let distance = math::ln(f / guess_freq).abs();
+                if best.is_none_or(|(_, d)| distance < d) {
+                    best = Some((f, distance));
+                }
+            }
+        }
+        best.map(|(f, _)| f)
+    }
+}
 
-    let inertance = (AIR_DENSITY * t_eff) / area;
-    Complex64::new(0.0, omega * inertance)
+/// A minimum of |Z| has Im(Z) rising through zero at `freq`
+fn is_minimum(bore: &Bore, freq: f64) -> bool {
+    freq.is_finite()
+        && bore.impedance(freq * 0.995).im < 0.0
+        && bore.impedance(freq * 1.005).im >= 0.0
 }
 
 #[cfg(test)]
@@ -290,21 +1490,9 @@ mod tests {
         // If sorted back-to-front, it would be 30, 20, 10.
         // We want to ensure it remains 10, 30, 20 after calculation.
 
-        flute.holes.push(Hole {
-            position: 10.0,
-            radius: 0.3,
-            open: true,
-        });
-        flute.holes.push(Hole {
-            position: 30.0,
-            radius: 0.3,
-            open: true,
-        });
-        flute.holes.push(Hole {
-            position: 20.0,
-            radius: 0.3,
-            open: true,
-        });
+        flute.holes.push(Hole::new(10.0, 0.3, true)).unwrap();
+        flute.holes.push(Hole::new(30.0, 0.3, true)).unwrap();
+        flute.holes.push(Hole::new(20.0, 0.3, true)).unwrap();
 
         // Initial order check
         assert_eq!(flute.holes[0].position, 10.0);
@@ -320,6 +1508,416 @@ mod tests {
         assert_eq!(flute.holes[2].position, 20.0, "Hole 2 moved!");
     }
 
+    #[test]
+    fn test_cached_hole_order_follows_direct_edits() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for pos in [40.0, 30.0, 35.0] {
+            flute.holes.push(Hole::new(pos, 0.35, true)).unwrap();
+        }
+        flute.calculate_pitch(f64::NAN);
+        assert_eq!(flute.holes.back_to_front, vec![0, 2, 1]);
+
+        // Edits straight to `holes` that reorder them or change the count are picked up
+        let fresh = |flute: &Flute| {
+            let mut copy = Flute::new(flute.length, flute.bore_radius, flute.wall_thickness);
+            copy.holes = flute.holes.clone();
+            copy.calculate_pitch(f64::NAN)
+        };
+        flute.holes[1].position = 45.0;
+        assert_eq!(flute.calculate_pitch(f64::NAN), fresh(&flute));
+        assert_eq!(flute.holes.back_to_front, vec![1, 0, 2]);
+        flute.holes.swap(0, 2);
+        flute.holes.push(Hole::new(25.0, 0.3, false)).unwrap();
+        assert_eq!(flute.calculate_pitch(f64::NAN), fresh(&flute));
+        assert_eq!(flute.holes.back_to_front, vec![1, 2, 0, 3]);
+    }
+
+    #[test]
+    fn test_holes_keep_ids_and_reject_bad_values() {
+        let mut holes = Holes::new();
+        let a = holes.push(Hole::new(40.0, 0.35, true)).unwrap();
+        let b = holes.push(Hole::new(30.0, 0.35, true)).unwrap();
+        let c = holes.insert(1, Hole::new(35.0, 0.3, true)).unwrap();
+        assert_eq!(holes.ids(), [a, c, b]);
+        assert_eq!(holes.front_to_back(), vec![2, 1, 0]);
+
+        // Ids follow their holes through removals and swaps and are never reused
+        holes.remove(0);
+        holes.swap(0, 1);
+        assert_eq!(holes.index_of(b), Some(0));
+        assert_eq!(holes.index_of(a), None);
+        let d = holes.push(Hole::new(45.0, 0.3, true)).unwrap();
+        assert!(![a, b, c].contains(&d));
+
+        // Nothing changes when a hole is turned away
+        for bad in [
+            Hole::new(f64::NAN, 0.3, true),
+            Hole::new(-1.0, 0.3, true),
+            Hole::new(20.0, 0.0, true),
+        ] {
+            assert!(holes.push(bad).is_err());
+        }
+        assert_eq!(holes.len(), 3);
+
+        // Saved as a plain array; a loaded set numbers its holes afresh
+        let json = serde_json::to_string(&holes).unwrap();
+        assert!(json.starts_with("[{"));
+        let loaded: Holes = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, holes);
+        assert_eq!(loaded.ids().len(), 3);
+        assert!(serde_json::from_str::<Holes>(r#"[{"position": 20, "radius": 0}]"#).is_err());
+        assert!(Holes::try_from(vec![Hole::new(-1.0, 0.3, true)]).is_err());
+        holes.clear();
+        assert!(holes.back_to_front().is_empty());
+
+        // A design also turns away holes that don't fit its tube
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.add_hole(Hole::new(40.0, 0.35, true)).unwrap();
+        flute.add_hole(Hole::new(30.0, 0.3, true)).unwrap();
+        for bad in [
+            Hole::new(61.0, 0.3, true),
+            Hole::new(30.0, 0.95, true),
+            Hole::new(40.5, 0.3, true),
+        ] {
+            assert!(flute.add_hole(bad.clone()).is_err());
+            assert!(flute.insert_hole(0, bad.clone()).is_err());
+            assert!(flute.set_hole(1, bad).is_err());
+        }
+        assert_eq!(flute.holes.len(), 2);
+        let id = flute.holes.ids()[0];
+        flute.set_hole(0, Hole::new(40.2, 0.35, false)).unwrap();
+        assert_eq!(flute.holes.ids()[0], id);
+        flute.insert_hole(0, Hole::new(25.0, 0.3, true)).unwrap();
+        assert!(flute.validate().is_ok());
+    }
+
+    #[test]
+    fn test_design_json_round_trip() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, false)).unwrap();
+        flute.holes[0].azimuth = 180.0;
+        flute.cork_position = 2.1;
+        flute.instrument = InstrumentType::Notch(NotchCut::default());
+        let json = serde_json::to_string(&flute).unwrap();
+        let loaded: Flute = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), json);
+
+        // Older files without the embouchure fields get the usual defaults, not zero
+        let minimal: Flute = serde_json::from_str(
+            r#"{"length": 60, "bore_radius": 0.95, "wall_thickness": 0.4, "holes": []}"#,
+        )
+        .unwrap();
+        assert_eq!(minimal.cork_position, 1.7);
+        assert_eq!(minimal.embouchure_chimney, 0.5);
+        assert_eq!(minimal.instrument, InstrumentType::Transverse);
+
+        // Hole objects from JS only need a position and radius
+        let hole: Hole = serde_json::from_str(r#"{"position": 30, "radius": 0.3}"#).unwrap();
+        assert!(hole.open);
+        assert_eq!(hole.azimuth, 0.0);
+    }
+
+    #[test]
+    fn test_impedance_spectrum_dips_at_resonance() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        let spectrum = flute.impedance_spectrum(200.0, 400.0, 201);
+        assert_eq!(spectrum.len(), 603);
+        assert_eq!((spectrum[0], spectrum[600]), (200.0, 400.0));
+        let pitch = flute.find_resonance(280.0);
+        let min = spectrum
+            .chunks(3)
+            .min_by(|a, b| a[1].total_cmp(&b[1]))
+            .unwrap();
+        assert!((min[0] - pitch).abs() <= 1.0, "{} vs {}", min[0], pitch);
+    }
+
+    #[test]
+    fn test_fingering_spectra_match_one_spectrum_per_fingering() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for (pos, open) in [(40.0, true), (30.0, false), (35.0, true)] {
+            flute.holes.push(Hole::new(pos, 0.35, open)).unwrap();
+        }
+        let fingerings = [0b000, 0b001, 0b101, 0b111, 0b010];
+        let spectra = flute.fingering_spectra(&fingerings, 200.0, 2500.0, 40);
+        assert_eq!(spectra.len(), fingerings.len());
+        for (&fingering, spectrum) in fingerings.iter().zip(&spectra) {
+            let mut fingered = flute.clone();
+            for (i, hole) in fingered.holes.iter_mut().enumerate() {
+                hole.open = fingering & (1 << i) != 0;
+            }
+            let expected = fingered.impedance_spectrum(200.0, 2500.0, 40);
+            assert!(spectrum
+                .iter()
+                .zip(&expected)
+                .all(|(a, b)| a.to_bits() == b.to_bits()));
+            assert_eq!(spectrum.len(), expected.len());
+        }
+    }
+
+    #[test]
+    fn test_sampling_refines_around_resonances() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, false)).unwrap();
+        let pitch = flute.resonance_near(280.0);
+
+        // A scan this coarse steps straight over the resonance; the dip refinement finds it
+        for guess in [0.9, 1.0, 1.1] {
+            let found = flute.minimum_in_window(pitch * guess, 1.8, 1.3, Precision::F64);
+            assert!(
+                found.is_some_and(|f| (f / pitch - 1.0).abs() < 1e-6),
+                "{:?}",
+                found
+            );
+        }
+
+        let opts = AdaptiveSampling::default();
+        let spectrum = flute.adaptive_spectrum(100.0, 3000.0, &opts);
+        let points: Vec<&[f64]> = spectrum.chunks(3).collect();
+        assert!(points.len() > opts.initial_points && points.len() < opts.max_points);
+        assert!(points.windows(2).all(|w| w[0][0] < w[1][0]));
+        for w in points.windows(2) {
+            assert!(
+                w[1][0] - w[0][0] <= opts.resolution || (w[1][2] - w[0][2]).abs() <= 0.2,
+                "{:?}",
+                w
+            );
+        }
+        // Dense where it matters: the sampled minimum sits on the resonance
+        let lowest = points
+            .iter()
+            .filter(|p| p[0] < 1.5 * pitch)
+            .min_by(|a, b| a[1].total_cmp(&b[1]))
+            .unwrap();
+        assert!(
+            (lowest[0] - pitch).abs() < 0.5,
+            "{} vs {}",
+            lowest[0],
+            pitch
+        );
+    }
+
+    #[test]
+    fn test_track_resonance_follows_a_drag_smoothly() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(45.0, 0.4, true)).unwrap();
+        let mut pitch = flute.calculate_pitch(0.0);
+        let start = pitch;
+        // Drag the hole 10 cm towards the embouchure in 2 mm frames
+        for frame in 1..=50 {
+            flute.holes[0].position = 45.0 - 0.2 * frame as f64;
+            let next = flute.track_resonance(pitch).expect("mode lost");
+            assert!(next >= pitch, "pitch fell at frame {}", frame);
+            assert!(
+                next / pitch < 1.02,
+                "jump at frame {}: {} -> {}",
+                frame,
+                pitch,
+                next
+            );
+            pitch = next;
+        }
+        assert!(pitch > start * 1.1);
+        assert!(flute.track_resonance(f64::NAN).is_none());
+    }
+
+    #[test]
+    fn test_f32_preview_stays_close_to_f64() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for (pos, open) in [(40.0, true), (35.0, false), (30.0, true)] {
+            flute.holes.push(Hole::new(pos, 0.35, open)).unwrap();
+        }
+        let pitch = flute.calculate_pitch(0.0);
+        let full = flute.track_resonance(pitch * 1.01).unwrap();
+        let fast = flute
+            .track_resonance_with(pitch * 1.01, Precision::F32)
+            .unwrap();
+        assert!(
+            (1200.0 * (fast / full).log2()).abs() < 0.5,
+            "{} vs {}",
+            fast,
+            full
+        );
+
+        let full = flute.impedance_spectrum(100.0, 3000.0, 60);
+        let fast = flute.impedance_spectrum_with(100.0, 3000.0, 60, Precision::F32);
+        for (a, b) in full.chunks(3).zip(fast.chunks(3)) {
+            assert_eq!(a[0], b[0]);
+            assert!((a[1] - b[1]).abs() < 1e-3 * a[1], "{:?} vs {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_checked_pitch_reports_bad_input() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        assert!(flute.checked_pitch(0.0).is_ok());
+        // `push` turns the hole away; a direct edit or a loaded design gets it past
+        assert!(flute.holes.push(Hole::new(40.0, f64::NAN, true)).is_err());
+        flute.holes.push(Hole::new(40.0, 0.35, true)).unwrap();
+        flute.holes[0].radius = f64::NAN;
+        assert!(matches!(
+            flute.checked_pitch(0.0),
+            Err(FlyteError::InvalidGeometry { field, .. }) if field == "holes[0].radius"
+        ));
+    }
+
+    #[test]
+    fn test_solver_config_widens_the_search() {
+        // A whistle-sized stub sounding well above the default 5 kHz ceiling
+        let mut tiny = Flute::new(2.5, 0.3, 0.2);
+        tiny.cork_position = 0.5;
+        assert!(tiny.checked_pitch(f64::NAN).is_err());
+        tiny.solver.search_bounds = (20.0, 12_000.0);
+        let high = tiny.checked_pitch(f64::NAN).unwrap();
+        assert!(high > 5000.0, "{}", high);
+
+        // Tighter tolerance, same pitch; one step from a poor guess doesn't get there
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        let loose = flute.calculate_pitch(f64::NAN);
+        flute.solver.frequency_tolerance = 1e-6;
+        assert!((flute.calculate_pitch(f64::NAN) - loose).abs() < 0.01);
+        assert!((flute.find_resonance(0.95 * loose) - loose).abs() < 0.01);
+        flute.solver.max_iterations = 1;
+        assert!((flute.find_resonance(0.95 * loose) - loose).abs() > 1.0);
+
+        let json = serde_json::to_value(Flute::new(60.0, 0.95, 0.4)).unwrap();
+        assert!(json.get("solver").is_none());
+        for bad in [
+            SolverConfig {
+                max_iterations: 0,
+                ..SolverConfig::default()
+            },
+            SolverConfig {
+                search_bounds: (5000.0, 20.0),
+                ..SolverConfig::default()
+            },
+            SolverConfig {
+                scan_resolution: 1.0,
+                ..SolverConfig::default()
+            },
+        ] {
+            flute.solver = bad;
+            assert!(matches!(
+                flute.validate(),
+                Err(FlyteError::InvalidInput { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_nonsense_geometry() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, true)).unwrap();
+        flute.holes.push(Hole::new(30.0, 0.3, true)).unwrap();
+        assert!(flute.validate().is_ok());
+        let field = |flute: &Flute| match flute.validate() {
+            Err(FlyteError::InvalidGeometry { field, .. }) => field,
+            other => panic!("{:?}", other),
+        };
+
+        let mut bad = flute.clone();
+        bad.holes[1].position = 75.0;
+        assert_eq!(field(&bad), "holes[1].position");
+        bad.holes[1].position = -5.0;
+        assert_eq!(field(&bad), "holes[1].position");
+
+        let mut bad = flute.clone();
+        bad.holes[0].radius = 1.0;
+        assert_eq!(field(&bad), "holes[0].radius");
+
+        let mut bad = flute.clone();
+        bad.cork_position = 60.0;
+        assert_eq!(field(&bad), "cork_position");
+
+        let mut bad = flute.clone();
+        bad.holes[1].position = 40.5;
+        assert!(matches!(
+            bad.validate(),
+            Err(FlyteError::FailedCheck { check, hole: Some(0), .. }) if check == "holes_overlap"
+        ));
+        // Side by side around the tube rather than along it is fine
+        bad.holes[1].azimuth = 180.0;
+        assert!(bad.validate().is_ok());
+    }
+
+    #[test]
+    fn test_range_policy_clamps_or_rejects() {
+        let flute = Flute::new(60.0, 0.95, 0.4);
+        let clamp = |position, radius| {
+            flute
+                .fit_hole(0, position, radius, RangePolicy::Clamp)
+                .unwrap()
+        };
+        assert_eq!(clamp(40.0, 0.35), (40.0, 0.35));
+        assert_eq!(clamp(75.0, 0.35), (60.0, 0.35));
+        assert_eq!(clamp(-3.0, 2.0), (0.0, 0.95 * 0.95));
+        assert_eq!(clamp(f64::NAN, 0.0), (0.0, MIN_HOLE_RADIUS));
+
+        assert_eq!(
+            flute.fit_hole(2, 40.0, 0.35, RangePolicy::Reject).unwrap(),
+            (40.0, 0.35)
+        );
+        for (position, radius, bad) in [
+            (75.0, 0.35, "holes[2].position"),
+            (f64::NAN, 0.35, "holes[2].position"),
+            (40.0, 0.95, "holes[2].radius"),
+        ] {
+            assert!(matches!(
+                flute.fit_hole(2, position, radius, RangePolicy::Reject),
+                Err(FlyteError::InvalidGeometry { field, .. }) if field == bad
+            ));
+        }
+    }
+
+    #[test]
+    fn test_non_finite_impedance_is_guarded_and_reported() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, true)).unwrap();
+        let (_, diagnostics) = flute.calculate_pitch_diagnosed(f64::NAN);
+        assert!(diagnostics.is_clean());
+
+        // A zero-size hole has infinite inertance, which turns the chain into NaN
+        flute.holes.push(Hole::new(30.0, 0.3, true)).unwrap();
+        flute.holes[1].radius = 0.0;
+        let (pitch, diagnostics) = flute.calculate_pitch_diagnosed(f64::NAN);
+        assert!(diagnostics.non_finite_impedance > 0);
+        assert!(!pitch.is_nan());
+        let spectrum = flute.impedance_spectrum(100.0, 2000.0, 50);
+        assert!(spectrum.iter().all(|v| v.is_finite()));
+        assert!(flute.checked_pitch(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_detailed_pitch_reports_note_mode_and_q() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, false)).unwrap();
+        let result = flute.calculate_pitch_detailed(f64::NAN);
+        assert_eq!(result.frequency, flute.calculate_pitch(f64::NAN));
+        assert!(result.converged && result.diagnostics.is_clean());
+        assert_eq!(result.mode, 1);
+        assert_eq!(result.note, tuning::note_info(result.frequency).name);
+        assert!(result.q > 10.0 && result.q < 1000.0, "{:?}", result);
+
+        for mode in [2, 3] {
+            let f = flute.resonance_near(mode as f64 * result.frequency);
+            assert_eq!(flute.describe_resonance(f).mode, mode);
+        }
+        let between = flute.describe_resonance(1.3 * result.frequency);
+        assert!(!between.converged);
+        assert_eq!(between.mode, 0);
+        assert!(between.q.is_nan());
+    }
+
+    #[test]
+    fn test_notch_flute_is_open_open() {
+        // 40 cm quena body: no cork, blowing end radiates like an open end
+        let mut quena = Flute::new(40.0, 0.9, 0.3);
+        quena.instrument = InstrumentType::Notch(NotchCut::default());
+        // The secant search is only reliable when seeded near the mode
+        let freq = quena.find_resonance(420.0);
+        let ideal = SPEED_OF_SOUND / (2.0 * (40.0 + 2.0 * 0.61 * 0.9));
+        assert!((freq - ideal).abs() / ideal < 0.05, "{} vs {}", freq, ideal);
+    }
+
     #[test]
     fn test_pitch_predictions() {
         let bore_radius = 0.95; // 19mm / 2
@@ -338,4 +1936,21 @@ mod tests {
         let freq_a4 = flute_a4.find_resonance(440.0);
         println!("A4 (39.2cm): {:.2} Hz (Expected ~440)", freq_a4);
     }
+
+    #[cfg(feature = "deterministic")]
+    #[test]
+    fn test_deterministic_pitch_is_bit_exact() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for (pos, open) in [(40.0, true), (36.0, false), (32.0, true)] {
+            flute.holes.push(Hole::new(pos, 0.35, open)).unwrap();
+        }
+        // Golden values: any platform or libm drift shows up in the last bits
+        assert_eq!(
+            flute.calculate_pitch(f64::NAN).to_bits(),
+            0x407c_e2fe_9706_7f0c
+        );
+        let spectrum = flute.impedance_spectrum(250.0, 2500.0, 7);
+        assert_eq!(spectrum[1].to_bits(), 0x4008_e696_5ccc_971f);
+        assert_eq!(spectrum[20].to_bits(), 0xbff7_6ee9_7190_7d40);
+    }
 }
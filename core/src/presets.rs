@@ -0,0 +1,329 @@
+//! Starting designs for common instruments. Each preset is tuned on build: the tube is cut
+//! so the all-closed note sounds the requested key, then every hole is slid along the tube
+//! until it plays its scale step, so a preset in any key starts out in tune with the model.
+//! Notes are found by bracketing impedance minima, as `one_handed_layout` does.
+
+use crate::ergonomics::{fit, pitch_near};
+use crate::error::FlyteError;
+use crate::geometry::MIN_BRIDGE;
+use crate::physics::{DuctGeometry, Flute, Hole, InstrumentType, NotchCut, NotchShape};
+use serde::{Deserialize, Serialize};
+
+/// How far a preset can be moved from its nominal key, in octaves either way
+const MAX_TRANSPOSE: f64 = 1.0;
+/// Rounds of hole fitting; closed holes above shift the notes below a little
+const TUNING_PASSES: usize = 2;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Preset {
+    /// Six-hole simple-system body in C with a plain transverse embouchure
+    ConcertFlute,
+    TinWhistle,
+    /// Native American flute in A minor, modelled as a single-chamber duct flute
+    NativeAmericanFlute,
+    Bansuri,
+    /// 1.8 shaku, four front holes and a thumb hole
+    Shakuhachi,
+}
+
+pub const PRESETS: [Preset; 5] = [
+    Preset::ConcertFlute,
+    Preset::TinWhistle,
+    Preset::NativeAmericanFlute,
+    Preset::Bansuri,
+    Preset::Shakuhachi,
+];
+
+/// What the picker shows for a preset
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PresetInfo {
+    pub id: Preset,
+    pub name: &'static str,
+    pub description: &'static str,
+    /// All-closed note in Hz at the nominal key
+    pub key: f64,
+    /// Semitones above the key for each hole, opened from the foot up
+    pub scale: &'static [f64],
+}
+
+/// Nominal dimensions (cm) before tuning. Radii scale with the square root of the
+/// transposition so low keys get wider bores rather than proportionally huge ones.
+struct Template {
+    bore_radius: f64,
+    wall_thickness: f64,
+    hole_radius: f64,
+    instrument: InstrumentType,
+    embouchure_hole_radius: f64,
+    /// Closest the top hole may come to x = 0
+    head_clearance: f64,
+    /// Azimuth of the top hole; 180 puts it under the thumb
+    top_hole_azimuth: f64,
+}
+
+impl Preset {
+    /// The snake_case id used in JSON, e.g. `tin_whistle`
+    pub fn id(self) -> &'static str {
+        match self {
+            Preset::ConcertFlute => "concert_flute",
+            Preset::TinWhistle => "tin_whistle",
+            Preset::NativeAmericanFlute => "native_american_flute",
+            Preset::Bansuri => "bansuri",
+            Preset::Shakuhachi => "shakuhachi",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Result<Preset, FlyteError> {
+        PRESETS
+            .into_iter()
+            .find(|p| p.id() == id)
+            .ok_or_else(|| FlyteError::invalid_input(format!("unknown preset {:?}", id)))
+    }
+
+    /// Hz of the all-closed note followed by each scale step
+    pub fn targets(self, key: f64) -> Vec<f64> {
+        let info = self.info();
+        let key = if key.is_nan() { info.key } else { key };
+        std::iter::once(0.0)
+            .chain(info.scale.iter().copied())
+            .map(|semis| key * 2f64.powf(semis / 12.0))
+            .collect()
+    }
+
+    pub fn info(self) -> PresetInfo {
+        let (name, description, key, scale): (_, _, _, &'static [f64]) = match self {
+            Preset::ConcertFlute => (
+                "Concert flute in C",
+                "Headless transverse body, C4 to B4 on six open holes",
+                261.63,
+                &[2.0, 4.0, 5.0, 7.0, 9.0, 11.0],
+            ),
+            Preset::TinWhistle => (
+                "Tin whistle in D",
+                "Six-hole duct whistle playing D major from D5",
+                587.33,
+                &[2.0, 4.0, 5.0, 7.0, 9.0, 11.0],
+            ),
+            Preset::NativeAmericanFlute => (
+                "Native American flute, A minor",
+                "Five-hole minor pentatonic duct flute on A4",
+                440.0,
+                &[3.0, 5.0, 7.0, 10.0, 12.0],
+            ),
+            Preset::Bansuri => (
+                "Bansuri in G",
+                "Six-hole transverse bamboo flute, Sa = G4 with three holes closed",
+                293.66,
+                &[2.0, 4.0, 5.0, 7.0, 9.0, 10.0],
+            ),
+            Preset::Shakuhachi => (
+                "Shakuhachi 1.8",
+                "End-blown, D4 fundamental, four front holes and a thumb hole",
+                293.66,
+                &[3.0, 5.0, 7.0, 10.0, 12.0],
+            ),
+        };
+        PresetInfo {
+            id: self,
+            name,
+            description,
+            key,
+            scale,
+        }
+    }
+
+    fn template(self) -> Template {
+        let transverse = |bore_radius, wall_thickness, hole_radius| Template {
+            bore_radius,
+            wall_thickness,
+            hole_radius,
+            instrument: InstrumentType::Transverse,
+            embouchure_hole_radius: 0.5,
+            head_clearance: 10.0,
+            top_hole_azimuth: 0.0,
+        };
+        match self {
+            Preset::ConcertFlute => transverse(0.95, 0.35, 0.45),
+            Preset::Bansuri => transverse(0.95, 0.3, 0.42),
+            Preset::TinWhistle => Template {
+                bore_radius: 0.65,
+                wall_thickness: 0.15,
+                hole_radius: 0.3,
+                instrument: InstrumentType::Duct(DuctGeometry {
+                    windway_length: 3.0,
+                    windway_width: 0.9,
+                    windway_height: 0.12,
+                    window_length: 0.45,
+                    ..DuctGeometry::default()
+                }),
+                embouchure_hole_radius: 0.5,
+                head_clearance: 5.0,
+                top_hole_azimuth: 0.0,
+            },
+            Preset::NativeAmericanFlute => Template {
+                bore_radius: 1.1,
+                wall_thickness: 0.4,
+                hole_radius: 0.45,
+                instrument: InstrumentType::Duct(DuctGeometry {
+                    windway_length: 5.0,
+                    windway_width: 1.0,
+                    windway_height: 0.15,
+                    window_length: 0.6,
+                    ..DuctGeometry::default()
+                }),
+                embouchure_hole_radius: 0.5,
+                head_clearance: 12.0,
+                top_hole_azimuth: 0.0,
+            },
+            Preset::Shakuhachi => Template {
+                bore_radius: 1.0,
+                wall_thickness: 0.5,
+                hole_radius: 0.5,
+                instrument: InstrumentType::Notch(NotchCut {
+                    shape: NotchShape::V,
+                    width: 1.2,
+                    depth: 0.5,
+                    bevel_angle: 20.0,
+                }),
+                embouchure_hole_radius: 0.5,
+                head_clearance: 20.0,
+                top_hole_azimuth: 180.0,
+            },
+        }
+    }
+
+    /// The preset tuned so the all-closed note is `key` Hz (NaN for the nominal key), with
+    /// holes in playing order, embouchure to foot. Keys more than an octave from nominal
+    /// are rejected.
+    pub fn build(self, key: f64) -> Result<Flute, FlyteError> {
+        trace_span!(INFO, "build_preset", preset = self.id(), key);
+        let info = self.info();
+        let key = if key.is_nan() { info.key } else { key };
+        if key <= 0.0 || (key / info.key).log2().abs() > MAX_TRANSPOSE {
+            return Err(FlyteError::invalid_input(format!(
+                "{} key must be within an octave of {} Hz, got {}",
+                info.name, info.key, key
+            )));
+        }
+        let t = self.template();
+        let k = (info.key / key).sqrt();
+
+        // Half a wavelength as a starting point for `fit_length`
+        let length = 0.5 * 34_500.0 / key;
+        let mut flute = Flute::new(length, t.bore_radius * k, t.wall_thickness);
+        flute.instrument = t.instrument;
+        flute.embouchure_hole_radius = t.embouchure_hole_radius * k;
+        let n = info.scale.len();
+        let hole_radius = t.hole_radius * k;
+        let gap = 2.0 * hole_radius + MIN_BRIDGE;
+        let head = t.head_clearance * k;
+        let all_open = |holes: usize| (1u32 << holes) - 1;
+
+        // First pass: add holes from the foot up, each fitted with every hole below it open.
+        // Holes above aren't there yet, so they can't pull the search onto another mode.
+        fit_length(&mut flute, key);
+        for open in 1..=n {
+            let target = key * 2f64.powf(info.scale[open - 1] / 12.0);
+            let below = flute
+                .holes
+                .first()
+                .map_or(flute.length - hole_radius - 1.0, |h| h.position - gap);
+            flute.holes.insert(0, Hole::new(below, hole_radius, true))?;
+            flute.holes[0].position = fit((head.min(below), below), target, false, |position| {
+                flute.holes[0].position = position;
+                pitch_near(&flute, all_open(open), target)
+            });
+        }
+        flute.holes[0].azimuth = t.top_hole_azimuth;
+
+        // Closed holes add volume, so retune the tube and nudge each hole between its
+        // neighbours until it settles
+        for _ in 0..TUNING_PASSES {
+            fit_length(&mut flute, key);
+            for open in 1..=n {
+                let i = n - open;
+                let target = key * 2f64.powf(info.scale[open - 1] / 12.0);
+                let below = match flute.holes.get(i + 1) {
+                    Some(h) => h.position - gap,
+                    None => flute.length - hole_radius - 1.0,
+                };
+                let above = match i {
+                    0 => head,
+                    _ => flute.holes[i - 1].position + gap,
+                };
+                let fingering = all_open(n) & !all_open(i);
+                flute.holes[i].position =
+                    fit((above.min(below), below), target, false, |position| {
+                        flute.holes[i].position = position;
+                        pitch_near(&flute, fingering, target)
+                    });
+            }
+        }
+        flute.validate()?;
+        Ok(flute)
+    }
+}
+
+/// Cut the tube so the all-closed note sounds `key`
+pub(crate) fn fit_length(flute: &mut Flute, key: f64) {
+    let length = flute.length;
+    let shortest = flute
+        .holes
+        .iter()
+        .map(|h| h.position + h.radius + 1.0)
+        .fold(0.0, f64::max);
+    flute.length = fit(
+        (shortest.max(0.5 * length), 1.5 * length),
+        key,
+        false,
+        |length| {
+            flute.length = length;
+            pitch_near(flute, 0, key)
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_play_their_scale() {
+        for preset in PRESETS {
+            let info = preset.info();
+            let flute = preset.build(f64::NAN).unwrap();
+            let n = info.scale.len();
+            assert_eq!(flute.holes.len(), n);
+            for (open, semis) in [0.0].iter().chain(info.scale).enumerate() {
+                let fingering = ((1u32 << n) - 1) & !((1u32 << (n - open)) - 1);
+                let target = info.key * 2f64.powf(semis / 12.0);
+                let cents = 1200.0 * (pitch_near(&flute, fingering, target) / target).log2();
+                assert!(
+                    cents.abs() < 5.0,
+                    "{:?} +{}: {} cents",
+                    preset,
+                    semis,
+                    cents
+                );
+            }
+        }
+
+        // The chart agrees where the secant search is seeded well
+        let flute = Preset::ConcertFlute.build(f64::NAN).unwrap();
+        let chart = crate::tuning::fingering_chart(&flute, f64::NAN);
+        assert_eq!(chart[6].note.name, "B4");
+        assert!(chart.iter().all(|row| row.note.cents.abs() < 5.0));
+
+        // A C whistle is longer than the D one
+        let d = Preset::TinWhistle.build(f64::NAN).unwrap();
+        let c = Preset::TinWhistle.build(523.25).unwrap();
+        assert!(c.length > d.length);
+        assert!(Preset::TinWhistle.build(100.0).is_err());
+        for preset in PRESETS {
+            let json = serde_json::to_value(preset).unwrap();
+            assert_eq!(json, preset.id());
+            assert_eq!(Preset::from_id(preset.id()), Ok(preset));
+        }
+        assert!(Preset::from_id("kazoo").is_err());
+    }
+}
@@ -0,0 +1,658 @@
+//! Python bindings, built as the `flyte` extension module (`maturin build -m core/pyproject.toml`).
+//!
+//! Structured values (holes, instrument types, charts, reports) cross the boundary as plain
+//! dicts and lists with the same field names as the JSON API.
+
+use crate::document::{self, DesignDocument};
+use crate::error::FlyteError;
+use crate::physics::{AdaptiveSampling, Flute, Hole, Holes};
+use crate::{
+    air, calibration, dynamics, ergonomics, export, geometry, moisture, organ, tolerance, tuning,
+    wizard,
+};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// A flute design with its acoustic model. Lengths are in cm.
+#[pyclass(name = "Flute", module = "flyte")]
+#[derive(Clone)]
+pub struct PyFlute {
+    inner: Flute,
+}
+
+#[pymethods]
+impl PyFlute {
+    #[new]
+    fn new(length: f64, bore_radius: f64, wall_thickness: f64) -> PyResult<Self> {
+        let inner = Flute::new(length, bore_radius, wall_thickness);
+        inner.validate()?;
+        Ok(PyFlute { inner })
+    }
+
+    /// Load a design saved by `to_json` or by the web app, including older file versions
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        let inner = DesignDocument::from_json(json)?.to_flute();
+        inner.validate()?;
+        Ok(PyFlute { inner })
+    }
+
+    fn to_json(&self) -> String {
+        DesignDocument::new(&self.inner).to_json()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Flute(length={}, bore_radius={}, wall_thickness={}, holes={})",
+            self.inner.length,
+            self.inner.bore_radius,
+            self.inner.wall_thickness,
+            self.inner.holes.len()
+        )
+    }
+
+    #[getter]
+    fn length(&self) -> f64 {
+        self.inner.length
+    }
+
+    #[setter]
+    fn set_length(&mut self, value: f64) -> PyResult<()> {
+        self.edit(|f| f.length = value)
+    }
+
+    #[getter]
+    fn bore_radius(&self) -> f64 {
+        self.inner.bore_radius
+    }
+
+    #[setter]
+    fn set_bore_radius(&mut self, value: f64) -> PyResult<()> {
+        self.edit(|f| f.bore_radius = value)
+    }
+
+    #[getter]
+    fn wall_thickness(&self) -> f64 {
+        self.inner.wall_thickness
+    }
+
+    #[setter]
+    fn set_wall_thickness(&mut self, value: f64) -> PyResult<()> {
+        self.edit(|f| f.wall_thickness = value)
+    }
+
+    #[getter]
+    fn cork_position(&self) -> f64 {
+        self.inner.cork_position
+    }
+
+    #[setter]
+    fn set_cork_position(&mut self, value: f64) -> PyResult<()> {
+        self.edit(|f| f.cork_position = value)
+    }
+
+    #[getter]
+    fn embouchure_hole_radius(&self) -> f64 {
+        self.inner.embouchure_hole_radius
+    }
+
+    #[setter]
+    fn set_embouchure_hole_radius(&mut self, value: f64) -> PyResult<()> {
+        self.edit(|f| f.embouchure_hole_radius = value)
+    }
+
+    #[getter]
+    fn embouchure_chimney(&self) -> f64 {
+        self.inner.embouchure_chimney
+    }
+
+    #[setter]
+    fn set_embouchure_chimney(&mut self, value: f64) -> PyResult<()> {
+        self.edit(|f| f.embouchure_chimney = value)
+    }
+
+    #[getter]
+    fn embouchure_oval(&self) -> f64 {
+        self.inner.embouchure_oval
+    }
+
+    #[setter]
+    fn set_embouchure_oval(&mut self, value: f64) -> PyResult<()> {
+        self.edit(|f| f.embouchure_oval = value)
+    }
+
+    /// Bore out-of-roundness, percent
+    #[getter]
+    fn bore_ovality(&self) -> f64 {
+        self.inner.bore_ovality
+    }
+
+    #[setter]
+    fn set_bore_ovality(&mut self, value: f64) -> PyResult<()> {
+        self.edit(|f| f.bore_ovality = value)
+    }
+
+    /// e.g. `{"kind": "notch", "shape": "u", "width": 1.0, ...}`
+    #[getter]
+    fn instrument(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_py(py, &self.inner.instrument)
+    }
+
+    #[setter]
+    fn set_instrument(&mut self, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        let instrument = from_py(value)?;
+        self.edit(|f| f.instrument = instrument)
+    }
+
+    /// `{"max_iterations", "frequency_tolerance", "search_bounds", "scan_resolution"}`;
+    /// assign a partial dict to change some, the rest taking their defaults
+    #[getter]
+    fn solver(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_py(py, &self.inner.solver)
+    }
+
+    #[setter]
+    fn set_solver(&mut self, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        let solver = from_py(value)?;
+        self.edit(|f| f.solver = solver)
+    }
+
+    /// `{"open_end_correction", "hole_end_correction", "embouchure_end_correction",
+    /// "loss_factor"}`, the model's empirical constants; assign a partial dict to change some
+    #[getter]
+    fn calibration(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_py(py, &self.inner.calibration)
+    }
+
+    #[setter]
+    fn set_calibration(&mut self, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        let calibration = from_py(value)?;
+        self.edit(|f| f.calibration = calibration)
+    }
+
+    /// `[{"position", "radius", "open", "azimuth"}, ...]`; assign a new list to replace them
+    #[getter]
+    fn holes(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_py(py, &self.inner.holes)
+    }
+
+    #[setter]
+    fn set_holes(&mut self, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        let holes: Vec<Hole> = from_py(value)?;
+        let holes = Holes::try_from(holes)?;
+        self.edit(|f| f.holes = holes)
+    }
+
+    #[pyo3(signature = (position, radius, open = true, azimuth = 0.0))]
+    fn add_hole(&mut self, position: f64, radius: f64, open: bool, azimuth: f64) -> PyResult<()> {
+        let mut hole = Hole::new(position, radius, open);
+        hole.azimuth = azimuth.rem_euclid(360.0);
+        self.inner.add_hole(hole)?;
+        Ok(())
+    }
+
+    /// Stable id of every hole in stored order; it follows the hole through edits and
+    /// through insertions and removals of other holes
+    fn hole_ids(&self) -> Vec<u32> {
+        self.inner.holes.ids().iter().map(|id| id.get()).collect()
+    }
+
+    fn remove_hole(&mut self, index: usize) -> PyResult<()> {
+        let len = self.inner.holes.len();
+        if index >= len {
+            return Err(FlyteError::IndexOutOfBounds { index, len }.into());
+        }
+        self.inner.holes.remove(index);
+        Ok(())
+    }
+
+    /// Sounding pitch (Hz) of the current open/closed state. NaN jet velocity uses the default.
+    #[pyo3(signature = (jet_velocity = f64::NAN))]
+    fn pitch(&mut self, jet_velocity: f64) -> PyResult<f64> {
+        Ok(self.inner.checked_pitch(jet_velocity)?)
+    }
+
+    /// `pitch` as a dict of frequency, note, cents, converged, mode, q and diagnostics,
+    /// without raising when the search does not converge
+    #[pyo3(signature = (jet_velocity = f64::NAN))]
+    fn pitch_detailed(&mut self, py: Python<'_>, jet_velocity: f64) -> PyResult<PyObject> {
+        to_py(py, &self.inner.calculate_pitch_detailed(jet_velocity))
+    }
+
+    /// Pitch for each fingering (bit i set = hole i open)
+    #[pyo3(signature = (fingerings, jet_velocity = f64::NAN))]
+    fn pitches(&self, fingerings: Vec<u32>, jet_velocity: f64) -> Vec<f64> {
+        tuning::pitches_for_fingerings(&self.inner, &fingerings, jet_velocity)
+    }
+
+    /// The impedance minimum nearest `guess` (Hz)
+    fn resonance_near(&self, guess: f64) -> f64 {
+        self.inner.resonance_near(guess)
+    }
+
+    /// `n` points from `f0` to `f1` Hz as `(freq, |Z|, phase)` tuples
+    fn impedance_spectrum(&self, f0: f64, f1: f64, n: usize) -> Vec<(f64, f64, f64)> {
+        self.inner
+            .impedance_spectrum(f0, f1, n)
+            .chunks_exact(3)
+            .map(|c| (c[0], c[1], c[2]))
+            .collect()
+    }
+
+    /// `(freq, |Z|, phase)` tuples from `f0` to `f1` Hz, sampled densely only around
+    /// resonances. Keyword arguments are the `AdaptiveSampling` fields.
+    #[pyo3(signature = (f0, f1, **options))]
+    fn adaptive_spectrum(
+        &self,
+        f0: f64,
+        f1: f64,
+        options: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<Vec<(f64, f64, f64)>> {
+        let options = match options {
+            Some(options) => from_py(options)?,
+            None => AdaptiveSampling::default(),
+        };
+        Ok(self
+            .inner
+            .adaptive_spectrum(f0, f1, &options)
+            .chunks_exact(3)
+            .map(|c| (c[0], c[1], c[2]))
+            .collect())
+    }
+
+    /// `impedance_spectrum` for each fingering (bit i = hole i open), one list per fingering
+    fn fingering_spectra(
+        &self,
+        fingerings: Vec<u32>,
+        f0: f64,
+        f1: f64,
+        n: usize,
+    ) -> Vec<Vec<(f64, f64, f64)>> {
+        self.inner
+            .fingering_spectra(&fingerings, f0, f1, n)
+            .iter()
+            .map(|spectrum| {
+                spectrum
+                    .chunks_exact(3)
+                    .map(|c| (c[0], c[1], c[2]))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[pyo3(signature = (jet_velocity = f64::NAN))]
+    fn fingering_chart(&self, py: Python<'_>, jet_velocity: f64) -> PyResult<PyObject> {
+        to_py(py, &tuning::fingering_chart(&self.inner, jet_velocity))
+    }
+
+    /// Design problems that would produce impossible or fragile geometry
+    fn check_geometry(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_py(py, &geometry::check_geometry(&self.inner))
+    }
+
+    /// How far each note can land from the design when it is made within workshop
+    /// tolerances. Keyword arguments are the `ToleranceOptions` fields (mm, samples, seed).
+    #[pyo3(signature = (**options))]
+    fn tolerance_study(
+        &self,
+        py: Python<'_>,
+        options: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<PyObject> {
+        let options = match options {
+            Some(options) => from_py(options)?,
+            None => tolerance::ToleranceOptions::default(),
+        };
+        let study = py.allow_threads(|| tolerance::tolerance_study(&self.inner, &options));
+        to_py(py, &study)
+    }
+
+    /// What a bore `ovality` percent out of round does to every note and the lowest note's Q
+    #[pyo3(signature = (ovality, jet_velocity = f64::NAN))]
+    fn ovality_effect(
+        &self,
+        py: Python<'_>,
+        ovality: f64,
+        jet_velocity: f64,
+    ) -> PyResult<PyObject> {
+        to_py(
+            py,
+            &tolerance::ovality_effect(&self.inner, ovality, jet_velocity)?,
+        )
+    }
+
+    /// Cents per mm of position and per 0.1 mm of diameter of one hole, for the current
+    /// fingering
+    fn quick_sensitivity(&self, py: Python<'_>, hole_index: usize) -> PyResult<PyObject> {
+        to_py(
+            py,
+            &tolerance::quick_sensitivity(&self.inner, hole_index, f64::NAN)?,
+        )
+    }
+
+    /// How each note moves when a wooden body goes from one humidity to another. Keyword
+    /// arguments are the `DriftOptions` fields (material, from_humidity, to_humidity, ...).
+    #[pyo3(signature = (**options))]
+    fn moisture_drift(
+        &self,
+        py: Python<'_>,
+        options: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<PyObject> {
+        let options = match options {
+            Some(options) => from_py(options)?,
+            None => moisture::DriftOptions::default(),
+        };
+        to_py(py, &moisture::moisture_drift(&self.inner, &options))
+    }
+
+    /// Every progressive fingering cold in room air and warm while played. Keyword arguments
+    /// are the `PlayedOptions` fields (ambient, breath_temperature, foot_temperature, ...).
+    #[pyo3(signature = (**options))]
+    fn cold_vs_played(
+        &self,
+        py: Python<'_>,
+        options: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<PyObject> {
+        let options = match options {
+            Some(options) => from_py(options)?,
+            None => air::PlayedOptions::default(),
+        };
+        to_py(py, &air::cold_vs_played(&self.inner, &options))
+    }
+
+    /// Each note's deviation over a sweep of room temperatures. Keyword arguments are the
+    /// `TemperatureChartOptions` fields (fingerings, from, to, points, reference, ...).
+    #[pyo3(signature = (**options))]
+    fn temperature_chart(
+        &self,
+        py: Python<'_>,
+        options: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<PyObject> {
+        let options = match options {
+            Some(options) => from_py(options)?,
+            None => air::TemperatureChartOptions::default(),
+        };
+        to_py(py, &air::temperature_chart(&self.inner, &options))
+    }
+
+    /// Single changes (enlarge or line a hole, trim the foot) that bring the design to
+    /// `targets`, each checked on the model. Keyword arguments are the `WizardOptions` fields
+    /// (targets, fingerings, max_steps, tolerance, ...); the design itself is not changed.
+    #[pyo3(signature = (**options))]
+    fn tuning_wizard(
+        &self,
+        py: Python<'_>,
+        options: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<PyObject> {
+        let options = match options {
+            Some(options) => from_py(options)?,
+            None => wizard::WizardOptions::default(),
+        };
+        to_py(py, &wizard::tuning_wizard(&self.inner, &options)?)
+    }
+
+    /// Pitch and loudness traces of a note through a crescendo with breath vibrato.
+    /// Keyword arguments are the `TraceOptions` fields (fingering, duration, points, start,
+    /// end, vibrato_rate, vibrato_depth).
+    #[pyo3(signature = (**options))]
+    fn dynamics_trace(
+        &self,
+        py: Python<'_>,
+        options: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<PyObject> {
+        let options = match options {
+            Some(options) => from_py(options)?,
+            None => dynamics::TraceOptions::default(),
+        };
+        to_py(py, &dynamics::dynamics_trace(&self.inner, &options)?)
+    }
+
+    /// Cut-up ratio, scale and voicing wind of an organ-pipe design
+    #[pyo3(signature = (jet_velocity = f64::NAN))]
+    fn organ_pipe_report(&self, py: Python<'_>, jet_velocity: f64) -> PyResult<PyObject> {
+        to_py(py, &organ::pipe_report(&self.inner, jet_velocity)?)
+    }
+
+    /// Replace the finger holes with a one-handed layout fitted to `targets` and return the
+    /// holes and resulting notes; thumb holes stay. Keyword arguments are the
+    /// `OneHandedOptions` fields.
+    #[pyo3(signature = (**options))]
+    fn apply_one_handed_layout(
+        &mut self,
+        py: Python<'_>,
+        options: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<PyObject> {
+        let options = match options {
+            Some(options) => from_py(options)?,
+            None => ergonomics::OneHandedOptions::default(),
+        };
+        let layout = ergonomics::one_handed_layout(&self.inner, &options);
+        self.inner.holes = layout.relaid_holes(&self.inner)?;
+        to_py(py, &layout)
+    }
+
+    /// Binary STL. `mesh_options` is a partial `MeshOptions` dict (units, up axis, LOD, ...).
+    #[pyo3(signature = (mesh_options = None))]
+    fn export_stl<'py>(
+        &self,
+        py: Python<'py>,
+        mesh_options: Option<&Bound<'py, PyAny>>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let opts = mesh_options_from_py(mesh_options)?;
+        let mesh = geometry::generate_flute_mesh_with(&self.inner, &opts);
+        let stl = export::stl::export_stl(&mesh, opts.units, opts.up_axis);
+        Ok(PyBytes::new(py, &stl))
+    }
+
+    #[pyo3(signature = (mesh_options = None))]
+    fn export_3mf<'py>(
+        &self,
+        py: Python<'py>,
+        mesh_options: Option<&Bound<'py, PyAny>>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let opts = mesh_options_from_py(mesh_options)?;
+        let mesh = geometry::generate_flute_mesh_with(&self.inner, &opts);
+        let package = export::threemf::export_3mf(&mesh, opts.units);
+        Ok(PyBytes::new(py, &package))
+    }
+
+    #[pyo3(signature = (mesh_options = None))]
+    fn export_obj(&self, mesh_options: Option<&Bound<'_, PyAny>>) -> PyResult<String> {
+        let opts = mesh_options_from_py(mesh_options)?;
+        let mesh = geometry::generate_flute_mesh_with(&self.inner, &opts);
+        Ok(mesh.to_obj_string(opts.units, opts.up_axis))
+    }
+
+    fn export_glb<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        let mesh = geometry::generate_flute_mesh_with(&self.inner, &Default::default());
+        PyBytes::new(py, &export::gltf::export_glb(&mesh))
+    }
+
+    fn export_dxf(&self) -> String {
+        export::dxf::export_dxf(&self.inner)
+    }
+
+    #[pyo3(signature = (jet_velocity = f64::NAN))]
+    fn export_pdf_plan<'py>(&self, py: Python<'py>, jet_velocity: f64) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &export::pdf::export_pdf_plan(&self.inner, jet_velocity))
+    }
+
+    fn export_spec_json(&self) -> String {
+        export::spec::export_spec_json(&self.inner)
+    }
+
+    #[pyo3(signature = (jet_velocity = f64::NAN))]
+    fn export_hole_csv(&self, jet_velocity: f64) -> String {
+        export::csv::export_hole_csv(&self.inner, jet_velocity)
+    }
+
+    #[pyo3(signature = (jet_velocity = f64::NAN))]
+    fn export_flutomat(&self, jet_velocity: f64) -> String {
+        export::flutomat::export_flutomat_json(&self.inner, jet_velocity)
+    }
+}
+
+impl PyFlute {
+    /// Apply an edit only if the result is still a valid design
+    fn edit(&mut self, change: impl FnOnce(&mut Flute)) -> PyResult<()> {
+        let mut candidate = self.inner.clone();
+        change(&mut candidate);
+        candidate.validate()?;
+        self.inner = candidate;
+        Ok(())
+    }
+}
+
+/// The same fingerings played on two designs, with the difference in cents
+#[pyfunction]
+#[pyo3(signature = (a, b, jet_velocity = f64::NAN))]
+fn compare_designs(
+    py: Python<'_>,
+    a: &PyFlute,
+    b: &PyFlute,
+    jet_velocity: f64,
+) -> PyResult<PyObject> {
+    to_py(
+        py,
+        &tuning::compare_designs(&a.inner, &b.inner, jet_velocity),
+    )
+}
+
+/// Every candidate played on the same fingerings and scored against `targets` (Hz, one per
+/// fingering), ranked best first. Without `fingerings` the targets go with the first
+/// candidate's progressive fingerings.
+#[pyfunction]
+#[pyo3(signature = (candidates, targets, fingerings = Vec::new(), jet_velocity = f64::NAN))]
+fn leaderboard(
+    py: Python<'_>,
+    candidates: Vec<PyRef<'_, PyFlute>>,
+    targets: Vec<f64>,
+    fingerings: Vec<u32>,
+    jet_velocity: f64,
+) -> PyResult<PyObject> {
+    let candidates: Vec<Flute> = candidates.iter().map(|c| c.inner.clone()).collect();
+    let fingerings = match candidates.first() {
+        Some(first) => tuning::target_fingerings(first, &fingerings, targets.len()),
+        None => fingerings,
+    };
+    let board = tuning::leaderboard(&candidates, &fingerings, &targets, jet_velocity)?;
+    to_py(py, &board)
+}
+
+/// A made instrument and its measured `(fingering, frequency)` notes
+type Measured<'py> = (PyRef<'py, PyFlute>, Vec<(u32, f64)>);
+
+/// The calibration that best matches the model to measured instruments, given as
+/// `[(flute, [(fingering, frequency), ...]), ...]`. Keyword arguments are the
+/// `CalibrationOptions` fields (initial, fit, max_iterations).
+#[pyfunction]
+#[pyo3(signature = (instruments, **options))]
+fn fit_calibration(
+    py: Python<'_>,
+    instruments: Vec<Measured<'_>>,
+    options: Option<&Bound<'_, PyAny>>,
+) -> PyResult<PyObject> {
+    let options = match options {
+        Some(options) => from_py(options)?,
+        None => calibration::CalibrationOptions::default(),
+    };
+    let instruments: Vec<calibration::KnownInstrument> = instruments
+        .iter()
+        .map(|(flute, notes)| calibration::KnownInstrument {
+            name: String::new(),
+            design: DesignDocument::new(&flute.inner),
+            notes: notes
+                .iter()
+                .map(|&(fingering, frequency)| calibration::MeasuredNote {
+                    fingering,
+                    frequency,
+                })
+                .collect(),
+        })
+        .collect();
+    to_py(py, &calibration::fit_calibration(&instruments, &options)?)
+}
+
+/// Nearest equal-tempered note to `frequency` and the deviation in cents
+#[pyfunction]
+fn note_info(py: Python<'_>, frequency: f64) -> PyResult<PyObject> {
+    to_py(py, &tuning::note_info(frequency))
+}
+
+/// JSON Schema of the design document written by `Flute.to_json`
+#[pyfunction]
+fn design_schema() -> String {
+    document::json_schema()
+}
+
+#[pymodule]
+fn flyte(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyFlute>()?;
+    m.add_function(wrap_pyfunction!(compare_designs, m)?)?;
+    m.add_function(wrap_pyfunction!(leaderboard, m)?)?;
+    m.add_function(wrap_pyfunction!(fit_calibration, m)?)?;
+    m.add_function(wrap_pyfunction!(note_info, m)?)?;
+    m.add_function(wrap_pyfunction!(design_schema, m)?)?;
+    Ok(())
+}
+
+/// Convert a result to plain Python objects through its JSON form
+fn to_py<T: Serialize>(py: Python<'_>, value: &T) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value).map_err(|e| FlyteError::Serialization {
+        message: e.to_string(),
+    })?;
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+/// Read a dict or list into a Rust value, reporting bad fields as `ValueError`
+fn from_py<T: DeserializeOwned>(value: &Bound<'_, PyAny>) -> PyResult<T> {
+    let json: String = value
+        .py()
+        .import("json")?
+        .call_method1("dumps", (value,))?
+        .extract()?;
+    Ok(serde_json::from_str(&json).map_err(FlyteError::invalid_input)?)
+}
+
+fn mesh_options_from_py(value: Option<&Bound<'_, PyAny>>) -> PyResult<geometry::MeshOptions> {
+    match value {
+        Some(value) if !value.is_none() => from_py(value),
+        _ => Ok(geometry::MeshOptions::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_python_round_trip() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let error = PyFlute::new(-1.0, 0.95, 0.4).err().unwrap();
+            assert!(error.to_string().contains("length"));
+
+            let mut flute = PyFlute::new(60.0, 0.95, 0.4).unwrap();
+            flute.add_hole(40.0, 0.35, true, 0.0).unwrap();
+            flute.add_hole(35.0, 0.35, true, 0.0).unwrap();
+            let mut expected = Flute::new(60.0, 0.95, 0.4);
+            expected.holes = Holes::try_from(vec![
+                Hole::new(40.0, 0.35, true),
+                Hole::new(35.0, 0.35, true),
+            ])
+            .unwrap();
+            let pitch = flute.pitch(f64::NAN).unwrap();
+            assert_eq!(pitch, expected.calculate_pitch(f64::NAN));
+
+            // Holes cross as a list of dicts and come back unchanged
+            let holes = flute.holes(py).unwrap();
+            let holes: Vec<Hole> = from_py(holes.bind(py)).unwrap();
+            assert_eq!(holes, Vec::from(expected.holes.clone()));
+
+            let copy = PyFlute::from_json(&flute.to_json()).unwrap();
+            assert_eq!(copy.inner.holes, flute.inner.holes);
+            assert!(PyFlute::from_json("{").is_err());
+        });
+    }
+}
@@ -0,0 +1,249 @@
+use crate::physics::Flute;
+use crate::tuning;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// Harmonics rendered above the fundamental, before the Nyquist cut
+pub const MAX_PARTIALS: usize = 16;
+/// Longest note `render_note` will produce, in seconds
+pub const MAX_DURATION: f64 = 30.0;
+/// Highest sample rate `render_note` renders at, in Hz
+pub const MAX_SAMPLE_RATE: f64 = 192_000.0;
+/// Peak level of the rendered buffer, leaving headroom for mixing
+const PEAK: f64 = 0.8;
+/// Silence between notes in `render_scale`, in seconds
+pub const SCALE_GAP: f64 = 0.15;
+
+/// Settings for `render_note`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct SynthOptions {
+    /// Hz, capped at `MAX_SAMPLE_RATE`
+    pub sample_rate: f64,
+    /// Seconds, capped at `MAX_DURATION`
+    pub duration: f64,
+    /// Bitmask of open holes (bit i = `holes[i]`); `None` plays the design as it is set
+    pub fingering: Option<u32>,
+    /// NaN uses the solver default
+    pub jet_velocity: f64,
+    /// Level of the breath noise relative to the tone
+    pub breath: f64,
+    pub vibrato_rate: f64,  // Hz
+    pub vibrato_depth: f64, // cents either side
+    pub attack: f64,        // seconds
+    pub release: f64,       // seconds
+}
+
+impl SynthOptions {
+    /// The sample rate a WAV file of these options is rendered at and labelled with: whole
+    /// hertz, as the header stores it, and capped like `render_note`
+    pub fn wav_sample_rate(&self) -> u32 {
+        self.sample_rate.min(MAX_SAMPLE_RATE).round() as u32
+    }
+}
+
+impl Default for SynthOptions {
+    fn default() -> Self {
+        SynthOptions {
+            sample_rate: 44100.0,
+            duration: 1.0,
+            fingering: None,
+            jet_velocity: f64::NAN,
+            breath: 0.03,
+            vibrato_rate: 5.0,
+            vibrato_depth: 8.0,
+            attack: 0.06,
+            release: 0.15,
+        }
+    }
+}
+
+/// The steady spectrum of a note: sounding frequency and the relative level of each harmonic
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NoteSpectrum {
+    pub frequency: f64,
+    /// Amplitude of harmonic k + 1, fundamental = 1
+    pub partials: Vec<f64>,
+}
+
+/// Spectrum of the note the design plays. The jet drives harmonics with a 1/k^2 rolloff and
+/// the air column passes each one in proportion to its input admittance, so harmonics that
+/// land near a higher bore resonance come through stronger, as on a real flute.
+pub fn note_spectrum(flute: &Flute, fingering: Option<u32>, jet_velocity: f64) -> NoteSpectrum {
+    let mut fingered = flute.clone();
+    if let Some(fingering) = fingering {
+        for (i, hole) in fingered.holes.iter_mut().enumerate() {
+            hole.open = i < 32 && fingering & (1 << i) != 0;
+        }
+    }
+    let frequency = fingered.calculate_pitch(jet_velocity);
+    if !frequency.is_finite() || frequency <= 0.0 {
+        return NoteSpectrum {
+            frequency,
+            partials: Vec::new(),
+        };
+    }
+    let magnitude = |f: f64| fingered.impedance_spectrum(f, f, 1)[1];
+    let fundamental = magnitude(frequency);
+    let partials = (1..=MAX_PARTIALS)
+        .map(|k| {
+            let k = k as f64;
+            let admittance = (fundamental / magnitude(k * frequency)).min(1.0);
+            admittance / (k * k)
+        })
+        .collect();
+    NoteSpectrum {
+        frequency,
+        partials,
+    }
+}
+
+/// Mono samples of one note in -1..1, ready for a Web Audio `AudioBuffer`
+pub fn render_note(flute: &Flute, opts: &SynthOptions) -> Vec<f32> {
+    trace_span!(
+        INFO,
+        "render_note",
+        duration = opts.duration,
+        sample_rate = opts.sample_rate
+    );
+    let sample_rate = opts.sample_rate.min(MAX_SAMPLE_RATE);
+    if sample_rate.is_nan() || sample_rate <= 0.0 || opts.duration.is_nan() || opts.duration <= 0.0
+    {
+        return Vec::new();
+    }
+    let n = (opts.duration.min(MAX_DURATION) * sample_rate) as usize;
+    let spectrum = note_spectrum(flute, opts.fingering, opts.jet_velocity);
+    if spectrum.partials.is_empty() {
+        return vec![0.0; n];
+    }
+    let mut out = vec![0.0f64; n];
+
+    let f0 = spectrum.frequency;
+    let nyquist = 0.45 * sample_rate;
+    let partials: Vec<(f64, f64)> = spectrum
+        .partials
+        .iter()
+        .enumerate()
+        .map(|(k, &a)| ((k + 1) as f64, a))
+        .filter(|&(k, _)| k * f0 < nyquist)
+        .collect();
+    let mut phases = vec![0.0f64; partials.len()];
+
+    // Breath: low-passed white noise from a fixed seed so renders are repeatable
+    let mut seed = 0x9e37_79b9u32;
+    let cutoff = (4.0 * f0).min(nyquist);
+    let smoothing = 1.0 - (-2.0 * PI * cutoff / sample_rate).exp();
+    let mut noise = 0.0;
+
+    let attack = (opts.attack.max(0.0) * sample_rate) as usize;
+    let release = (opts.release.max(0.0) * sample_rate) as usize;
+    let (attack, release) = if attack + release > n {
+        (n / 2, n - n / 2)
+    } else {
+        (attack, release)
+    };
+
+    for (i, sample) in out.iter_mut().enumerate() {
+        let t = i as f64 / sample_rate;
+        let cents = opts.vibrato_depth * (2.0 * PI * opts.vibrato_rate * t).sin();
+        let step = 2.0 * PI * f0 * 2f64.powf(cents / 1200.0) / sample_rate;
+        let mut tone = 0.0;
+        for ((k, amplitude), phase) in partials.iter().zip(phases.iter_mut()) {
+            tone += amplitude * phase.sin();
+            *phase = (*phase + k * step) % (2.0 * PI);
+        }
+
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        let white = seed as f64 / u32::MAX as f64 * 2.0 - 1.0;
+        noise += smoothing * (white - noise);
+
+        let envelope = if i < attack {
+            i as f64 / attack as f64
+        } else if i >= n - release {
+            (n - i) as f64 / release as f64
+        } else {
+            1.0
+        };
+        *sample = envelope * (tone + opts.breath * noise);
+    }
+
+    let peak = out.iter().fold(0.0f64, |m, s| m.max(s.abs()));
+    let gain = if peak > 0.0 { PEAK / peak } else { 0.0 };
+    out.into_iter().map(|s| (s * gain) as f32).collect()
+}
+
+/// Every note of the fingering chart, lowest first, each `opts.duration` long and separated
+/// by `SCALE_GAP` of silence. `opts.fingering` is ignored.
+pub fn render_scale(flute: &Flute, opts: &SynthOptions) -> Vec<f32> {
+    let gap = vec![0.0f32; (SCALE_GAP * opts.sample_rate.clamp(0.0, MAX_SAMPLE_RATE)) as usize];
+    let mut out = Vec::new();
+    for fingering in tuning::progressive_fingerings(flute) {
+        let note = SynthOptions {
+            fingering: Some(fingering),
+            ..opts.clone()
+        };
+        if !out.is_empty() {
+            out.extend_from_slice(&gap);
+        }
+        out.extend(render_note(flute, &note));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Hole;
+
+    #[test]
+    fn test_rendered_note_sounds_at_the_pitch() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, true)).unwrap();
+        let opts = SynthOptions {
+            sample_rate: 8000.0,
+            duration: 0.5,
+            fingering: Some(0),
+            breath: 0.0,
+            vibrato_depth: 0.0,
+            ..SynthOptions::default()
+        };
+        let samples = render_note(&flute, &opts);
+        assert_eq!(samples.len(), 4000);
+        let peak = samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!((peak - 0.8).abs() < 1e-3);
+
+        // Upward zero crossings count the fundamental's cycles
+        let crossings = samples
+            .windows(2)
+            .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+            .count();
+        let expected = note_spectrum(&flute, Some(0), f64::NAN).frequency * 0.5;
+        assert!(
+            (crossings as f64 - expected).abs() < 0.05 * expected,
+            "{} crossings, expected about {}",
+            crossings,
+            expected
+        );
+
+        let scale = render_scale(&flute, &opts);
+        let gap = (SCALE_GAP * 8000.0) as usize;
+        assert_eq!(scale.len(), 2 * 4000 + gap);
+        assert_eq!(&scale[..4000], &samples[..]);
+
+        // An absurd rate renders at the cap rather than allocating without bound
+        let opts = SynthOptions {
+            sample_rate: 1e12,
+            duration: 0.01,
+            ..opts
+        };
+        assert_eq!(render_note(&flute, &opts).len(), 1920);
+        assert_eq!(opts.wav_sample_rate(), 192_000);
+        let opts = SynthOptions {
+            sample_rate: 22_050.6,
+            ..opts
+        };
+        assert_eq!(opts.wav_sample_rate(), 22_051);
+    }
+}
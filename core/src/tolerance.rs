@@ -0,0 +1,342 @@
+//! Monte Carlo tolerance studies: how far a finished instrument's notes can land from the
+//! design when every dimension is only made to within workshop tolerances. Also what an
+//! out-of-round bore does, for bamboo and aged wooden bodies, and how touchy one hole is,
+//! cheap enough for a tooltip.
+
+use crate::error::FlyteError;
+use crate::physics::Flute;
+use crate::transfer;
+use crate::tuning::{self, ComparisonRow};
+use serde::{Deserialize, Serialize};
+
+/// Most samples `tolerance_study` will run
+pub const MAX_SAMPLES: usize = 100_000;
+
+/// Workshop tolerances (+/- mm, each dimension drawn uniformly within them) and sampling
+/// settings for `tolerance_study`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ToleranceOptions {
+    pub length: f64,
+    pub bore_diameter: f64,
+    pub wall_thickness: f64,
+    pub hole_position: f64,
+    pub hole_diameter: f64,
+    /// Capped at `MAX_SAMPLES`
+    pub samples: usize,
+    /// Same seed, same study, however many threads run it
+    pub seed: u64,
+    /// NaN uses the solver default
+    pub jet_velocity: f64,
+}
+
+impl Default for ToleranceOptions {
+    fn default() -> Self {
+        // The manufacturing spec's default tolerances
+        ToleranceOptions {
+            length: 0.5,
+            bore_diameter: 0.05,
+            wall_thickness: 0.1,
+            hole_position: 0.2,
+            hole_diameter: 0.05,
+            samples: 1000,
+            seed: 1,
+            jet_velocity: f64::NAN,
+        }
+    }
+}
+
+/// Spread of one progressive fingering over the samples, in cents from the design's pitch
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NoteSpread {
+    pub fingering: u32,
+    /// The design's own pitch, Hz
+    pub frequency: f64,
+    pub mean_cents: f64,
+    pub std_cents: f64,
+    pub min_cents: f64,
+    pub max_cents: f64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ToleranceStudy {
+    pub samples: usize,
+    /// Lowest note first, as in the fingering chart
+    pub notes: Vec<NoteSpread>,
+}
+
+/// Build `opts.samples` copies of the design with every dimension off by a random amount
+/// within tolerance and report how far each note moves. Samples are independent, so with
+/// the `parallel` feature they run across all cores.
+pub fn tolerance_study(flute: &Flute, opts: &ToleranceOptions) -> ToleranceStudy {
+    trace_span!(INFO, "tolerance_study", samples = opts.samples);
+    let fingerings = tuning::progressive_fingerings(flute);
+    let solved = tuning::pitches_for_fingerings(flute, &fingerings, opts.jet_velocity);
+    let samples = opts.samples.min(MAX_SAMPLES);
+    // Every note is followed from the design's pitch rather than solved from scratch, so a
+    // small change cannot jump to another mode. The nominal pitches go through the same
+    // search so an exact copy reads zero cents.
+    let mut design = flute.clone();
+    let nominal: Vec<f64> = fingerings
+        .iter()
+        .zip(&solved)
+        .map(|(&fingering, &guess)| followed(&mut design, fingering, guess, opts.jet_velocity))
+        .collect();
+    let cents = |sample: usize| {
+        let mut made = perturbed(flute, opts, sample as u64);
+        fingerings
+            .iter()
+            .zip(&nominal)
+            .map(|(&fingering, &f0)| {
+                let f = followed(&mut made, fingering, f0, opts.jet_velocity);
+                1200.0 * (f / f0).log2()
+            })
+            .collect::<Vec<f64>>()
+    };
+    #[cfg(feature = "parallel")]
+    let runs: Vec<Vec<f64>> = {
+        use rayon::prelude::*;
+        (0..samples).into_par_iter().map(cents).collect()
+    };
+    #[cfg(not(feature = "parallel"))]
+    let runs: Vec<Vec<f64>> = (0..samples).map(cents).collect();
+
+    let notes = fingerings
+        .iter()
+        .zip(&nominal)
+        .enumerate()
+        .map(|(k, (&fingering, &frequency))| {
+            let values: Vec<f64> = runs
+                .iter()
+                .map(|run| run[k])
+                .filter(|c| c.is_finite())
+                .collect();
+            let n = values.len().max(1) as f64;
+            let mean = values.iter().sum::<f64>() / n;
+            let variance = values.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / n;
+            NoteSpread {
+                fingering,
+                frequency,
+                mean_cents: mean,
+                std_cents: variance.sqrt(),
+                min_cents: values.iter().copied().fold(f64::INFINITY, f64::min),
+                max_cents: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            }
+        })
+        .collect();
+    ToleranceStudy { samples, notes }
+}
+
+/// What an oval bore does to a design, against the same design with a round bore of equal
+/// area
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct OvalityEffect {
+    /// Wall perimeter over the round bore's
+    pub perimeter_ratio: f64,
+    /// Every progressive fingering: `a` round, `b` oval, `cents` b from a
+    pub notes: Vec<ComparisonRow>,
+    /// Quality factor of the all-closed note, round and oval; wall loss shows here first
+    pub q_round: f64,
+    pub q_oval: f64,
+}
+
+/// How much `ovality` percent out of round matters for `flute`. Only the wall losses
+/// change, so expect fractions of a cent and a slightly duller resonance; an oval bore
+/// mostly matters where it changes how the tone holes meet it, which isn't modelled.
+pub fn ovality_effect(
+    flute: &Flute,
+    ovality: f64,
+    jet_velocity: f64,
+) -> Result<OvalityEffect, FlyteError> {
+    let mut round = flute.clone();
+    round.bore_ovality = 0.0;
+    let mut oval = flute.clone();
+    oval.bore_ovality = ovality;
+    oval.validate()?;
+    let notes = tuning::compare_designs(&round, &oval, jet_velocity);
+    let lowest = &notes[0];
+    let q = |design: &mut Flute, frequency: f64| {
+        for (i, hole) in design.holes.iter_mut().enumerate() {
+            hole.open = i < 32 && lowest.fingering & (1 << i) != 0;
+        }
+        design.describe_resonance(frequency).q
+    };
+    Ok(OvalityEffect {
+        perimeter_ratio: transfer::oval_perimeter_ratio(ovality),
+        q_round: q(&mut round, lowest.a),
+        q_oval: q(&mut oval, lowest.b),
+        notes,
+    })
+}
+
+/// How far the current note moves for small changes to one hole
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct HoleSensitivity {
+    pub hole: usize,
+    /// The current fingering's pitch the slopes are taken at, Hz
+    pub frequency: f64,
+    /// Cents per mm the hole moves towards the foot; negative = flatter
+    pub cents_per_mm: f64,
+    /// Cents per 0.1 mm wider the hole is drilled
+    pub cents_per_tenth_mm: f64,
+}
+
+/// Slopes of the current fingering's pitch with hole `hole`'s position and diameter, from
+/// central differences of half a millimetre and 0.05 mm, each side tracked from
+/// `frequency` rather than solved afresh. NaN `frequency` solves the current note first.
+/// Slopes come out NaN when the note can't be followed across a step.
+pub fn quick_sensitivity(
+    flute: &Flute,
+    hole: usize,
+    frequency: f64,
+) -> Result<HoleSensitivity, FlyteError> {
+    if hole >= flute.holes.len() {
+        return Err(FlyteError::invalid_input(format!(
+            "hole {} out of range ({} holes)",
+            hole,
+            flute.holes.len()
+        )));
+    }
+    let frequency = if frequency.is_nan() {
+        flute.clone().calculate_pitch(f64::NAN)
+    } else {
+        frequency
+    };
+    let mut design = flute.clone();
+    let mut slope = |change: &dyn Fn(&mut Flute, f64)| {
+        let mut side = |sign: f64| {
+            change(&mut design, sign);
+            let f = design.track_resonance(frequency).unwrap_or(f64::NAN);
+            change(&mut design, -sign);
+            f
+        };
+        let (up, down) = (side(1.0), side(-1.0));
+        1200.0 * (up / down).log2()
+    };
+    // A 1 mm and a 0.1 mm span, in cm
+    let cents_per_mm = slope(&|f, sign| f.holes[hole].position += sign * 0.05);
+    let cents_per_tenth_mm = slope(&|f, sign| f.holes[hole].radius += sign * 0.0025);
+    Ok(HoleSensitivity {
+        hole,
+        frequency,
+        cents_per_mm,
+        cents_per_tenth_mm,
+    })
+}
+
+/// Pitch of `fingering` on `flute` tracked from `guess`, solving afresh if the mode is lost
+fn followed(flute: &mut Flute, fingering: u32, guess: f64, jet_velocity: f64) -> f64 {
+    for (i, hole) in flute.holes.iter_mut().enumerate() {
+        hole.open = i < 32 && fingering & (1 << i) != 0;
+    }
+    flute
+        .track_resonance(guess)
+        .unwrap_or_else(|| flute.calculate_pitch(jet_velocity))
+}
+
+/// Sample `index` of the study: its own random stream, so the result does not depend on
+/// which thread draws it
+fn perturbed(flute: &Flute, opts: &ToleranceOptions, index: u64) -> Flute {
+    let mut state = opts.seed ^ index.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    // splitmix64, mapped to -1..1
+    let mut unit = || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0
+    };
+    // Tolerances are mm on diameters; the model is cm on radii
+    let mut made = flute.clone();
+    made.length += unit() * opts.length / 10.0;
+    made.bore_radius += unit() * opts.bore_diameter / 20.0;
+    made.wall_thickness += unit() * opts.wall_thickness / 10.0;
+    for hole in &mut made.holes {
+        hole.position += unit() * opts.hole_position / 10.0;
+        hole.radius += unit() * opts.hole_diameter / 20.0;
+    }
+    made
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Hole;
+
+    #[test]
+    fn test_tolerance_study_spread() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for pos in [40.0, 35.0] {
+            flute.holes.push(Hole::new(pos, 0.35, true)).unwrap();
+        }
+        let opts = ToleranceOptions {
+            samples: 40,
+            ..ToleranceOptions::default()
+        };
+        let study = tolerance_study(&flute, &opts);
+        assert_eq!(study.samples, 40);
+        assert_eq!(study.notes.len(), 3);
+        for note in &study.notes {
+            // Workshop tolerances move a note a few cents, not a semitone
+            assert!(note.std_cents > 0.0 && note.std_cents < 10.0, "{:?}", note);
+            assert!(note.min_cents <= note.mean_cents && note.mean_cents <= note.max_cents);
+        }
+        // Repeatable from the seed
+        assert_eq!(tolerance_study(&flute, &opts), study);
+
+        let exact = ToleranceOptions {
+            length: 0.0,
+            bore_diameter: 0.0,
+            wall_thickness: 0.0,
+            hole_position: 0.0,
+            hole_diameter: 0.0,
+            samples: 3,
+            ..ToleranceOptions::default()
+        };
+        let study = tolerance_study(&flute, &exact);
+        assert!(study.notes.iter().all(|n| n.max_cents.abs() < 1e-9));
+    }
+
+    #[test]
+    fn test_ovality_effect() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for pos in [40.0, 35.0] {
+            flute.holes.push(Hole::new(pos, 0.35, true)).unwrap();
+        }
+        assert_eq!(transfer::oval_perimeter_ratio(0.0), 1.0);
+        // 10 % out of round adds well under a percent of wall
+        let ratio = transfer::oval_perimeter_ratio(10.0);
+        assert!(ratio > 1.0 && ratio < 1.005, "{}", ratio);
+
+        let slight = ovality_effect(&flute, 5.0, f64::NAN).unwrap();
+        let heavy = ovality_effect(&flute, 20.0, f64::NAN).unwrap();
+        assert_eq!(slight.notes.len(), 3);
+        for (s, h) in slight.notes.iter().zip(&heavy.notes) {
+            // Flatter the more oval, and by little
+            assert!(h.cents < s.cents && s.cents < 0.0, "{:?} {:?}", s, h);
+            assert!(h.cents > -5.0);
+        }
+        assert!(heavy.q_oval < slight.q_oval && slight.q_oval < slight.q_round);
+        assert!(ovality_effect(&flute, 60.0, f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_quick_sensitivity() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        flute.holes.push(Hole::new(40.0, 0.35, true)).unwrap();
+        flute.holes.push(Hole::new(35.0, 0.35, true)).unwrap();
+        let sounding = quick_sensitivity(&flute, 1, f64::NAN).unwrap();
+        // Down the tube flattens, wider sharpens, as a full re-solve says
+        assert!(sounding.cents_per_mm < 0.0 && sounding.cents_per_tenth_mm > 0.0);
+        let mut moved = flute.clone();
+        moved.holes[1].position += 0.1;
+        let solved = 1200.0 * (moved.calculate_pitch(f64::NAN) / sounding.frequency).log2();
+        assert!((solved - sounding.cents_per_mm).abs() < 0.05 * solved.abs());
+
+        // The open hole below it hardly matters
+        let vent = quick_sensitivity(&flute, 0, sounding.frequency).unwrap();
+        assert!(vent.cents_per_mm.abs() < 0.2 * sounding.cents_per_mm.abs());
+        assert!(quick_sensitivity(&flute, 2, f64::NAN).is_err());
+    }
+}
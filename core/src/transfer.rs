@@ -0,0 +1,1361 @@
+//! Transfer (ABCD) matrices of the bore. Every tube segment and tone-hole shunt is a 2x2
+//! complex matrix taking pressure and flow at its downstream end to its upstream end, so
+//! the bore from the embouchure to the foot is their product and any run of it can be
+//! multiplied out once and reused. `SpectrumCache` keeps the products either side of one
+//! hole, so dragging that hole only recomputes its own section at each frequency.
+
+use crate::math;
+use crate::physics::{
+    Calibration, DuctGeometry, FluePipe, Flute, InstrumentType, AIR_DENSITY, AIR_VISCOSITY,
+    SPEED_OF_SOUND,
+};
+use num_complex::{Complex32, Complex64};
+use std::f64::consts::PI;
+use std::ops::Mul;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Stands in for a NaN or infinite impedance part: the open-circuit magnitude `with_mouth`
+/// already reports when the admittances cancel
+const LIMIT: f64 = 1e10;
+
+/// `[[a, b], [c, d]]`: (p, U) upstream = M (p, U) downstream. Only the ratio p/U matters
+/// here, so matrices are kept up to a scale factor (segments are divided by cos kL).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Abcd {
+    a: Complex64,
+    b: Complex64,
+    c: Complex64,
+    d: Complex64,
+}
+
+impl Abcd {
+    pub(crate) const IDENTITY: Abcd = Abcd {
+        a: Complex64::new(1.0, 0.0),
+        b: Complex64::new(0.0, 0.0),
+        c: Complex64::new(0.0, 0.0),
+        d: Complex64::new(1.0, 0.0),
+    };
+
+    /// Input impedance with `z_load` terminating the downstream end
+    pub(crate) fn apply(&self, z_load: Complex64) -> Complex64 {
+        (self.a * z_load + self.b) / (self.c * z_load + self.d)
+    }
+}
+
+impl Mul for Abcd {
+    type Output = Abcd;
+
+    /// `self` upstream of `rhs`
+    fn mul(self, rhs: Abcd) -> Abcd {
+        Abcd {
+            a: self.a * rhs.a + self.b * rhs.c,
+            b: self.a * rhs.b + self.b * rhs.d,
+            c: self.c * rhs.a + self.d * rhs.c,
+            d: self.c * rhs.b + self.d * rhs.d,
+        }
+    }
+}
+
+/// Everything the model needs about one frequency
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Wave {
+    omega: f64,
+    /// Lossless wavenumber, for radiation and end corrections
+    real_k: f64,
+    /// With viscothermal loss: k = w/c - j alpha
+    k: Complex64,
+}
+
+impl Wave {
+    const ZERO: Wave = Wave {
+        omega: 0.0,
+        real_k: 0.0,
+        k: Complex64::new(0.0, 0.0),
+    };
+}
+
+/// The frequency-independent parts of a tone hole's shunt impedance, open and closed
+#[derive(Debug, Clone, Copy)]
+struct Chimney {
+    radius: f64,
+    /// Open: inertance of the chimney with Benade's end correction, plus radiation
+    /// resistance `radiation * 0.25 (ka)^2`
+    inertance: f64,
+    radiation: f64,
+    /// Closed: the chimney is a small compliance
+    stiffness: f64,
+}
+
+impl Chimney {
+    fn impedance(&self, wave: &Wave, open: bool) -> Complex64 {
+        if open {
+            let ka = wave.real_k * self.radius;
+            Complex64::new(
+                self.radiation * 0.25 * ka.powi(2),
+                wave.omega * self.inertance,
+            )
+        } else {
+            Complex64::new(0.0, -self.stiffness / wave.omega)
+        }
+    }
+}
+
+/// A side resonator's neck and cavity: neck inertance (with 0.85 a at each end), the
+/// cavity's stiffness and the neck's boundary-layer resistance, l sqrt(2 w rho mu) / (pi a^3)
+#[derive(Debug, Clone, Copy)]
+struct Cavity {
+    inertance: f64,
+    stiffness: f64,
+    /// Resistance over sqrt(w)
+    resistance: f64,
+}
+
+impl Cavity {
+    fn impedance(&self, wave: &Wave) -> Complex64 {
+        Complex64::new(
+            self.resistance * wave.omega.sqrt(),
+            wave.omega * self.inertance - self.stiffness / wave.omega,
+        )
+    }
+}
+
+/// What hangs off the bore at a shunt
+#[derive(Debug, Clone, Copy)]
+enum Branch {
+    Hole(Chimney),
+    Cavity(Cavity),
+}
+
+impl Branch {
+    /// A cavity is the same whatever the fingering, so it ignores `open`
+    fn impedance(&self, wave: &Wave, open: bool) -> Complex64 {
+        match self {
+            Branch::Hole(chimney) => chimney.impedance(wave, open),
+            Branch::Cavity(cavity) => cavity.impedance(wave),
+        }
+    }
+
+    /// `impedance` in single precision, for `Bore::impedance_f32`
+    fn impedance_f32(&self, real_k: f32, omega: f32, open: bool) -> Complex32 {
+        match self {
+            Branch::Hole(chimney) if open => {
+                let ka = real_k * chimney.radius as f32;
+                Complex32::new(
+                    chimney.radiation as f32 * 0.25 * ka.powi(2),
+                    omega * chimney.inertance as f32,
+                )
+            }
+            Branch::Hole(chimney) => Complex32::new(0.0, -chimney.stiffness as f32 / omega),
+            Branch::Cavity(cavity) => Complex32::new(
+                cavity.resistance as f32 * omega.sqrt(),
+                omega * cavity.inertance as f32 - cavity.stiffness as f32 / omega,
+            ),
+        }
+    }
+}
+
+/// A tone hole or side resonator as the walk from the foot meets it
+#[derive(Debug, Clone, Copy)]
+struct Shunt {
+    position: f64,
+    /// Index into `holes`, i.e. the hole's bit in a fingering; None for resonators and
+    /// tuning slots, which no fingering changes
+    index: Option<usize>,
+    /// Always false for resonators and true for tuning slots
+    open: bool,
+    branch: Branch,
+}
+
+/// One design's bore with its holes in a fixed walk order (back to front), reduced to the
+/// constants the model needs. Hole areas, inertances and the characteristic impedance are
+/// worked out once here, so a solve or sweep built on it does only per-frequency arithmetic
+/// and allocates nothing per frequency.
+#[derive(Debug)]
+pub(crate) struct Bore {
+    length: f64,
+    bore_radius: f64,
+    /// Wall perimeter over that of the round bore of equal area; 1 for a round bore
+    perimeter_ratio: f64,
+    /// Characteristic impedance of the bore, rho c / A
+    z_char: Complex64,
+    /// Holes and resonators back to front
+    shunts: Vec<Shunt>,
+    /// Closed stub behind the embouchure, if any
+    cork: Option<f64>,
+    /// Closed far end (stopped organ pipe) instead of an open foot
+    stopped: bool,
+    emb_radius: f64,
+    emb_inertance: f64,
+    emb_radiation: f64,
+    /// `Calibration::open_end_correction` and `loss_factor`
+    end_correction: f64,
+    loss_factor: f64,
+    /// Evaluations whose impedance came out NaN or infinite; see `guard`
+    non_finite: AtomicU32,
+}
+
+impl Flute {
+    /// The model's constants for this design, walking the holes in `order` (back to front).
+    /// Side resonators are slotted into the walk by position.
+    pub(crate) fn bore(&self, order: &[usize]) -> Bore {
+        let bore_area = PI * self.bore_radius.powi(2);
+        let Calibration {
+            open_end_correction,
+            hole_end_correction,
+            embouchure_end_correction,
+            loss_factor,
+        } = self.calibration;
+        let mut shunts: Vec<Shunt> = order
+            .iter()
+            .map(|&i| {
+                let hole = &self.holes[i];
+                let area = PI * hole.radius.powi(2);
+                let t_eff = self.wall_thickness + hole_end_correction * hole.radius;
+                Shunt {
+                    position: hole.position,
+                    index: Some(i),
+                    open: hole.open,
+                    branch: Branch::Hole(Chimney {
+                        radius: hole.radius,
+                        inertance: (AIR_DENSITY * t_eff) / area,
+                        radiation: (AIR_DENSITY * SPEED_OF_SOUND) / area,
+                        stiffness: (AIR_DENSITY * SPEED_OF_SOUND.powi(2)) / (area * t_eff),
+                    }),
+                }
+            })
+            .collect();
+        for resonator in &self.resonators {
+            let a = resonator.neck_radius;
+            let area = PI * a.powi(2);
+            let shunt = Shunt {
+                position: resonator.position,
+                index: None,
+                open: false,
+                branch: Branch::Cavity(Cavity {
+                    inertance: AIR_DENSITY * (resonator.neck_length + 1.7 * a) / area,
+                    stiffness: AIR_DENSITY * SPEED_OF_SOUND.powi(2) / resonator.volume,
+                    resistance: resonator.neck_length * (2.0 * AIR_DENSITY * AIR_VISCOSITY).sqrt()
+                        / (PI * a.powi(3)),
+                }),
+            };
+            // After every shunt further down the bore, so ties keep the holes first
+            let rank = shunts.partition_point(|s| s.position >= shunt.position);
+            shunts.insert(rank, shunt);
+        }
+        // An organ pipe's tuning slot: an always-open hole of the same area at its middle
+        if let InstrumentType::OrganPipe(FluePipe {
+            tuning_slot: Some(slot),
+            ..
+        }) = &self.instrument
+        {
+            let r_eq = (slot.length * slot.width / PI).sqrt();
+            let area = PI * r_eq.powi(2);
+            let t_eff = self.wall_thickness + hole_end_correction * r_eq;
+            shunts.insert(
+                0,
+                Shunt {
+                    position: self.length - 0.5 * slot.length,
+                    index: None,
+                    open: true,
+                    branch: Branch::Hole(Chimney {
+                        radius: r_eq,
+                        inertance: (AIR_DENSITY * t_eff) / area,
+                        radiation: (AIR_DENSITY * SPEED_OF_SOUND) / area,
+                        stiffness: (AIR_DENSITY * SPEED_OF_SOUND.powi(2)) / (area * t_eff),
+                    }),
+                },
+            );
+        }
+
+        // End-blown flutes have no cork cavity; the blowing end is the open bore itself
+        // (unflanged end correction, 0.61 r by default)
+        let (emb_radius, emb_t_eff) = match &self.instrument {
+            InstrumentType::Transverse => (
+                self.embouchure_hole_radius,
+                self.embouchure_chimney + embouchure_end_correction * self.embouchure_hole_radius,
+            ),
+            InstrumentType::Notch(_) => (self.bore_radius, open_end_correction * self.bore_radius),
+            // The window acts as a mouth hole of the same area through the wall
+            InstrumentType::Duct(duct) => {
+                duct_mouth(duct, self.wall_thickness, hole_end_correction)
+            }
+            InstrumentType::OrganPipe(pipe) => {
+                duct_mouth(&pipe.duct(), self.wall_thickness, hole_end_correction)
+            }
+        };
+        let emb_area = PI * emb_radius.powi(2);
+        Bore {
+            length: self.length,
+            bore_radius: self.bore_radius,
+            perimeter_ratio: oval_perimeter_ratio(self.bore_ovality),
+            z_char: Complex64::new((AIR_DENSITY * SPEED_OF_SOUND) / bore_area, 0.0),
+            shunts,
+            cork: self.instrument.has_cork().then_some(self.cork_position),
+            stopped: self.instrument.is_stopped(),
+            emb_radius,
+            emb_inertance: (AIR_DENSITY * emb_t_eff) / emb_area,
+            emb_radiation: (AIR_DENSITY * SPEED_OF_SOUND) / emb_area,
+            end_correction: open_end_correction,
+            loss_factor,
+            non_finite: AtomicU32::new(0),
+        }
+    }
+}
+
+/// Refinements of `Bore::fundamental_estimate`
+const ESTIMATE_PASSES: usize = 3;
+
+/// Perimeter of an ellipse `ovality` percent out of round over the circle of the same area,
+/// from Ramanujan's approximation. Plane waves only see the area, so this is all an oval
+/// bore changes: more wall per unit of air, so more boundary-layer loss.
+pub(crate) fn oval_perimeter_ratio(ovality: f64) -> f64 {
+    if ovality <= 0.0 {
+        return 1.0;
+    }
+    // Semi-axes 1 + e/2 and 1 - e/2 about the mean, scaled to unit area
+    let e = ovality / 100.0;
+    let scale = (1.0 - e * e / 4.0).sqrt();
+    let (a, b) = ((1.0 + e / 2.0) / scale, (1.0 - e / 2.0) / scale);
+    let perimeter = PI * (3.0 * (a + b) - ((3.0 * a + b) * (a + 3.0 * b)).sqrt());
+    perimeter / (2.0 * PI)
+}
+
+/// Radius and effective length of the mouth hole a duct's window stands in for
+fn duct_mouth(duct: &DuctGeometry, wall_thickness: f64, end_correction: f64) -> (f64, f64) {
+    let r_eq = (duct.window_length * duct.windway_width / PI).sqrt();
+    (r_eq, wall_thickness + end_correction * r_eq)
+}
+
+impl Bore {
+    pub(crate) fn wave(&self, freq: f64) -> Wave {
+        let omega = 2.0 * PI * freq;
+        // Viscothermal loss for wide tubes, alpha ~ 1.2e-5 sqrt(f) / r. The round bore
+        // leaves out the boundary-layer slowing that goes with it; the extra wall of an
+        // oval bore adds both its loss and that slowing, k = w/c + (1 - j) alpha, so what
+        // changes from round is the oval's own doing.
+        let round = self.loss_factor * (1.2e-5 * freq.sqrt()) / self.bore_radius;
+        let extra = (self.perimeter_ratio - 1.0) * round;
+        let real_k = omega / SPEED_OF_SOUND;
+        Wave {
+            omega,
+            real_k,
+            k: Complex64::new(real_k + extra, -(round + extra)),
+        }
+    }
+
+    /// A length of plain bore; nothing for zero or negative lengths (holes past the foot or
+    /// on top of each other). Z_in = Zc (Z_L + j Zc tan kL) / (Zc + j Z_L tan kL), with
+    /// `math::tan` in its exponential form so long lossy segments at high frequencies stay
+    /// finite and accurate next to the poles of tan kL.
+    pub(crate) fn segment(&self, wave: &Wave, len: f64) -> Abcd {
+        if len <= 0.0 {
+            return Abcd::IDENTITY;
+        }
+        let j_tan = Complex64::i() * math::tan(wave.k * len);
+        Abcd {
+            a: Complex64::new(1.0, 0.0),
+            b: self.z_char * j_tan,
+            c: j_tan / self.z_char,
+            d: Complex64::new(1.0, 0.0),
+        }
+    }
+
+    /// Radiation from the unflanged foot: Z_c (0.25 (ka)^2 + j 0.61 ka), with the end
+    /// correction from the calibration. A stopped end is rigid, `LIMIT`.
+    pub(crate) fn foot_load(&self, wave: &Wave) -> Complex64 {
+        if self.stopped {
+            return Complex64::new(LIMIT, 0.0);
+        }
+        let ka = wave.real_k * self.bore_radius;
+        self.z_char * Complex64::new(0.25 * ka.powi(2), self.end_correction * ka)
+    }
+
+    /// Tone hole `rank` of the walk and the `below` cm of bore between it and the next hole
+    /// down (or the foot), as one step of the walk from the foot to the embouchure
+    fn section(&self, wave: &Wave, rank: usize, below: f64) -> Abcd {
+        let hole = &self.shunts[rank];
+        shunt(hole.branch.impedance(wave, hole.open)) * self.segment(wave, below)
+    }
+
+    /// Where the walk stands after hole `rank`: its position, or the foot before any hole
+    fn position(&self, rank: Option<usize>) -> f64 {
+        rank.map_or(self.length, |rank| self.shunts[rank].position)
+    }
+
+    /// Length of bore between hole `rank` and the previous hole of the walk (or the foot)
+    fn below(&self, rank: usize) -> f64 {
+        self.position(rank.checked_sub(1)) - self.shunts[rank].position
+    }
+
+    /// Hole `rank` with the bore below it down to the previous hole of the walk
+    fn section_at(&self, wave: &Wave, rank: usize) -> Abcd {
+        self.section(wave, rank, self.below(rank))
+    }
+
+    /// Everything from the front-most hole (or the foot) up to the embouchure at x = 0
+    fn top_segment(&self, wave: &Wave) -> Abcd {
+        self.segment(wave, self.position(self.shunts.len().checked_sub(1)))
+    }
+
+    /// Input impedance at the embouchure: the product of transfer matrices from the foot up
+    pub(crate) fn impedance(&self, freq: f64) -> Complex64 {
+        let wave = self.wave(freq);
+        let mut bore = Abcd::IDENTITY;
+        for rank in 0..self.shunts.len() {
+            bore = self.section_at(&wave, rank) * bore;
+        }
+        bore = self.top_segment(&wave) * bore;
+        self.guard(self.at_embouchure(&wave, bore.apply(self.foot_load(&wave))))
+    }
+
+    /// `z` itself when finite. Otherwise (a zero-size hole, a frequency of zero, overflow
+    /// deep in a long lossy bore) infinities become +/-`LIMIT` and NaN becomes `LIMIT`, so
+    /// the solvers keep comparing numbers, and the evaluation is counted in `non_finite`.
+    fn guard(&self, z: Complex64) -> Complex64 {
+        if z.is_finite() {
+            return z;
+        }
+        self.non_finite.fetch_add(1, Ordering::Relaxed);
+        let limit = |x: f64| {
+            if x.is_nan() {
+                LIMIT
+            } else {
+                x.clamp(-LIMIT, LIMIT)
+            }
+        };
+        Complex64::new(limit(z.re), limit(z.im))
+    }
+
+    /// How many evaluations on this bore so far needed `guard`'s stand-in
+    pub(crate) fn non_finite(&self) -> u32 {
+        self.non_finite.load(Ordering::Relaxed)
+    }
+
+    /// What the jet sees at x = 0: the bore in parallel with the cork cavity (transverse
+    /// flutes) and the embouchure hole, window or open end. The playing frequency sits
+    /// where the summed admittance is real, i.e. at a minimum of this impedance.
+    pub(crate) fn at_embouchure(&self, wave: &Wave, z_bore: Complex64) -> Complex64 {
+        with_mouth(self.mouth(wave), z_bore)
+    }
+
+    /// Input impedance of `len` cm of bore closed at the far end: -j Zc cot(kL)
+    fn stub(&self, wave: &Wave, len: f64) -> Complex64 {
+        -Complex64::i() * self.z_char / math::tan(wave.k * len)
+    }
+
+    /// The bore stopped at the far end like the cork cavity and radiating from the open
+    /// near end as the foot does, as a pan pipe: the series impedance around that loop,
+    /// whose imaginary part crosses zero upwards at each resonance. Holes are ignored.
+    pub(crate) fn stopped_impedance(&self, freq: f64) -> Complex64 {
+        let wave = self.wave(freq);
+        self.guard(self.stub(&wave, self.length) + self.foot_load(&wave))
+    }
+
+    /// Lowest resonance of the current fingering from a lossless lumped walk up the bore,
+    /// where `calculate_pitch` starts its search. Below each open hole the bore is an
+    /// inertance in parallel with the hole's, so the air column ends short of the foot by an
+    /// amount that depends on the hole's size and on every open hole further down (the
+    /// lattice correction). A closed hole adds its chimney volume, weighted by the pressure
+    /// it sees, so the standing wave is refined a few times from the plain open pipe. The
+    /// cork cavity's volume is weighted the same way over its depth past the embouchure;
+    /// it sits by the pressure node, so it lengthens the column only a little.
+    pub(crate) fn fundamental_estimate(&self) -> f64 {
+        let area = PI * self.bore_radius.powi(2);
+        let ends = if self.stopped { 4.0 } else { 2.0 };
+        let mut frequency = SPEED_OF_SOUND / (ends * self.length);
+        for _ in 0..ESTIMATE_PASSES {
+            let k = 2.0 * PI * frequency / SPEED_OF_SOUND;
+            let mut stopped = self.stopped;
+            let mut end = if stopped {
+                self.length
+            } else {
+                self.length + self.end_correction * self.bore_radius
+            };
+            if let Some(cork) = self.cork.filter(|&cork| cork > 0.0) {
+                // Mean of sin^2(kx) over the cavity, x from -cork to 0
+                let kc = k * cork;
+                let pressure = 0.5 - math::sin(2.0 * kc) / (4.0 * kc);
+                end += 2.0 * pressure * cork;
+            }
+            for shunt in &self.shunts {
+                let Branch::Hole(chimney) = &shunt.branch else {
+                    continue;
+                };
+                let hole = chimney.inertance * area / AIR_DENSITY;
+                // A zero-size hole doesn't vent anything
+                if shunt.open && hole.is_finite() {
+                    end = if stopped {
+                        shunt.position + hole
+                    } else {
+                        let below = end - shunt.position;
+                        shunt.position + below * hole / (below + hole)
+                    };
+                    stopped = false;
+                } else {
+                    let volume = AIR_DENSITY * SPEED_OF_SOUND.powi(2) / chimney.stiffness;
+                    let pressure = math::sin(k * shunt.position);
+                    end += 2.0 * pressure.powi(2) * volume / area;
+                }
+            }
+            let ends = if stopped { 4.0 } else { 2.0 };
+            frequency = SPEED_OF_SOUND / (ends * end);
+        }
+        frequency
+    }
+
+    /// Admittances of the cork cavity and the embouchure, which sit beside the bore at x = 0
+    /// and depend on the frequency alone
+    fn mouth(&self, wave: &Wave) -> (Complex64, Complex64) {
+        let y_cork = match self.cork {
+            Some(cork) => admittance(self.stub(wave, cork)),
+            None => Complex64::new(0.0, 0.0),
+        };
+
+        // Inertance plus radiation resistance
+        let ka_emb = wave.real_k * self.emb_radius;
+        let emb_resistance = self.emb_radiation * 0.25 * ka_emb.powi(2);
+        let y_emb = admittance(Complex64::new(
+            emb_resistance,
+            wave.omega * self.emb_inertance,
+        ));
+        (y_cork, y_emb)
+    }
+}
+
+fn admittance(z: Complex64) -> Complex64 {
+    if math::norm(z) < 1e-10 {
+        Complex64::new(1e10, 0.0)
+    } else {
+        1.0 / z
+    }
+}
+
+/// The bore's input impedance `z_bore` in parallel with the `mouth` admittances
+fn with_mouth((y_cork, y_emb): (Complex64, Complex64), z_bore: Complex64) -> Complex64 {
+    let y_total = admittance(z_bore) + y_cork + y_emb;
+    if math::norm(y_total) < 1e-10 {
+        Complex64::new(1e10, 1e10)
+    } else {
+        1.0 / y_total
+    }
+}
+
+/// An impedance in parallel with the bore
+fn shunt(z: Complex64) -> Abcd {
+    if math::norm(z) < 1e-10 {
+        // A short: the bore above sees zero whatever hangs below
+        return Abcd {
+            a: Complex64::new(0.0, 0.0),
+            b: Complex64::new(0.0, 0.0),
+            c: Complex64::new(1.0, 0.0),
+            d: Complex64::new(1.0, 0.0),
+        };
+    }
+    Abcd {
+        c: 1.0 / z,
+        ..Abcd::IDENTITY
+    }
+}
+
+/// `Abcd` in single precision, for `Bore::impedance_f32`
+#[derive(Debug, Clone, Copy)]
+struct Abcd32 {
+    a: Complex32,
+    b: Complex32,
+    c: Complex32,
+    d: Complex32,
+}
+
+impl Abcd32 {
+    const IDENTITY: Abcd32 = Abcd32 {
+        a: Complex32::new(1.0, 0.0),
+        b: Complex32::new(0.0, 0.0),
+        c: Complex32::new(0.0, 0.0),
+        d: Complex32::new(1.0, 0.0),
+    };
+
+    fn apply(&self, z_load: Complex32) -> Complex32 {
+        (self.a * z_load + self.b) / (self.c * z_load + self.d)
+    }
+}
+
+impl Mul for Abcd32 {
+    type Output = Abcd32;
+
+    fn mul(self, rhs: Abcd32) -> Abcd32 {
+        Abcd32 {
+            a: self.a * rhs.a + self.b * rhs.c,
+            b: self.a * rhs.b + self.b * rhs.d,
+            c: self.c * rhs.a + self.d * rhs.c,
+            d: self.c * rhs.b + self.d * rhs.d,
+        }
+    }
+}
+
+impl Bore {
+    /// `impedance` with the per-frequency work in single precision: the same model, a
+    /// fraction of a cent off in pitch and much cheaper where f64 maths is slow. For live
+    /// previews only; anything reported or optimised uses `impedance`.
+    pub(crate) fn impedance_f32(&self, freq: f64) -> Complex64 {
+        use std::f32::consts::PI;
+        let freq = freq as f32;
+        let omega = 2.0 * PI * freq;
+        let round = self.loss_factor as f32 * (1.2e-5 * freq.sqrt()) / self.bore_radius as f32;
+        let extra = (self.perimeter_ratio as f32 - 1.0) * round;
+        let real_k = omega / SPEED_OF_SOUND as f32;
+        let k = Complex32::new(real_k + extra, -(round + extra));
+        let z_char = Complex32::new(self.z_char.re as f32, 0.0);
+        let i = Complex32::i();
+
+        let segment = |len: f64| {
+            if len <= 0.0 {
+                return Abcd32::IDENTITY;
+            }
+            let j_tan = i * math::tan_f32(k * len as f32);
+            Abcd32 {
+                a: Complex32::new(1.0, 0.0),
+                b: z_char * j_tan,
+                c: j_tan / z_char,
+                d: Complex32::new(1.0, 0.0),
+            }
+        };
+        let admittance = |z: Complex32| {
+            if math::norm_f32(z) < 1e-10 {
+                Complex32::new(1e10, 0.0)
+            } else {
+                1.0 / z
+            }
+        };
+
+        let mut bore = Abcd32::IDENTITY;
+        for (rank, hole) in self.shunts.iter().enumerate() {
+            let z = hole.branch.impedance_f32(real_k, omega, hole.open);
+            let shunt = if math::norm_f32(z) < 1e-10 {
+                Abcd32 {
+                    a: Complex32::new(0.0, 0.0),
+                    b: Complex32::new(0.0, 0.0),
+                    c: Complex32::new(1.0, 0.0),
+                    d: Complex32::new(1.0, 0.0),
+                }
+            } else {
+                Abcd32 {
+                    c: 1.0 / z,
+                    ..Abcd32::IDENTITY
+                }
+            };
+            let below = self.position(rank.checked_sub(1)) - hole.position;
+            bore = shunt * segment(below) * bore;
+        }
+        bore = segment(self.position(self.shunts.len().checked_sub(1))) * bore;
+        let ka = real_k * self.bore_radius as f32;
+        let foot = if self.stopped {
+            Complex32::new(LIMIT as f32, 0.0)
+        } else {
+            z_char * Complex32::new(0.25 * ka.powi(2), self.end_correction as f32 * ka)
+        };
+        let z_bore = bore.apply(foot);
+
+        let y_cork = match self.cork {
+            Some(cork) => admittance(-i * z_char / math::tan_f32(k * cork as f32)),
+            None => Complex32::new(0.0, 0.0),
+        };
+        let ka_emb = real_k * self.emb_radius as f32;
+        let y_emb = admittance(Complex32::new(
+            self.emb_radiation as f32 * 0.25 * ka_emb.powi(2),
+            omega * self.emb_inertance as f32,
+        ));
+        let y_total = admittance(z_bore) + y_cork + y_emb;
+        let z = if math::norm_f32(y_total) < 1e-10 {
+            Complex32::new(1e10, 1e10)
+        } else {
+            1.0 / y_total
+        };
+        self.guard(Complex64::new(z.re as f64, z.im as f64))
+    }
+}
+
+/// Frequencies evaluated together by `Bore::sweep`
+const BLOCK: usize = 64;
+
+/// One complex value per frequency of a block, real and imaginary parts in separate arrays
+/// so lane-wise arithmetic compiles to SIMD (SSE2/AVX, NEON, wasm simd128)
+#[derive(Clone, Copy)]
+struct Lanes {
+    re: [f64; BLOCK],
+    im: [f64; BLOCK],
+}
+
+impl Lanes {
+    const fn splat(z: Complex64) -> Lanes {
+        Lanes {
+            re: [z.re; BLOCK],
+            im: [z.im; BLOCK],
+        }
+    }
+
+    fn get(&self, i: usize) -> Complex64 {
+        Complex64::new(self.re[i], self.im[i])
+    }
+
+    fn set(&mut self, i: usize, z: Complex64) {
+        self.re[i] = z.re;
+        self.im[i] = z.im;
+    }
+}
+
+/// A transfer matrix per frequency of a block
+#[derive(Clone, Copy)]
+struct AbcdLanes {
+    a: Lanes,
+    b: Lanes,
+    c: Lanes,
+    d: Lanes,
+}
+
+impl AbcdLanes {
+    const IDENTITY: AbcdLanes = AbcdLanes {
+        a: Lanes::splat(Abcd::IDENTITY.a),
+        b: Lanes::splat(Abcd::IDENTITY.b),
+        c: Lanes::splat(Abcd::IDENTITY.c),
+        d: Lanes::splat(Abcd::IDENTITY.d),
+    };
+
+    fn get(&self, i: usize) -> Abcd {
+        Abcd {
+            a: self.a.get(i),
+            b: self.b.get(i),
+            c: self.c.get(i),
+            d: self.d.get(i),
+        }
+    }
+
+    fn set(&mut self, i: usize, m: Abcd) {
+        self.a.set(i, m.a);
+        self.b.set(i, m.b);
+        self.c.set(i, m.c);
+        self.d.set(i, m.d);
+    }
+
+    /// `self = upstream * self` in the first `n` lanes, operation for operation as `Abcd`'s
+    /// `Mul` so the results match the scalar path bit for bit
+    fn premultiply(&mut self, upstream: &AbcdLanes, n: usize) {
+        let (u, m) = (upstream, &mut *self);
+        for i in 0..n {
+            let mul = |x: &Lanes, y: &Lanes| {
+                (
+                    x.re[i] * y.re[i] - x.im[i] * y.im[i],
+                    x.re[i] * y.im[i] + x.im[i] * y.re[i],
+                )
+            };
+            let (ab_re, ab_im) = {
+                let (p, q) = (mul(&u.a, &m.a), mul(&u.b, &m.c));
+                (p.0 + q.0, p.1 + q.1)
+            };
+            let (bb_re, bb_im) = {
+                let (p, q) = (mul(&u.a, &m.b), mul(&u.b, &m.d));
+                (p.0 + q.0, p.1 + q.1)
+            };
+            let (cb_re, cb_im) = {
+                let (p, q) = (mul(&u.c, &m.a), mul(&u.d, &m.c));
+                (p.0 + q.0, p.1 + q.1)
+            };
+            let (db_re, db_im) = {
+                let (p, q) = (mul(&u.c, &m.b), mul(&u.d, &m.d));
+                (p.0 + q.0, p.1 + q.1)
+            };
+            m.a.re[i] = ab_re;
+            m.a.im[i] = ab_im;
+            m.b.re[i] = bb_re;
+            m.b.im[i] = bb_im;
+            m.c.re[i] = cb_re;
+            m.c.im[i] = cb_im;
+            m.d.re[i] = db_re;
+            m.d.im[i] = db_im;
+        }
+    }
+}
+
+impl Bore {
+    /// Input impedance at each of `freqs`: the same values as one `impedance` call per
+    /// frequency, bit for bit. Frequencies go through in blocks, hole by hole, so the matrix
+    /// products run lane-wise over the block and only the per-frequency element setup (tan,
+    /// hypot) stays scalar. With the `parallel` feature the blocks are spread over all cores.
+    pub(crate) fn sweep(&self, freqs: &[f64]) -> Vec<Complex64> {
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            freqs
+                .par_chunks(BLOCK)
+                .flat_map_iter(|block| self.sweep_blocks(block))
+                .collect()
+        }
+        #[cfg(not(feature = "parallel"))]
+        self.sweep_blocks(freqs)
+    }
+
+    fn sweep_blocks(&self, freqs: &[f64]) -> Vec<Complex64> {
+        let mut out = Vec::with_capacity(freqs.len());
+        let mut element = AbcdLanes::IDENTITY;
+        let mut waves = [Wave::ZERO; BLOCK];
+        for block in freqs.chunks(BLOCK) {
+            let n = block.len();
+            let waves = &mut waves[..n];
+            for (wave, &f) in waves.iter_mut().zip(block) {
+                *wave = self.wave(f);
+            }
+            let mut bore = AbcdLanes::IDENTITY;
+            for rank in 0..self.shunts.len() {
+                for (i, wave) in waves.iter().enumerate() {
+                    element.set(i, self.section_at(wave, rank));
+                }
+                bore.premultiply(&element, n);
+            }
+            for (i, wave) in waves.iter().enumerate() {
+                element.set(i, self.top_segment(wave));
+            }
+            bore.premultiply(&element, n);
+            for (i, wave) in waves.iter().enumerate() {
+                let z_bore = bore.get(i).apply(self.foot_load(wave));
+                out.push(self.guard(self.at_embouchure(wave, z_bore)));
+            }
+        }
+        out
+    }
+}
+
+impl Bore {
+    /// Input impedance of each of `fingerings` (bit i = `holes[i]` open) at each of `freqs`,
+    /// one `Vec` per fingering. Everything that does not depend on which holes are open (the
+    /// wavenumber and losses, every bore segment, both states of every hole, the foot and
+    /// mouth terms) is worked out once per frequency and shared by all the fingerings, so
+    /// a spectrum per row of a fingering chart costs little more than one. Each value is
+    /// bit for bit what `impedance` gives on the fingered design.
+    pub(crate) fn fingering_sweep(&self, freqs: &[f64], fingerings: &[u32]) -> Vec<Vec<Complex64>> {
+        let mut out = vec![Vec::with_capacity(freqs.len()); fingerings.len()];
+        // Per rank: the segment below the hole, and the hole's shunt closed and open
+        let mut terms: Vec<(Abcd, [Abcd; 2])> = Vec::with_capacity(self.shunts.len());
+        for &freq in freqs {
+            let wave = self.wave(freq);
+            terms.clear();
+            terms.extend(self.shunts.iter().enumerate().map(|(rank, hole)| {
+                let segment = self.segment(&wave, self.below(rank));
+                let closed = shunt(hole.branch.impedance(&wave, false));
+                let open = shunt(hole.branch.impedance(&wave, true));
+                (segment, [closed, open])
+            }));
+            let top = self.top_segment(&wave);
+            let foot = self.foot_load(&wave);
+            let mouth = self.mouth(&wave);
+            for (&fingering, out) in fingerings.iter().zip(&mut out) {
+                let mut bore = Abcd::IDENTITY;
+                for (hole, (segment, shunts)) in self.shunts.iter().zip(&terms) {
+                    let open = match hole.index {
+                        Some(i) => i < 32 && fingering & (1 << i) != 0,
+                        None => hole.open,
+                    };
+                    bore = (shunts[open as usize] * *segment) * bore;
+                }
+                bore = top * bore;
+                out.push(self.guard(with_mouth(mouth, bore.apply(foot))));
+            }
+        }
+        out
+    }
+}
+
+/// Pressure and volume flow inside the bore at one frequency, for a unit of flow leaving
+/// the foot. Only ratios between points are meaningful; callers normalise.
+#[derive(Debug, Clone)]
+pub(crate) struct Field {
+    wave: Wave,
+    z_char: Complex64,
+    /// The foot, then each hole and resonator of the walk (back to front): where it is, the pressure
+    /// there and the flow heading down the bore just above it
+    stations: Vec<(f64, Complex64, Complex64)>,
+    /// Flow out through each open hole, as (index into `holes`, flow), back to front
+    hole_flows: Vec<(usize, Complex64)>,
+    /// Flow out through the embouchure hole, window or open blowing end: everything the
+    /// bore and the cork cavity draw in at x = 0, which the jet supplies there
+    mouth_flow: Complex64,
+}
+
+impl Bore {
+    /// The `Field` at `freq`, walked up from the foot with the exact (unscaled) segment
+    /// matrices: p1 = cos kd (p0 + j Zc tan kd U0), U1 = cos kd (U0 + j tan kd p0 / Zc)
+    pub(crate) fn field(&self, freq: f64) -> Field {
+        let wave = self.wave(freq);
+        let mut p = self.foot_load(&wave);
+        let mut u = Complex64::new(1.0, 0.0);
+        let mut stations = vec![(self.length, p, u)];
+        let mut hole_flows = Vec::new();
+        for (rank, hole) in self.shunts.iter().enumerate() {
+            (p, u) = along(&wave, self.z_char, p, u, self.below(rank));
+            let out = p / hole.branch.impedance(&wave, hole.open);
+            if let (true, Some(index)) = (hole.open, hole.index) {
+                hole_flows.push((index, out));
+            }
+            u += out;
+            stations.push((hole.position, p, u));
+        }
+        let top = self.position(self.shunts.len().checked_sub(1));
+        let (p_mouth, u_mouth) = along(&wave, self.z_char, p, u, top);
+        let (y_cork, _) = self.mouth(&wave);
+        Field {
+            wave,
+            z_char: self.z_char,
+            stations,
+            hole_flows,
+            mouth_flow: -(u_mouth + p_mouth * y_cork),
+        }
+    }
+}
+
+impl Field {
+    /// Pressure and downward flow at `x` cm from the embouchure, from the nearest station
+    /// below it
+    pub(crate) fn at(&self, x: f64) -> (Complex64, Complex64) {
+        let &(position, p, u) = self
+            .stations
+            .iter()
+            .rev()
+            .find(|station| station.0 >= x)
+            .unwrap_or(&self.stations[0]);
+        along(&self.wave, self.z_char, p, u, position - x)
+    }
+
+    /// Flow out of the foot: 1, the reference everything else is relative to
+    pub(crate) fn foot_flow(&self) -> Complex64 {
+        self.stations[0].2
+    }
+
+    pub(crate) fn hole_flows(&self) -> &[(usize, Complex64)] {
+        &self.hole_flows
+    }
+
+    pub(crate) fn mouth_flow(&self) -> Complex64 {
+        self.mouth_flow
+    }
+}
+
+/// What each part of the air column adds to the input admittance at one frequency. Every
+/// term is the volume flow that part draws over the pressure at the embouchure, so together
+/// they sum to the admittance the solvers look for a zero of.
+#[derive(Debug, Clone)]
+pub(crate) struct Admittances {
+    pub(crate) embouchure: Complex64,
+    pub(crate) cork: Option<Complex64>,
+    /// Flow out of the foot, or through the stopper of a stopped pipe
+    pub(crate) foot: Complex64,
+    /// Each length of bore between stations, as (top, bottom, admittance), back to front
+    pub(crate) segments: Vec<(f64, f64, Complex64)>,
+    /// Each hole, resonator and tuning slot of the walk, as (position, index into `holes`,
+    /// open, admittance), back to front
+    pub(crate) shunts: Vec<(f64, Option<usize>, bool, Complex64)>,
+}
+
+impl Bore {
+    /// The `Admittances` at `freq`, from the same walk up the bore as `field`: the flow
+    /// each segment and branch adds on the way up, divided by the pressure at the top
+    pub(crate) fn admittances(&self, freq: f64) -> Admittances {
+        let wave = self.wave(freq);
+        let mut p = self.foot_load(&wave);
+        let mut u = Complex64::new(1.0, 0.0);
+        let mut segments = Vec::with_capacity(self.shunts.len() + 1);
+        let mut shunts = Vec::with_capacity(self.shunts.len());
+        let mut bottom = self.length;
+        for (rank, hole) in self.shunts.iter().enumerate() {
+            let before = u;
+            (p, u) = along(&wave, self.z_char, p, u, self.below(rank));
+            segments.push((hole.position, bottom, u - before));
+            let out = p / hole.branch.impedance(&wave, hole.open);
+            shunts.push((hole.position, hole.index, hole.open, out));
+            u += out;
+            bottom = hole.position;
+        }
+        let top = self.position(self.shunts.len().checked_sub(1));
+        let (p_mouth, u_mouth) = along(&wave, self.z_char, p, u, top);
+        segments.push((0.0, bottom, u_mouth - u));
+        let scale = |(a, b, flow): (f64, f64, Complex64)| (a, b, flow / p_mouth);
+        let (y_cork, y_emb) = self.mouth(&wave);
+        Admittances {
+            embouchure: y_emb,
+            cork: self.cork.map(|_| y_cork),
+            foot: 1.0 / p_mouth,
+            segments: segments.into_iter().map(scale).collect(),
+            shunts: shunts
+                .into_iter()
+                .map(|(x, index, open, flow)| (x, index, open, flow / p_mouth))
+                .collect(),
+        }
+    }
+}
+
+/// Pressure and flow `d` cm further up the bore; nothing for zero or negative `d`, as in
+/// `Bore::segment`
+fn along(
+    wave: &Wave,
+    z_char: Complex64,
+    p: Complex64,
+    u: Complex64,
+    d: f64,
+) -> (Complex64, Complex64) {
+    if d <= 0.0 {
+        return (p, u);
+    }
+    let kd = wave.k * d;
+    let (cos, j_tan) = (math::complex_cos(kd), Complex64::i() * math::tan(kd));
+    (
+        cos * (p + z_char * j_tan * u),
+        cos * (u + j_tan / z_char * p),
+    )
+}
+
+/// Products either side of one hole, per frequency of the cached grid
+#[derive(Debug, Clone)]
+struct Split {
+    /// Index into `holes`
+    hole: usize,
+    /// The hole's place in the back-to-front walk
+    rank: usize,
+    /// From the embouchure down to (and including) the next hole up's shunt
+    upstream: Vec<Abcd>,
+    /// Looking down the bore from the next hole down (or the foot)
+    downstream: Vec<Complex64>,
+}
+
+/// `impedance_spectrum` over a fixed grid for a design under interactive editing. When the
+/// only change since the last call is to one hole, and it has not moved past a neighbour,
+/// the bore above and below that hole is multiplied out once and reused, so further edits
+/// to the same hole cost one section per frequency instead of the whole bore. Anything
+/// else recomputes in full. The cache checks the design itself; no invalidation needed.
+#[derive(Debug, Clone, Default)]
+pub struct SpectrumCache {
+    grid: Option<(u64, u64, usize)>,
+    /// The design and its back-to-front order as of the last call
+    last: Option<(Flute, Vec<usize>)>,
+    split: Option<Split>,
+}
+
+impl SpectrumCache {
+    /// Same (freq, |Z|, phase) triples as `Flute::impedance_spectrum`, to rounding
+    pub fn spectrum(&mut self, flute: &Flute, f0: f64, f1: f64, n: usize) -> Vec<f64> {
+        trace_span!(DEBUG, "spectrum_cache", n, split = self.split.is_some());
+        let grid = (f0.to_bits(), f1.to_bits(), n);
+        let order = flute.hole_order_back_to_front().into_owned();
+        let changed = match &self.last {
+            Some((last, last_order))
+                if self.grid == Some(grid) && *last_order == order && same_tube(last, flute) =>
+            {
+                Some(changed_holes(last, flute))
+            }
+            _ => None,
+        };
+        // Resonators and tuning slots sit between the holes in the walk, so a hole's rank
+        // isn't its place in `order`; such designs always recompute in full
+        let extra_shunts = !flute.resonators.is_empty()
+            || matches!(
+                &flute.instrument,
+                InstrumentType::OrganPipe(FluePipe {
+                    tuning_slot: Some(_),
+                    ..
+                })
+            );
+        let hole = match changed.as_deref() {
+            _ if extra_shunts => None,
+            Some([]) => self.split.as_ref().map(|split| split.hole),
+            Some(&[hole]) => Some(hole),
+            _ => None,
+        };
+        if hole.is_none() || self.split.as_ref().map(|split| split.hole) != hole {
+            self.split = None;
+        }
+        self.grid = Some(grid);
+        self.last = Some((flute.clone(), order));
+        let order = &self.last.as_ref().expect("just set").1;
+
+        let step = if n > 1 {
+            (f1 - f0) / (n - 1) as f64
+        } else {
+            0.0
+        };
+        let freqs = (0..n).map(|i| f0 + step * i as f64);
+        if let Some(hole) = hole {
+            let bore = flute.bore(order);
+            let split = self
+                .split
+                .get_or_insert_with(|| split_at(&bore, order, hole, freqs.clone()));
+            let mut out = Vec::with_capacity(3 * n);
+            for (i, freq) in freqs.enumerate() {
+                let z = split_impedance(&bore, split, i, freq);
+                out.extend_from_slice(&[freq, math::norm(z), math::arg(z)]);
+            }
+            out
+        } else {
+            flute.impedance_spectrum(f0, f1, n)
+        }
+    }
+}
+
+/// Everything but the holes
+fn same_tube(a: &Flute, b: &Flute) -> bool {
+    a.length == b.length
+        && a.bore_radius == b.bore_radius
+        && a.wall_thickness == b.wall_thickness
+        && a.cork_position == b.cork_position
+        && a.embouchure_hole_radius == b.embouchure_hole_radius
+        && a.embouchure_chimney == b.embouchure_chimney
+        && a.embouchure_oval == b.embouchure_oval
+        && a.instrument == b.instrument
+        && a.bore_ovality == b.bore_ovality
+        && a.resonators == b.resonators
+        && a.calibration == b.calibration
+}
+
+fn changed_holes(a: &Flute, b: &Flute) -> Vec<usize> {
+    if a.holes.len() != b.holes.len() {
+        return (0..b.holes.len()).collect();
+    }
+    (0..b.holes.len())
+        .filter(|&i| a.holes[i] != b.holes[i])
+        .collect()
+}
+
+fn split_at(bore: &Bore, order: &[usize], hole: usize, freqs: impl Iterator<Item = f64>) -> Split {
+    let rank = order
+        .iter()
+        .position(|&i| i == hole)
+        .expect("hole is in the order");
+    let mut upstream = Vec::new();
+    let mut downstream = Vec::new();
+    for freq in freqs {
+        let wave = bore.wave(freq);
+        let mut z = bore.foot_load(&wave);
+        for below in 0..rank {
+            z = bore.section_at(&wave, below).apply(z);
+        }
+        downstream.push(z);
+
+        // The segment just above the edited hole changes with it, so stop at the shunt
+        let mut up = Abcd::IDENTITY;
+        if rank + 1 < order.len() {
+            up = bore.section(&wave, rank + 1, 0.0);
+            for above in rank + 2..order.len() {
+                up = bore.section_at(&wave, above) * up;
+            }
+            up = bore.top_segment(&wave) * up;
+        }
+        upstream.push(up);
+    }
+    Split {
+        hole,
+        rank,
+        upstream,
+        downstream,
+    }
+}
+
+/// Input impedance at grid point `i`, recomputing only the split hole's section
+fn split_impedance(bore: &Bore, split: &Split, i: usize, freq: f64) -> Complex64 {
+    let wave = bore.wave(freq);
+    let position = bore.shunts[split.rank].position;
+    let above = bore
+        .shunts
+        .get(split.rank + 1)
+        .map_or(0.0, |above| above.position);
+    let section = bore.segment(&wave, position - above) * bore.section_at(&wave, split.rank);
+    let z_bore = (split.upstream[i] * section).apply(split.downstream[i]);
+    bore.guard(bore.at_embouchure(&wave, z_bore))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{Hole, SideResonator};
+
+    #[test]
+    fn test_sweep_matches_scalar_bit_for_bit() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for (pos, open) in [(40.0, true), (30.0, false), (35.0, true), (70.0, true)] {
+            flute.holes.push(Hole::new(pos, 0.35, open)).unwrap();
+        }
+        let order = flute.hole_order_back_to_front().into_owned();
+        // Spans a full block and a partial one, and includes f = 0
+        let freqs: Vec<f64> = (0..BLOCK + 13).map(|i| 40.0 * i as f64).collect();
+        let bore = flute.bore(&order);
+        let swept = bore.sweep(&freqs);
+        assert_eq!(swept.len(), freqs.len());
+        for (&f, z) in freqs.iter().zip(&swept) {
+            let scalar = bore.impedance(f);
+            assert!(
+                z.re.to_bits() == scalar.re.to_bits() && z.im.to_bits() == scalar.im.to_bits(),
+                "{} Hz: {} vs {}",
+                f,
+                z,
+                scalar
+            );
+        }
+    }
+
+    #[test]
+    fn test_field_matches_the_transfer_matrices() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for (pos, open) in [(40.0, true), (30.0, false), (35.0, true)] {
+            flute.holes.push(Hole::new(pos, 0.35, open)).unwrap();
+        }
+        let bore = flute.bore(&flute.hole_order_back_to_front());
+        for f in [260.0, 700.0, 1500.0] {
+            let wave = bore.wave(f);
+            let mut chain = Abcd::IDENTITY;
+            for rank in 0..bore.shunts.len() {
+                chain = bore.section_at(&wave, rank) * chain;
+            }
+            let z_bore = (bore.top_segment(&wave) * chain).apply(bore.foot_load(&wave));
+            let (p, u) = bore.field(f).at(0.0);
+            assert!((p / u - z_bore).norm() < 1e-9 * z_bore.norm(), "{} Hz", f);
+        }
+    }
+
+    #[test]
+    fn test_spectrum_cache_matches_full_recompute() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for pos in [40.0, 30.0, 35.0, 25.0] {
+            flute.holes.push(Hole::new(pos, 0.35, true)).unwrap();
+        }
+        let mut cache = SpectrumCache::default();
+        let close = |a: &[f64], b: &[f64]| {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|(x, y)| (x - y).abs() <= 1e-9 * x.abs().max(1.0))
+        };
+        let check = |cache: &mut SpectrumCache, flute: &Flute| {
+            let cached = cache.spectrum(flute, 200.0, 2000.0, 50);
+            assert!(close(&cached, &flute.impedance_spectrum(200.0, 2000.0, 50)));
+        };
+
+        check(&mut cache, &flute);
+        assert!(cache.split.is_none());
+        // Dragging one hole (the foot-most, then the front-most) reuses its split
+        for (hole, positions) in [(0, [41.0, 42.5, 44.0]), (3, [24.0, 22.0, 20.0])] {
+            for pos in positions {
+                flute.holes[hole].position = pos;
+                check(&mut cache, &flute);
+                assert_eq!(cache.split.as_ref().map(|s| s.hole), Some(hole));
+            }
+        }
+        // Closing a middle hole is a single-hole edit too
+        flute.holes[2].open = false;
+        check(&mut cache, &flute);
+        assert_eq!(cache.split.as_ref().map(|s| (s.hole, s.rank)), Some((2, 1)));
+        // Moving it past a neighbour, or touching the tube, recomputes in full
+        flute.holes[2].position = 29.0;
+        check(&mut cache, &flute);
+        assert!(cache.split.is_none());
+        flute.length = 58.0;
+        check(&mut cache, &flute);
+        assert!(cache.split.is_none());
+
+        // So does changing the calibration after a drag has cached a split
+        flute.holes[0].position = 43.0;
+        check(&mut cache, &flute);
+        assert!(cache.split.is_some());
+        flute.calibration.loss_factor = 2.0;
+        flute.calibration.hole_end_correction = 1.2;
+        check(&mut cache, &flute);
+        assert!(cache.split.is_none());
+    }
+
+    #[test]
+    fn test_side_resonator_pulls_nearby_notes() {
+        let bare = Flute::new(60.0, 0.95, 0.4);
+        let f0 = bare.clone().calculate_pitch(f64::NAN);
+        // A neck of radius `a`, 0.5 cm long, halfway down at the fundamental's pressure
+        // maximum, with the volume that puts its Helmholtz frequency at `tuned` Hz
+        let with = |tuned: f64, a: f64| {
+            let l = 0.5;
+            let w = 2.0 * PI * tuned;
+            let volume = PI * a * a * SPEED_OF_SOUND.powi(2) / (w * w * (l + 1.7 * a));
+            let mut flute = bare.clone();
+            flute
+                .resonators
+                .push(SideResonator::new(30.0, a, l, volume));
+            flute.validate().unwrap();
+            flute
+        };
+        let cents = |flute: &Flute| 1200.0 * (flute.clone().calculate_pitch(f64::NAN) / f0).log2();
+        // Tuned close above the note it drags it well down; a small one tuned far above is
+        // just a little extra compliance
+        let near = with(f0 * 1.05, 0.3);
+        let far = with(f0 * 4.0, 0.1);
+        assert!(cents(&near) < -100.0);
+        assert!(cents(&far) < 0.0 && cents(&far) > -15.0);
+
+        // Every path through the network agrees, and a cavity never shows up as a hole
+        let mut holed = near.clone();
+        holed.holes.push(Hole::new(40.0, 0.35, true)).unwrap();
+        holed.holes.push(Hole::new(20.0, 0.35, false)).unwrap();
+        let bore = holed.bore(&holed.hole_order_back_to_front());
+        let freqs = [f0 * 0.9, f0, f0 * 1.3];
+        let swept = bore.fingering_sweep(&freqs, &[0b01]);
+        for (i, &f) in freqs.iter().enumerate() {
+            assert_eq!(swept[0][i], bore.impedance(f));
+            assert_eq!(bore.sweep(&freqs)[i], bore.impedance(f));
+            let z32 = bore.impedance_f32(f);
+            assert!((z32 - bore.impedance(f)).norm() < 1e-3 * bore.impedance(f).norm());
+        }
+        let flows = bore.field(f0).hole_flows().to_vec();
+        assert_eq!(flows.len(), 1);
+        assert_eq!(flows[0].0, 0);
+
+        let mut wide = near;
+        wide.resonators[0].neck_radius = 1.0;
+        assert!(wide.validate().is_err());
+    }
+
+    #[test]
+    fn test_fundamental_estimate_lands_on_the_first_mode() {
+        // Small holes: the first open hole alone puts the note a major third sharp
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for pos in [44.0, 40.0, 36.0] {
+            flute.holes.push(Hole::new(pos, 0.15, true)).unwrap();
+        }
+        let solved = flute.calculate_pitch_detailed(f64::NAN);
+        assert_eq!(solved.mode, 1);
+        let estimate = flute
+            .bore(&flute.hole_order_back_to_front())
+            .fundamental_estimate();
+        assert!((1200.0 * (estimate / solved.frequency).log2()).abs() < 100.0);
+        let first_hole = SPEED_OF_SOUND / (2.0 * (36.0 + 0.61 * 0.95));
+        assert!(1200.0 * (first_hole / solved.frequency).log2() > 300.0);
+
+        // A deeper cork cavity lowers the guess, slightly
+        let mut deep = flute.clone();
+        deep.cork_position = 2.0 * flute.cork_position;
+        let deeper = deep
+            .bore(&deep.hole_order_back_to_front())
+            .fundamental_estimate();
+        assert!(
+            deeper < estimate && deeper > 0.99 * estimate,
+            "{deeper} {estimate}"
+        );
+    }
+
+    #[cfg(feature = "optimize")]
+    #[test]
+    fn test_every_whistle_fingering_solves_to_its_lowest_mode() {
+        // Cross fingerings included
+        let whistle = crate::presets::Preset::TinWhistle.build(f64::NAN).unwrap();
+        for mask in 0..1u32 << whistle.holes.len() {
+            let mut fingered = whistle.clone();
+            for (i, hole) in fingered.holes.iter_mut().enumerate() {
+                hole.open = mask & (1 << i) != 0;
+            }
+            assert_eq!(
+                fingered.calculate_pitch_detailed(f64::NAN).mode,
+                1,
+                "{:06b}",
+                mask
+            );
+        }
+    }
+}
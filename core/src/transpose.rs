@@ -0,0 +1,232 @@
+//! Scaling a design to another key, as whistle and NAF makers do to build a family from one
+//! mother instrument. Lengths follow the wavelength but the bore, holes and embouchure only
+//! follow its square root, so a low member isn't a bloated copy of a high one. End
+//! corrections and hole effects don't scale with either, so the scaled tube and holes are
+//! then re-fitted until every progressive fingering plays the mother design's note moved
+//! by the same interval, its own tuning quirks included.
+
+use crate::ergonomics::{fit, pitch_near};
+use crate::error::FlyteError;
+use crate::geometry::MIN_BRIDGE;
+use crate::physics::{Flute, InstrumentType};
+use crate::presets::fit_length;
+use crate::tuning;
+use serde::{Deserialize, Serialize};
+
+/// How far a design can be moved, in octaves either way
+const MAX_TRANSPOSE: f64 = 2.0;
+/// Rounds of hole fitting; moving one hole shifts its neighbours' notes a little
+const TUNING_PASSES: usize = 3;
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct TransposeOptions {
+    /// New all-holes-closed note, Hz
+    pub key: f64,
+    /// Cross-section dimensions scale with the length ratio to this power: 0.5 is the usual
+    /// family rule, 1 a plain photocopy, 0 keeps the mother's bore
+    pub bore_exponent: f64,
+    /// Scale the wall with the bore instead of keeping the mother's stock thickness
+    pub scale_wall: bool,
+    /// NaN uses the solver default
+    pub jet_velocity: f64,
+}
+
+impl Default for TransposeOptions {
+    fn default() -> Self {
+        TransposeOptions {
+            key: f64::NAN,
+            bore_exponent: 0.5,
+            scale_wall: false,
+            jet_velocity: f64::NAN,
+        }
+    }
+}
+
+/// One progressive fingering of the transposed design
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TransposedNote {
+    pub fingering: u32,
+    /// The mother design's note moved by the transposition interval
+    pub target: f64,
+    pub frequency: f64,
+    /// Left over after re-fitting, positive = sharp
+    pub cents: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Transposed {
+    pub flute: Flute,
+    /// New over old length before re-fitting, and the matching cross-section factor
+    pub length_scale: f64,
+    pub bore_scale: f64,
+    pub notes: Vec<TransposedNote>,
+}
+
+/// `flute` moved so its all-closed note sounds `opts.key`, with every other progressive
+/// fingering keeping its interval to that note. Rejects keys more than two octaves away and
+/// designs whose notes can't be solved.
+pub fn transpose(flute: &Flute, opts: &TransposeOptions) -> Result<Transposed, FlyteError> {
+    trace_span!(INFO, "transpose", key = opts.key);
+    let fingerings = tuning::progressive_fingerings(flute);
+    let mother = tuning::pitches_for_fingerings(flute, &fingerings, opts.jet_velocity);
+    if mother.iter().any(|f| !f.is_finite()) {
+        return Err(FlyteError::invalid_input(
+            "every progressive fingering of the design needs a pitch to transpose",
+        ));
+    }
+    let ratio = opts.key / mother[0];
+    if ratio.is_nan() || ratio <= 0.0 || ratio.log2().abs() > MAX_TRANSPOSE {
+        return Err(FlyteError::invalid_input(format!(
+            "key must be within two octaves of {:.2} Hz, got {}",
+            mother[0], opts.key
+        )));
+    }
+    let targets: Vec<f64> = mother.iter().map(|f| f * ratio).collect();
+    let length_scale = 1.0 / ratio;
+    let bore_scale = length_scale.powf(opts.bore_exponent);
+    let mut scaled = scaled(flute, length_scale, bore_scale, opts.scale_wall);
+
+    // Foot upwards, as the progressive fingerings open them. The top hole keeps at least
+    // half its scaled distance from the blowing end, clear of the embouchure or window.
+    let order = scaled.hole_order();
+    let head = order
+        .first()
+        .map_or(0.0, |&i| 0.5 * scaled.holes[i].position);
+    for _ in 0..TUNING_PASSES {
+        fit_length(&mut scaled, targets[0]);
+        for (rank, &i) in order.iter().enumerate().rev() {
+            let step = order.len() - rank;
+            // Holes past bit 31 have no fingering to fit against
+            let (Some(&fingering), Some(&target)) = (fingerings.get(step), targets.get(step))
+            else {
+                continue;
+            };
+            let radius = scaled.holes[i].radius;
+            let above = match rank {
+                0 => head,
+                _ => {
+                    let h = &scaled.holes[order[rank - 1]];
+                    h.position + h.radius + radius + MIN_BRIDGE
+                }
+            };
+            let below = match order.get(rank + 1) {
+                Some(&j) => {
+                    let h = &scaled.holes[j];
+                    h.position - h.radius - radius - MIN_BRIDGE
+                }
+                None => scaled.length - radius - 1.0,
+            };
+            scaled.holes[i].position = fit((above.min(below), below), target, false, |x| {
+                scaled.holes[i].position = x;
+                pitch_near(&scaled, fingering, target)
+            });
+        }
+    }
+    scaled.validate()?;
+
+    let notes = fingerings
+        .iter()
+        .zip(&targets)
+        .map(|(&fingering, &target)| {
+            let frequency = pitch_near(&scaled, fingering, target);
+            TransposedNote {
+                fingering,
+                target,
+                frequency,
+                cents: 1200.0 * (frequency / target).log2(),
+            }
+        })
+        .collect();
+    Ok(Transposed {
+        flute: scaled,
+        length_scale,
+        bore_scale,
+        notes,
+    })
+}
+
+/// First guess before re-fitting: positions along the tube by `length_scale`, everything
+/// across it by `bore_scale`
+fn scaled(flute: &Flute, length_scale: f64, bore_scale: f64, scale_wall: bool) -> Flute {
+    let mut scaled = flute.clone();
+    scaled.length *= length_scale;
+    scaled.bore_radius *= bore_scale;
+    if scale_wall {
+        scaled.wall_thickness *= bore_scale;
+    }
+    scaled.cork_position *= bore_scale;
+    scaled.embouchure_hole_radius *= bore_scale;
+    scaled.embouchure_chimney *= bore_scale;
+    for hole in &mut scaled.holes {
+        hole.position *= length_scale;
+        hole.radius *= bore_scale;
+    }
+    // A neck by `bore_scale` and the cavity by bore_scale * length_scale^2 keep each
+    // resonator's Helmholtz frequency moving with the key
+    for resonator in &mut scaled.resonators {
+        resonator.position *= length_scale;
+        resonator.neck_radius *= bore_scale;
+        resonator.neck_length *= bore_scale;
+        resonator.volume *= bore_scale * length_scale.powi(2);
+    }
+    match &mut scaled.instrument {
+        InstrumentType::Transverse => {}
+        InstrumentType::Notch(notch) => {
+            notch.width *= bore_scale;
+            notch.depth *= bore_scale;
+        }
+        InstrumentType::Duct(duct) => {
+            duct.windway_length *= bore_scale;
+            duct.windway_width *= bore_scale;
+            duct.windway_height *= bore_scale;
+            duct.window_length *= bore_scale;
+        }
+        InstrumentType::OrganPipe(pipe) => {
+            pipe.mouth_width *= bore_scale;
+            pipe.cut_up *= bore_scale;
+            pipe.flue_depth *= bore_scale;
+            pipe.languid_length *= bore_scale;
+            if let Some(slot) = &mut pipe.tuning_slot {
+                slot.length *= length_scale;
+                slot.width *= bore_scale;
+            }
+        }
+    }
+    scaled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::Preset;
+
+    #[test]
+    fn test_transpose_whistle_family() {
+        let d = Preset::TinWhistle.build(f64::NAN).unwrap();
+        let mother = tuning::fingering_chart(&d, f64::NAN);
+        // Down a fourth to an A whistle
+        let key = mother[0].frequency * 2f64.powf(-5.0 / 12.0);
+        let opts = TransposeOptions {
+            key,
+            ..TransposeOptions::default()
+        };
+        let a = transpose(&d, &opts).unwrap();
+        assert_eq!(a.notes.len(), mother.len());
+        for note in &a.notes {
+            assert!(note.cents.abs() < 3.0, "{:?}", note);
+        }
+        // Longer by more than the bore grows, and not a photocopy: the refit moves the tube
+        // away from the plain wavelength ratio
+        assert!(a.flute.length > d.length);
+        assert!((a.bore_scale - a.length_scale.sqrt()).abs() < 1e-12);
+        assert!((a.flute.bore_radius / d.bore_radius - a.bore_scale).abs() < 1e-12);
+        assert!((a.flute.length / d.length - a.length_scale).abs() > 1e-3);
+
+        let far = TransposeOptions {
+            key: key / 8.0,
+            ..opts
+        };
+        assert!(transpose(&d, &far).is_err());
+    }
+}
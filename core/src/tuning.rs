@@ -0,0 +1,344 @@
+use crate::error::FlyteError;
+use crate::physics::Flute;
+use serde::{Deserialize, Serialize};
+
+const A4: f64 = 440.0;
+const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// Nearest equal-tempered note (A4 = 440 Hz) and the deviation from it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NoteInfo {
+    pub name: String, // e.g. "D4"
+    pub target: f64,  // Hz of the nearest note
+    pub cents: f64,   // -50..+50, positive = sharp
+}
+
+/// Same mapping as the web UI's `getNoteInfo`
+pub fn note_info(freq: f64) -> NoteInfo {
+    if freq.is_nan() || freq < 20.0 {
+        return NoteInfo {
+            name: "--".to_string(),
+            target: 0.0,
+            cents: 0.0,
+        };
+    }
+    let semitones = 12.0 * (freq / A4).log2();
+    let rounded = semitones.round();
+    let midi = rounded as i64 + 69;
+    NoteInfo {
+        name: format!(
+            "{}{}",
+            NOTE_NAMES[midi.rem_euclid(12) as usize],
+            midi.div_euclid(12) - 1
+        ),
+        target: A4 * 2f64.powf(rounded / 12.0),
+        cents: (semitones - rounded) * 100.0,
+    }
+}
+
+/// One row of a fingering chart. `fingering` is a bitmask of open holes (bit i = `holes[i]`).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChartRow {
+    pub fingering: u32,
+    pub frequency: f64,
+    pub note: NoteInfo,
+}
+
+/// The simple-system scale: all holes closed, then opened one by one from the foot upwards
+pub fn progressive_fingerings(flute: &Flute) -> Vec<u32> {
+    let order = flute.hole_order();
+    let mut mask = 0u32;
+    let mut fingerings = vec![mask];
+    for &i in order.iter().rev().filter(|&&i| i < 32) {
+        mask |= 1 << i;
+        fingerings.push(mask);
+    }
+    fingerings
+}
+
+/// Playing pitch with the given holes open, leaving the design's own open flags untouched
+pub fn pitch_for_fingering(flute: &Flute, fingering: u32, jet_velocity: f64) -> f64 {
+    let mut fingered = flute.clone();
+    for (i, hole) in fingered.holes.iter_mut().enumerate() {
+        hole.open = i < 32 && fingering & (1 << i) != 0;
+    }
+    fingered.calculate_pitch(jet_velocity)
+}
+
+/// `pitch_for_fingering` for many fingerings, sharing one working copy of the design (one
+/// per thread with the `parallel` feature)
+pub fn pitches_for_fingerings(flute: &Flute, fingerings: &[u32], jet_velocity: f64) -> Vec<f64> {
+    let solve = |fingered: &mut Flute, &fingering: &u32| {
+        for (i, hole) in fingered.holes.iter_mut().enumerate() {
+            hole.open = i < 32 && fingering & (1 << i) != 0;
+        }
+        fingered.calculate_pitch(jet_velocity)
+    };
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        fingerings
+            .par_iter()
+            .map_init(|| flute.clone(), solve)
+            .collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        let mut fingered = flute.clone();
+        fingerings
+            .iter()
+            .map(|fingering| solve(&mut fingered, fingering))
+            .collect()
+    }
+}
+
+/// Pitch and nearest note for every progressive fingering, lowest note first
+pub fn fingering_chart(flute: &Flute, jet_velocity: f64) -> Vec<ChartRow> {
+    trace_span!(INFO, "fingering_chart", holes = flute.holes.len());
+    let fingerings = progressive_fingerings(flute);
+    let pitches = pitches_for_fingerings(flute, &fingerings, jet_velocity);
+    fingerings
+        .into_iter()
+        .zip(pitches)
+        .map(|(fingering, frequency)| ChartRow {
+            fingering,
+            frequency,
+            note: note_info(frequency),
+        })
+        .collect()
+}
+
+/// The same fingering played on two designs
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ComparisonRow {
+    pub fingering: u32,
+    pub a: f64,
+    pub b: f64,
+    /// b relative to a, positive = b sharper
+    pub cents: f64,
+}
+
+/// Pitch deltas for every progressive fingering of `a`, played on both designs. Bit i opens
+/// `holes[i]` on either design, so variants should keep their hole order.
+pub fn compare_designs(a: &Flute, b: &Flute, jet_velocity: f64) -> Vec<ComparisonRow> {
+    let fingerings = progressive_fingerings(a);
+    let pitches_a = pitches_for_fingerings(a, &fingerings, jet_velocity);
+    let pitches_b = pitches_for_fingerings(b, &fingerings, jet_velocity);
+    fingerings
+        .into_iter()
+        .zip(pitches_a.into_iter().zip(pitches_b))
+        .map(|(fingering, (a, b))| ComparisonRow {
+            fingering,
+            a,
+            b,
+            cents: 1200.0 * (b / a).log2(),
+        })
+        .collect()
+}
+
+/// The fingerings a design's saved targets belong to: its own list when it has one,
+/// otherwise its progressive fingerings, lowest first, one per target
+pub fn target_fingerings(flute: &Flute, fingerings: &[u32], targets: usize) -> Vec<u32> {
+    if fingerings.is_empty() {
+        progressive_fingerings(flute)
+            .into_iter()
+            .take(targets)
+            .collect()
+    } else {
+        fingerings.to_vec()
+    }
+}
+
+/// One candidate's line in a `Leaderboard`
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LeaderboardRow {
+    /// Index into the candidates as given
+    pub candidate: usize,
+    pub frequencies: Vec<f64>,
+    /// Error against each target, positive = sharp; NaN where no pitch was found
+    pub cents: Vec<f64>,
+    /// Root mean square of `cents`, NaN if any note failed
+    pub rms_cents: f64,
+    /// Largest error either way, NaN if any note failed
+    pub worst_cents: f64,
+}
+
+/// Many designs scored on the same fingerings and targets. `rows` follow the candidates'
+/// order, `ranking` lists candidate indices best first: lowest RMS error, designs with a
+/// note that failed to solve last.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Leaderboard {
+    pub fingerings: Vec<u32>,
+    pub targets: Vec<f64>,
+    pub rows: Vec<LeaderboardRow>,
+    pub ranking: Vec<usize>,
+}
+
+/// Play every fingering on every candidate and score the pitches against `targets`, one per
+/// fingering. As with `compare_designs`, bit i opens `holes[i]` on each candidate.
+pub fn leaderboard(
+    candidates: &[Flute],
+    fingerings: &[u32],
+    targets: &[f64],
+    jet_velocity: f64,
+) -> Result<Leaderboard, FlyteError> {
+    if fingerings.len() != targets.len() {
+        return Err(FlyteError::invalid_input(format!(
+            "{} fingerings but {} targets",
+            fingerings.len(),
+            targets.len()
+        )));
+    }
+    if let Some(bad) = targets.iter().find(|t| !t.is_finite() || **t <= 0.0) {
+        return Err(FlyteError::invalid_input(format!(
+            "targets must be positive, got {}",
+            bad
+        )));
+    }
+    let rows: Vec<LeaderboardRow> = candidates
+        .iter()
+        .enumerate()
+        .map(|(candidate, flute)| {
+            let frequencies = pitches_for_fingerings(flute, fingerings, jet_velocity);
+            let cents: Vec<f64> = frequencies
+                .iter()
+                .zip(targets)
+                .map(|(f, t)| 1200.0 * (f / t).log2())
+                .collect();
+            let (rms_cents, worst_cents) = if cents.iter().all(|c| c.is_finite()) {
+                let squares: f64 = cents.iter().map(|c| c * c).sum();
+                (
+                    (squares / cents.len().max(1) as f64).sqrt(),
+                    cents.iter().fold(0.0, |w: f64, c| w.max(c.abs())),
+                )
+            } else {
+                (f64::NAN, f64::NAN)
+            };
+            LeaderboardRow {
+                candidate,
+                frequencies,
+                cents,
+                rms_cents,
+                worst_cents,
+            }
+        })
+        .collect();
+    let mut ranking: Vec<usize> = (0..rows.len()).collect();
+    let score = |i: usize| {
+        let rms = rows[i].rms_cents;
+        if rms.is_nan() {
+            f64::INFINITY
+        } else {
+            rms
+        }
+    };
+    ranking.sort_by(|&a, &b| score(a).total_cmp(&score(b)));
+    Ok(Leaderboard {
+        fingerings: fingerings.to_vec(),
+        targets: targets.to_vec(),
+        rows,
+        ranking,
+    })
+}
+
+/// Both designs' input impedance on one frequency grid, interleaved as
+/// (freq, |Z| a, phase a, |Z| b, phase b)
+pub fn overlay_spectra(a: &Flute, b: &Flute, f0: f64, f1: f64, n: usize) -> Vec<f64> {
+    let za = a.impedance_spectrum(f0, f1, n);
+    let zb = b.impedance_spectrum(f0, f1, n);
+    za.chunks(3)
+        .zip(zb.chunks(3))
+        .flat_map(|(pa, pb)| [pa[0], pa[1], pa[2], pb[1], pb[2]])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Hole;
+
+    #[test]
+    fn test_note_info() {
+        let a4 = note_info(440.0);
+        assert_eq!(a4.name, "A4");
+        assert!(a4.cents.abs() < 1e-9);
+
+        let d4 = note_info(293.66 * 2f64.powf(10.0 / 1200.0));
+        assert_eq!(d4.name, "D4");
+        assert!((d4.cents - 10.0).abs() < 0.1);
+
+        assert_eq!(note_info(f64::NAN).name, "--");
+    }
+
+    #[test]
+    fn test_progressive_chart_rises() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for pos in [40.0, 30.0, 35.0] {
+            flute.holes.push(Hole::new(pos, 0.35, true)).unwrap();
+        }
+        // Opened from the foot: 40 (index 0), 35 (index 2), 30 (index 1)
+        assert_eq!(progressive_fingerings(&flute), vec![0, 0b001, 0b101, 0b111]);
+
+        // Zero jet velocity seeds each solve from the effective-length guess
+        let chart = fingering_chart(&flute, 0.0);
+        assert_eq!(chart.len(), 4);
+        for pair in chart.windows(2) {
+            assert!(pair[1].frequency > pair[0].frequency, "{:?}", chart);
+        }
+        // The design's own fingering is left alone
+        assert!(flute.holes.iter().all(|h| h.open));
+
+        let batch = pitches_for_fingerings(&flute, &[0b111, 0, 0b101], 0.0);
+        assert_eq!(
+            batch,
+            vec![chart[3].frequency, chart[0].frequency, chart[2].frequency]
+        );
+    }
+
+    #[test]
+    fn test_compare_designs() {
+        let mut a = Flute::new(60.0, 0.95, 0.4);
+        for pos in [40.0, 35.0] {
+            a.holes.push(Hole::new(pos, 0.35, true)).unwrap();
+        }
+        let mut b = a.clone();
+        b.holes[0].radius = 0.45;
+
+        let rows = compare_designs(&a, &b, 0.0);
+        assert_eq!(rows.len(), 3);
+        // The larger foot hole mostly moves the notes it vents
+        assert!(rows[1].cents > 0.0, "{:?}", rows);
+        assert!(rows[0].cents.abs() < rows[1].cents, "{:?}", rows);
+
+        let overlay = overlay_spectra(&a, &b, 200.0, 400.0, 5);
+        assert_eq!(overlay.len(), 25);
+        assert_eq!(&overlay[5..8], &a.impedance_spectrum(250.0, 250.0, 1)[..]);
+    }
+
+    #[test]
+    fn test_leaderboard_ranks_candidates() {
+        let mut flute = Flute::new(60.0, 0.95, 0.4);
+        for pos in [40.0, 35.0] {
+            flute.holes.push(Hole::new(pos, 0.35, true)).unwrap();
+        }
+        let fingerings = progressive_fingerings(&flute);
+        let targets = pitches_for_fingerings(&flute, &fingerings, 0.0);
+        let mut long = flute.clone();
+        long.length += 2.0;
+        let mut longer = flute.clone();
+        longer.length += 4.0;
+        let candidates = [longer, flute, long];
+
+        let board = leaderboard(&candidates, &fingerings, &targets, 0.0).unwrap();
+        assert_eq!(board.ranking, vec![1, 2, 0]);
+        assert_eq!(board.rows[1].rms_cents, 0.0);
+        // Longer tubes play flat, the closed note most
+        let row = &board.rows[0];
+        assert!(row.cents.iter().all(|&c| c < 0.0), "{:?}", row);
+        assert_eq!(row.worst_cents, -row.cents[0]);
+
+        assert!(leaderboard(&candidates, &fingerings, &targets[1..], 0.0).is_err());
+    }
+}
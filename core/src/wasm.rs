@@ -0,0 +1,1597 @@
+//! wasm-bindgen bindings: the `FluteEngine` handle the web app drives. Only built with the
+//! `wasm` feature; native users work with `Flute` and the modules directly.
+
+use crate::document::{self, DesignDocument};
+use crate::error::FlyteError;
+use crate::history::History;
+use crate::materials::Material;
+use crate::physics::{
+    AdaptiveSampling, Flute, Hole, Holes, Precision, RangePolicy, SideResonator, SolverConfig,
+};
+use std::collections::{BTreeMap, HashMap};
+
+#[cfg(feature = "optimize")]
+use crate::calibration;
+#[cfg(feature = "export")]
+use crate::export;
+#[cfg(feature = "optimize")]
+use crate::presets;
+#[cfg(feature = "synth")]
+use crate::synth;
+#[cfg(feature = "optimize")]
+use crate::transpose;
+#[cfg(feature = "optimize")]
+use crate::wizard;
+use crate::{
+    air, dynamics, ergonomics, field, geometry, import, jobs, moisture, organ, panflute, physics,
+    tolerance, transfer, tuning,
+};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct FluteEngine {
+    inner: Flute,
+    mesh_options: geometry::MeshOptions,
+    mesh_cache: geometry::MeshCache,
+    spectrum_cache: transfer::SpectrumCache,
+    job: Option<jobs::Job>,
+    /// Goes up with every edit that changes the acoustics other than which holes are open
+    revision: u64,
+    results: ResultCache,
+    /// Named snapshots for A/B comparison
+    designs: BTreeMap<String, Flute>,
+    history: History,
+    tracked_pitch: Option<PitchTrack>,
+    /// Used by the live-feedback calls only; see `set_preview_precision`
+    preview_precision: Precision,
+    /// What hole edits do with out-of-range values; see `set_range_policy`
+    range_policy: RangePolicy,
+    staging: HoleStaging,
+    /// Carried through `to_json` / `from_json` for the frontend; not used by the model
+    fingerings: Vec<u32>,
+    targets: Vec<f64>,
+}
+
+/// Flat mesh arrays handed to JS as `Float32Array` / `Uint32Array`
+#[wasm_bindgen]
+pub struct MeshBuffers {
+    inner: geometry::MeshBuffers,
+}
+
+/// Acoustic answers for one geometry revision. Frontends ask for the same pitch every
+/// animation frame and redraw the chart after unrelated UI changes; answers are keyed by
+/// fingering, so toggling holes back and forth is free too.
+#[derive(Default)]
+struct ResultCache {
+    /// The engine's `revision` the entries were computed at
+    revision: u64,
+    /// (fingering, jet velocity bits) -> Hz
+    pitches: HashMap<(u32, u64), f64>,
+    /// Interleaved spectrum for one fingering and grid
+    spectrum: Option<(SpectrumKey, Vec<f64>)>,
+}
+
+/// (fingering, (f0, f1) bits, n)
+type SpectrumKey = (u32, u64, u64, usize);
+
+impl ResultCache {
+    /// The entries for `revision`, dropping any left from an older geometry
+    fn at(&mut self, revision: u64) -> &mut Self {
+        if self.revision != revision {
+            self.pitches.clear();
+            self.spectrum = None;
+            self.revision = revision;
+        }
+        self
+    }
+}
+
+/// Hole arrays living in wasm memory that JS writes through typed-array views, applied by
+/// `commit_holes` without copying arguments across the boundary
+#[derive(Default)]
+struct HoleStaging {
+    positions: Vec<f64>,
+    radii: Vec<f64>,
+    open: Vec<u8>,
+}
+
+/// The last `calculate_pitch_incremental` answer and the fingering and jet it was for. Not
+/// cleared by edits: following the mode across geometry changes is the point.
+struct PitchTrack {
+    open: Vec<bool>,
+    jet: u64,
+    frequency: f64,
+}
+
+#[wasm_bindgen]
+impl MeshBuffers {
+    /// xyz per vertex
+    pub fn positions(&self) -> Vec<f32> {
+        self.inner.positions.clone()
+    }
+
+    /// Unit normal per vertex, matching `positions`
+    pub fn normals(&self) -> Vec<f32> {
+        self.inner.normals.clone()
+    }
+
+    pub fn group_count(&self) -> usize {
+        self.inner.groups.len()
+    }
+
+    pub fn group_name(&self, index: usize) -> Option<String> {
+        self.inner.groups.get(index).map(|(name, _)| name.clone())
+    }
+
+    /// Triangle indices of one group into `positions`
+    pub fn group_indices(&self, index: usize) -> Option<Vec<u32>> {
+        self.inner
+            .groups
+            .get(index)
+            .map(|(_, indices)| indices.clone())
+    }
+}
+
+#[wasm_bindgen]
+impl FluteEngine {
+    #[wasm_bindgen(constructor)]
+    pub fn new(length: f64, bore_radius: f64, wall_thickness: f64) -> FluteEngine {
+        console_error_panic_hook::set_once();
+        FluteEngine {
+            inner: Flute::new(length, bore_radius, wall_thickness),
+            mesh_options: geometry::MeshOptions::default(),
+            mesh_cache: geometry::MeshCache::default(),
+            spectrum_cache: transfer::SpectrumCache::default(),
+            job: None,
+            revision: 0,
+            results: ResultCache::default(),
+            designs: BTreeMap::new(),
+            history: History::default(),
+            tracked_pitch: None,
+            preview_precision: Precision::default(),
+            range_policy: RangePolicy::default(),
+            staging: HoleStaging::default(),
+            fingerings: Vec::new(),
+            targets: Vec::new(),
+        }
+    }
+
+    pub fn set_holes(
+        &mut self,
+        positions: &[f64],
+        radii: &[f64],
+        open: &[u8],
+    ) -> Result<(), JsValue> {
+        if positions.len() != radii.len() || positions.len() != open.len() {
+            return Err(FlyteError::invalid_input(format!(
+                "hole arrays differ in length ({} positions, {} radii, {} open flags)",
+                positions.len(),
+                radii.len(),
+                open.len()
+            ))
+            .into());
+        }
+
+        let fitted = (0..positions.len())
+            .map(|i| {
+                self.inner
+                    .fit_hole(i, positions[i], radii[i], self.range_policy)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.history.record("Set holes", &self.inner);
+
+        self.inner.holes.clear();
+        self.inner.holes.reserve(positions.len());
+        for (&(pos, rad), &open) in fitted.iter().zip(open) {
+            self.inner.holes.push(Hole::new(pos, rad, open != 0))?;
+        }
+        self.mesh_cache.invalidate();
+        self.geometry_changed();
+        Ok(())
+    }
+
+    /// Size the shared hole arrays for `count` holes, filled from the current design, e.g.
+    /// `new Float64Array(memory.buffer, engine.staged_positions_ptr(), count)`. Views must be
+    /// re-created after this call (and after anything that grows wasm memory).
+    pub fn stage_holes(&mut self, count: usize) {
+        let staging = &mut self.staging;
+        staging.positions.clear();
+        staging.radii.clear();
+        staging.open.clear();
+        for i in 0..count {
+            let hole = self.inner.holes.get(i);
+            staging.positions.push(hole.map_or(0.0, |h| h.position));
+            staging.radii.push(hole.map_or(0.1, |h| h.radius));
+            staging.open.push(hole.is_none_or(|h| h.open) as u8);
+        }
+    }
+
+    pub fn staged_hole_count(&self) -> usize {
+        self.staging.positions.len()
+    }
+
+    pub fn staged_positions_ptr(&mut self) -> *mut f64 {
+        self.staging.positions.as_mut_ptr()
+    }
+
+    pub fn staged_radii_ptr(&mut self) -> *mut f64 {
+        self.staging.radii.as_mut_ptr()
+    }
+
+    /// One byte per hole, nonzero = open
+    pub fn staged_open_ptr(&mut self) -> *mut u8 {
+        self.staging.open.as_mut_ptr()
+    }
+
+    /// Apply the staged arrays as `set_holes` would. With an unchanged hole count only the
+    /// holes whose values differ are touched, so their mesh parts alone are rebuilt.
+    pub fn commit_holes(&mut self) -> Result<(), JsValue> {
+        let staging = &self.staging;
+        let staged: Vec<(f64, f64, bool)> = (0..staging.positions.len())
+            .map(|i| {
+                let (pos, rad) = self.inner.fit_hole(
+                    i,
+                    staging.positions[i],
+                    staging.radii[i],
+                    self.range_policy,
+                )?;
+                Ok((pos, rad, staging.open[i] != 0))
+            })
+            .collect::<Result<_, FlyteError>>()?;
+        let changed: Vec<usize> = staged
+            .iter()
+            .enumerate()
+            .filter(|&(i, &(pos, rad, open))| {
+                self.inner
+                    .holes
+                    .get(i)
+                    .is_none_or(|h| h.position != pos || h.radius != rad || h.open != open)
+            })
+            .map(|(i, _)| i)
+            .collect();
+        let same_count = staged.len() == self.inner.holes.len();
+        if same_count && changed.is_empty() {
+            return Ok(());
+        }
+
+        // Keep each surviving hole's azimuth, which the arrays don't carry
+        let replaced = if same_count {
+            None
+        } else {
+            let holes: Vec<Hole> = staged
+                .iter()
+                .enumerate()
+                .map(|(i, &(pos, rad, open))| {
+                    let mut hole = Hole::new(pos, rad, open);
+                    hole.azimuth = self.inner.holes.get(i).map_or(0.0, |h| h.azimuth);
+                    hole
+                })
+                .collect();
+            Some(Holes::try_from(holes)?)
+        };
+
+        self.history.record("Set holes", &self.inner);
+        if let Some(holes) = replaced {
+            self.inner.holes = holes;
+            self.mesh_cache.invalidate();
+        } else {
+            for i in changed {
+                let (pos, rad, open) = staged[i];
+                let hole = &mut self.inner.holes[i];
+                hole.position = pos;
+                hole.radius = rad;
+                hole.open = open;
+                self.mesh_cache.invalidate_hole(i);
+            }
+        }
+        self.geometry_changed();
+        Ok(())
+    }
+
+    /// Append a hole and return its index; an error if it overlaps another hole
+    pub fn add_hole(&mut self, position: f64, radius: f64, open: bool) -> Result<usize, JsValue> {
+        let index = self.inner.holes.len();
+        let (position, radius) = self
+            .inner
+            .fit_hole(index, position, radius, self.range_policy)?;
+        let mut candidate = self.inner.clone();
+        candidate.add_hole(Hole::new(position, radius, open))?;
+        self.history.record("Add hole", &self.inner);
+        self.inner = candidate;
+        self.mesh_cache.insert_hole(index);
+        self.geometry_changed();
+        Ok(index)
+    }
+
+    /// Insert a hole before `index` (or at the end when `index == hole count`), shifting
+    /// later holes up by one. Returns the new hole's index.
+    pub fn insert_hole(
+        &mut self,
+        index: usize,
+        position: f64,
+        radius: f64,
+        open: bool,
+    ) -> Result<usize, JsValue> {
+        let len = self.inner.holes.len();
+        if index > len {
+            return Err(FlyteError::IndexOutOfBounds { index, len }.into());
+        }
+        let (position, radius) = self
+            .inner
+            .fit_hole(index, position, radius, self.range_policy)?;
+        let mut candidate = self.inner.clone();
+        candidate.insert_hole(index, Hole::new(position, radius, open))?;
+        self.history.record("Insert hole", &self.inner);
+        self.inner = candidate;
+        self.mesh_cache.insert_hole(index);
+        self.geometry_changed();
+        Ok(index)
+    }
+
+    /// Delete a hole, shifting later holes down by one
+    pub fn remove_hole(&mut self, index: usize) -> Result<(), JsValue> {
+        let len = self.inner.holes.len();
+        if index >= len {
+            return Err(FlyteError::IndexOutOfBounds { index, len }.into());
+        }
+        self.history
+            .record(&format!("Remove hole {}", index + 1), &self.inner);
+        self.inner.holes.remove(index);
+        self.mesh_cache.remove_hole(index);
+        self.geometry_changed();
+        Ok(())
+    }
+
+    /// Replace every hole from an array of `{ position, radius, open, azimuth }` objects.
+    /// `open` defaults to true and `azimuth` to 0; the design is left unchanged on error.
+    pub fn set_holes_js(&mut self, holes: JsValue) -> Result<(), JsValue> {
+        let mut holes: Vec<Hole> =
+            serde_wasm_bindgen::from_value(holes).map_err(FlyteError::invalid_input)?;
+        for hole in &mut holes {
+            hole.azimuth = if hole.azimuth.is_finite() {
+                hole.azimuth.rem_euclid(360.0)
+            } else {
+                0.0
+            };
+        }
+        let mut candidate = self.inner.clone();
+        candidate.holes = Holes::try_from(holes)?;
+        candidate.validate()?;
+        self.history.record("Set holes", &self.inner);
+        self.inner = candidate;
+        self.mesh_cache.invalidate();
+        self.geometry_changed();
+        Ok(())
+    }
+
+    /// Replace the side resonators from an array of `{ position, neck_radius, neck_length,
+    /// volume }` objects; the design is left unchanged on error. The mesh doesn't draw
+    /// them, so it is kept.
+    pub fn set_resonators(&mut self, resonators: JsValue) -> Result<(), JsValue> {
+        let resonators: Vec<SideResonator> =
+            serde_wasm_bindgen::from_value(resonators).map_err(FlyteError::invalid_input)?;
+        let mut candidate = self.inner.clone();
+        candidate.resonators = resonators;
+        candidate.validate()?;
+        self.history.record("Set resonators", &self.inner);
+        self.inner = candidate;
+        self.geometry_changed();
+        Ok(())
+    }
+
+    pub fn resonators(&self) -> Result<JsValue, JsValue> {
+        to_js(&self.inner.resonators)
+    }
+
+    pub fn hole_count(&self) -> usize {
+        self.inner.holes.len()
+    }
+
+    /// One hole as `{ position, radius, open, azimuth }`
+    pub fn get_hole(&self, index: usize) -> Result<JsValue, JsValue> {
+        let hole = self
+            .inner
+            .holes
+            .get(index)
+            .ok_or(FlyteError::IndexOutOfBounds {
+                index,
+                len: self.inner.holes.len(),
+            })?;
+        to_js(hole)
+    }
+
+    /// Every hole as `{ position, radius, open, azimuth }`, in stored order
+    pub fn get_holes_js(&self) -> Result<JsValue, JsValue> {
+        to_js(&self.inner.holes)
+    }
+
+    /// Stable id of every hole, in stored order. An id follows its hole through edits and
+    /// through insertions and removals of other holes, so the UI can keep a selection;
+    /// replacing the whole set or loading a design numbers them afresh.
+    pub fn hole_ids(&self) -> Vec<u32> {
+        self.inner.holes.ids().iter().map(|id| id.get()).collect()
+    }
+
+    pub fn update_hole(
+        &mut self,
+        index: usize,
+        position: f64,
+        radius: f64,
+        open: bool,
+    ) -> Result<(), JsValue> {
+        if index >= self.inner.holes.len() {
+            return Err(FlyteError::IndexOutOfBounds {
+                index,
+                len: self.inner.holes.len(),
+            }
+            .into());
+        }
+
+        let (position, radius) = self
+            .inner
+            .fit_hole(index, position, radius, self.range_policy)?;
+        self.history
+            .record(&format!("Edit hole {}", index + 1), &self.inner);
+        let hole = &mut self.inner.holes[index];
+        let before = (hole.position, hole.radius);
+        hole.position = position;
+        hole.radius = radius;
+        hole.open = open;
+        self.mesh_cache.invalidate_hole(index);
+        // Toggling a hole only changes the fingering the cached answers are looked up by
+        if (hole.position, hole.radius) != before {
+            self.geometry_changed();
+        }
+
+        Ok(())
+    }
+
+    /// Rotate a hole around the tube (degrees, 0 = top, 180 = thumb side).
+    /// Only affects exported geometry; the acoustics are axisymmetric.
+    pub fn set_hole_azimuth(&mut self, index: usize, degrees: f64) -> Result<(), JsValue> {
+        let len = self.inner.holes.len();
+        if index < len {
+            self.history
+                .record(&format!("Rotate hole {}", index + 1), &self.inner);
+        }
+        let hole = self
+            .inner
+            .holes
+            .get_mut(index)
+            .ok_or(FlyteError::IndexOutOfBounds { index, len })?;
+        hole.azimuth = if degrees.is_finite() {
+            degrees.rem_euclid(360.0)
+        } else {
+            0.0
+        };
+        self.mesh_cache.invalidate_hole(index);
+        Ok(())
+    }
+
+    /// Set the tube's length, bore radius and wall thickness; values that aren't positive
+    /// are ignored
+    pub fn set_physics_params(&mut self, length: f64, bore_radius: f64, wall_thickness: f64) {
+        self.history.record("Set dimensions", &self.inner);
+        if !length.is_nan() && length > 0.0 {
+            self.inner.length = length;
+        }
+        if !bore_radius.is_nan() && bore_radius > 0.0 {
+            self.inner.bore_radius = bore_radius;
+        }
+        if !wall_thickness.is_nan() && wall_thickness > 0.0 {
+            self.inner.wall_thickness = wall_thickness;
+        }
+        self.mesh_cache.invalidate();
+        self.geometry_changed();
+    }
+
+    /// Calculate pitch using TMM and Resonance search
+    /// Starts from the lattice-corrected length of the current fingering so the search finds the fundamental
+    pub fn calculate_pitch(&mut self, jet_velocity: f64) -> f64 {
+        let Some(fingering) = self.fingering() else {
+            return self.inner.calculate_pitch(jet_velocity);
+        };
+        let key = (fingering, jet_velocity.to_bits());
+        let results = self.results.at(self.revision);
+        if let Some(&pitch) = results.pitches.get(&key) {
+            return pitch;
+        }
+        let pitch = self.inner.calculate_pitch(jet_velocity);
+        results.pitches.insert(key, pitch);
+        pitch
+    }
+
+    /// `calculate_pitch` for use while dragging: seeded with the previous answer and held to
+    /// the same mode, so the readout moves smoothly instead of occasionally jumping an octave.
+    /// Starts from scratch when the fingering or jet velocity changes, or the mode is lost.
+    pub fn calculate_pitch_incremental(&mut self, jet_velocity: f64) -> f64 {
+        let open: Vec<bool> = self.inner.holes.iter().map(|h| h.open).collect();
+        let jet = jet_velocity.to_bits();
+        let tracked = match &self.tracked_pitch {
+            Some(track) if track.open == open && track.jet == jet => self
+                .inner
+                .track_resonance_with(track.frequency, self.preview_precision),
+            _ => None,
+        };
+        let frequency = tracked.unwrap_or_else(|| self.calculate_pitch(jet_velocity));
+        self.tracked_pitch = frequency.is_finite().then_some(PitchTrack {
+            open,
+            jet,
+            frequency,
+        });
+        frequency
+    }
+
+    /// `calculate_pitch` that throws `{ code: "solver_did_not_converge" }` or
+    /// `{ code: "invalid_geometry" }` instead of returning a meaningless frequency
+    pub fn checked_pitch(&mut self, jet_velocity: f64) -> Result<f64, JsValue> {
+        Ok(self.inner.checked_pitch(jet_velocity)?)
+    }
+
+    /// `calculate_pitch` as `{ frequency, note, cents, converged, mode, q, diagnostics }`:
+    /// the nearest note, whether the search ended on an impedance minimum, which resonance
+    /// of the fingering it is (1 = lowest), its quality factor and any numerical trouble
+    pub fn calculate_pitch_detailed(&mut self, jet_velocity: f64) -> Result<JsValue, JsValue> {
+        to_js(&self.inner.calculate_pitch_detailed(jet_velocity))
+    }
+
+    /// `{ frequency, positions, frames }` for animating the standing wave: `frames[t][i]` is
+    /// the pressure at `positions[i]` cm, t / frames of the way through one period, scaled
+    /// to a peak swing of 1. NaN `frequency` uses the current pitch (default jet).
+    pub fn standing_wave(
+        &mut self,
+        frequency: f64,
+        points: usize,
+        frames: usize,
+    ) -> Result<JsValue, JsValue> {
+        let frequency = self.frequency_or_pitch(frequency);
+        to_js(&field::standing_wave(
+            &self.inner,
+            frequency,
+            points,
+            frames,
+        ))
+    }
+
+    /// `{ frequency, nodes, antinodes, vent_regions, tuning_regions }` for resonance `mode`
+    /// of the current fingering (1 = lowest): pressure nodes and antinodes in cm from the
+    /// embouchure, and `{ start, end }` stretches where a vent leaves the note alone or
+    /// where a hole moves it most
+    pub fn node_hints(&mut self, mode: usize) -> Result<JsValue, JsValue> {
+        let frequency = self.inner.mode_frequency(mode);
+        to_js(&field::node_hints(&self.inner, frequency))
+    }
+
+    /// A virtual microphone at every opening: `[{ opening, position, strength, level, phase }]`
+    /// for the embouchure (`"embouchure"`), each open hole (`{ hole: index }`) and the foot
+    /// (`"foot"`), with the strongest at strength 1 / level 0 dB. NaN `frequency` uses the
+    /// current pitch (default jet).
+    pub fn sources(&mut self, frequency: f64) -> Result<JsValue, JsValue> {
+        let frequency = self.frequency_or_pitch(frequency);
+        to_js(&field::sources(&self.inner, frequency))
+    }
+
+    /// `{ frequency, angles, levels }` polar-plot data: dB relative to the loudest direction
+    /// at `points` angles in degrees (0 = out of the foot along the axis, 180 = past the
+    /// embouchure). NaN `frequency` uses the current pitch (default jet).
+    pub fn directivity(&mut self, frequency: f64, points: usize) -> Result<JsValue, JsValue> {
+        let frequency = self.frequency_or_pitch(frequency);
+        to_js(&field::directivity(&self.inner, frequency, points))
+    }
+
+    /// `{ frequency, conductance, susceptance, contributions }`: the input admittance split
+    /// into `{ element, conductance, susceptance, share }` for the embouchure, cork, each
+    /// length of bore (`{ bore: { from, to } }`), each hole (`{ hole: index }`), resonator
+    /// and tuning slot, and the foot. NaN `frequency` uses the current pitch (default jet).
+    pub fn admittance_breakdown(&mut self, frequency: f64) -> Result<JsValue, JsValue> {
+        let frequency = self.frequency_or_pitch(frequency);
+        to_js(&field::admittance_breakdown(&self.inner, frequency))
+    }
+
+    /// `{ moisture_change, bore_change, length_change, hole_change, notes }`: how each note
+    /// moves when a wooden body goes from one humidity to another. `options` holds the
+    /// `DriftOptions` fields (`material`, `from_humidity`, `to_humidity`, `temperature`,
+    /// `jet_velocity`); missing fields take their defaults.
+    pub fn moisture_drift(&self, options: JsValue) -> Result<JsValue, JsValue> {
+        let options: moisture::DriftOptions = options_from_js(options)?;
+        to_js(&moisture::moisture_drift(&self.inner, &options))
+    }
+
+    /// `{ ambient_speed, breath_speed, notes }`: every progressive fingering cold in room air
+    /// (`a`) and warm while played (`b`), with the shift in cents. `options` holds the
+    /// `PlayedOptions` fields (`ambient: { temperature, humidity, co2 }`,
+    /// `breath_temperature`, `foot_temperature`, `breath_humidity`, `breath_co2`,
+    /// `jet_velocity`); missing fields take their defaults.
+    pub fn cold_vs_played(&self, options: JsValue) -> Result<JsValue, JsValue> {
+        let options: air::PlayedOptions = options_from_js(options)?;
+        to_js(&air::cold_vs_played(&self.inner, &options))
+    }
+
+    /// `{ temperatures, notes: [{ fingering, frequency, cents }] }`: each note's deviation
+    /// from its pitch at the reference temperature over a sweep of room temperatures, ready
+    /// to plot. `options` holds the `TemperatureChartOptions` fields (`fingerings`, `from`,
+    /// `to`, `points`, `reference`, `played`, `breath`); missing fields take their defaults.
+    pub fn temperature_chart(&self, options: JsValue) -> Result<JsValue, JsValue> {
+        let options: air::TemperatureChartOptions = options_from_js(options)?;
+        to_js(&air::temperature_chart(&self.inner, &options))
+    }
+
+    /// Step-by-step finishing suggestions, `{ fingerings, targets, steps: [{ adjustment,
+    /// value, summary, notes, rms_before, rms_after }], start_cents, end_cents, flute }`.
+    /// `options` holds the `WizardOptions` fields; without `targets` the engine's targets and
+    /// fingerings are used. Nothing is applied to the design.
+    #[cfg(feature = "optimize")]
+    pub fn tuning_wizard(&self, options: JsValue) -> Result<JsValue, JsValue> {
+        let mut options: wizard::WizardOptions = options_from_js(options)?;
+        if options.targets.is_empty() {
+            options.targets = self.targets.clone();
+            options.fingerings = self.fingerings.clone();
+        }
+        to_js(&wizard::tuning_wizard(&self.inner, &options)?)
+    }
+
+    /// The calibration that best matches the model to measured instruments: `{ calibration,
+    /// rms_before, rms_after, residuals: [{ instrument, fingering, measured, before, after }],
+    /// iterations }`. `instruments` is `[{ name, design, notes: [{ fingering, frequency }] }]`
+    /// with `design` a saved design document; `options` holds the `CalibrationOptions` fields
+    /// (`initial`, `fit`, `max_iterations`). Nothing is applied to the design.
+    #[cfg(feature = "optimize")]
+    pub fn fit_calibration(
+        &self,
+        instruments: JsValue,
+        options: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let instruments: Vec<calibration::KnownInstrument> = options_from_js(instruments)?;
+        let options: calibration::CalibrationOptions = options_from_js(options)?;
+        to_js(&calibration::fit_calibration(&instruments, &options)?)
+    }
+
+    /// Pitch and loudness of a note under a slowly changing breath: `{ resonance,
+    /// preferred_jet, points: [{ time, jet_velocity, pressure, frequency, cents, level }],
+    /// vibrato_cents, vibrato_level, cents_per_db }`. `options` holds the `TraceOptions`
+    /// fields (`fingering`, `duration`, `points`, `start`, `end`, `vibrato_rate`,
+    /// `vibrato_depth`); missing fields take their defaults.
+    pub fn dynamics_trace(&self, options: JsValue) -> Result<JsValue, JsValue> {
+        let options: dynamics::TraceOptions = options_from_js(options)?;
+        to_js(&dynamics::dynamics_trace(&self.inner, &options)?)
+    }
+
+    /// `{ frequency, note, stopped, cut_up_ratio, mouth_ratio, scale, jet_velocity,
+    /// wind_pressure }` for an organ-pipe design; an error for other instrument types
+    pub fn organ_pipe_report(&self, jet_velocity: f64) -> Result<JsValue, JsValue> {
+        to_js(&organ::pipe_report(&self.inner, jet_velocity)?)
+    }
+
+    /// `{ perimeter_ratio, notes: [{ fingering, a, b, cents }], q_round, q_oval }`: what a
+    /// bore `ovality` percent out of round does to every note, against a round bore
+    pub fn ovality_effect(&self, ovality: f64, jet_velocity: f64) -> Result<JsValue, JsValue> {
+        to_js(&tolerance::ovality_effect(
+            &self.inner,
+            ovality,
+            jet_velocity,
+        )?)
+    }
+
+    /// `{ hole, frequency, cents_per_mm, cents_per_tenth_mm }`: how far the current note
+    /// moves per mm the hole moves towards the foot and per 0.1 mm wider it is drilled.
+    /// Cheap enough to refresh on hover; starts from the cached current pitch.
+    pub fn quick_sensitivity(&mut self, hole_index: usize) -> Result<JsValue, JsValue> {
+        let frequency = self.frequency_or_pitch(f64::NAN);
+        to_js(&tolerance::quick_sensitivity(
+            &self.inner,
+            hole_index,
+            frequency,
+        )?)
+    }
+
+    /// `{ non_finite_impedance }` for the solve behind `calculate_pitch`, so a frozen or wild
+    /// readout can say why: nonzero means NaN or infinite impedances were replaced by safe
+    /// limits on the way and the pitch may be meaningless
+    pub fn pitch_diagnostics(&mut self, jet_velocity: f64) -> Result<JsValue, JsValue> {
+        let (_, diagnostics) = self.inner.calculate_pitch_diagnosed(jet_velocity);
+        to_js(&diagnostics)
+    }
+
+    /// Pitch for each fingering (bit i set = hole i open) in one call. The design's own
+    /// open/closed state is left untouched.
+    pub fn calculate_pitches(&mut self, fingerings: &[u32], jet_velocity: f64) -> Vec<f64> {
+        let jet = jet_velocity.to_bits();
+        let results = self.results.at(self.revision);
+        let missing: Vec<u32> = fingerings
+            .iter()
+            .copied()
+            .filter(|&f| !results.pitches.contains_key(&(f, jet)))
+            .collect();
+        let solved = tuning::pitches_for_fingerings(&self.inner, &missing, jet_velocity);
+        for (f, pitch) in missing.into_iter().zip(solved) {
+            results.pitches.insert((f, jet), pitch);
+        }
+        fingerings
+            .iter()
+            .map(|&f| results.pitches[&(f, jet)])
+            .collect()
+    }
+
+    /// `n` points from `f0` to `f1` Hz as one `Float64Array` of interleaved
+    /// (freq, |Z|, phase) triples
+    pub fn impedance_spectrum(&mut self, f0: f64, f1: f64, n: usize) -> Vec<f64> {
+        let key = self
+            .fingering()
+            .map(|fingering| (fingering, f0.to_bits(), f1.to_bits(), n));
+        let results = self.results.at(self.revision);
+        match &results.spectrum {
+            Some((cached, spectrum)) if Some(*cached) == key => spectrum.clone(),
+            _ => {
+                // Redrawn on every drag; only the dragged hole's section is recomputed
+                let spectrum = match self.preview_precision {
+                    Precision::F64 => self.spectrum_cache.spectrum(&self.inner, f0, f1, n),
+                    precision => self.inner.impedance_spectrum_with(f0, f1, n, precision),
+                };
+                results.spectrum = key.map(|key| (key, spectrum.clone()));
+                spectrum
+            }
+        }
+    }
+
+    /// (freq, |Z|, phase) triples from `f0` to `f1` Hz, sampled densely only around
+    /// resonances. `options` is a partial `AdaptiveSampling` object, e.g.
+    /// `{ initial_points: 32, max_level_step: 0.5 }`; undefined uses the defaults.
+    pub fn adaptive_spectrum(
+        &self,
+        f0: f64,
+        f1: f64,
+        options: JsValue,
+    ) -> Result<Vec<f64>, JsValue> {
+        let options: AdaptiveSampling = options_from_js(options)?;
+        Ok(self.inner.adaptive_spectrum(f0, f1, &options))
+    }
+
+    /// `impedance_spectrum` for each fingering (bit i set = hole i open) over the same grid,
+    /// one `Float64Array` of `fingerings.length` runs of `n` triples. Much cheaper than one
+    /// call per fingering, e.g. for a spectrum beside every row of the fingering chart.
+    pub fn fingering_spectra(&self, fingerings: &[u32], f0: f64, f1: f64, n: usize) -> Vec<f64> {
+        self.inner.fingering_spectra(fingerings, f0, f1, n).concat()
+    }
+
+    /// Geometry revision: goes up with every edit that changes the acoustics other than
+    /// which holes are open. A frontend can compare it with the revision it last drew to
+    /// skip recomputing after unrelated UI changes.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// `"f32"` computes `calculate_pitch_incremental` and `impedance_spectrum` in single
+    /// precision, a fraction of a cent off but much faster on phones; `"f64"` (the default)
+    /// or undefined goes back to full precision. Every other call always uses f64.
+    pub fn set_preview_precision(&mut self, precision: JsValue) -> Result<(), JsValue> {
+        self.preview_precision = options_from_js(precision)?;
+        self.results.spectrum = None;
+        Ok(())
+    }
+
+    /// `"clamp"` (the default, or undefined) pulls hole positions back onto the tube and radii
+    /// into range on `add_hole`, `insert_hole`, `update_hole`, `set_holes` and `commit_holes`,
+    /// so a drag past the foot stops there; `"reject"` makes those calls fail instead and
+    /// leave the design unchanged
+    pub fn set_range_policy(&mut self, policy: JsValue) -> Result<(), JsValue> {
+        self.range_policy = options_from_js(policy)?;
+        Ok(())
+    }
+
+    /// Pitch-search limits as a partial `SolverConfig` object (`max_iterations`,
+    /// `frequency_tolerance`, `search_bounds: [low, high]`, `scan_resolution`); undefined
+    /// restores the defaults. Saved with the design; the settings are left unchanged on
+    /// error.
+    pub fn set_solver_config(&mut self, config: JsValue) -> Result<(), JsValue> {
+        let solver: SolverConfig = options_from_js(config)?;
+        solver.validate()?;
+        self.history.record("Solver settings", &self.inner);
+        self.inner.solver = solver;
+        self.geometry_changed();
+        Ok(())
+    }
+
+    pub fn solver_config(&self) -> Result<JsValue, JsValue> {
+        to_js(&self.inner.solver)
+    }
+
+    /// The model's empirical constants as a partial `Calibration` object (`open_end_correction`,
+    /// `hole_end_correction`, `embouchure_end_correction`, `loss_factor`)
+    pub fn set_calibration(&mut self, calibration: JsValue) -> Result<(), JsValue> {
+        let calibration: physics::Calibration = options_from_js(calibration)?;
+        calibration.validate()?;
+        self.history.record("Calibration", &self.inner);
+        self.inner.calibration = calibration;
+        self.geometry_changed();
+        Ok(())
+    }
+
+    pub fn calibration(&self) -> Result<JsValue, JsValue> {
+        to_js(&self.inner.calibration)
+    }
+
+    /// Export-only shaping (edges, rings, engravings, units, LOD) as a partial `MeshOptions` object
+    pub fn set_mesh_options(&mut self, options: JsValue) -> Result<(), JsValue> {
+        self.mesh_options = options_from_js(options)?;
+        self.mesh_cache.invalidate();
+        Ok(())
+    }
+
+    /// Switch between transverse, notch and duct flutes and organ pipes, e.g.
+    /// `{ kind: "notch", shape: "u", width: 1.0, depth: 0.6, bevel_angle: 30 }`
+    pub fn set_instrument_type(&mut self, instrument: JsValue) -> Result<(), JsValue> {
+        let instrument = options_from_js(instrument)?;
+        self.history.record("Set instrument type", &self.inner);
+        self.inner.instrument = instrument;
+        self.mesh_cache.invalidate();
+        self.geometry_changed();
+        Ok(())
+    }
+
+    /// Distance from the embouchure centre to the cork face (cm). NaN or non-positive is ignored.
+    pub fn set_cork_position(&mut self, cork_position: f64) {
+        self.history.record("Set cork position", &self.inner);
+        if !cork_position.is_nan() && cork_position > 0.0 {
+            self.inner.cork_position = cork_position;
+        }
+        self.mesh_cache.invalidate();
+        self.geometry_changed();
+    }
+
+    /// Embouchure hole radius and chimney (lip plate) height in cm. NaN leaves a value unchanged;
+    /// the chimney may be zero for a hole cut straight through the wall.
+    pub fn set_embouchure(&mut self, radius: f64, chimney: f64) {
+        self.history.record("Set embouchure", &self.inner);
+        if !radius.is_nan() && radius > 0.0 {
+            self.inner.embouchure_hole_radius = radius;
+        }
+        if !chimney.is_nan() && chimney >= 0.0 {
+            self.inner.embouchure_chimney = chimney;
+        }
+        self.mesh_cache.invalidate();
+        self.geometry_changed();
+    }
+
+    /// Axial / transverse aspect of the embouchure hole at equal area; 1.0 is round
+    pub fn set_embouchure_oval(&mut self, oval: f64) {
+        self.history.record("Set embouchure oval", &self.inner);
+        if !oval.is_nan() && oval > 0.0 {
+            self.inner.embouchure_oval = oval;
+        }
+        self.mesh_cache.invalidate();
+        self.geometry_changed();
+    }
+
+    /// Out-of-roundness of the bore in percent, 0 for round. Out-of-range values are
+    /// ignored. Acoustics only; the mesh stays round.
+    pub fn set_bore_ovality(&mut self, ovality: f64) {
+        self.history.record("Set bore ovality", &self.inner);
+        if (0.0..physics::MAX_OVALITY).contains(&ovality) {
+            self.inner.bore_ovality = ovality;
+        }
+        self.geometry_changed();
+    }
+
+    pub fn bore_ovality(&self) -> f64 {
+        self.inner.bore_ovality
+    }
+
+    pub fn length(&self) -> f64 {
+        self.inner.length
+    }
+
+    pub fn bore_radius(&self) -> f64 {
+        self.inner.bore_radius
+    }
+
+    pub fn wall_thickness(&self) -> f64 {
+        self.inner.wall_thickness
+    }
+
+    pub fn cork_position(&self) -> f64 {
+        self.inner.cork_position
+    }
+
+    pub fn embouchure_hole_radius(&self) -> f64 {
+        self.inner.embouchure_hole_radius
+    }
+
+    pub fn embouchure_chimney(&self) -> f64 {
+        self.inner.embouchure_chimney
+    }
+
+    pub fn embouchure_oval(&self) -> f64 {
+        self.inner.embouchure_oval
+    }
+
+    /// Hole data in the same layout `set_holes` takes, in stored (not playing) order
+    pub fn hole_positions(&self) -> Vec<f64> {
+        self.inner.holes.iter().map(|h| h.position).collect()
+    }
+
+    pub fn hole_radii(&self) -> Vec<f64> {
+        self.inner.holes.iter().map(|h| h.radius).collect()
+    }
+
+    pub fn hole_open(&self) -> Vec<u8> {
+        self.inner.holes.iter().map(|h| h.open as u8).collect()
+    }
+
+    pub fn hole_azimuths(&self) -> Vec<f64> {
+        self.inner.holes.iter().map(|h| h.azimuth).collect()
+    }
+
+    /// Fingerings saved with the design, as hole bitmasks (bit i set = hole i open)
+    pub fn fingerings(&self) -> Vec<u32> {
+        self.fingerings.clone()
+    }
+
+    pub fn set_fingerings(&mut self, fingerings: Vec<u32>) {
+        self.fingerings = fingerings;
+    }
+
+    /// Target pitches (Hz) saved with the design
+    pub fn targets(&self) -> Vec<f64> {
+        self.targets.clone()
+    }
+
+    pub fn set_targets(&mut self, targets: Vec<f64>) {
+        self.targets = targets;
+    }
+
+    /// The instrument type in the `set_instrument_type` format, e.g. `{ kind: "transverse" }`
+    pub fn instrument_type(&self) -> Result<JsValue, JsValue> {
+        to_js(&self.inner.instrument)
+    }
+
+    /// The complete design (dimensions, holes, embouchure, cork, instrument type, fingerings
+    /// and targets) as a versioned design document. The same format is read back by `from_json`.
+    pub fn to_json(&self) -> String {
+        self.document().to_json()
+    }
+
+    /// Rebuild an engine from `to_json` output, including files saved by older versions.
+    /// Fields missing from older files take the `new` defaults; mesh options are not part of
+    /// the design and start fresh.
+    pub fn from_json(json: &str) -> Result<FluteEngine, JsValue> {
+        FluteEngine::from_document(DesignDocument::from_json(json)?)
+    }
+
+    /// Send solver, optimizer and mesh spans to the browser console and performance timeline
+    /// at `level` ("error", "warn", "info", "debug" or "trace") and above. Only the first call
+    /// takes effect. Built with the `tracing` feature.
+    #[cfg(feature = "tracing")]
+    pub fn enable_tracing(level: &str) -> Result<(), JsValue> {
+        let level: tracing::Level = level.parse().map_err(FlyteError::invalid_input)?;
+        static INSTALL: std::sync::Once = std::sync::Once::new();
+        INSTALL.call_once(|| {
+            let config = tracing_wasm::WASMLayerConfigBuilder::new()
+                .set_max_level(level)
+                .build();
+            tracing_wasm::set_as_global_default_with_config(config);
+        });
+        Ok(())
+    }
+
+    /// JSON Schema of the `to_json` document, for validating designs from other tools
+    pub fn design_schema() -> String {
+        document::json_schema()
+    }
+
+    /// `[{ id, name, description, key, scale }]` for every built-in starting design
+    #[cfg(feature = "optimize")]
+    pub fn presets() -> Result<JsValue, JsValue> {
+        let infos: Vec<_> = presets::PRESETS.iter().map(|p| p.info()).collect();
+        to_js(&infos)
+    }
+
+    /// `[{ id, name, density, youngs_modulus, speed_of_sound, ... }]` for every material in
+    /// `materials`, e.g. to fill the density of `print_estimate` or the material of
+    /// `moisture_drift`
+    pub fn materials() -> Result<JsValue, JsValue> {
+        to_js(&Material::catalogue())
+    }
+
+    /// `{ bore_radius, wall_thickness, pipes, cut_list, pitch }` for a pan-flute set: one
+    /// stopped pipe per note with its air and cut lengths. `options` holds the
+    /// `PanFluteOptions` fields (`notes`, `bore_radius`, `wall_thickness`, `stopper`,
+    /// `stock_length`, `kerf`, `spacing`).
+    pub fn design_pan_flute(options: JsValue) -> Result<JsValue, JsValue> {
+        let options: panflute::PanFluteOptions = options_from_js(options)?;
+        to_js(&panflute::design_pan_flute(&options)?)
+    }
+
+    /// Engine holding a flue organ pipe cut to sound `frequency` Hz, with that as its target.
+    /// `options` holds the `PipeOptions` fields (`stopped`, `scale`, `mouth_ratio`,
+    /// `cut_up_ratio`, `wall_thickness`).
+    pub fn design_organ_pipe(frequency: f64, options: JsValue) -> Result<FluteEngine, JsValue> {
+        let options: organ::PipeOptions = options_from_js(options)?;
+        let mut doc = DesignDocument::new(&organ::design_pipe(frequency, &options)?);
+        doc.fingerings = vec![0];
+        doc.targets = vec![frequency];
+        FluteEngine::from_document(doc)
+    }
+
+    /// Engine holding a preset tuned so the all-closed note is `key` Hz (NaN for the
+    /// preset's own key), with its scale as the targets
+    #[cfg(feature = "optimize")]
+    pub fn from_preset(id: &str, key: f64) -> Result<FluteEngine, JsValue> {
+        let preset = presets::Preset::from_id(id)?;
+        let mut doc = DesignDocument::new(&preset.build(key)?);
+        doc.targets = preset.targets(key);
+        FluteEngine::from_document(doc)
+    }
+
+    /// A new engine holding this design moved so the all-closed note sounds `options.key`
+    /// Hz, with the bore scaled by the family rule and the tube and holes re-fitted so every
+    /// note keeps its interval (see `transpose::TransposeOptions`). Fingerings carry over
+    /// and the targets move with the key.
+    #[cfg(feature = "optimize")]
+    pub fn transposed(&self, options: JsValue) -> Result<FluteEngine, JsValue> {
+        let options: transpose::TransposeOptions = options_from_js(options)?;
+        let transposed = transpose::transpose(&self.inner, &options)?;
+        let mut doc = DesignDocument::new(&transposed.flute);
+        doc.fingerings = self.fingerings.clone();
+        doc.targets = self
+            .targets
+            .iter()
+            .map(|f| f / transposed.length_scale)
+            .collect();
+        FluteEngine::from_document(doc)
+    }
+
+    /// Engine for a Flutomat design (its form values as JSON, see `import::flutomat`), with
+    /// its note frequencies as targets
+    pub fn import_flutomat(json: &str) -> Result<FluteEngine, JsValue> {
+        FluteEngine::from_document(import::flutomat::import_flutomat(json)?)
+    }
+
+    /// Engine for a TWCalc whistle calculation (`Name = value` text, see `import::twcalc`)
+    pub fn import_twcalc(text: &str) -> Result<FluteEngine, JsValue> {
+        FluteEngine::from_document(import::twcalc::import_twcalc(text)?)
+    }
+
+    /// The design (with fingerings and targets) as a compact base64url string for share
+    /// links, far shorter than `to_json`
+    pub fn encode_share_string(&self) -> String {
+        self.document().to_share_string()
+    }
+
+    /// Rebuild an engine from `encode_share_string` output, as `from_json` does
+    pub fn decode_share_string(share: &str) -> Result<FluteEngine, JsValue> {
+        FluteEngine::from_document(DesignDocument::from_share_string(share)?)
+    }
+
+    /// The full export mesh as OBJ text, one `g` group per part, in the units and up axis
+    /// chosen through `set_mesh_options` (millimetres, Y-up by default)
+    pub fn export_obj(&self) -> String {
+        let mesh = geometry::generate_flute_mesh_with(&self.inner, &self.mesh_options);
+        mesh.to_obj_string(self.mesh_options.units, self.mesh_options.up_axis)
+    }
+
+    /// The export mesh as typed arrays (cm, Y-up) for building a three.js `BufferGeometry`
+    /// without parsing OBJ text. `draft` drops to a coarse mesh for use while dragging.
+    /// Only the parts touched since the last call are rebuilt.
+    pub fn mesh_buffers(&mut self, draft: bool) -> MeshBuffers {
+        let lod = if draft {
+            geometry::Lod::Draft
+        } else {
+            self.mesh_options.lod
+        };
+        let mesh = self.mesh_cache.mesh(&self.inner, &self.mesh_options, lod);
+        MeshBuffers {
+            inner: mesh.to_buffers(),
+        }
+    }
+
+    /// Whether the design changed since the last `mesh_buffers` call
+    pub fn is_mesh_dirty(&self) -> bool {
+        self.mesh_cache.is_dirty()
+    }
+
+    /// Binary glTF (metres, Y-up) with one node per mesh group, for the 3D preview
+    #[cfg(feature = "export")]
+    pub fn export_glb(&self) -> Vec<u8> {
+        let mesh = geometry::generate_flute_mesh_with(&self.inner, &self.mesh_options);
+        export::gltf::export_glb(&mesh)
+    }
+
+    /// Binary STL in the mesh-option units and up axis, as a `Uint8Array`
+    #[cfg(feature = "export")]
+    pub fn export_stl(&self) -> Vec<u8> {
+        let mesh = geometry::generate_flute_mesh_with(&self.inner, &self.mesh_options);
+        export::stl::export_stl(&mesh, self.mesh_options.units, self.mesh_options.up_axis)
+    }
+
+    /// Binary STL of a pan-flute set (see `design_pan_flute`) as one raft, in this engine's
+    /// mesh-option units, up axis and resolution
+    #[cfg(feature = "export")]
+    pub fn export_pan_flute_stl(&self, options: JsValue) -> Result<Vec<u8>, JsValue> {
+        let options: panflute::PanFluteOptions = options_from_js(options)?;
+        let set = panflute::design_pan_flute(&options)?;
+        let mesh = panflute::pan_flute_mesh(&set, &self.mesh_options);
+        Ok(export::stl::export_stl(
+            &mesh,
+            self.mesh_options.units,
+            self.mesh_options.up_axis,
+        ))
+    }
+
+    /// 3MF package (Z-up, mesh-option units) with one object per part, as a `Uint8Array`
+    #[cfg(feature = "export")]
+    pub fn export_3mf(&self) -> Vec<u8> {
+        let mesh = geometry::generate_flute_mesh_with(&self.inner, &self.mesh_options);
+        export::threemf::export_3mf(&mesh, self.mesh_options.units)
+    }
+
+    /// OBJ of the internal air column (bore plus open-hole chimney stubs) for FEM solvers
+    pub fn export_air_column_obj(&self) -> String {
+        let mesh = geometry::generate_air_column_mesh(&self.inner, self.mesh_options.lod);
+        mesh.to_obj_string(self.mesh_options.units, self.mesh_options.up_axis)
+    }
+
+    /// Finished mass (g) and balance point (cm from the embouchure) at a solid density in
+    /// g/cm^3 (NaN = printing default), with how much each ring or riser shifts the balance.
+    /// Model a crown as a decorative ring at the head end.
+    pub fn balance_report(&self, density: f64) -> Result<JsValue, JsValue> {
+        let density = if density.is_nan() || density <= 0.0 {
+            geometry::DEFAULT_PRINT_DENSITY
+        } else {
+            density
+        };
+        let report = geometry::estimate_balance(&self.inner, &self.mesh_options, density);
+        to_js(&report)
+    }
+
+    /// Finger stretches that are uncomfortable or out of reach per hand, for `"small"`,
+    /// `"medium"` (default), `"large"` or `{ custom: { finger_comfortable, ... } }` hands
+    pub fn check_ergonomics(&self, hand: JsValue) -> Result<JsValue, JsValue> {
+        let hand = options_from_js(hand)?;
+        let report = ergonomics::check_ergonomics(&self.inner, hand);
+        to_js(&report)
+    }
+
+    /// Step back one edit. Returns the undone edit's description, or undefined at the start
+    /// of the history.
+    pub fn undo(&mut self) -> Option<String> {
+        let (description, state) = self.history.undo(&self.inner)?;
+        self.restore(state);
+        Some(description)
+    }
+
+    /// Re-apply the last undone edit, returning its description
+    pub fn redo(&mut self) -> Option<String> {
+        let (description, state) = self.history.redo(&self.inner)?;
+        self.restore(state);
+        Some(description)
+    }
+
+    /// Label of the edit `undo` would revert, e.g. "Edit hole 3"
+    pub fn undo_description(&self) -> Option<String> {
+        self.history.undo_description().map(str::to_string)
+    }
+
+    pub fn redo_description(&self) -> Option<String> {
+        self.history.redo_description().map(str::to_string)
+    }
+
+    /// Close the current undo step. Repeated edits with the same label (a hole being dragged)
+    /// merge into one step until this is called, e.g. on pointer-up.
+    pub fn history_checkpoint(&mut self) {
+        self.history.checkpoint();
+    }
+
+    /// Store a copy of the current design under `name`, replacing any design of that name
+    pub fn save_design(&mut self, name: &str) {
+        self.designs.insert(name.to_string(), self.inner.clone());
+    }
+
+    /// Make a stored design the current one; the stored copy is kept
+    pub fn load_design(&mut self, name: &str) -> Result<(), JsValue> {
+        let design = self.design(name)?.clone();
+        self.history
+            .record(&format!("Load design {}", name), &self.inner);
+        self.inner = design;
+        self.mesh_cache.invalidate();
+        self.geometry_changed();
+        Ok(())
+    }
+
+    pub fn remove_design(&mut self, name: &str) -> bool {
+        self.designs.remove(name).is_some()
+    }
+
+    /// Stored design names in sorted order
+    pub fn design_names(&self) -> Vec<String> {
+        self.designs.keys().cloned().collect()
+    }
+
+    /// `[{ fingering, a, b, cents }]` for every progressive fingering of `a`.
+    /// An empty name stands for the current design.
+    pub fn compare_designs(&self, a: &str, b: &str, jet_velocity: f64) -> Result<JsValue, JsValue> {
+        let rows = tuning::compare_designs(self.design(a)?, self.design(b)?, jet_velocity);
+        to_js(&rows)
+    }
+
+    /// Stored designs scored against the engine's targets as `{ fingerings, targets, rows:
+    /// [{ candidate, frequencies, cents, rms_cents, worst_cents }], ranking }`, for a
+    /// leaderboard of optimizer candidates. Without saved fingerings the targets go with the
+    /// current design's progressive ones. An empty name stands for the current design.
+    pub fn leaderboard(&self, names: Vec<String>, jet_velocity: f64) -> Result<JsValue, JsValue> {
+        let candidates = names
+            .iter()
+            .map(|name| self.design(name).cloned())
+            .collect::<Result<Vec<_>, _>>()?;
+        let fingerings =
+            tuning::target_fingerings(&self.inner, &self.fingerings, self.targets.len());
+        let board = tuning::leaderboard(&candidates, &fingerings, &self.targets, jet_velocity)?;
+        to_js(&board)
+    }
+
+    /// Overlaid spectra of two designs as one `Float64Array` of
+    /// (freq, |Z| a, phase a, |Z| b, phase b). An empty name stands for the current design.
+    pub fn compare_spectra(
+        &self,
+        a: &str,
+        b: &str,
+        f0: f64,
+        f1: f64,
+        n: usize,
+    ) -> Result<Vec<f64>, JsValue> {
+        Ok(tuning::overlay_spectra(
+            self.design(a)?,
+            self.design(b)?,
+            f0,
+            f1,
+            n,
+        ))
+    }
+
+    /// Begin a long computation to be advanced with `poll`, replacing any running job.
+    /// `spec` is `{ kind: "spectrum", f0, f1, n }`, `{ kind: "fingering_chart", jet_velocity }`
+    /// or `{ kind: "one_handed_layout", options }`. The job works on a copy of the current design.
+    pub fn start_job(&mut self, spec: JsValue) -> Result<JsValue, JsValue> {
+        let spec: jobs::JobSpec =
+            serde_wasm_bindgen::from_value(spec).map_err(FlyteError::invalid_input)?;
+        let job = jobs::Job::new(&self.inner, &spec);
+        let progress = job.progress();
+        self.job = Some(job);
+        to_js(&progress)
+    }
+
+    /// Like `start_job`, but only part `index` of `count`, for spreading one job over a pool
+    /// of Web Workers that each hold an engine with the same design. Combine the finished
+    /// parts, in order, with `FluteEngine.merge_job_results`.
+    pub fn start_job_shard(
+        &mut self,
+        spec: JsValue,
+        index: usize,
+        count: usize,
+    ) -> Result<JsValue, JsValue> {
+        if index >= count {
+            return Err(FlyteError::invalid_input(format!(
+                "shard {} of {} does not exist",
+                index, count
+            ))
+            .into());
+        }
+        let spec: jobs::JobSpec =
+            serde_wasm_bindgen::from_value(spec).map_err(FlyteError::invalid_input)?;
+        let job = jobs::Job::shard(&self.inner, &spec, index, count);
+        let progress = job.progress();
+        self.job = Some(job);
+        to_js(&progress)
+    }
+
+    /// One `{ kind, result }` from the `job_result`s of every shard of a job, in shard order
+    pub fn merge_job_results(results: JsValue) -> Result<JsValue, JsValue> {
+        let results: Vec<jobs::JobResult> =
+            serde_wasm_bindgen::from_value(results).map_err(FlyteError::invalid_input)?;
+        let merged = jobs::JobResult::merge(results)
+            .ok_or_else(|| FlyteError::invalid_input("no results, or results of different jobs"))?;
+        to_js(&merged)
+    }
+
+    /// Run up to `n_steps` steps of the current job and return `{ done, total, finished }`
+    pub fn poll(&mut self, n_steps: usize) -> Result<JsValue, JsValue> {
+        let job = self
+            .job
+            .as_mut()
+            .ok_or_else(|| FlyteError::invalid_input("no job running"))?;
+        to_js(&job.poll(n_steps))
+    }
+
+    /// `{ kind, result }` of the current job; partial until `poll` reports `finished`
+    pub fn job_result(&self) -> Result<JsValue, JsValue> {
+        let job = self
+            .job
+            .as_ref()
+            .ok_or_else(|| FlyteError::invalid_input("no job running"))?;
+        to_js(&job.result())
+    }
+
+    /// Drop the current job and its partial result
+    pub fn cancel(&mut self) {
+        self.job = None;
+    }
+
+    /// Render a note of the design as a mono `Float32Array` for a Web Audio `AudioBuffer`.
+    /// `options` is a partial `SynthOptions` object, e.g. `{ sample_rate: 48000, duration: 2,
+    /// fingering: 0b0111 }`; undefined plays one second of the current fingering at 44.1 kHz.
+    #[cfg(feature = "synth")]
+    pub fn render_note(&self, options: JsValue) -> Result<Vec<f32>, JsValue> {
+        let options: synth::SynthOptions = options_from_js(options)?;
+        Ok(synth::render_note(&self.inner, &options))
+    }
+
+    /// Every note of the fingering chart in sequence, lowest first, as one `Float32Array`.
+    /// Takes the `render_note` options; `duration` is per note.
+    #[cfg(feature = "synth")]
+    pub fn render_scale(&self, options: JsValue) -> Result<Vec<f32>, JsValue> {
+        let options: synth::SynthOptions = options_from_js(options)?;
+        Ok(synth::render_scale(&self.inner, &options))
+    }
+
+    /// `render_scale` as a 16-bit mono WAV file, for download or sharing
+    #[cfg(all(feature = "synth", feature = "export"))]
+    pub fn export_scale_wav(&self, options: JsValue) -> Result<Vec<u8>, JsValue> {
+        let options: synth::SynthOptions = options_from_js(options)?;
+        let rate = options.wav_sample_rate();
+        let options = synth::SynthOptions {
+            sample_rate: rate.into(),
+            ..options
+        };
+        let samples = synth::render_scale(&self.inner, &options);
+        Ok(export::wav::export_wav(&samples, rate))
+    }
+
+    /// Sounding frequency and relative harmonic levels (`{ frequency, partials }`) used by
+    /// `render_note`, for driving a custom Web Audio graph
+    #[cfg(feature = "synth")]
+    pub fn note_spectrum(
+        &self,
+        fingering: Option<u32>,
+        jet_velocity: f64,
+    ) -> Result<JsValue, JsValue> {
+        to_js(&synth::note_spectrum(&self.inner, fingering, jet_velocity))
+    }
+
+    /// Replace the finger holes with a one-handed layout and return the holes and the
+    /// resulting notes; thumb holes stay. Pass `undefined` for four holes sized for a medium
+    /// hand.
+    #[cfg(feature = "optimize")]
+    pub fn apply_one_handed_layout(&mut self, options: JsValue) -> Result<JsValue, JsValue> {
+        let options = options_from_js(options)?;
+        let layout = ergonomics::one_handed_layout(&self.inner, &options);
+        let holes = layout.relaid_holes(&self.inner)?;
+        self.history.record("One-handed layout", &self.inner);
+        self.inner.holes = holes;
+        self.mesh_cache.invalidate();
+        self.geometry_changed();
+        to_js(&layout)
+    }
+
+    /// Check the exported mesh for slicer-breaking defects (open edges, flipped faces, ...)
+    pub fn validate_mesh(&self) -> Result<JsValue, JsValue> {
+        let report = geometry::generate_flute_mesh_with(&self.inner, &self.mesh_options).validate();
+        to_js(&report)
+    }
+
+    /// Design problems that would produce impossible or fragile geometry (thin walls,
+    /// overlapping or oversized holes, ...), as a list of `{ severity, code, message, hole }`
+    pub fn check_geometry(&self) -> Result<JsValue, JsValue> {
+        let issues = geometry::check_geometry(&self.inner);
+        to_js(&issues)
+    }
+
+    /// Half longitudinal section (outer and bore lines, holes marked) as 1:1 SVG, for
+    /// grinding a lathe form tool or setting up a duplicator
+    #[cfg(feature = "export")]
+    pub fn export_profile_svg(&self) -> String {
+        export::profile::profile_svg(&self.inner)
+    }
+
+    /// The same profile as DXF polylines (cm, y = radius)
+    #[cfg(feature = "export")]
+    pub fn export_profile_dxf(&self) -> String {
+        export::profile::profile_dxf(&self.inner)
+    }
+
+    /// Reamer radius along the bore as `[{ distance, bore_radius, reamer_radius }]` (cm).
+    /// `allowance` is the radius left for finishing; NaN uses the defaults.
+    #[cfg(feature = "export")]
+    pub fn reamer_table(&self, allowance: f64, step: f64) -> Result<JsValue, JsValue> {
+        let allowance = if allowance.is_nan() {
+            export::profile::DEFAULT_REAMER_ALLOWANCE
+        } else {
+            allowance
+        };
+        let step = if step.is_nan() || step <= 0.0 {
+            export::profile::DEFAULT_REAMER_STEP
+        } else {
+            step
+        };
+        let table = export::profile::reamer_table(&self.inner, allowance, step);
+        to_js(&table)
+    }
+
+    /// Reamer blade outline for grinding, as DXF (cm, tip at x = 0)
+    #[cfg(feature = "export")]
+    pub fn export_reamer_dxf(&self, allowance: f64) -> String {
+        let allowance = if allowance.is_nan() {
+            export::profile::DEFAULT_REAMER_ALLOWANCE
+        } else {
+            allowance
+        };
+        export::profile::reamer_dxf(&self.inner, allowance)
+    }
+
+    /// 1:1 wrap-around template tiled over `"a4"` (default) or `"letter"` pages, with
+    /// registration marks for taping the pages together around a tube blank
+    #[cfg(feature = "export")]
+    pub fn export_paper_template(&self, paper: JsValue) -> Result<Vec<u8>, JsValue> {
+        let paper = options_from_js(paper)?;
+        Ok(export::pdf::export_paper_template(&self.inner, paper))
+    }
+
+    /// 1:1 SVG wrap-around template for hand-drilling holes into a tube blank
+    #[cfg(feature = "export")]
+    pub fn export_drill_template_svg(&self) -> String {
+        export::svg::drill_template(&self.inner)
+    }
+
+    /// DXF section + plan drawing (cm) with bore, holes and centrelines on separate layers
+    #[cfg(feature = "export")]
+    pub fn export_dxf(&self) -> String {
+        export::dxf::export_dxf(&self.inner)
+    }
+
+    /// Printable A3 PDF plan: dimensioned views, hole table and tuning chart
+    #[cfg(feature = "export")]
+    pub fn export_pdf_plan(&self, jet_velocity: f64) -> Vec<u8> {
+        export::pdf::export_pdf_plan(&self.inner, jet_velocity)
+    }
+
+    /// Standard MIDI File of the scale, lowest note first, with pitch bends carrying each
+    /// note's cents deviation so a DAW plays the design's actual intonation
+    #[cfg(feature = "export")]
+    pub fn export_midi(&self, jet_velocity: f64) -> Vec<u8> {
+        export::midi::export_scale_midi(&self.inner, jet_velocity)
+    }
+
+    /// Hole table as CSV (mm, front to back) with the note each hole plays; see
+    /// `export::csv` for the columns
+    #[cfg(feature = "export")]
+    pub fn export_hole_csv(&self, jet_velocity: f64) -> String {
+        export::csv::export_hole_csv(&self.inner, jet_velocity)
+    }
+
+    /// The design as Flutomat JSON, readable by `import_flutomat` and Flutomat users
+    #[cfg(feature = "export")]
+    pub fn export_flutomat(&self, jet_velocity: f64) -> String {
+        export::flutomat::export_flutomat_json(&self.inner, jet_velocity)
+    }
+
+    /// Peck-drilling G-code (mm). `options` is a partial `GcodeOptions` object or undefined.
+    #[cfg(feature = "export")]
+    pub fn export_gcode(&self, options: JsValue) -> Result<String, JsValue> {
+        let opts: export::gcode::GcodeOptions = options_from_js(options)?;
+        Ok(export::gcode::export_gcode(&self.inner, &opts))
+    }
+
+    /// Versioned JSON manufacturing spec (every dimension with tolerances, in mm)
+    #[cfg(feature = "export")]
+    pub fn export_spec_json(&self) -> String {
+        export::spec::export_spec_json(&self.inner)
+    }
+
+    /// Solid volume, surface area, weight and print time of the body.
+    /// Pass NaN for density/infill to use PLA at 20%.
+    pub fn print_estimate(&self, density: f64, infill: f64) -> Result<JsValue, JsValue> {
+        let density = if density.is_nan() || density <= 0.0 {
+            geometry::DEFAULT_PRINT_DENSITY
+        } else {
+            density
+        };
+        let infill = if infill.is_nan() {
+            geometry::DEFAULT_INFILL
+        } else {
+            infill
+        };
+        let estimate = geometry::estimate_print(&self.inner, density, infill);
+        to_js(&estimate)
+    }
+}
+
+impl FluteEngine {
+    fn from_document(doc: DesignDocument) -> Result<FluteEngine, JsValue> {
+        let inner = doc.to_flute();
+        inner.validate()?;
+        console_error_panic_hook::set_once();
+        Ok(FluteEngine {
+            inner,
+            mesh_options: geometry::MeshOptions::default(),
+            mesh_cache: geometry::MeshCache::default(),
+            spectrum_cache: transfer::SpectrumCache::default(),
+            job: None,
+            revision: 0,
+            results: ResultCache::default(),
+            designs: BTreeMap::new(),
+            history: History::default(),
+            tracked_pitch: None,
+            preview_precision: Precision::default(),
+            range_policy: RangePolicy::default(),
+            staging: HoleStaging::default(),
+            fingerings: doc.fingerings,
+            targets: doc.targets,
+        })
+    }
+
+    fn document(&self) -> DesignDocument {
+        let mut doc = DesignDocument::new(&self.inner);
+        doc.fingerings = self.fingerings.clone();
+        doc.targets = self.targets.clone();
+        doc
+    }
+
+    fn restore(&mut self, state: Flute) {
+        self.inner = state;
+        self.mesh_cache.invalidate();
+        self.geometry_changed();
+    }
+
+    /// `frequency`, or the current pitch at the default jet when it is NaN
+    fn frequency_or_pitch(&mut self, frequency: f64) -> f64 {
+        if frequency.is_nan() {
+            self.calculate_pitch(f64::NAN)
+        } else {
+            frequency
+        }
+    }
+
+    /// Start a new geometry revision, retiring every cached acoustic answer
+    fn geometry_changed(&mut self) {
+        self.revision += 1;
+    }
+
+    /// The open holes as a bitmask, or `None` for designs too big to key the caches by
+    fn fingering(&self) -> Option<u32> {
+        (self.inner.holes.len() <= 32).then(|| {
+            self.inner
+                .holes
+                .iter()
+                .enumerate()
+                .filter(|(_, hole)| hole.open)
+                .fold(0, |mask, (i, _)| mask | 1 << i)
+        })
+    }
+
+    /// A stored design, or the current one for an empty name
+    fn design(&self, name: &str) -> Result<&Flute, FlyteError> {
+        if name.is_empty() {
+            return Ok(&self.inner);
+        }
+        self.designs
+            .get(name)
+            .ok_or_else(|| FlyteError::invalid_input(format!("no design named {:?}", name)))
+    }
+}
+
+/// Serialize a result for JS
+fn to_js<T: serde::Serialize>(value: &T) -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(value).map_err(|e| {
+        FlyteError::Serialization {
+            message: e.to_string(),
+        }
+        .into()
+    })
+}
+
+/// Deserialize a (partial) options object; `undefined` / `null` give the defaults
+fn options_from_js<T: serde::de::DeserializeOwned + Default>(value: JsValue) -> Result<T, JsValue> {
+    if value.is_undefined() || value.is_null() {
+        return Ok(T::default());
+    }
+    serde_wasm_bindgen::from_value(value).map_err(|e| FlyteError::invalid_input(e).into())
+}
@@ -0,0 +1,384 @@
+//! Finishing a made instrument the way a maker does at the bench: one small change at a
+//! time, each checked before the next. Only what a finished tube still allows is offered:
+//! opening a hole up, lining it smaller and trimming the foot. Every suggestion is solved
+//! again on the model before it is listed, so the effects it shows on each note are the
+//! model's rather than a linear guess.
+
+use crate::ergonomics::pitch_near;
+use crate::error::FlyteError;
+use crate::physics::Flute;
+use crate::tuning;
+use serde::{Deserialize, Serialize};
+
+/// Finite-difference steps for the sensitivities, cm of diameter and of length
+const DIAMETER_STEP: f64 = 0.01;
+const LENGTH_STEP: f64 = 0.1;
+/// Smallest hole radius the wizard will line a hole down to, cm
+const MIN_HOLE_RADIUS: f64 = 0.1;
+/// A step has to bring the RMS error down by at least this many cents to be worth making
+const MIN_GAIN: f64 = 0.1;
+/// Notes a step moves by less than this aren't named in its summary, cents
+const SUMMARY_THRESHOLD: f64 = 1.0;
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct WizardOptions {
+    /// Hole bitmasks to tune (bit i = hole i open). Empty uses the progressive fingerings,
+    /// lowest first, one per target.
+    pub fingerings: Vec<u32>,
+    /// Hz, one per fingering
+    pub targets: Vec<f64>,
+    pub max_steps: usize,
+    /// Stop once every note is within this many cents
+    pub tolerance: f64,
+    /// Changes are rounded to this many cm, about what a reamer or a lining can do
+    pub resolution: f64,
+    /// Also suggest lining holes smaller, not just opening them up
+    pub allow_shrink: bool,
+    /// Also suggest trimming the foot
+    pub allow_trim: bool,
+}
+
+impl Default for WizardOptions {
+    fn default() -> Self {
+        WizardOptions {
+            fingerings: Vec::new(),
+            targets: Vec::new(),
+            max_steps: 8,
+            tolerance: 2.0,
+            resolution: 0.005,
+            allow_shrink: true,
+            allow_trim: true,
+        }
+    }
+}
+
+/// One single-parameter change
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Adjustment {
+    /// Hole `hole`'s diameter by `change` cm: positive opens it up, negative lines it
+    HoleDiameter { hole: usize, change: f64 },
+    /// `change` cm taken off the foot
+    TrimFoot { change: f64 },
+}
+
+impl Adjustment {
+    fn apply(self, flute: &mut Flute) {
+        match self {
+            Adjustment::HoleDiameter { hole, change } => flute.holes[hole].radius += change / 2.0,
+            Adjustment::TrimFoot { change } => flute.length -= change,
+        }
+    }
+}
+
+/// What a step does to one note, cents from the target before and after
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NoteEffect {
+    pub fingering: u32,
+    pub target: f64,
+    /// Nearest note name of the target, e.g. "E5"
+    pub note: String,
+    pub before: f64,
+    pub after: f64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct WizardStep {
+    pub adjustment: Adjustment,
+    /// The changed dimension afterwards: the hole's diameter or the tube length, cm
+    pub value: f64,
+    /// For the UI, e.g. "Enlarge hole 3 by 0.30 mm: E5 +18 cents, F#5 +4 cents"
+    pub summary: String,
+    pub notes: Vec<NoteEffect>,
+    pub rms_before: f64,
+    pub rms_after: f64,
+}
+
+/// Steps in the order to make them, each checked on the design with the earlier ones made
+#[derive(Serialize, Debug, Clone)]
+pub struct TuningPlan {
+    pub fingerings: Vec<u32>,
+    pub targets: Vec<f64>,
+    pub steps: Vec<WizardStep>,
+    /// Cents from each target before the first step and after the last
+    pub start_cents: Vec<f64>,
+    pub end_cents: Vec<f64>,
+    /// The design with every step made
+    pub flute: Flute,
+}
+
+/// A sequence of single changes that brings `flute` towards `opts.targets`. Each round
+/// estimates every note's sensitivity to each hole's diameter and to the foot, takes the
+/// least-squares amount of each change alone, rounds it to `opts.resolution`, solves the
+/// changed design and keeps the change that lowers the RMS error most. Stops when every
+/// note is within `opts.tolerance`, after `opts.max_steps`, or when nothing helps.
+pub fn tuning_wizard(flute: &Flute, opts: &WizardOptions) -> Result<TuningPlan, FlyteError> {
+    trace_span!(INFO, "tuning_wizard", steps = opts.max_steps);
+    flute.validate()?;
+    if let Some(bad) = opts.targets.iter().find(|t| !t.is_finite() || **t <= 0.0) {
+        return Err(FlyteError::invalid_input(format!(
+            "targets must be positive, got {}",
+            bad
+        )));
+    }
+    if !opts.resolution.is_finite() || opts.resolution <= 0.0 {
+        return Err(FlyteError::invalid_input(format!(
+            "resolution must be positive, got {}",
+            opts.resolution
+        )));
+    }
+    let fingerings = tuning::target_fingerings(flute, &opts.fingerings, opts.targets.len());
+    if fingerings.len() != opts.targets.len() {
+        return Err(FlyteError::invalid_input(format!(
+            "{} fingerings but {} targets",
+            fingerings.len(),
+            opts.targets.len()
+        )));
+    }
+    let targets = &opts.targets;
+    let errors = |design: &Flute| -> Vec<f64> {
+        fingerings
+            .iter()
+            .zip(targets)
+            .map(|(&fingering, &target)| {
+                1200.0 * (pitch_near(design, fingering, target) / target).log2()
+            })
+            .collect()
+    };
+
+    let mut current = flute.clone();
+    let mut cents = errors(&current);
+    let start_cents = cents.clone();
+    let mut steps = Vec::new();
+    for _ in 0..opts.max_steps {
+        if cents.iter().all(|c| c.abs() <= opts.tolerance) {
+            break;
+        }
+        let rms_before = rms(&cents);
+        let mut best: Option<(Adjustment, Flute, Vec<f64>, f64)> = None;
+        for probe in probes(&current, opts) {
+            let Some(adjustment) = sized(&current, probe, &cents, opts, errors) else {
+                continue;
+            };
+            let mut changed = current.clone();
+            adjustment.apply(&mut changed);
+            if changed.validate().is_err() {
+                continue;
+            }
+            let after = errors(&changed);
+            let rms_after = rms(&after);
+            if best.as_ref().is_none_or(|b| rms_after < b.3) {
+                best = Some((adjustment, changed, after, rms_after));
+            }
+        }
+        let Some((adjustment, changed, after, rms_after)) = best else {
+            break;
+        };
+        if rms_after >= rms_before - MIN_GAIN {
+            break;
+        }
+        let notes: Vec<NoteEffect> = fingerings
+            .iter()
+            .zip(targets)
+            .zip(cents.iter().zip(&after))
+            .map(|((&fingering, &target), (&before, &after))| NoteEffect {
+                fingering,
+                target,
+                note: tuning::note_info(target).name,
+                before,
+                after,
+            })
+            .collect();
+        let value = match adjustment {
+            Adjustment::HoleDiameter { hole, .. } => 2.0 * changed.holes[hole].radius,
+            Adjustment::TrimFoot { .. } => changed.length,
+        };
+        steps.push(WizardStep {
+            adjustment,
+            value,
+            summary: summary(adjustment, &notes),
+            notes,
+            rms_before,
+            rms_after,
+        });
+        current = changed;
+        cents = after;
+    }
+    Ok(TuningPlan {
+        fingerings,
+        targets: targets.clone(),
+        steps,
+        start_cents,
+        end_cents: cents,
+        flute: current,
+    })
+}
+
+/// A unit change of every parameter the wizard may touch
+fn probes(flute: &Flute, opts: &WizardOptions) -> Vec<Adjustment> {
+    let mut probes: Vec<Adjustment> = (0..flute.holes.len())
+        .map(|hole| Adjustment::HoleDiameter {
+            hole,
+            change: DIAMETER_STEP,
+        })
+        .collect();
+    if opts.allow_trim {
+        probes.push(Adjustment::TrimFoot {
+            change: LENGTH_STEP,
+        });
+    }
+    probes
+}
+
+/// `probe` scaled to the least-squares amount for the current errors, kept within what the
+/// options and a sensible hole allow and rounded to the resolution. None if that rounds
+/// away or the parameter moves no note.
+fn sized(
+    flute: &Flute,
+    probe: Adjustment,
+    cents: &[f64],
+    opts: &WizardOptions,
+    errors: impl Fn(&Flute) -> Vec<f64>,
+) -> Option<Adjustment> {
+    let unit = match probe {
+        Adjustment::HoleDiameter { change, .. } | Adjustment::TrimFoot { change } => change,
+    };
+    let mut probed = flute.clone();
+    probe.apply(&mut probed);
+    // Sensitivities s in cents per cm; the step minimising sum (e + s x)^2 is -(s.e) / (s.s)
+    let (mut dot, mut square) = (0.0, 0.0);
+    for (after, before) in errors(&probed).iter().zip(cents) {
+        let s = (after - before) / unit;
+        dot += s * before;
+        square += s * s;
+    }
+    let amount = -dot / square;
+    if !amount.is_finite() {
+        return None;
+    }
+    let amount = match probe {
+        Adjustment::HoleDiameter { hole, .. } => {
+            let diameter = 2.0 * flute.holes[hole].radius;
+            let smallest = if opts.allow_shrink {
+                2.0 * MIN_HOLE_RADIUS
+            } else {
+                diameter
+            };
+            amount.clamp(smallest - diameter, 2.0 * flute.bore_radius - diameter)
+        }
+        // Wood only comes off the foot
+        Adjustment::TrimFoot { .. } => amount.max(0.0),
+    };
+    let amount = (amount / opts.resolution).trunc() * opts.resolution;
+    if amount == 0.0 {
+        return None;
+    }
+    Some(match probe {
+        Adjustment::HoleDiameter { hole, .. } => Adjustment::HoleDiameter {
+            hole,
+            change: amount,
+        },
+        Adjustment::TrimFoot { .. } => Adjustment::TrimFoot { change: amount },
+    })
+}
+
+fn rms(cents: &[f64]) -> f64 {
+    if cents.iter().any(|c| !c.is_finite()) {
+        return f64::INFINITY;
+    }
+    (cents.iter().map(|c| c * c).sum::<f64>() / cents.len().max(1) as f64).sqrt()
+}
+
+/// "Enlarge hole 3 by 0.30 mm: E5 +18 cents, F#5 +4 cents", the notes it moves most first
+fn summary(adjustment: Adjustment, notes: &[NoteEffect]) -> String {
+    let action = match adjustment {
+        Adjustment::HoleDiameter { hole, change } if change > 0.0 => {
+            format!("Enlarge hole {} by {:.2} mm", hole + 1, 10.0 * change)
+        }
+        Adjustment::HoleDiameter { hole, change } => {
+            format!("Line hole {} down by {:.2} mm", hole + 1, -10.0 * change)
+        }
+        Adjustment::TrimFoot { change } => format!("Trim {:.2} mm off the foot", 10.0 * change),
+    };
+    let mut moved: Vec<(&NoteEffect, f64)> = notes
+        .iter()
+        .map(|n| (n, n.after - n.before))
+        .filter(|(_, shift)| shift.abs() >= SUMMARY_THRESHOLD)
+        .collect();
+    moved.sort_by(|a, b| b.1.abs().total_cmp(&a.1.abs()));
+    let effects: Vec<String> = moved
+        .iter()
+        .map(|(n, shift)| format!("{} {:+.0} cents", n.note, shift))
+        .collect();
+    if effects.is_empty() {
+        action
+    } else {
+        format!("{}: {}", action, effects.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Hole;
+
+    #[test]
+    fn test_tuning_wizard_finishes_a_detuned_design() {
+        let mut made = Flute::new(60.0, 0.95, 0.4);
+        for pos in [40.0, 35.0, 30.0] {
+            made.holes.push(Hole::new(pos, 0.35, true)).unwrap();
+        }
+        let fingerings = tuning::progressive_fingerings(&made);
+        let targets: Vec<f64> = fingerings
+            .iter()
+            .map(|&f| pitch_near(&made, f, 300.0 * 2f64.powf(f.count_ones() as f64 / 6.0)))
+            .collect();
+        // Left long, with the middle hole drilled small
+        let mut rough = made.clone();
+        rough.length += 1.0;
+        rough.holes[1].radius = 0.3;
+
+        let opts = WizardOptions {
+            targets: targets.clone(),
+            ..WizardOptions::default()
+        };
+        let plan = tuning_wizard(&rough, &opts).unwrap();
+        assert!(!plan.steps.is_empty());
+        assert!(
+            rms(&plan.end_cents) < 0.25 * rms(&plan.start_cents),
+            "{:?}",
+            plan
+        );
+        // Every step is checked on the design with the earlier ones made
+        let mut replayed = rough.clone();
+        let mut cents = plan.start_cents.clone();
+        for step in &plan.steps {
+            assert!(step.rms_after < step.rms_before);
+            let before: Vec<f64> = step.notes.iter().map(|n| n.before).collect();
+            assert_eq!(before, cents);
+            step.adjustment.apply(&mut replayed);
+            cents = step.notes.iter().map(|n| n.after).collect();
+        }
+        assert_eq!(replayed.length, plan.flute.length);
+        assert!(plan.steps.iter().any(|s| matches!(
+            s.adjustment,
+            Adjustment::HoleDiameter { hole: 1, change } if change > 0.0
+        )));
+        assert!(
+            plan.steps[0].summary.contains(" cents"),
+            "{}",
+            plan.steps[0].summary
+        );
+
+        // Nothing to trim or open up on a design already in tune
+        let settled = tuning_wizard(&made, &opts).unwrap();
+        assert!(settled.steps.is_empty());
+        let mismatched = WizardOptions {
+            targets: targets[1..].to_vec(),
+            fingerings,
+            ..WizardOptions::default()
+        };
+        assert!(tuning_wizard(&rough, &mismatched).is_err());
+    }
+}
@@ -40,3 +40,137 @@ fn test_hole_opening_shifts_pitch() {
         new_pitch
     );
 }
+
+#[test]
+fn test_cached_results_follow_edits() {
+    let mut engine = FluteEngine::new(60.0, 0.95, 0.4);
+    engine.add_hole(30.0, 0.3, false).unwrap();
+    let closed = engine.calculate_pitch(0.0);
+    assert_eq!(engine.calculate_pitch(0.0), closed);
+    let spectrum = engine.impedance_spectrum(200.0, 400.0, 11);
+    assert_eq!(engine.impedance_spectrum(200.0, 400.0, 11), spectrum);
+
+    // Toggling a hole changes the fingering, not the geometry revision
+    let revision = engine.revision();
+    engine.update_hole(0, 30.0, 0.3, true).unwrap();
+    assert_eq!(engine.revision(), revision);
+    assert!(engine.calculate_pitch(0.0) > closed);
+    assert_ne!(engine.impedance_spectrum(200.0, 400.0, 11), spectrum);
+    assert_eq!(
+        engine.calculate_pitches(&[0, 1], 0.0),
+        vec![closed, engine.calculate_pitch(0.0)]
+    );
+    engine.update_hole(0, 30.0, 0.3, false).unwrap();
+    assert_eq!(engine.impedance_spectrum(200.0, 400.0, 11), spectrum);
+
+    // Any geometric edit starts a new revision and drops the cached answers
+    engine.update_hole(0, 28.0, 0.3, false).unwrap();
+    assert!(engine.revision() > revision);
+    assert_ne!(engine.calculate_pitch(0.0), closed);
+    assert_ne!(engine.impedance_spectrum(200.0, 400.0, 11), spectrum);
+}
+
+#[test]
+fn test_undo_redo_through_the_engine() {
+    let mut engine = FluteEngine::new(60.0, 0.95, 0.4);
+    engine.add_hole(30.0, 0.3, true).unwrap();
+    // A drag is one step until the checkpoint
+    engine.update_hole(0, 31.0, 0.3, true).unwrap();
+    engine.update_hole(0, 32.0, 0.3, true).unwrap();
+    engine.history_checkpoint();
+    engine.set_cork_position(2.0);
+
+    assert_eq!(
+        engine.undo_description().as_deref(),
+        Some("Set cork position")
+    );
+    assert_eq!(engine.undo().as_deref(), Some("Set cork position"));
+    assert_eq!(engine.cork_position(), 1.7);
+    assert_eq!(engine.undo().as_deref(), Some("Edit hole 1"));
+    assert_eq!(engine.hole_positions(), vec![30.0]);
+    assert_eq!(engine.undo().as_deref(), Some("Add hole"));
+    assert_eq!(engine.hole_count(), 0);
+    assert!(engine.undo().is_none());
+
+    assert_eq!(engine.redo().as_deref(), Some("Add hole"));
+    assert_eq!(engine.hole_count(), 1);
+}
+
+#[test]
+fn test_saved_design_round_trip() {
+    let mut engine = FluteEngine::new(60.0, 0.95, 0.4);
+    engine.add_hole(30.0, 0.3, true).unwrap();
+    engine.set_targets(vec![440.0, 493.88]);
+    let json = engine.to_json();
+    assert!(json.contains("\"version\": 1"));
+
+    let loaded = FluteEngine::from_json(&json).unwrap();
+    assert_eq!(loaded.hole_positions(), vec![30.0]);
+    assert_eq!(loaded.targets(), vec![440.0, 493.88]);
+
+    // Files saved before the versioned format
+    let old = r#"{"length": 55, "bore_radius": 0.9, "wall_thickness": 0.4, "holes": []}"#;
+    assert_eq!(FluteEngine::from_json(old).unwrap().length(), 55.0);
+}
+
+#[test]
+fn test_incremental_pitch_during_drag() {
+    let mut engine = FluteEngine::new(60.0, 0.95, 0.4);
+    engine.add_hole(45.0, 0.4, true).unwrap();
+    let mut pitch = engine.calculate_pitch_incremental(0.0);
+    assert_eq!(pitch, engine.calculate_pitch(0.0));
+    for frame in 1..=25 {
+        engine
+            .update_hole(0, 45.0 - 0.4 * frame as f64, 0.4, true)
+            .unwrap();
+        let next = engine.calculate_pitch_incremental(0.0);
+        assert!(next >= pitch && next / pitch < 1.03, "frame {}", frame);
+        pitch = next;
+    }
+}
+
+#[test]
+fn test_staged_holes_commit() {
+    let mut engine = FluteEngine::new(60.0, 0.95, 0.4);
+    engine.add_hole(40.0, 0.3, true).unwrap();
+    engine.add_hole(35.0, 0.3, true).unwrap();
+    engine.stage_holes(2);
+    assert_eq!(engine.staged_hole_count(), 2);
+    unsafe {
+        let positions = std::slice::from_raw_parts_mut(engine.staged_positions_ptr(), 2);
+        assert_eq!(positions, [40.0, 35.0]);
+        positions[1] = 33.0;
+        *engine.staged_open_ptr() = 0;
+    }
+    engine.commit_holes().unwrap();
+    assert_eq!(engine.hole_positions(), vec![40.0, 33.0]);
+    assert_eq!(engine.hole_open(), vec![0, 1]);
+    assert_eq!(engine.undo().as_deref(), Some("Set holes"));
+    assert_eq!(engine.hole_positions(), vec![40.0, 35.0]);
+
+    engine.stage_holes(3);
+    engine.commit_holes().unwrap();
+    assert_eq!(engine.hole_count(), 3);
+}
+
+#[test]
+fn test_share_string_round_trip() {
+    let mut engine = FluteEngine::new(60.0, 0.95, 0.4);
+    for i in 0..6 {
+        engine.add_hole(30.0 + 3.0 * i as f64, 0.3, true).unwrap();
+    }
+    engine.set_targets(vec![293.66]);
+    let share = engine.encode_share_string();
+    assert!(share.len() < engine.to_json().len() / 2);
+    let loaded = FluteEngine::decode_share_string(&share).unwrap();
+    assert_eq!(loaded.to_json(), engine.to_json());
+}
+
+#[test]
+fn test_engine_from_preset() {
+    let mut engine = FluteEngine::from_preset("tin_whistle", f64::NAN).unwrap();
+    assert_eq!(engine.hole_count(), 6);
+    assert_eq!(engine.targets().len(), 7);
+    let lowest = engine.calculate_pitches(&[0], f64::NAN)[0];
+    assert!((lowest - 587.33).abs() < 2.0, "{}", lowest);
+}